@@ -1,7 +1,10 @@
 use std::fs::File;
 use std::io::Write;
+use std::path::{Path, PathBuf};
 use std::sync::OnceLock;
 
+use sha2::{Digest, Sha256};
+
 type Result<T, E = Error> = std::result::Result<T, E>;
 
 pub use oodle_lz::{CompressionLevel, Compressor};
@@ -92,6 +95,17 @@ mod oodle_lz {
 
     pub type GetCompressedBufferSizeNeeded =
         unsafe extern "system" fn(compressor: Compressor, rawSize: usize) -> usize;
+
+    pub type GetCompressScratchMemBound = unsafe extern "system" fn(
+        compressor: Compressor,
+        level: CompressionLevel,
+        rawLen: isize,
+        pOptions: *const (),
+    ) -> isize;
+
+    /// `compressor` is an `OodleLZ_Compressor`, -1 (Invalid) sizes for any compressor.
+    pub type DecoderMemorySizeNeeded =
+        unsafe extern "system" fn(compressor: i32, rawLen: isize) -> isize;
 }
 
 struct OodlePlatform {
@@ -111,12 +125,27 @@ static OODLE_PLATFORM: OodlePlatform = OodlePlatform {
     bytes: include_bytes!("../../oo2core_9_win64.dll"),
 };
 
+/// Environment variable pointing at an Oodle library to load instead of the embedded one.
+pub const OODLE_PATH_ENV: &str = "REPAK_OODLE_PATH";
+
+/// SHA-256 of the Oodle 2.9 core libraries we are willing to load.
+const KNOWN_HASHES: &[&str] = &[
+    // liboo2corelinux64.so.9
+    "ed7e98f70be1254a80644efd3ae442ff61f854a2fe9debb0b978b95289884e9c",
+    // oo2core_9_win64.dll
+    "6f5d41a7892ea6b2db420f2458dad2f84a63901c9a93ce9497337b16c195f457",
+];
+
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
     #[error("Oodle lib hash mismatch expected: {expected} got {found}")]
     HashMismatch { expected: String, found: String },
     #[error("Oodle compression failed")]
     CompressionFailed,
+    #[error("Oodle decompression failed")]
+    DecompressionFailed,
+    #[error("Oodle output buffer too small: needed {needed} got {got}")]
+    OutputTooSmall { needed: usize, got: usize },
     #[error("Oodle initialization failed previously")]
     InitializationFailed,
     #[error("IO error {0:?}")]
@@ -125,22 +154,86 @@ pub enum Error {
     LibLoading(#[from] libloading::Error),
 }
 
-fn fetch_oodle() -> Result<std::path::PathBuf> {
-    let oodle_path = std::env::current_exe()?.with_file_name(OODLE_PLATFORM.name);
-    if !oodle_path.exists() {
-        // fuck downloading the lib virustotal smacks me for it
-        // we finna embed the whole DLL into our program
-        // and pull it out of our asses if we need it
-        File::create(&oodle_path)?.write_all(&OODLE_PLATFORM.bytes)?;
+fn sha256_hex(bytes: &[u8]) -> String {
+    hex::encode(Sha256::digest(bytes))
+}
+
+fn verify_hash(path: &Path) -> Result<()> {
+    let found = sha256_hex(&std::fs::read(path)?);
+    if KNOWN_HASHES.contains(&found.as_str()) {
+        Ok(())
+    } else {
+        Err(Error::HashMismatch {
+            expected: KNOWN_HASHES.join(" or "),
+            found,
+        })
+    }
+}
+
+/// Per-user location the embedded library is extracted to.
+fn cache_path() -> PathBuf {
+    dirs::cache_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("repak")
+        .join(OODLE_PLATFORM.name)
+}
+
+/// Extracts the embedded library into the user cache unless a verified copy is already there.
+fn extract_embedded() -> Result<PathBuf> {
+    let oodle_path = cache_path();
+    if verify_hash(&oodle_path).is_ok() {
+        return Ok(oodle_path);
+    }
+    let dir = oodle_path.parent().expect("cache path has a parent");
+    std::fs::create_dir_all(dir)?;
+    // fuck downloading the lib virustotal smacks me for it
+    // we finna embed the whole DLL into our program
+    // and pull it out of our asses if we need it
+    // write to a unique temp file first so concurrent processes never load a partial library
+    let tmp_path = dir.join(format!(".{}.{}", OODLE_PLATFORM.name, std::process::id()));
+    File::create(&tmp_path)?.write_all(OODLE_PLATFORM.bytes)?;
+    if let Err(err) = std::fs::rename(&tmp_path, &oodle_path) {
+        let _ = std::fs::remove_file(&tmp_path);
+        // another process may have won the race and holds the library open
+        if verify_hash(&oodle_path).is_err() {
+            return Err(err.into());
+        }
     }
     Ok(oodle_path)
 }
 
+static OODLE_PATH: OnceLock<PathBuf> = OnceLock::new();
+
+/// Loads Oodle from `path` instead of the embedded library. Must be called before the first
+/// [`oodle`] call; returns `false` if a path was already set.
+pub fn set_oodle_path(path: impl Into<PathBuf>) -> bool {
+    OODLE_PATH.set(path.into()).is_ok()
+}
+
+/// Resolves the library to load: [`set_oodle_path`], then [`OODLE_PATH_ENV`], then the embedded
+/// library extracted to the user cache directory.
+fn fetch_oodle() -> Result<PathBuf> {
+    let explicit = OODLE_PATH.get().cloned().or_else(|| {
+        std::env::var_os(OODLE_PATH_ENV)
+            .filter(|path| !path.is_empty())
+            .map(PathBuf::from)
+    });
+    match explicit {
+        Some(path) => {
+            verify_hash(&path)?;
+            Ok(path)
+        }
+        None => extract_embedded(),
+    }
+}
+
 pub struct Oodle {
     _library: libloading::Library,
     compress: oodle_lz::Compress,
     decompress: oodle_lz::Decompress,
     get_compressed_buffer_size_needed: oodle_lz::GetCompressedBufferSizeNeeded,
+    get_compress_scratch_mem_bound: oodle_lz::GetCompressScratchMemBound,
+    decoder_memory_size_needed: oodle_lz::DecoderMemorySizeNeeded,
 }
 impl Oodle {
     pub fn compress(
@@ -203,10 +296,140 @@ impl Oodle {
     }
 }
 
+/// Reusable Oodle context that owns its scratch memory so compressing or decompressing many
+/// blocks doesn't allocate per block. Not `Sync`, keep one per thread.
+pub struct OodleCompressor {
+    oodle: &'static Oodle,
+    compressor: Compressor,
+    level: CompressionLevel,
+    scratch: Vec<u8>,
+    decoder_memory: Vec<u8>,
+}
+
+impl OodleCompressor {
+    pub fn new(compressor: Compressor, level: CompressionLevel) -> Result<Self> {
+        Ok(Self {
+            oodle: oodle()?,
+            compressor,
+            level,
+            scratch: vec![],
+            decoder_memory: vec![],
+        })
+    }
+
+    /// Output size [`Self::compress_into`] requires for `raw_len` input bytes.
+    pub fn compressed_bound(&self, raw_len: usize) -> usize {
+        self.oodle
+            .get_compressed_buffer_size_needed(self.compressor, raw_len)
+    }
+
+    /// Compresses `input` into `output`, returning the compressed length. `output` must be at
+    /// least [`Self::compressed_bound`] bytes.
+    pub fn compress_into(&mut self, input: &[u8], output: &mut [u8]) -> Result<usize> {
+        let needed = self.compressed_bound(input.len());
+        if output.len() < needed {
+            return Err(Error::OutputTooSmall {
+                needed,
+                got: output.len(),
+            });
+        }
+        let scratch_size = unsafe {
+            (self.oodle.get_compress_scratch_mem_bound)(
+                self.compressor,
+                self.level,
+                input.len() as isize,
+                std::ptr::null(),
+            )
+        };
+        // negative means no bound, Oodle allocates internally in that case
+        if scratch_size > self.scratch.len() as isize {
+            self.scratch.resize(scratch_size as usize, 0);
+        }
+        // an empty Vec's pointer is dangling, not null, so Oodle would use it as scratch
+        let scratch = if self.scratch.is_empty() {
+            std::ptr::null_mut()
+        } else {
+            self.scratch.as_mut_ptr()
+        };
+        let len = unsafe {
+            (self.oodle.compress)(
+                self.compressor,
+                input.as_ptr(),
+                input.len(),
+                output.as_mut_ptr(),
+                self.level,
+                std::ptr::null(),
+                std::ptr::null(),
+                std::ptr::null(),
+                scratch,
+                self.scratch.len(),
+            )
+        };
+        // empty input compresses to nothing
+        if len < 0 || (len == 0 && !input.is_empty()) {
+            return Err(Error::CompressionFailed);
+        }
+        Ok(len as usize)
+    }
+
+    /// Compresses `input` and appends the result to `output`, reusing its spare capacity.
+    pub fn compress_append(&mut self, input: &[u8], output: &mut Vec<u8>) -> Result<()> {
+        let start = output.len();
+        output.resize(start + self.compressed_bound(input.len()), 0);
+        match self.compress_into(input, &mut output[start..]) {
+            Ok(len) => {
+                output.truncate(start + len);
+                Ok(())
+            }
+            Err(err) => {
+                output.truncate(start);
+                Err(err)
+            }
+        }
+    }
+
+    /// Decompresses `input` into `output`, which must be exactly the uncompressed size.
+    pub fn decompress_into(&mut self, input: &[u8], output: &mut [u8]) -> Result<()> {
+        let memory_size =
+            unsafe { (self.oodle.decoder_memory_size_needed)(-1, output.len() as isize) };
+        if memory_size > self.decoder_memory.len() as isize {
+            self.decoder_memory.resize(memory_size as usize, 0);
+        }
+        let decoder_memory = if self.decoder_memory.is_empty() {
+            std::ptr::null_mut()
+        } else {
+            self.decoder_memory.as_mut_ptr()
+        };
+        let len = unsafe {
+            (self.oodle.decompress)(
+                input.as_ptr(),
+                input.len(),
+                output.as_mut_ptr(),
+                output.len(),
+                1,
+                1,
+                0,
+                0,
+                0,
+                0,
+                0,
+                decoder_memory,
+                self.decoder_memory.len(),
+                3,
+            )
+        };
+        if len as usize != output.len() {
+            return Err(Error::DecompressionFailed);
+        }
+        Ok(())
+    }
+}
+
 static OODLE: OnceLock<Option<Oodle>> = OnceLock::new();
 
 fn load_oodle() -> Result<Oodle> {
     let path = fetch_oodle()?;
+    // the hash is checked before loading, the library runs code on load
     unsafe {
         let library = libloading::Library::new(path)?;
         Ok(Oodle {
@@ -214,6 +437,8 @@ fn load_oodle() -> Result<Oodle> {
             decompress: *library.get(b"OodleLZ_Decompress")?,
             get_compressed_buffer_size_needed: *library
                 .get(b"OodleLZ_GetCompressedBufferSizeNeeded")?,
+            get_compress_scratch_mem_bound: *library.get(b"OodleLZ_GetCompressScratchMemBound")?,
+            decoder_memory_size_needed: *library.get(b"OodleLZDecoder_MemorySizeNeeded")?,
             _library: library,
         })
     }
@@ -263,4 +488,39 @@ mod test {
 
         assert_eq!(data[..], uncomp[..]);
     }
+
+    #[test]
+    fn test_oodle_compressor_reuse() {
+        let mut compressor =
+            OodleCompressor::new(Compressor::Mermaid, CompressionLevel::Normal).unwrap();
+        let mut compressed = vec![];
+        let mut sizes = vec![];
+        let blocks = (0..4u8)
+            .map(|i| (0..0x10000).map(|j| (j / 3) as u8 ^ i).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        for block in &blocks {
+            let start = compressed.len();
+            compressor.compress_append(block, &mut compressed).unwrap();
+            sizes.push(compressed.len() - start);
+        }
+
+        let mut input = &compressed[..];
+        let mut output = vec![0; 0x10000];
+        for (block, size) in blocks.iter().zip(sizes) {
+            compressor
+                .decompress_into(&input[..size], &mut output)
+                .unwrap();
+            assert_eq!(block, &output);
+            input = &input[size..];
+        }
+        assert!(matches!(
+            compressor.compress_into(&blocks[0], &mut [0; 16]),
+            Err(Error::OutputTooSmall { .. })
+        ));
+    }
+
+    #[test]
+    fn test_embedded_hash_known() {
+        assert!(KNOWN_HASHES.contains(&sha256_hex(OODLE_PLATFORM.bytes).as_str()));
+    }
 }
@@ -1,16 +1,20 @@
-use crate::utoc_utils::read_utoc;
+use crate::install_mod::install_mod_logic::iotoc::to_legacy_package;
+use crate::utoc_utils::{extract_toc_files, read_utoc};
 use eframe::egui;
 use eframe::egui::OutputCommand::CopyText;
-use eframe::egui::RichText;
+use eframe::egui::{Color32, RichText};
 use egui_extras::{Column, TableBuilder};
+use repak::iostore::TocFile;
 use repak::PakReader;
+use repak_manager::utoc_utils::FileEntry;
 use rfd::FileDialog;
 use sha2::Digest;
 use std::fs::File;
 use std::io::{BufReader, Write};
 use std::path::{Path, PathBuf};
-use std::str::FromStr;
-use tracing::{info, instrument};
+use std::thread;
+use std::time::Duration;
+use tracing::{error, info, instrument};
 
 pub struct FileTable {
     striped: bool,
@@ -22,21 +26,16 @@ pub struct FileTable {
     selection: usize,
     showing_utoc: bool,
     search_query: String,
+    /// Game `Paks` directory, needed to convert IoStore packages to legacy assets.
+    game_paks_dir: Option<PathBuf>,
+    /// Why the container's packages could not be listed.
+    error: Option<String>,
+    /// Package extraction running in the background, see [`PackageAction`].
+    package_worker: Option<thread::JoinHandle<Result<String, String>>>,
+    /// Outcome of the last package extraction.
+    package_status: Option<Result<String, String>>,
 }
 
-#[derive(Clone, Debug)]
-pub(crate) struct FileEntry {
-    /// This is the path of the internal file mounting path
-    pub(crate) file_path: String,
-    pub(crate) pak_path: PathBuf,
-    // entry: Entry,
-    pub(crate) pak_reader: PakReader,
-    pub(crate) compressed: String,
-    pub(crate) uncompressed: String,
-    pub(crate) offset: String,
-    pub(crate) bulkdata: Option<usize>,
-    pub(crate) package_data: Option<usize>,
-}
 impl Default for FileTable {
     fn default() -> Self {
         Self {
@@ -47,20 +46,29 @@ impl Default for FileTable {
             selection: usize::MAX,
             showing_utoc: false,
             search_query: String::new(),
+            game_paks_dir: None,
+            error: None,
+            package_worker: None,
+            package_status: None,
         }
     }
 }
 
 impl FileTable {
-    pub fn new(pak_reader: &PakReader, pak_path: &Path) -> Self {
+    pub fn new(pak_reader: &PakReader, pak_path: &Path, game_paks_dir: Option<PathBuf>) -> Self {
         // If the utoc exists, use the utoc
 
         let mut utoc_path = pak_path.to_path_buf();
         utoc_path.set_extension("utoc");
 
+        let mut error = None;
         let file_entries = {
             if utoc_path.exists() {
-                read_utoc(&utoc_path, pak_reader, pak_path)
+                read_utoc(&utoc_path, pak_reader, pak_path).unwrap_or_else(|e| {
+                    error!(utoc = %utoc_path.display(), error = %e, "Failed to list IoStore packages");
+                    error = Some(e);
+                    Vec::new()
+                })
             } else {
                 let entries = pak_reader.files().to_vec();
 
@@ -76,8 +84,10 @@ impl FileTable {
                             compressed: entry_pak.compressed.to_string(),
                             uncompressed: entry_pak.uncompressed.to_string(),
                             offset: format!("{:#x}", entry_pak.offset),
+                            compression: String::new(),
                             bulkdata: None,
                             package_data: None,
+                            toc_files: Vec::new(),
                         }
                     })
                     .collect::<Vec<_>>()
@@ -87,11 +97,18 @@ impl FileTable {
         Self {
             file_contents: file_entries,
             showing_utoc: utoc_path.exists(),
+            game_paks_dir,
+            error,
             ..Default::default()
         }
     }
 
     pub fn table_ui(&mut self, ui: &mut egui::Ui) {
+        if let Some(error) = &self.error {
+            ui.colored_label(Color32::RED, error);
+            ui.add_space(8.0);
+        }
+        self.package_status_ui(ui);
         ui.horizontal(|ui| {
             ui.label("Search:");
             ui.add(
@@ -123,6 +140,8 @@ impl FileTable {
             .map(|indices| indices.len())
             .unwrap_or(self.file_contents.len());
 
+        let package_busy = self.package_worker.is_some();
+        let mut package_action = None;
         let available_height = ui.available_height();
         let mut table = TableBuilder::new(ui)
             .striped(self.striped)
@@ -137,7 +156,15 @@ impl FileTable {
             ) // PATH
             .column(Column::remainder()) // Offset
             .column(Column::remainder()) // Compressed Size
-            .column(Column::remainder()) // Uncompressed Size
+            .column(Column::remainder()); // Uncompressed Size
+        if self.showing_utoc {
+            table = table
+                .column(Column::remainder()) // Compression
+                .column(Column::remainder()) // Ratio
+                .column(Column::remainder()) // Bulkdata Chunks
+                .column(Column::remainder()); // PackageData Chunks
+        }
+        let mut table = table
             .min_scrolled_height(0.0)
             .max_scroll_height(available_height);
 
@@ -151,24 +178,30 @@ impl FileTable {
                     ui.label("Path");
                 });
 
+                header.col(|ui| {
+                    ui.label("Offset");
+                });
+
+                header.col(|ui| {
+                    ui.label("Compressed");
+                });
+                header.col(|ui| {
+                    ui.label("Uncompressed");
+                });
+
                 if self.showing_utoc {
                     header.col(|ui| {
-                        ui.label("Bulkdata Chunks");
+                        ui.label("Compression");
                     });
-
                     header.col(|ui| {
-                        ui.label("PackageData Chunks");
+                        ui.label("Ratio");
                     });
-                } else {
                     header.col(|ui| {
-                        ui.label("Offset");
+                        ui.label("Bulkdata Chunks");
                     });
 
                     header.col(|ui| {
-                        ui.label("Compressed");
-                    });
-                    header.col(|ui| {
-                        ui.label("Uncompressed");
+                        ui.label("PackageData Chunks");
                     });
                 }
                 // header.col(|ui| {
@@ -192,30 +225,83 @@ impl FileTable {
                         };
                     })
                     .1
-                    .context_menu(|ui| show_ctx_menu(ui, entry, self.showing_utoc));
+                    .context_menu(|ui| {
+                        if self.showing_utoc {
+                            package_action = show_package_ctx_menu(
+                                ui,
+                                entry,
+                                self.game_paks_dir.as_deref(),
+                                package_busy,
+                            );
+                        } else {
+                            show_ctx_menu(ui, entry);
+                        }
+                    });
+
+                    row.col(|ui| {
+                        ui.label(&entry.offset);
+                    });
+                    row.col(|ui| {
+                        ui.label(&entry.compressed);
+                    });
+                    row.col(|ui| {
+                        ui.label(&entry.uncompressed);
+                    });
 
                     if self.showing_utoc {
                         row.col(|ui| {
-                            ui.label(&entry.bulkdata.unwrap_or(0).to_string());
+                            ui.label(&entry.compression);
                         });
                         row.col(|ui| {
-                            ui.label(&entry.package_data.unwrap_or(0).to_string());
-                        });
-                    } else {
-                        row.col(|ui| {
-                            ui.label(&entry.offset);
+                            ui.label(compression_ratio(entry));
                         });
                         row.col(|ui| {
-                            ui.label(&entry.compressed);
+                            ui.label(&entry.bulkdata.unwrap_or(0).to_string());
                         });
                         row.col(|ui| {
-                            ui.label(&entry.uncompressed);
+                            ui.label(&entry.package_data.unwrap_or(0).to_string());
                         });
                     }
 
                     self.toggle_row_selection(row_idx, &row.response());
                 });
             });
+
+        if let Some(action) = package_action {
+            self.package_status = None;
+            self.package_worker = Some(thread::spawn(move || action.run()));
+        }
+    }
+
+    /// Progress and outcome of the package extraction started from the context menu.
+    fn package_status_ui(&mut self, ui: &mut egui::Ui) {
+        if self
+            .package_worker
+            .as_ref()
+            .is_some_and(|worker| worker.is_finished())
+        {
+            if let Some(worker) = self.package_worker.take() {
+                self.package_status = Some(
+                    worker
+                        .join()
+                        .unwrap_or_else(|_| Err("Package extraction panicked".to_string())),
+                );
+            }
+        }
+        if self.package_worker.is_some() {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label("Extracting package...");
+            });
+            ui.ctx().request_repaint_after(Duration::from_millis(100));
+            ui.add_space(8.0);
+        } else if let Some(status) = &self.package_status {
+            match status {
+                Ok(message) => ui.label(message),
+                Err(e) => ui.colored_label(Color32::RED, e),
+            };
+            ui.add_space(8.0);
+        }
     }
     fn toggle_row_selection(&mut self, row_index: usize, row_response: &egui::Response) {
         if row_response.clicked() {
@@ -223,52 +309,22 @@ impl FileTable {
         }
     }
 }
-#[instrument(skip(ui, entry), fields(file_path = %entry.file_path, is_utoc))]
-fn show_ctx_menu(ui: &mut egui::Ui, entry: &FileEntry, is_utoc: bool) {
-    if ui.button("Extract").clicked() {
-        if is_utoc {
-            let name = PathBuf::from(&entry.file_path)
-                .file_name()
-                .unwrap()
-                .to_string_lossy()
-                .to_string();
-            let dialog = FileDialog::new().set_file_name(name).save_file();
-
-            if let Some(path) = dialog {
-                let root = path.file_stem().unwrap().to_str().unwrap();
-                let result_path = path.parent().unwrap().join(root);
-                info!(?result_path, "Creating extraction directory");
-
-                let _ = std::fs::create_dir(&result_path)
-                    .expect("Failed to create extraction directory");
-
-                let action: retoc::ActionUnpack = retoc::ActionUnpack {
-                    utoc: PathBuf::from(&entry.file_path),
-                    output: result_path,
-                    verbose: true,
-                };
-
-                let mut config = retoc::Config {
-                    container_header_version_override: None,
-                    ..Default::default()
-                };
-
-                let aes_toc = retoc::AesKey::from_str(
-                    "0C263D8C22DCB085894899C3A3796383E9BF9DE0CBFB08C9BF2DEF2E84F29D74",
-                )
-                .unwrap();
-
-                config
-                    .aes_keys
-                    .insert(retoc::FGuid::default(), aes_toc.clone());
-                let config = std::sync::Arc::new(config);
-
-                retoc::action_unpack(action, config).expect("Failed to extract");
-            }
-            ui.label("This is a utoc file, you can't extract files from it.");
-            ui.close_menu();
+/// Stored size as a share of the uncompressed size, for entries with known sizes.
+fn compression_ratio(entry: &FileEntry) -> String {
+    match (
+        entry.compressed.parse::<u64>(),
+        entry.uncompressed.parse::<u64>(),
+    ) {
+        (Ok(compressed), Ok(uncompressed)) if uncompressed > 0 => {
+            format!("{:.1}%", compressed as f64 / uncompressed as f64 * 100.0)
         }
+        _ => "Unavailable".to_string(),
+    }
+}
 
+#[instrument(skip(ui, entry), fields(file_path = %entry.file_path))]
+fn show_ctx_menu(ui: &mut egui::Ui, entry: &FileEntry) {
+    if ui.button("Extract").clicked() {
         let name = PathBuf::from(&entry.file_path)
             .file_name()
             .unwrap()
@@ -294,11 +350,9 @@ fn show_ctx_menu(ui: &mut egui::Ui, entry: &FileEntry, is_utoc: bool) {
         ui.output_mut(|o| o.commands = vec![CopyText(entry.file_path.clone())]);
         ui.close_menu();
     }
-    if !is_utoc {
-        if ui.button("Copy Offset").clicked() {
-            ui.output_mut(|o| o.commands = vec![CopyText(entry.offset.clone().to_string())]);
-            ui.close_menu();
-        }
+    if ui.button("Copy Offset").clicked() {
+        ui.output_mut(|o| o.commands = vec![CopyText(entry.offset.clone().to_string())]);
+        ui.close_menu();
     }
 
     let mut hasher = sha2::Sha256::new();
@@ -322,3 +376,154 @@ fn show_ctx_menu(ui: &mut egui::Ui, entry: &FileEntry, is_utoc: bool) {
         ui.output_mut(|o| o.commands = vec![CopyText(hex::encode(hasher.finalize()))]);
     }
 }
+
+/// Extraction of an IoStore package picked from its context menu. Converting to legacy assets
+/// reads the game containers, so it runs off the UI thread.
+enum PackageAction {
+    Legacy {
+        pak_path: PathBuf,
+        package: String,
+        package_file: String,
+        output_dir: PathBuf,
+        game_paks_dir: PathBuf,
+    },
+    Raw {
+        utoc_path: PathBuf,
+        package: String,
+        toc_files: Vec<TocFile>,
+        output_dir: PathBuf,
+    },
+}
+
+impl PackageAction {
+    /// Runs the extraction, returning the message shown above the file table.
+    fn run(self) -> Result<String, String> {
+        match self {
+            PackageAction::Legacy {
+                pak_path,
+                package,
+                package_file,
+                output_dir,
+                game_paks_dir,
+            } => {
+                #[cfg(all(windows, not(debug_assertions)))]
+                {
+                    crate::ensure_console();
+                    crate::redirect_stdio();
+                }
+                let mods_dir = pak_path.parent().unwrap_or(Path::new(".")).to_path_buf();
+                let result = to_legacy_package(
+                    pak_path,
+                    &package_file,
+                    output_dir.clone(),
+                    mods_dir,
+                    game_paks_dir,
+                );
+                #[cfg(all(windows, not(debug_assertions)))]
+                crate::free_console();
+                match result {
+                    Ok(()) => {
+                        info!(%package, "Extracted package to legacy assets");
+                        Ok(format!("Extracted {package} to {}", output_dir.display()))
+                    }
+                    Err(e) => {
+                        error!(%package, error = %e, "Failed to extract package");
+                        Err(format!("Failed to extract {package}: {e}"))
+                    }
+                }
+            }
+            PackageAction::Raw {
+                utoc_path,
+                package,
+                toc_files,
+                output_dir,
+            } => match extract_toc_files(&utoc_path, &toc_files, &output_dir) {
+                Ok(files) => {
+                    info!(%package, count = files.len(), "Extracted raw chunks");
+                    Ok(format!(
+                        "Extracted {} raw chunks of {package} to {}",
+                        files.len(),
+                        output_dir.display()
+                    ))
+                }
+                Err(e) => {
+                    error!(%package, error = %e, "Failed to extract raw chunks");
+                    Err(format!("Failed to extract raw chunks of {package}: {e}"))
+                }
+            },
+        }
+    }
+}
+
+/// Context menu of an IoStore package: extraction to legacy assets or raw chunk data. Returns
+/// the extraction picked, for the file table to run in the background. `busy` disables
+/// extraction while another one runs.
+fn show_package_ctx_menu(
+    ui: &mut egui::Ui,
+    entry: &FileEntry,
+    game_paks_dir: Option<&Path>,
+    busy: bool,
+) -> Option<PackageAction> {
+    let mut action = None;
+    let utoc_path = entry.pak_path.with_extension("utoc");
+    let package_file = entry.toc_files.iter().find(|file| {
+        Path::new(&file.path).extension().is_some_and(|ext| {
+            ext.eq_ignore_ascii_case("uasset") || ext.eq_ignore_ascii_case("umap")
+        })
+    });
+
+    ui.add_enabled_ui(!busy, |ui| {
+        ui.menu_button("Extract this package", |ui| {
+            let legacy = ui
+                .add_enabled(
+                    game_paks_dir.is_some() && package_file.is_some(),
+                    egui::Button::new("As legacy .uasset/.uexp"),
+                )
+                .on_disabled_hover_text(
+                    "Needs the game Paks folder and a container with a directory index",
+                );
+            if legacy.clicked() {
+                if let (Some(output_dir), Some(game_paks_dir), Some(package_file)) =
+                    (FileDialog::new().pick_folder(), game_paks_dir, package_file)
+                {
+                    action = Some(PackageAction::Legacy {
+                        pak_path: entry.pak_path.clone(),
+                        package: entry.file_path.clone(),
+                        package_file: package_file.path.clone(),
+                        output_dir,
+                        game_paks_dir: game_paks_dir.to_path_buf(),
+                    });
+                }
+                ui.close_menu();
+            }
+
+            let raw = ui
+                .add_enabled(
+                    !entry.toc_files.is_empty(),
+                    egui::Button::new("Raw chunk data"),
+                )
+                .on_disabled_hover_text("The container has no directory index");
+            if raw.clicked() {
+                if let Some(output_dir) = FileDialog::new().pick_folder() {
+                    action = Some(PackageAction::Raw {
+                        utoc_path: utoc_path.clone(),
+                        package: entry.file_path.clone(),
+                        toc_files: entry.toc_files.clone(),
+                        output_dir,
+                    });
+                }
+                ui.close_menu();
+            }
+        });
+    });
+
+    if ui.button("Copy Path").clicked() {
+        ui.output_mut(|o| o.commands = vec![CopyText(entry.file_path.clone())]);
+        ui.close_menu();
+    }
+    if ui.button("Copy Offset").clicked() {
+        ui.output_mut(|o| o.commands = vec![CopyText(entry.offset.clone())]);
+        ui.close_menu();
+    }
+    action
+}
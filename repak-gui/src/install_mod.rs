@@ -1,111 +1,28 @@
-pub mod install_mod_logic;
-
-use crate::install_mod::install_mod_logic::archives::*;
 use crate::main_ui::setup_custom_style;
-use crate::utils::{collect_files, get_current_pak_characteristics};
-use crate::utoc_utils::read_utoc;
 use crate::ICON;
 use eframe::egui;
 use eframe::egui::{Align, Checkbox, ComboBox, Context, Label, TextEdit};
 use egui_extras::{Column, TableBuilder};
 use egui_flex::{item, Flex, FlexAlign};
 use install_mod_logic::install_mods_in_viewport;
-use repak::utils::AesKey;
-use repak::Compression::Oodle;
-use repak::{Compression, PakReader};
+use repak::Compression;
+pub use repak_manager::install_mod::{
+    install_mod_logic, install_progress_units, map_paths_to_mods, map_to_mods_internal,
+    retoc_aes_key, InstallableMod,
+};
 use rfd::MessageButtons;
-use std::fs::File;
-use std::io::BufReader;
 use std::path::PathBuf;
-use std::str::FromStr;
 use std::sync::atomic::Ordering::SeqCst;
 use std::sync::atomic::{AtomicBool, AtomicI32};
-use std::sync::{Arc, LazyLock};
+use std::sync::Arc;
 use std::thread;
-use tempfile::{tempdir, TempDir};
-use tracing::{debug, error, info, instrument, warn};
-use walkdir::WalkDir;
+use tracing::{info, instrument, warn};
 
 fn install_needs_to_legacy_console(mods: &[InstallableMod]) -> bool {
     mods.iter()
         .any(|mods| mods.enabled && mods.iostore && mods.repak)
 }
 
-#[derive(Debug, Clone)]
-pub struct InstallableMod {
-    pub mod_name: String,
-    pub mod_type: String,
-    pub repak: bool,
-    pub fix_mesh: bool,
-    pub kawaii_porter: bool,
-    pub default_hidden_material_patch: bool,
-    pub is_dir: bool,
-    pub editing: bool,
-    pub path_hash_seed: String,
-    pub mount_point: String,
-    pub compression: Compression,
-    pub reader: Option<PakReader>,
-    pub mod_path: PathBuf,
-    pub total_files: usize,
-    pub iostore: bool,
-    // the only reason we keep this is to filter out the archives during collection
-    pub is_archived: bool,
-    pub enabled: bool,
-    pub obfuscated: bool,
-    // Keeps extracted archive files alive while the install dialog/worker still references them.
-    #[allow(dead_code)]
-    pub extracted_archive_dir: Option<Arc<TempDir>>,
-    // pub audio_mod: bool,
-}
-
-impl Default for InstallableMod {
-    fn default() -> Self {
-        InstallableMod {
-            obfuscated: false,
-            mod_name: "".to_string(),
-            mod_type: "".to_string(),
-            repak: false,
-            fix_mesh: false,
-            kawaii_porter: true,
-            default_hidden_material_patch: true,
-            is_dir: false,
-            editing: false,
-            path_hash_seed: "".to_string(),
-            mount_point: "".to_string(),
-            compression: Default::default(),
-            reader: None,
-            mod_path: Default::default(),
-            total_files: 0,
-            iostore: false,
-            is_archived: false,
-            enabled: true,
-            extracted_archive_dir: None,
-        }
-    }
-}
-
-fn processable_asset_count<'a>(paths: impl IntoIterator<Item = &'a str>) -> usize {
-    paths
-        .into_iter()
-        .filter(|path| path.to_ascii_lowercase().ends_with(".uasset"))
-        .count()
-        .max(1)
-}
-
-fn processable_iostore_asset_count<'a>(paths: impl IntoIterator<Item = &'a str>) -> usize {
-    paths.into_iter().count().max(1)
-}
-
-pub(crate) fn install_progress_units(mods: &InstallableMod) -> usize {
-    let asset_units = mods.total_files.max(1);
-
-    if mods.iostore && mods.repak {
-        asset_units.saturating_mul(2)
-    } else {
-        asset_units
-    }
-}
-
 #[derive(Debug)]
 pub struct ModInstallRequest {
     pub(crate) mods: Vec<InstallableMod>,
@@ -199,6 +116,7 @@ impl ModInstallRequest {
                     ui.label("Mods to install");
                     ui.set_min_width(ui.available_width());
                     ui.set_min_height(ui.available_height());
+                    self.variant_chooser_ui(ui);
                     // ScrollArea::vertical()
                     //     .auto_shrink([false, false])
                     //     .show(ui, |ui| {
@@ -322,6 +240,63 @@ impl ModInstallRequest {
         );
     }
 
+    /// One combo box per group of archive options, ticking the mods of the picked option and
+    /// unticking those of the others.
+    fn variant_chooser_ui(&mut self, ui: &mut egui::Ui) {
+        let mut groups: Vec<(String, Vec<String>)> = Vec::new();
+        for variant in self.mods.iter().filter_map(|mods| mods.variant.as_ref()) {
+            match groups.iter_mut().find(|(group, _)| *group == variant.group) {
+                Some((_, names)) if names.contains(&variant.name) => {}
+                Some((_, names)) => names.push(variant.name.clone()),
+                None => groups.push((variant.group.clone(), vec![variant.name.clone()])),
+            }
+        }
+
+        for (group, names) in groups {
+            let ticked = names
+                .iter()
+                .filter(|name| {
+                    self.mods.iter().any(|mods| {
+                        mods.enabled
+                            && mods.variant.as_ref().is_some_and(|variant| {
+                                variant.group == group && variant.name == **name
+                            })
+                    })
+                })
+                .collect::<Vec<_>>();
+            let selected_text = match ticked.as_slice() {
+                [] => "None".to_string(),
+                [name] => name.to_string(),
+                _ => "Several".to_string(),
+            };
+
+            let mut picked = None;
+            ui.horizontal(|ui| {
+                ui.label(format!("Options in {group}"));
+                ComboBox::from_id_salt(("variant_group", &group))
+                    .selected_text(selected_text)
+                    .show_ui(ui, |ui| {
+                        for name in &names {
+                            let selected = ticked.len() == 1 && ticked[0] == name;
+                            if ui.selectable_label(selected, name).clicked() {
+                                picked = Some(name.clone());
+                            }
+                        }
+                    });
+            });
+            if let Some(picked) = picked {
+                info!(group = %group, variant = %picked, "Picked archive option");
+                for mods in &mut self.mods {
+                    if let Some(variant) = &mods.variant {
+                        if variant.group == group {
+                            mods.enabled = variant.name == picked;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
     fn table_ui(&mut self, ui: &mut egui::Ui) {
         let available_height = ui.available_height();
         ui.separator();
@@ -416,7 +391,10 @@ impl ModInstallRequest {
                             } else {
                                 "Pakfile"
                             };
-                            ui.label(label);
+                            match &mods.variant {
+                                Some(variant) => ui.label(format!("{label} ({})", variant.name)),
+                                None => ui.label(label),
+                            };
                         });
                         row.col(|ui| {
                             ui.collapsing("Options", |ui| {
@@ -500,273 +478,6 @@ impl ModInstallRequest {
     }
 }
 
-pub static AES_KEY: LazyLock<AesKey> = LazyLock::new(|| {
-    AesKey::from_str("0C263D8C22DCB085894899C3A3796383E9BF9DE0CBFB08C9BF2DEF2E84F29D74")
-        .expect("Unable to initialise AES_KEY")
-});
-
-#[instrument(fields(path))]
-fn archive_payload_root(root: &std::path::Path) -> PathBuf {
-    let Ok(entries) = std::fs::read_dir(root) else {
-        return root.to_path_buf();
-    };
-    let mut dirs = Vec::new();
-    let mut files = 0usize;
-    for entry in entries.flatten() {
-        let path = entry.path();
-        if path.is_dir() {
-            dirs.push(path);
-        } else {
-            files += 1;
-        }
-    }
-    if files == 0 && dirs.len() == 1 {
-        dirs.pop().unwrap()
-    } else {
-        root.to_path_buf()
-    }
-}
-
-fn find_mods_from_archive(
-    path: &std::path::Path,
-    archive_dir: Arc<TempDir>,
-) -> Vec<InstallableMod> {
-    let mut new_mods = Vec::<InstallableMod>::new();
-    debug!("Scanning extracted archive directory");
-    for entry in WalkDir::new(path) {
-        let entry = entry.expect("Failed to read directory entry");
-        let path = entry.path();
-        if path.is_file() {
-            let builder = repak::PakBuilder::new()
-                .key(AES_KEY.clone().0)
-                .reader(&mut BufReader::new(File::open(path).unwrap()));
-
-            if let Ok(builder) = builder {
-                let mut len = 1;
-                let mut modtype = String::from("Unknown");
-                let mut iostore = false;
-
-                let pak_path = path.with_extension("pak");
-                let utoc_path = path.with_extension("utoc");
-                let ucas_path = path.with_extension("ucas");
-
-                if pak_path.exists() && utoc_path.exists() && ucas_path.exists() {
-                    // this is a mod of type s2, create a new Installable mod from its characteristics
-                    let utoc_path = path.with_extension("utoc");
-
-                    let files = read_utoc(&utoc_path, &builder, &path);
-                    let files = files
-                        .iter()
-                        .map(|x| x.file_path.clone())
-                        .collect::<Vec<_>>();
-                    len = processable_iostore_asset_count(files.iter().map(String::as_str));
-                    modtype = get_current_pak_characteristics(files);
-                    iostore = true;
-                }
-                // IF ONLY PAK IS FOUND WE NEED TO EXTRACT AND INSTALL THE PAK
-                else if pak_path.exists() {
-                    let files = builder.files();
-                    len = processable_asset_count(files.iter().map(String::as_str));
-                    modtype = get_current_pak_characteristics(files);
-                }
-
-                let installable_mod = InstallableMod {
-                    mod_name: path.file_stem().unwrap().to_str().unwrap().to_string(),
-                    mod_type: modtype.to_string(),
-                    repak: !iostore,
-                    fix_mesh: false,
-                    is_dir: false,
-                    reader: Some(builder),
-                    mod_path: path.to_path_buf(),
-                    mount_point: "../../../".to_string(),
-                    path_hash_seed: "00000000".to_string(),
-                    total_files: len,
-                    iostore,
-                    is_archived: false,
-                    editing: false,
-                    compression: Oodle,
-                    extracted_archive_dir: Some(archive_dir.clone()),
-                    ..Default::default()
-                };
-
-                debug!(mod_name = %installable_mod.mod_name, mod_type = %installable_mod.mod_type, "Discovered archived mod");
-                new_mods.push(installable_mod);
-            }
-        }
-    }
-
-    new_mods
-}
-
-fn extracted_archive_raw_directory_mod(
-    archive_path: &std::path::Path,
-    root: PathBuf,
-    archive_dir: Arc<TempDir>,
-) -> Result<Option<InstallableMod>, repak::Error> {
-    let mut files = vec![];
-    collect_files(&mut files, &root)?;
-    if !files.iter().any(|path| {
-        path.extension()
-            .and_then(|ext| ext.to_str())
-            .is_some_and(|ext| ext.eq_ignore_ascii_case("uasset"))
-    }) {
-        return Ok(None);
-    }
-
-    let file_names = files
-        .iter()
-        .map(|s| s.to_str().unwrap().to_string())
-        .collect::<Vec<_>>();
-    let len = processable_asset_count(file_names.iter().map(String::as_str));
-    let modtype = get_current_pak_characteristics(file_names);
-
-    Ok(Some(InstallableMod {
-        mod_name: archive_path
-            .file_stem()
-            .unwrap()
-            .to_str()
-            .unwrap()
-            .to_string(),
-        mod_type: modtype,
-        is_dir: true,
-        mod_path: root,
-        mount_point: "../../../".to_string(),
-        path_hash_seed: "00000000".to_string(),
-        total_files: len,
-        extracted_archive_dir: Some(archive_dir),
-        ..Default::default()
-    }))
-}
-
-#[instrument(skip(paths), fields(path_count = paths.len()))]
-pub fn map_to_mods_internal(paths: &[PathBuf]) -> Vec<InstallableMod> {
-    let mut extensible_vec: Vec<InstallableMod> = Vec::new();
-    info!("Mapping paths into installable mods");
-    let mut installable_mods = paths
-        .iter()
-        .map(|path| {
-            let is_dir = path.clone().is_dir();
-            let extension = path
-                .extension()
-                .and_then(|ext| ext.to_str())
-                .map(|ext| ext.to_ascii_lowercase())
-                .unwrap_or_default();
-            let is_archive = matches!(extension.as_str(), "7z" | "zip" | "rar");
-
-            let mut modtype = "Unknown".to_string();
-            let mut pak = None;
-            let mut len = 1;
-            let mut iostore = false;
-            let mut mod_path = path.clone();
-
-            if !is_dir && !is_archive {
-                debug!(?path, "Inspecting pak file");
-                let pak_path = path.with_extension("pak");
-                let utoc_path = path.with_extension("utoc");
-                let ucas_path = path.with_extension("ucas");
-                let reader_path = if extension == "utoc" || extension == "ucas" {
-                    pak_path.clone()
-                } else {
-                    path.clone()
-                };
-                mod_path = reader_path.clone();
-
-                let builder = repak::PakBuilder::new()
-                    .key(AES_KEY.clone().0)
-                    .reader(&mut BufReader::new(File::open(reader_path).unwrap()));
-                match builder {
-                    Ok(builder) => {
-                        pak = Some(builder.clone());
-                        if pak_path.exists() && utoc_path.exists() && ucas_path.exists() {
-                            let files = read_utoc(&utoc_path, &builder, &pak_path);
-                            let files = files
-                                .iter()
-                                .map(|x| x.file_path.clone())
-                                .collect::<Vec<_>>();
-                            len = processable_iostore_asset_count(files.iter().map(String::as_str));
-                            modtype = get_current_pak_characteristics(files);
-                            iostore = true;
-                        } else {
-                            let files = builder.files();
-                            len = processable_asset_count(files.iter().map(String::as_str));
-                            modtype = get_current_pak_characteristics(files);
-                        }
-                    }
-                    Err(e) => {
-                        error!(?path, error = %e, "Error reading pak file");
-                        return Err(e);
-                    }
-                }
-            }
-
-            if is_dir {
-                debug!(?path, "Inspecting directory mod");
-                let mut files = vec![];
-                collect_files(&mut files, path)?;
-                let files = files
-                    .iter()
-                    .map(|s| s.to_str().unwrap().to_string())
-                    .collect::<Vec<_>>();
-                len = processable_asset_count(files.iter().map(String::as_str));
-                modtype = get_current_pak_characteristics(files);
-            }
-
-            if is_archive {
-                info!(?path, "Extracting archive for inspection");
-                modtype = "Season 2 Archives".to_string();
-                let archive_dir = Arc::new(tempdir().unwrap());
-
-                if extension == "zip" {
-                    extract_zip(path, archive_dir.path()).expect("Unable to install mod")
-                } else if extension == "rar" {
-                    extract_rar(path, archive_dir.path()).expect("Unable to install mod")
-                } else if extension == "7z" {
-                    extract_7z(path, archive_dir.path()).expect("Unable to install mod")
-                }
-
-                let root = archive_payload_root(archive_dir.path());
-                if let Some(mods) =
-                    extracted_archive_raw_directory_mod(path, root.clone(), archive_dir.clone())?
-                {
-                    extensible_vec.push(mods);
-                }
-
-                // Now find pak files / s2 archives and turn them into installable mods
-                let mut new_mods = find_mods_from_archive(&root, archive_dir);
-                extensible_vec.append(&mut new_mods);
-            }
-
-            Ok(InstallableMod {
-                mod_name: path.file_stem().unwrap().to_str().unwrap().to_string(),
-                mod_type: modtype,
-                repak: !is_dir && pak.is_some() && !iostore,
-                fix_mesh: false,
-                is_dir,
-                reader: pak,
-                mod_path,
-                mount_point: "../../../".to_string(),
-                path_hash_seed: "00000000".to_string(),
-                total_files: len,
-                iostore,
-                is_archived: is_archive,
-                ..Default::default()
-            })
-        })
-        .filter_map(|x: Result<InstallableMod, repak::Error>| x.ok())
-        .filter(|x| !x.is_archived)
-        .collect::<Vec<_>>();
-
-    installable_mods.extend(extensible_vec);
-
-    // debug!("Mapped installable mods: {:?}", installable_mods);
-    installable_mods
-}
-
-pub fn map_paths_to_mods(paths: &[PathBuf]) -> Vec<InstallableMod> {
-    let installable_mods = map_to_mods_internal(paths);
-    installable_mods
-}
-
 pub fn map_dropped_file_to_mods(dropped_files: &[egui::DroppedFile]) -> Vec<InstallableMod> {
     let paths = dropped_files
         .iter()
@@ -8,33 +8,31 @@ mod install_terminal;
 mod launch_game;
 mod main_ui;
 mod updater;
-mod utils;
 
 pub mod ios_widget;
-mod utoc_utils;
 mod welcome;
-use crate::install_mod::install_mod_logic::install_mods_in_viewport;
-use crate::install_mod::install_mod_logic::iotoc::{
-    convert_directory_to_iostore, to_legacy_uasset_fast_batch,
-};
-use crate::install_mod::map_to_mods_internal;
+use crate::install_mod::install_mod_logic::iotoc::convert_directory_to_iostore;
+use crate::install_mod::{map_to_mods_internal, retoc_aes_key};
 use crate::main_ui::{setup_custom_style, RepakModManager};
-use crate::utils::SkinEntry;
 use eframe::egui::{self, IconData};
+use repak_manager::{
+    archive, catalog, classification, duplicates, installed, keys, load_order, mod_conflicts,
+    mod_profiles, mod_toggle, skin_db, state, utils, utoc_utils,
+};
 use retoc::{action_unpack, ActionUnpack, FGuid};
 use std::cell::LazyCell;
 use std::collections::HashMap;
 use std::env::args;
-use std::fs::{self, create_dir, File};
+use std::fs::{create_dir, File};
 use std::io::BufWriter;
 use std::io::Write;
 use std::path::PathBuf;
 use std::process::exit;
 use std::str::FromStr;
-use std::sync::atomic::{AtomicBool, AtomicI32};
+use std::sync::atomic::AtomicI32;
 use std::sync::Arc;
 use std::thread;
-use tracing::{debug, info, instrument};
+use tracing::{info, instrument};
 use tracing_subscriber::filter::{LevelFilter, Targets};
 use tracing_subscriber::fmt;
 use tracing_subscriber::prelude::*;
@@ -223,123 +221,6 @@ fn custom_panic(_info: &PanicHookInfo) -> ! {
     std::process::exit(1);
 }
 
-#[instrument(name = "fetch_skins_in_background")]
-pub fn fetch_skins_in_background(
-) -> thread::JoinHandle<Result<(), Box<dyn std::error::Error + Send + Sync>>> {
-    info!("Fetching updated skin data");
-    thread::spawn(|| {
-        let client = reqwest::blocking::Client::new();
-        let response = client
-            .get("https://raw.githubusercontent.com/donutman07/MarvelRivalsCharacterIDs/refs/heads/main/MarvelRivalsCharacterIDs.md")
-            .send()?
-            .error_for_status()?;
-        let body = response.text()?;
-        debug!("Received markdown response ({} bytes)", body.len());
-
-        let skins = parse_markdown_to_skin_entries(&body);
-        debug!("Parsed {} skin entries", skins.len());
-
-        let json = serde_json::to_string_pretty(&skins)?;
-        fs::create_dir_all("data")?;
-        fs::write("data/character_data.json", json)?;
-        Ok(())
-    })
-}
-
-#[instrument(skip(markdown))]
-fn parse_markdown_to_skin_entries(markdown: &str) -> Vec<SkinEntry> {
-    let mut entries = Vec::new();
-    let mut current_char_id: Option<String> = None;
-    let mut current_char_name: Option<String> = None;
-
-    for line in markdown.lines() {
-        let line = line.trim();
-        if !line.starts_with('|') {
-            continue;
-        }
-
-        if line.contains("NAME") || line.contains(":--:") {
-            continue;
-        }
-
-        let cols: Vec<&str> = line.split('|').map(|s| s.trim()).collect();
-
-        if cols.len() < 5 {
-            continue;
-        }
-
-        let id_col = cols[1];
-        let name_col = cols[2];
-        let skin_id_col = cols[3];
-        let skin_name_col = cols[4];
-
-        // Update current character if this row has an ID
-        if !id_col.is_empty() && id_col != "????" {
-            // Validate it's a numeric character ID
-            if id_col.chars().all(|c| c.is_ascii_digit()) {
-                let name = name_col.to_string();
-                // Skip placeholder/old/bot entries that would override real characters
-                let is_placeholder = name.contains("(Old)")
-                    || name.contains("Old)")
-                    || name_col.is_empty()
-                    || name.ends_with("Bot")
-                    || name.contains("Bot (")
-                    || name.starts_with("Zombie")
-                    || name.starts_with("No Data")
-                    || name.contains("Mislabeled");
-
-                if is_placeholder {
-                    current_char_id = None;
-                    current_char_name = None;
-                    continue; // don't update current character context
-                }
-
-                current_char_id = Some(id_col.to_string());
-                current_char_name = Some(name_col.to_string());
-
-                // Generate default skin entry: 4-digit char ID + "001"
-                let default_skin_id = format!("{}001", id_col);
-                let default_skin_name = "(Default)".to_string();
-                entries.push(SkinEntry {
-                    skinid: default_skin_id,
-                    skin_name: default_skin_name,
-                    name: name_col.to_string(),
-                });
-            } else {
-                current_char_id = None;
-                current_char_name = None;
-            }
-        }
-
-        // Only emit an entry if we have a valid skin ID
-        let skin_id = skin_id_col.trim();
-        if skin_id.is_empty() || !skin_id.chars().all(|c| c.is_ascii_digit()) {
-            continue;
-        }
-
-        let Some(ref char_id) = current_char_id else {
-            continue;
-        };
-        let char_name = current_char_name.as_deref().unwrap_or("Unknown");
-        let skin_name = if skin_name_col.is_empty() {
-            // Fall back to character name for the base/default skin
-            char_name.to_string()
-        } else {
-            skin_name_col.to_string()
-        };
-
-        entries.push(SkinEntry {
-            skinid: skin_id.to_string(),
-            skin_name,
-            name: char_name.to_string(),
-        });
-
-        // Suppress unused variable warning when char_id is only used in the guard
-        let _ = char_id;
-    }
-
-    entries
-}
 #[instrument(name = "fetch_mesh_list_in_bg")]
 pub fn fetch_mesh_list_in_bg(
 ) -> thread::JoinHandle<Result<(), Box<dyn std::error::Error + Send + Sync>>> {
@@ -365,121 +246,15 @@ pub fn fetch_mesh_list_in_bg(
     })
 }
 
-#[derive(serde::Deserialize)]
-struct CliState {
-    game_path: PathBuf,
-    game_chunk_path: Option<PathBuf>,
-    kawaii_physics_usmap: Option<PathBuf>,
-}
-
-fn cli_config_paths() -> [PathBuf; 2] {
-    let dir = dirs::config_dir()
-        .unwrap_or_else(|| PathBuf::from("."))
-        .join("repak_manager");
-    [dir.join("repak_mod_manager.json"), dir.join("state.json")]
-}
-
-fn installed_iostore_paks(mods_dir: &std::path::Path) -> std::io::Result<Vec<PathBuf>> {
-    let mut paks = Vec::new();
-    for entry in fs::read_dir(mods_dir)? {
-        let path = entry?.path();
-        if path.extension().and_then(|ext| ext.to_str()) != Some("utoc") {
-            continue;
-        }
-        let pak = path.with_extension("pak");
-        let ucas = path.with_extension("ucas");
-        if pak.exists() && ucas.exists() {
-            paks.push(pak);
-        }
-    }
-    paks.sort();
-    Ok(paks)
-}
-
 fn run_fix_kawaii_physics_cli() -> Result<(), String> {
-    let config_path = cli_config_paths()
-        .into_iter()
-        .find(|path| path.exists())
-        .ok_or_else(|| "Could not find saved repak-rivals state".to_string())?;
-    let state = fs::read_to_string(&config_path)
-        .map_err(|e| format!("Failed to read {}: {e}", config_path.display()))?;
-    let state: CliState = serde_json::from_str(&state)
-        .map_err(|e| format!("Failed to parse {}: {e}", config_path.display()))?;
-
-    let mods_dir = state.game_path;
-    let game_paks_dir = state
-        .game_chunk_path
-        .ok_or_else(|| "No game Paks directory found in saved state".to_string())?;
-    let kawaii_physics_usmap = state
-        .kawaii_physics_usmap
-        .ok_or_else(|| "No KawaiiPhysics USMAP file found in saved state".to_string())?;
-
-    let paks = installed_iostore_paks(&mods_dir)
-        .map_err(|e| format!("Failed to scan {}: {e}", mods_dir.display()))?;
-    if paks.is_empty() {
-        return Err(format!(
-            "No installed IoStore mods found in {}",
-            mods_dir.display()
-        ));
-    }
-
-    println!("Found {} installed IoStore mods", paks.len());
-    println!("Extracting installed IoStore mods to legacy assets...");
-    let extracted_temp =
-        tempfile::tempdir().map_err(|e| format!("Failed to create temp directory: {e}"))?;
-    let extracted_dirs =
-        to_legacy_uasset_fast_batch(&paks, extracted_temp.path().to_path_buf(), game_paks_dir)
-            .map_err(|e| format!("Batch to-legacy extraction failed: {e}"))?;
-    println!(
-        "Extracted {} mods. Rebuilding fixed IoStore mods...",
-        extracted_dirs.len()
-    );
-
-    let fixed_mods_dir = PathBuf::from("./fixed-mods");
-    fs::create_dir_all(&fixed_mods_dir)
-        .map_err(|e| format!("Failed to create {}: {e}", fixed_mods_dir.display()))?;
-
-    for (idx, extracted_dir) in extracted_dirs.into_iter().enumerate() {
-        let mod_name = extracted_dir
-            .file_stem()
-            .and_then(|name| name.to_str())
-            .ok_or_else(|| {
-                format!(
-                    "Invalid extracted mod directory: {}",
-                    extracted_dir.display()
-                )
-            })?
-            .to_string();
-        println!("[{}/{}] Rebuilding {}", idx + 1, paks.len(), mod_name);
-        let mod_output_dir = fixed_mods_dir.join(&mod_name);
-        fs::create_dir_all(&mod_output_dir).map_err(|e| {
-            format!(
-                "Failed to create fixed mod directory {}: {e}",
-                mod_output_dir.display()
-            )
-        })?;
-
-        let mut mods = map_to_mods_internal(&[extracted_dir]);
-        for installable_mod in &mut mods {
-            installable_mod.enabled = true;
-            installable_mod.is_dir = true;
-            installable_mod.iostore = false;
-            installable_mod.repak = false;
-            installable_mod.kawaii_porter = true;
-            installable_mod.default_hidden_material_patch = false;
-        }
-
-        install_mods_in_viewport(
-            &mut mods,
-            &mod_output_dir,
-            Arc::new(AtomicI32::new(0)),
-            &AtomicBool::new(false),
-            &None,
-            &Some(kawaii_physics_usmap.clone()),
-        );
-    }
-
-    println!("Wrote fixed mods to {}", fixed_mods_dir.display());
+    let aes_key = retoc_aes_key()?;
+    let options = retoc_rivals_cli::FixKawaiiOptions {
+        output: PathBuf::from("./fixed-mods"),
+        ..Default::default()
+    };
+    let progress = retoc_rivals_cli::progress::print_started("Rebuilding");
+    retoc_rivals_cli::rebuild_installed_mods(&aes_key, &options, &progress)
+        .map_err(|e| e.report())?;
     Ok(())
 }
 
@@ -506,6 +281,12 @@ fn main() {
     std::panic::set_hook(Box::new(move |info| {
         custom_panic(info.into());
     }));
+    #[cfg(all(windows, not(debug_assertions)))]
+    crate::install_mod::install_mod_logic::register_to_legacy_console_hook(|| {
+        if has_attached_console() {
+            redirect_stdio();
+        }
+    });
 
     let log_dir = dirs::config_dir()
         .unwrap_or_else(|| PathBuf::from("."))
@@ -554,10 +335,7 @@ fn main() {
                     ..Default::default()
                 };
 
-                let aes_toc = retoc::AesKey::from_str(
-                    "0C263D8C22DCB085894899C3A3796383E9BF9DE0CBFB08C9BF2DEF2E84F29D74",
-                )
-                .unwrap();
+                let aes_toc = retoc_aes_key().expect("Failed to parse AES key");
 
                 config.aes_keys.insert(FGuid::default(), aes_toc.clone());
                 let config = Arc::new(config);
@@ -618,9 +396,7 @@ fn main() {
                     ..Default::default()
                 };
 
-                let aes_toc = match retoc::AesKey::from_str(
-                    "0C263D8C22DCB085894899C3A3796383E9BF9DE0CBFB08C9BF2DEF2E84F29D74",
-                ) {
+                let aes_toc = match retoc_aes_key() {
                     Ok(k) => k,
                     Err(e) => {
                         failure_log.push((path.clone(), format!("Invalid AES key: {}", e)));
@@ -702,9 +478,6 @@ fn main() {
         ..Default::default()
     };
 
-    // spawn a background thread to get skins
-    fetch_skins_in_background();
-
     #[cfg(not(debug_assertions))]
     fetch_mesh_list_in_bg();
     eframe::run_native(
@@ -1,16 +1,35 @@
 extern crate core;
 
+use crate::archive;
+use crate::catalog::{fingerprint_or_warn, Catalog, CatalogEntry};
+use crate::classification::{classify_files, AssetChange, ModClassification};
+use crate::duplicates::{
+    exact_extras, find_duplicates, DuplicateGroup, DuplicateKind, NEAR_DUPLICATE_THRESHOLD,
+};
 use crate::file_table::FileTable;
 use crate::install_mod::install_mod_logic::iotoc::{to_legacy_uasset, to_legacy_uasset_fast};
 use crate::install_mod::{
-    self, map_dropped_file_to_mods, map_paths_to_mods, InstallableMod, ModInstallRequest, AES_KEY,
+    self, map_dropped_file_to_mods, map_paths_to_mods, InstallableMod, ModInstallRequest,
+};
+use crate::installed::{
+    delete_mod_files, migrate_legacy_disabled_mods, mod_file_state, mod_files,
+    normalized_mod_identity_string, same_mod_identity, scan_installed_mods, InstalledMod,
+    ModFileSignature,
 };
 use crate::ios_widget;
+use crate::keys::KEYS;
+use crate::load_order::{
+    apply_priorities, load_order_key, load_order_priority, mod_base_name, mod_priority,
+};
+use crate::mod_conflicts::{analyze_mod_conflicts, ModConflictReport};
+use crate::mod_profiles::{apply_profile, profile_mod_name, ModProfile};
+use crate::mod_toggle::set_mod_enabled;
+use crate::skin_db::{import_overrides as import_skin_overrides, skin_db};
+use crate::state::{config_path, ModTags};
 use crate::utils::{
-    find_marvel_rivals, get_current_pak_characteristics, latest_depot_usmap_path,
-    match_exact_paks_suffix, mods_need_kawaii_mapping,
+    find_marvel_rivals, latest_depot_usmap_path, match_exact_paks_suffix, mods_need_kawaii_mapping,
 };
-use crate::utoc_utils::{is_iostore_obfuscated, read_utoc_package_names};
+use crate::utoc_utils::is_iostore_obfuscated;
 use crate::welcome::ShowWelcome;
 
 use eframe::egui::{
@@ -19,6 +38,7 @@ use eframe::egui::{
 };
 use egui_flex::{item, Flex, FlexAlign};
 use install_mod::install_mod_logic::pak_files::extract_pak_to_dir;
+use install_mod::install_mod_logic::transaction::recover_interrupted_installs;
 use install_mod::install_mod_logic::{
     fix_installed_iostore_kawaii_physics, patch_installed_iostore_default_hidden_materials,
     register_kawaii_runtime_error_sender,
@@ -28,7 +48,7 @@ use path_clean::PathClean;
 use repak::PakReader;
 use rfd::{FileDialog, MessageButtons};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::fs::File;
 use std::io::BufReader;
@@ -37,9 +57,8 @@ use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
 use std::sync::mpsc::{channel, Receiver, Sender};
 use std::sync::Arc;
 use std::thread;
-use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use std::time::{Duration, Instant};
 use tracing::{debug, error, info, instrument, trace, warn};
-use walkdir::WalkDir;
 
 const VERSION: &'static str = env!("CARGO_PKG_VERSION");
 const RED_THEME_COLOR: Color32 = Color32::from_rgb(255, 31, 75);
@@ -128,6 +147,12 @@ pub struct RepakModManager {
     #[serde(skip)]
     mod_files_search_query: String,
     #[serde(skip)]
+    catalog: Catalog,
+    #[serde(skip)]
+    duplicate_groups: Vec<DuplicateGroup>,
+    #[serde(skip)]
+    show_duplicates_window: bool,
+    #[serde(skip)]
     selected_tag_filters: Vec<String>,
     #[serde(skip)]
     selected_category_filters: Vec<String>,
@@ -143,6 +168,18 @@ pub struct RepakModManager {
     default_hidden_material_creator_lods: Vec<u64>,
     #[serde(skip)]
     default_hidden_material_creator_slots: usize,
+    #[serde(skip)]
+    conflict_worker: Option<thread::JoinHandle<ModConflictReport>>,
+    #[serde(skip)]
+    conflict_report: Option<ModConflictReport>,
+    #[serde(skip)]
+    show_conflicts_window: bool,
+    #[serde(skip)]
+    show_load_order_window: bool,
+    #[serde(skip)]
+    load_order_draft: Vec<PathBuf>,
+    #[serde(skip)]
+    new_profile_name: String,
     version: Option<String>,
 
     game_chunk_path: Option<PathBuf>,
@@ -150,12 +187,15 @@ pub struct RepakModManager {
     kawaii_physics_usmap: Option<PathBuf>,
     #[serde(skip)]
     launch_game_paths: Option<Result<crate::launch_game::GameLaunchPaths, String>>,
+    #[serde(flatten)]
+    tags: ModTags,
     #[serde(default)]
-    tag_catalog: Vec<String>,
+    mod_metadata_cache: Vec<ModMetadataCacheEntry>,
+    /// Mod base names from highest to lowest priority, as last applied in the load order window.
     #[serde(default)]
-    mod_tags: Vec<ModTagAssignment>,
+    load_order: Vec<String>,
     #[serde(default)]
-    mod_metadata_cache: Vec<ModMetadataCacheEntry>,
+    mod_profiles: Vec<ModProfile>,
 
     #[serde(default)]
     show_load_order_suffix: bool,
@@ -168,12 +208,6 @@ fn default_true() -> bool {
     true
 }
 
-#[derive(Clone, Deserialize, Serialize)]
-struct ModTagAssignment {
-    path: PathBuf,
-    tags: Vec<String>,
-}
-
 #[derive(Clone)]
 struct ModEntry {
     path: PathBuf,
@@ -182,15 +216,21 @@ struct ModEntry {
     signature: ModFileSignature,
     category: String,
     characteristic: String,
+    classification: Option<ModClassification>,
     obfuscated: String,
     metadata_pending: bool,
     file_count: Option<usize>,
 }
 
-#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
-struct ModFileSignature {
-    size: u64,
-    modified_secs: u64,
+impl ModEntry {
+    /// Every kind of asset in the mod, so filtering by a kind also finds mods listed under
+    /// another category.
+    fn asset_kinds(&self) -> impl Iterator<Item = String> + '_ {
+        self.classification
+            .iter()
+            .flat_map(|classification| classification.kinds.keys())
+            .map(|kind| kind.to_string())
+    }
 }
 
 #[derive(Clone, Deserialize, Serialize)]
@@ -199,17 +239,18 @@ struct ModMetadataCacheEntry {
     signature: ModFileSignature,
     category: String,
     characteristic: String,
+    /// Missing from caches written before mods were classified.
+    #[serde(default)]
+    classification: Option<ModClassification>,
     obfuscated: String,
     file_count: Option<usize>,
 }
 
 struct MetadataJob {
     generation: u64,
-    path: PathBuf,
+    installed: InstalledMod,
     identity: String,
-    signature: ModFileSignature,
     obfuscated: String,
-    is_iostore: bool,
 }
 
 struct MetadataResult {
@@ -218,6 +259,7 @@ struct MetadataResult {
     signature: ModFileSignature,
     category: String,
     characteristic: String,
+    classification: ModClassification,
     obfuscated: String,
     file_count: Option<usize>,
 }
@@ -361,6 +403,7 @@ impl RepakModManager {
             return;
         }
 
+        self.migrate_legacy_disabled_mods();
         self.metadata_generation = self.metadata_generation.wrapping_add(1);
         let generation = self.metadata_generation;
         let selected_identity = self
@@ -374,32 +417,16 @@ impl RepakModManager {
             .collect::<HashMap<_, _>>();
         let mut next_entries = Vec::new();
         let mut jobs = Vec::new();
+        self.reload_catalog();
+        let installed_mods = scan_installed_mods(&self.game_path);
 
-        for entry in WalkDir::new(&self.game_path)
-            .into_iter()
-            .filter_map(Result::ok)
-            .filter(|entry| entry.file_type().is_file())
-        {
-            let path = entry.path();
-            let Some((enabled, is_mod_file)) = mod_file_state(path) else {
-                continue;
-            };
-            if !is_mod_file {
-                continue;
-            }
-
-            let Some(signature) = mod_file_signature(path) else {
-                warn!(
-                    file_name = %path.file_name().and_then(|name| name.to_str()).unwrap_or("<unknown>"),
-                    "Skipping mod with unreadable metadata"
-                );
-                continue;
-            };
+        for installed in &installed_mods {
+            let path = installed.path.as_path();
+            let (enabled, is_iostore, signature) =
+                (installed.enabled, installed.is_iostore, installed.signature);
             let identity = normalized_mod_identity_string(path);
-            let utoc_path = path.with_extension("utoc");
-            let is_iostore = utoc_path.exists();
             let obfuscated = if is_iostore {
-                match is_iostore_obfuscated(&utoc_path) {
+                match is_iostore_obfuscated(&path.with_extension("utoc")) {
                     Ok(value) => value.to_string(),
                     Err(e) => {
                         warn!(error = %e, "Failed to read IoStore obfuscation flag");
@@ -412,26 +439,33 @@ impl RepakModManager {
 
             let cached = cache_by_identity
                 .get(&identity)
-                .filter(|entry| entry.signature == signature);
-            let (category, characteristic, metadata_pending, file_count) = match cached {
-                Some(entry) => (
-                    entry.category.clone(),
-                    entry.characteristic.clone(),
-                    false,
-                    entry.file_count,
-                ),
-                None => {
-                    jobs.push(MetadataJob {
-                        generation,
-                        path: path.to_path_buf(),
-                        identity: identity.clone(),
-                        signature,
-                        obfuscated: obfuscated.clone(),
-                        is_iostore,
-                    });
-                    ("Pending".to_string(), "Pending".to_string(), true, None)
-                }
-            };
+                .filter(|entry| entry.signature == signature && entry.classification.is_some());
+            // mods cached before the catalog existed still need their file list indexed
+            if cached.is_none() || !self.catalog.is_current(installed) {
+                jobs.push(MetadataJob {
+                    generation,
+                    installed: installed.clone(),
+                    identity: identity.clone(),
+                    obfuscated: obfuscated.clone(),
+                });
+            }
+            let (category, characteristic, classification, metadata_pending, file_count) =
+                match cached {
+                    Some(entry) => (
+                        entry.category.clone(),
+                        entry.characteristic.clone(),
+                        entry.classification.clone(),
+                        false,
+                        entry.file_count,
+                    ),
+                    None => (
+                        "Pending".to_string(),
+                        "Pending".to_string(),
+                        None,
+                        true,
+                        None,
+                    ),
+                };
 
             next_entries.push(ModEntry {
                 path: path.to_path_buf(),
@@ -440,6 +474,7 @@ impl RepakModManager {
                 signature,
                 category,
                 characteristic,
+                classification,
                 obfuscated,
                 metadata_pending,
                 file_count,
@@ -457,6 +492,16 @@ impl RepakModManager {
             self.selected_pak_details = None;
         }
 
+        if self.catalog.sync_installed(&installed_mods) {
+            match Catalog::update(|catalog| {
+                catalog.sync_installed(&installed_mods);
+            }) {
+                Ok(catalog) => self.catalog = catalog,
+                Err(e) => warn!(error = %e, "Failed to update mod catalog"),
+            }
+        }
+        self.duplicate_groups = find_duplicates(&self.catalog, NEAR_DUPLICATE_THRESHOLD);
+
         self.start_metadata_worker(generation, jobs);
         self.prune_metadata_cache();
         info!(
@@ -465,6 +510,14 @@ impl RepakModManager {
         );
     }
 
+    /// Keeps the previous snapshot when the catalog cannot be read, so searching still works.
+    fn reload_catalog(&mut self) {
+        match Catalog::load() {
+            Ok(catalog) => self.catalog = catalog,
+            Err(e) => warn!(error = %e, "Failed to load mod catalog"),
+        }
+    }
+
     fn start_metadata_worker(&mut self, generation: u64, jobs: Vec<MetadataJob>) {
         if let Some(cancel) = &self.metadata_cancel {
             cancel.store(true, Ordering::Relaxed);
@@ -481,12 +534,15 @@ impl RepakModManager {
         self.metadata_receiver = Some(rx);
         self.metadata_cancel = Some(cancel.clone());
         std::thread::spawn(move || {
+            let mut indexed = Vec::new();
             for job in jobs {
                 if cancel.load(Ordering::Relaxed) {
                     return;
                 }
                 match classify_mod_metadata(&job) {
-                    Ok(result) => {
+                    Ok((result, files)) => {
+                        let fingerprint = fingerprint_or_warn(&job.installed);
+                        indexed.push((job.installed, files, fingerprint));
                         if tx.send(MetadataMessage::Entry(result)).is_err() {
                             return;
                         }
@@ -496,6 +552,15 @@ impl RepakModManager {
                     }
                 }
             }
+            if !indexed.is_empty() {
+                if let Err(e) = Catalog::update(|catalog| {
+                    for (installed, files, fingerprint) in indexed {
+                        catalog.index(&installed, files, fingerprint);
+                    }
+                }) {
+                    warn!(error = %e, "Failed to index mods in catalog");
+                }
+            }
             if !cancel.load(Ordering::Relaxed) {
                 let _ = tx.send(MetadataMessage::Done(generation));
             }
@@ -537,6 +602,8 @@ impl RepakModManager {
         if finished_generation.is_some() {
             self.metadata_receiver = None;
             self.metadata_cancel = None;
+            self.reload_catalog();
+            self.duplicate_groups = find_duplicates(&self.catalog, NEAR_DUPLICATE_THRESHOLD);
             if self.metadata_cache_dirty {
                 if let Err(e) = self.save_state() {
                     warn!(error = %e, "Failed to save metadata cache");
@@ -557,6 +624,7 @@ impl RepakModManager {
             }
             entry.category = result.category.clone();
             entry.characteristic = result.characteristic.clone();
+            entry.classification = Some(result.classification.clone());
             entry.obfuscated = result.obfuscated.clone();
             entry.metadata_pending = false;
             entry.file_count = result.file_count;
@@ -567,6 +635,7 @@ impl RepakModManager {
             signature: result.signature,
             category: result.category,
             characteristic: result.characteristic,
+            classification: Some(result.classification),
             obfuscated: result.obfuscated,
             file_count: result.file_count,
         };
@@ -599,7 +668,7 @@ impl RepakModManager {
 
     fn open_pak_reader(pak_path: &Path) -> Result<PakReader, String> {
         let mut builder = repak::PakBuilder::new();
-        builder = builder.key(AES_KEY.clone().0);
+        builder = builder.keys(KEYS.clone());
         let file = File::open(pak_path)
             .map_err(|e| format!("Failed to open {}: {e}", pak_path.display()))?;
         builder
@@ -622,7 +691,11 @@ impl RepakModManager {
                         path_hash_seed: format!("{:?}", reader.path_hash_seed()),
                         version: format!("{:?}", reader.version()),
                     }));
-                self.table = Some(FileTable::new(&reader, &pak_path));
+                self.table = Some(FileTable::new(
+                    &reader,
+                    &pak_path,
+                    self.game_chunk_path.clone(),
+                ));
             }
             Err(error) => {
                 self.selected_pak_details = Some(SelectedPakDetailsState::Failed {
@@ -712,6 +785,7 @@ impl RepakModManager {
         };
         let obfuscated = current_mod.obfuscated.clone();
         let characteristic = current_mod.characteristic.clone();
+        let classification = current_mod.classification.clone();
 
         egui::CollapsingHeader::new("Pak details")
             .default_open(true)
@@ -764,6 +838,42 @@ impl RepakModManager {
             ));
             ui.add(Label::new(characteristic));
         });
+        let Some(classification) = classification else {
+            return;
+        };
+        if !classification.skins.is_empty() {
+            ui.horizontal_wrapped(|ui| {
+                ui.add(Label::new(RichText::new("Skins: ").strong()));
+                let skins = classification
+                    .skins
+                    .iter()
+                    .map(|skin| skin.label())
+                    .collect::<Vec<_>>();
+                ui.add(Label::new(skins.join(", ")));
+            });
+        }
+        if !classification.kinds.is_empty() {
+            ui.horizontal_wrapped(|ui| {
+                ui.add(Label::new(RichText::new("Assets: ").strong()));
+                let kinds = classification
+                    .kinds
+                    .iter()
+                    .map(|(kind, count)| format!("{kind} {count}"))
+                    .collect::<Vec<_>>();
+                ui.add(Label::new(kinds.join(", ")));
+            });
+        }
+        ui.horizontal(|ui| {
+            ui.add(Label::new(RichText::new("Changes: ").strong()));
+            ui.add(Label::new(match classification.change() {
+                AssetChange::Replaces => "Replaces game assets".to_string(),
+                AssetChange::Adds => "Adds new assets".to_string(),
+                AssetChange::Both => format!(
+                    "Replaces {} game assets, adds {} new",
+                    classification.replaced, classification.added
+                ),
+            }));
+        });
     }
     #[instrument(skip(self, ui))]
     fn show_pak_files_in_dir(&mut self, ui: &mut egui::Ui) {
@@ -774,7 +884,7 @@ impl RepakModManager {
                     ui.horizontal_wrapped(|ui| {
                         ui.add(
                             TextEdit::singleline(&mut self.mod_files_search_query)
-                                .hint_text("Search mods, paths, tags...")
+                                .hint_text("Search mods, heroes, skins, files...")
                                 .desired_width(230.0),
                         );
                         egui::ComboBox::from_id_salt("category_filter")
@@ -823,7 +933,7 @@ impl RepakModManager {
                                     self.selected_tag_filters.clear();
                                 }
                                 ui.separator();
-                                for tag in self.tag_catalog.clone() {
+                                for tag in self.tags.tag_catalog.clone() {
                                     let mut selected = self.selected_tag_filters.contains(&tag);
                                     if ui.checkbox(&mut selected, &tag).changed() {
                                         toggle_filter_value(
@@ -846,8 +956,20 @@ impl RepakModManager {
                     });
                     ui.add_space(8.0);
 
+                    let search_query = self.mod_files_search_query.trim().to_lowercase();
+                    // catalog hits cover skins, heroes and packaged files, see Catalog::search
+                    let mut catalog_hits = HashSet::new();
+                    let mut removed_hits = Vec::new();
+                    if !search_query.is_empty() {
+                        for hit in self.catalog.search(&search_query, &self.tags) {
+                            if hit.entry.installed {
+                                catalog_hits.insert(hit.entry.identity.clone());
+                            } else {
+                                removed_hits.push(hit.entry.clone());
+                            }
+                        }
+                    }
                     for i in 0..self.pak_files.len() {
-                        let search_query = self.mod_files_search_query.trim().to_lowercase();
                         let pak_path = self.pak_files[i].path.clone();
                         let pak_category = self.pak_files[i].category.clone();
                         let pak_enabled = self.pak_files[i].enabled;
@@ -857,10 +979,14 @@ impl RepakModManager {
                             .unwrap_or_default()
                             .to_string();
                         let display_name = normalize_mod_display_name(&raw_name.clone());
-                        let has_suffix_indicator = has_mod_suffix(&raw_name);
+                        let load_order_suffix = mod_priority(&raw_name)
+                            .filter(|_| self.show_load_order_suffix)
+                            .map(|priority| format!("_{priority}_P"));
                         let raw_path = pak_path.to_string_lossy().to_string();
-                        let tag_list = self.tags_for_mod(&pak_path);
+                        let tag_list = self.tags.tags_for_mod(&pak_path);
+                        let identity = normalized_mod_identity_string(&pak_path);
                         if !search_query.is_empty()
+                            && !catalog_hits.contains(&identity)
                             && !raw_name.to_lowercase().contains(&search_query)
                             && !display_name.to_lowercase().contains(&search_query)
                             && !raw_path.to_lowercase().contains(&search_query)
@@ -870,6 +996,9 @@ impl RepakModManager {
                         }
                         if !self.selected_category_filters.is_empty()
                             && !self.selected_category_filters.contains(&pak_category)
+                            && !self.pak_files[i].asset_kinds().any(|kind| {
+                                self.selected_category_filters.contains(&kind)
+                            })
                         {
                             continue;
                         }
@@ -917,10 +1046,10 @@ impl RepakModManager {
 
                                         ui.add_space(2.0);
                                         ui.horizontal_wrapped(|ui| {
-                                            if has_suffix_indicator && self.show_load_order_suffix{
+                                            if let Some(suffix) = &load_order_suffix {
                                                 self.metadata_chip(
                                                     ui,
-                                                    "_9999999_P",
+                                                    suffix,
                                                     Color32::from_rgb(180, 180, 180),
                                                     Color32::from_rgb(58, 58, 58),
                                                     Color32::from_rgb(82, 82, 82),
@@ -935,6 +1064,24 @@ impl RepakModManager {
                                                 category_fill,
                                                 category_stroke,
                                             );
+                                            let change_label = match self.pak_files[i]
+                                                .classification
+                                                .as_ref()
+                                                .map(ModClassification::change)
+                                            {
+                                                Some(AssetChange::Adds) => Some("New assets"),
+                                                Some(AssetChange::Both) => Some("Adds assets"),
+                                                _ => None,
+                                            };
+                                            if let Some(change_label) = change_label {
+                                                self.metadata_chip(
+                                                    ui,
+                                                    change_label,
+                                                    Color32::from_rgb(170, 230, 170),
+                                                    Color32::from_rgb(30, 64, 34),
+                                                    Color32::from_rgb(56, 110, 62),
+                                                );
+                                            }
                                             for tag in &tag_list {
                                                 let (tag_text, tag_fill, tag_stroke) =
                                                     tag_colors(tag);
@@ -946,6 +1093,54 @@ impl RepakModManager {
                                                     tag_stroke,
                                                 );
                                             }
+                                            if let Some(summary) = self
+                                                .conflict_report
+                                                .as_ref()
+                                                .and_then(|report| report.summary_for(&pak_path))
+                                            {
+                                                if summary.shadowed() {
+                                                    self.metadata_chip(
+                                                        ui,
+                                                        "Shadowed",
+                                                        Color32::from_rgb(255, 170, 170),
+                                                        Color32::from_rgb(80, 28, 32),
+                                                        Color32::from_rgb(140, 50, 58),
+                                                    );
+                                                } else if summary.overridden > 0 {
+                                                    self.metadata_chip(
+                                                        ui,
+                                                        &format!("{} overridden", summary.overridden),
+                                                        Color32::from_rgb(255, 210, 150),
+                                                        Color32::from_rgb(74, 52, 24),
+                                                        Color32::from_rgb(128, 92, 40),
+                                                    );
+                                                }
+                                                if summary.wins > 0 {
+                                                    self.metadata_chip(
+                                                        ui,
+                                                        &format!("Overrides {}", summary.wins),
+                                                        Color32::from_rgb(170, 230, 170),
+                                                        Color32::from_rgb(30, 64, 34),
+                                                        Color32::from_rgb(56, 110, 62),
+                                                    );
+                                                }
+                                            }
+                                            if let Some(group) = self
+                                                .duplicate_groups
+                                                .iter()
+                                                .find(|group| group.extras().contains(&pak_path))
+                                            {
+                                                self.metadata_chip(
+                                                    ui,
+                                                    match group.kind {
+                                                        DuplicateKind::Exact => "Duplicate",
+                                                        DuplicateKind::Near => "Near duplicate",
+                                                    },
+                                                    Color32::from_rgb(255, 210, 150),
+                                                    Color32::from_rgb(74, 52, 24),
+                                                    Color32::from_rgb(128, 92, 40),
+                                                );
+                                            }
                                             if self.show_char_details{
                                                 let chars = &self.pak_files[i].characteristic;
                                                 let (tag_text, tag_fill, tag_stroke) =
@@ -971,7 +1166,7 @@ impl RepakModManager {
                                             if let Some(new_path) =
                                                 Self::toggle_mod_file(&toggled_path, enable_mod)
                                             {
-                                                self.update_tag_path(&toggled_path, &new_path);
+                                                self.tags.update_path(&toggled_path, &new_path);
                                                 if let Err(e) = self.save_state() {
                                                     warn!(error = %e, "Failed to save tag path after toggle");
                                                 }
@@ -1014,10 +1209,64 @@ impl RepakModManager {
                         });
                         ui.add_space(4.0);
                     }
+
+                    self.show_removed_catalog_hits(ui, &removed_hits);
                 });
             });
     }
 
+    /// Lists removed mods matching the search so they can be found and reinstalled.
+    fn show_removed_catalog_hits(&mut self, ui: &mut egui::Ui, removed: &[CatalogEntry]) {
+        if removed.is_empty() {
+            return;
+        }
+
+        ui.separator();
+        ui.label(RichText::new("Not installed").strong());
+        let mut reinstall = None;
+        for hit in removed {
+            ui.horizontal(|ui| {
+                ui.label(hit.name());
+                let skins = hit
+                    .skins
+                    .iter()
+                    .map(|skin| format!("{} - {}", skin.hero, skin.skin_name))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                if !skins.is_empty() {
+                    ui.weak(skins);
+                }
+                let source = hit.source.as_ref().filter(|source| source.path.exists());
+                if let Some(source) = source {
+                    if ui
+                        .button("Reinstall")
+                        .on_hover_text(source.path.display().to_string())
+                        .clicked()
+                    {
+                        reinstall = Some(source.path.clone());
+                    }
+                }
+            });
+        }
+
+        let Some(source) = reinstall else {
+            return;
+        };
+        let mods = map_paths_to_mods(&[source]);
+        if mods.is_empty() {
+            error!("Catalog source no longer contains installable mods");
+            return;
+        }
+        self.update_kawaii_usmap_if_needed(mods_need_kawaii_mapping(&mods));
+        self.file_drop_viewport_open = true;
+        self.install_mod_dialog = Some(ModInstallRequest::new(
+            mods,
+            self.game_path.clone(),
+            &self.game_chunk_path,
+            &self.kawaii_physics_usmap,
+        ));
+    }
+
     fn metadata_chip(
         &self,
         ui: &mut egui::Ui,
@@ -1208,6 +1457,9 @@ impl RepakModManager {
                     enabled: true,
                     obfuscated: false,
                     extracted_archive_dir: None,
+                    source: None,
+                    variant: None,
+                    pending_archive_payload: None,
                 }
             };
 
@@ -1314,10 +1566,10 @@ impl RepakModManager {
             }
         }
         if ui.button("Delete mod").clicked() {
-            if Self::delete_mod_files(pak_path).is_err() {
+            if delete_mod_files(pak_path).is_err() {
                 return;
             }
-            self.remove_all_tags_for_mod(pak_path);
+            self.tags.remove_all(pak_path);
             if let Err(e) = self.save_state() {
                 warn!(error = %e, "Failed to save tag cleanup after delete");
             }
@@ -1343,7 +1595,7 @@ impl RepakModManager {
             if ui.add(create_button).clicked() {
                 let tag = self.new_tag_name.trim().to_string();
                 if !tag.is_empty() {
-                    self.add_tag_to_mod(mod_path, &tag);
+                    self.tags.add_tag(mod_path, &tag);
                     self.new_tag_name.clear();
                     if let Err(e) = self.save_state() {
                         warn!(error = %e, "Failed to save tag assignment");
@@ -1352,14 +1604,14 @@ impl RepakModManager {
                 }
             }
 
-            let catalog = self.tag_catalog.clone();
+            let catalog = self.tags.tag_catalog.clone();
             for tag in catalog {
                 let (text_color, fill, stroke) = tag_colors(&tag);
                 let tag_button = Button::new(RichText::new(format!("#{tag}")).color(text_color))
                     .fill(fill)
                     .stroke(Stroke::new(1.0_f32, stroke));
                 if ui.add(tag_button).clicked() {
-                    self.add_tag_to_mod(mod_path, &tag);
+                    self.tags.add_tag(mod_path, &tag);
                     if let Err(e) = self.save_state() {
                         warn!(error = %e, "Failed to save tag assignment");
                     }
@@ -1368,12 +1620,12 @@ impl RepakModManager {
             }
         });
 
-        let tags = self.tags_for_mod(mod_path);
+        let tags = self.tags.tags_for_mod(mod_path);
         if !tags.is_empty() {
             ui.menu_button("Remove tag", |ui| {
                 for tag in tags {
                     if ui.button(format!("Remove #{tag}")).clicked() {
-                        self.remove_tag_from_mod(mod_path, &tag);
+                        self.tags.remove_tag(mod_path, &tag);
                         if let Err(e) = self.save_state() {
                             warn!(error = %e, "Failed to save tag removal");
                         }
@@ -1529,99 +1781,477 @@ impl RepakModManager {
         self.show_default_hidden_material_bitmap_creator = open;
     }
 
-    fn category_options(&self) -> Vec<String> {
-        let mut categories = self
+    fn start_conflict_check(&mut self) {
+        let mods = self
             .pak_files
             .iter()
-            .map(|entry| entry.category.clone())
-            .filter(|category| category != "Pending")
+            .filter(|entry| entry.enabled)
+            .map(|entry| (entry.path.clone(), entry.is_iostore))
             .collect::<Vec<_>>();
-        categories.sort();
-        categories.dedup();
-        categories
+        info!(enabled_mods = mods.len(), "Checking mod conflicts");
+        self.conflict_worker = Some(thread::spawn(move || {
+            let file_lists = mods
+                .into_iter()
+                .filter_map(|(path, is_iostore)| {
+                    mod_files(&path, is_iostore)
+                        .inspect_err(|e| warn!(error = %e, "Skipping mod in conflict check"))
+                        .ok()
+                        .map(|files| (path, files))
+                })
+                .collect::<Vec<_>>();
+            analyze_mod_conflicts(file_lists)
+        }));
+    }
+
+    fn process_conflict_worker(&mut self, ctx: &egui::Context) {
+        if !self
+            .conflict_worker
+            .as_ref()
+            .is_some_and(|worker| worker.is_finished())
+        {
+            return;
+        }
+        let Some(worker) = self.conflict_worker.take() else {
+            return;
+        };
+        match worker.join() {
+            Ok(report) => {
+                info!(
+                    conflicting_assets = report.assets.len(),
+                    "Finished checking mod conflicts"
+                );
+                self.conflict_report = Some(report);
+                self.show_conflicts_window = true;
+            }
+            Err(_) => error!("Mod conflict worker panicked"),
+        }
+        ctx.request_repaint();
     }
 
-    fn tags_for_mod(&self, mod_path: &Path) -> Vec<String> {
-        self.mod_tags
-            .iter()
-            .find(|assignment| same_mod_identity(&assignment.path, mod_path))
-            .map(|assignment| assignment.tags.clone())
-            .unwrap_or_default()
+    fn show_conflicts_window(&mut self, ctx: &egui::Context) {
+        if !self.show_conflicts_window {
+            return;
+        }
+        let Some(report) = &self.conflict_report else {
+            return;
+        };
+        let mod_name = |path: &Path| {
+            normalize_mod_display_name(
+                path.file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .unwrap_or_default(),
+            )
+        };
+
+        let mut open = self.show_conflicts_window;
+        egui::Window::new("Mod conflicts")
+            .open(&mut open)
+            .resizable(true)
+            .default_width(640.0)
+            .show(ctx, |ui| {
+                if report.assets.is_empty() {
+                    ui.label("No enabled mods override the same assets.");
+                    return;
+                }
+                ui.label(format!(
+                    "{} assets are provided by more than one enabled mod. The mod loaded last wins.",
+                    report.assets.len()
+                ));
+                ui.separator();
+                ScrollArea::vertical().max_height(480.0).show(ui, |ui| {
+                    for summary in report.mods.iter().filter(|summary| summary.overridden > 0) {
+                        let text = if summary.shadowed() {
+                            RichText::new(format!(
+                                "{}: shadowed entirely ({} assets)",
+                                mod_name(&summary.path),
+                                summary.asset_count
+                            ))
+                            .color(Color32::from_rgb(255, 170, 170))
+                        } else {
+                            RichText::new(format!(
+                                "{}: {} of {} assets overridden",
+                                mod_name(&summary.path),
+                                summary.overridden,
+                                summary.asset_count
+                            ))
+                        };
+                        ui.label(text);
+                    }
+                    ui.separator();
+                    egui::CollapsingHeader::new("Conflicting assets").show(ui, |ui| {
+                        for conflict in &report.assets {
+                            ui.label(RichText::new(&conflict.asset).monospace());
+                            ui.label(format!("    winner: {}", mod_name(&conflict.winner)));
+                            for path in &conflict.overridden {
+                                ui.label(format!("    overridden: {}", mod_name(path)));
+                            }
+                        }
+                    });
+                });
+            });
+        self.show_conflicts_window = open;
     }
 
-    fn add_tag_to_mod(&mut self, mod_path: &Path, tag: &str) {
-        let tag = tag.trim();
-        if tag.is_empty() {
+    fn show_duplicates_window(&mut self, ctx: &egui::Context) {
+        if !self.show_duplicates_window {
             return;
         }
+        let mod_name = |path: &Path| {
+            normalize_mod_display_name(
+                path.file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .unwrap_or_default(),
+            )
+        };
 
-        if !self.tag_catalog.iter().any(|existing| existing == tag) {
-            self.tag_catalog.push(tag.to_string());
-            self.tag_catalog.sort();
-        }
+        let mut open = self.show_duplicates_window;
+        let mut remove = Vec::new();
+        egui::Window::new("Duplicate mods")
+            .open(&mut open)
+            .resizable(true)
+            .default_width(560.0)
+            .show(ctx, |ui| {
+                if self.metadata_receiver.is_some() {
+                    ui.label("Still reading mods, results may be incomplete.");
+                }
+                if self.duplicate_groups.is_empty() {
+                    ui.label("No mod is installed more than once.");
+                    return;
+                }
+                ui.label(
+                    "The enabled copy loaded last is kept, as it is the one currently used in game.",
+                );
+                ui.label(
+                    "Near duplicates are often different versions of a mod and are never removed \
+                     here, delete the ones you don't want from the mod list.",
+                );
+                if ui.button("Remove all exact extras").clicked() {
+                    remove.extend(exact_extras(&self.duplicate_groups).cloned());
+                }
+                ui.separator();
+                ScrollArea::vertical().max_height(480.0).show(ui, |ui| {
+                    for group in &self.duplicate_groups {
+                        ui.label(match group.kind {
+                            DuplicateKind::Exact => "Exact duplicates".to_string(),
+                            DuplicateKind::Near => format!(
+                                "Near duplicates, {:.0}% of files shared",
+                                group.similarity * 100.0
+                            ),
+                        });
+                        ui.label(format!("    keep: {}", mod_name(group.keep())));
+                        let label = match group.kind {
+                            DuplicateKind::Exact => "extra",
+                            DuplicateKind::Near => "other",
+                        };
+                        for extra in group.extras() {
+                            ui.label(format!("    {label}: {}", mod_name(extra)));
+                        }
+                        if group.kind == DuplicateKind::Exact
+                            && ui.button("Remove extras").clicked()
+                        {
+                            remove.extend(group.extras().iter().cloned());
+                        }
+                        ui.separator();
+                    }
+                });
+            });
+        self.show_duplicates_window = open;
 
-        if let Some(assignment) = self
-            .mod_tags
-            .iter_mut()
-            .find(|assignment| same_mod_identity(&assignment.path, mod_path))
-        {
-            if !assignment.tags.iter().any(|existing| existing == tag) {
-                assignment.tags.push(tag.to_string());
-                assignment.tags.sort();
+        if remove.is_empty() {
+            return;
+        }
+        for path in &remove {
+            if let Err(e) = delete_mod_files(path) {
+                error!(error = %e, "Failed to delete duplicate mod");
+                continue;
             }
+            self.tags.remove_all(path);
+        }
+        if let Err(e) = self.save_state() {
+            warn!(error = %e, "Failed to save tag cleanup after delete");
+        }
+        self.current_pak_file_idx = None;
+        self.table = None;
+        self.selected_pak_details = None;
+        self.collect_pak_files();
+    }
+
+    fn import_skin_data(&mut self) {
+        let Some(path) = FileDialog::new()
+            .set_title("Import skin data")
+            .add_filter("Skin data", &["json", "md"])
+            .pick_file()
+        else {
             return;
+        };
+        let result = fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read {}: {e}", path.display()))
+            .and_then(|data| import_skin_overrides(&data));
+        match result {
+            Ok(count) => {
+                info!(skins = count, "Imported skin data");
+                // character details are cached per mod, read every mod again
+                self.mod_metadata_cache.clear();
+                self.collect_pak_files();
+            }
+            Err(e) => {
+                rfd::MessageDialog::new()
+                    .set_buttons(MessageButtons::Ok)
+                    .set_title("Failed to import skin data")
+                    .set_description(e)
+                    .show();
+            }
         }
+    }
 
-        self.mod_tags.push(ModTagAssignment {
-            path: mod_path.to_path_buf(),
-            tags: vec![tag.to_string()],
+    fn open_load_order_window(&mut self) {
+        // highest priority first: the mod at the top loads last and wins conflicts
+        let mut draft = self
+            .pak_files
+            .iter()
+            .map(|entry| entry.path.clone())
+            .collect::<Vec<_>>();
+        draft.sort_by_cached_key(|path| std::cmp::Reverse(load_order_key(path)));
+        // mods missing from the saved order were installed since it was applied, keep them on top
+        draft.sort_by_cached_key(|path| {
+            let stem = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .unwrap_or_default();
+            let base_name = mod_base_name(stem);
+            self.load_order
+                .iter()
+                .position(|name| name.eq_ignore_ascii_case(base_name))
         });
+        self.load_order_draft = draft;
+        self.show_load_order_window = true;
     }
 
-    fn remove_tag_from_mod(&mut self, mod_path: &Path, tag: &str) {
-        if let Some(assignment) = self
-            .mod_tags
-            .iter_mut()
-            .find(|assignment| same_mod_identity(&assignment.path, mod_path))
-        {
-            assignment.tags.retain(|existing| existing != tag);
+    fn show_load_order_window(&mut self, ctx: &egui::Context) {
+        if !self.show_load_order_window {
+            return;
+        }
+
+        let mut open = self.show_load_order_window;
+        let mut apply = false;
+        let mut moved = None;
+        egui::Window::new("Load order")
+            .open(&mut open)
+            .resizable(true)
+            .default_width(480.0)
+            .show(ctx, |ui| {
+                ui.label("Drag mods to reorder. Mods at the top load last and win conflicts.");
+                ui.separator();
+                ScrollArea::vertical().max_height(480.0).show(ui, |ui| {
+                    for (idx, path) in self.load_order_draft.iter().enumerate() {
+                        let name = path
+                            .file_stem()
+                            .and_then(|stem| stem.to_str())
+                            .unwrap_or_default();
+                        let enabled = mod_file_state(path).is_some_and(|(enabled, _)| enabled);
+                        let response = ui
+                            .dnd_drag_source(Id::new(("load_order", idx)), idx, |ui| {
+                                ui.horizontal(|ui| {
+                                    ui.label(RichText::new("≡").weak());
+                                    ui.label(format!("{}.", idx + 1));
+                                    let text = RichText::new(normalize_mod_display_name(name));
+                                    ui.label(if enabled { text } else { text.weak() });
+                                });
+                            })
+                            .response;
+
+                        if response.dnd_hover_payload::<usize>().is_some() {
+                            ui.painter().hline(
+                                response.rect.x_range(),
+                                response.rect.top(),
+                                Stroke::new(2.0_f32, RED_THEME_COLOR),
+                            );
+                        }
+                        if let Some(from) = response.dnd_release_payload::<usize>() {
+                            moved = Some((*from, idx));
+                        }
+                    }
+                });
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.button("Apply").clicked() {
+                        apply = true;
+                    }
+                    if ui.button("Reset").clicked() {
+                        self.load_order.clear();
+                        self.open_load_order_window();
+                    }
+                });
+            });
+
+        if let Some((from, to)) = moved {
+            if from != to && from < self.load_order_draft.len() {
+                let path = self.load_order_draft.remove(from);
+                self.load_order_draft.insert(to, path);
+            }
+        }
+        if apply {
+            self.apply_load_order();
+            open = false;
         }
-        self.mod_tags
-            .retain(|assignment| !assignment.tags.is_empty());
+        self.show_load_order_window = open;
     }
 
-    fn remove_all_tags_for_mod(&mut self, mod_path: &Path) {
-        self.mod_tags
-            .retain(|assignment| !same_mod_identity(&assignment.path, mod_path));
+    fn apply_load_order(&mut self) {
+        let draft = std::mem::take(&mut self.load_order_draft);
+        info!(mods = draft.len(), "Applying load order");
+        let priorities = draft
+            .iter()
+            .enumerate()
+            .map(|(idx, path)| Ok((path.clone(), load_order_priority(idx)?)))
+            .collect::<std::io::Result<Vec<_>>>();
+        match priorities.and_then(|priorities| apply_priorities(&priorities)) {
+            Ok(renamed) => {
+                let mut load_order = Vec::with_capacity(renamed.len());
+                for (path, new_path) in draft.iter().zip(&renamed) {
+                    self.tags.update_path(path, new_path);
+                    let stem = new_path
+                        .file_stem()
+                        .and_then(|stem| stem.to_str())
+                        .unwrap_or_default();
+                    load_order.push(mod_base_name(stem).to_string());
+                }
+                self.load_order = load_order;
+                if let Err(e) = self.save_state() {
+                    warn!(error = %e, "Failed to save load order");
+                }
+            }
+            Err(e) => {
+                warn!(error = %e, "Failed to apply load order");
+                rfd::MessageDialog::new()
+                    .set_buttons(MessageButtons::Ok)
+                    .set_title("Failed to apply load order")
+                    .set_description(format!(
+                        "Failed to rename mods: {e}. No mod was changed. Make sure game is not running."
+                    ))
+                    .show();
+            }
+        }
+        self.collect_pak_files();
     }
 
-    fn update_tag_path(&mut self, old_path: &Path, new_path: &Path) {
-        if let Some(assignment) = self
-            .mod_tags
+    fn save_current_profile(&mut self) {
+        let name = self.new_profile_name.trim().to_string();
+        let profile = ModProfile {
+            name: name.clone(),
+            enabled_mods: self
+                .pak_files
+                .iter()
+                .filter(|entry| entry.enabled)
+                .map(|entry| profile_mod_name(&entry.path))
+                .collect(),
+        };
+        info!(profile = %name, enabled_mods = profile.enabled_mods.len(), "Saving mod profile");
+        match self
+            .mod_profiles
             .iter_mut()
-            .find(|assignment| same_mod_identity(&assignment.path, old_path))
+            .find(|existing| existing.name == name)
         {
-            assignment.path = new_path.to_path_buf();
-        }
-    }
-    fn config_path() -> PathBuf {
-        let mut path = dirs::config_dir()
-            .unwrap_or_else(|| PathBuf::from("."))
-            .join("repak_manager");
-        if !path.exists() {
-            if let Err(e) = fs::create_dir_all(&path) {
-                warn!(
-                    error = %e,
-                    "Failed to create config directory"
-                );
-            } else {
-                debug!("Created config directory");
+            Some(existing) => *existing = profile,
+            None => self.mod_profiles.push(profile),
+        }
+        self.new_profile_name.clear();
+        if let Err(e) = self.save_state() {
+            warn!(error = %e, "Failed to save profiles");
+        }
+    }
+
+    fn apply_mod_profile(&mut self, idx: usize) {
+        let Some(profile) = self.mod_profiles.get(idx).cloned() else {
+            return;
+        };
+        let installed = self
+            .pak_files
+            .iter()
+            .map(|entry| (entry.path.clone(), entry.enabled))
+            .collect::<Vec<_>>();
+        match apply_profile(&installed, &profile) {
+            Ok(renames) => {
+                for (old_path, new_path) in &renames {
+                    self.tags.update_path(old_path, new_path);
+                }
+                if let Err(e) = self.save_state() {
+                    warn!(error = %e, "Failed to save tag paths after applying profile");
+                }
+                self.collect_pak_files();
+            }
+            Err(e) => {
+                rfd::MessageDialog::new()
+                    .set_buttons(MessageButtons::Ok)
+                    .set_title("Failed to apply profile")
+                    .set_description(format!("{e}\nNo mods were changed."))
+                    .show();
             }
         }
+    }
 
-        path.push("repak_mod_manager.json");
+    fn category_options(&self) -> Vec<String> {
+        let mut categories = self
+            .pak_files
+            .iter()
+            .flat_map(|entry| std::iter::once(entry.category.clone()).chain(entry.asset_kinds()))
+            .filter(|category| category != "Pending")
+            .collect::<Vec<_>>();
+        categories.sort();
+        categories.dedup();
+        categories
+    }
 
-        path
+    /// Finishes or rolls back installs interrupted by a crash or a closed window.
+    fn recover_interrupted_installs(&self) {
+        let report = recover_interrupted_installs(&self.game_path, false);
+        if report.is_empty() {
+            return;
+        }
+        info!(
+            completed = report.completed.len(),
+            rolled_back = report.rolled_back.len(),
+            discarded = report.discarded,
+            errors = report.errors.len(),
+            "Recovered interrupted installs"
+        );
+        if report.rolled_back.is_empty() && report.errors.is_empty() {
+            return;
+        }
+
+        let mut description = String::new();
+        if !report.rolled_back.is_empty() {
+            description.push_str(&format!(
+                "These installs were interrupted and undone, reinstall them:\n{}\n",
+                report.rolled_back.join("\n")
+            ));
+        }
+        if !report.errors.is_empty() {
+            description.push_str(&format!(
+                "\nSome interrupted installs could not be recovered:\n{}",
+                report.errors.join("\n")
+            ));
+        }
+        rfd::MessageDialog::new()
+            .set_buttons(MessageButtons::Ok)
+            .set_title("Interrupted installs")
+            .set_description(description)
+            .show();
+    }
+
+    /// Moves mods disabled by older versions into the `disabled` folder, keeping their tags.
+    fn migrate_legacy_disabled_mods(&mut self) {
+        let migrated = migrate_legacy_disabled_mods(&self.game_path);
+        if migrated.is_empty() {
+            return;
+        }
+
+        for (old_path, new_path) in &migrated {
+            self.tags.update_path(old_path, new_path);
+        }
+        if let Err(e) = self.save_state() {
+            warn!(error = %e, "Failed to save tag paths after migrating disabled mods");
+        }
     }
 
     #[instrument(skip(ctx))]
@@ -1629,7 +2259,7 @@ impl RepakModManager {
         let (tx, rx) = channel();
         let (kawaii_tx, kawaii_rx) = channel();
         register_kawaii_runtime_error_sender(kawaii_tx);
-        let path = Self::config_path();
+        let path = config_path();
         let mut persist_config = false;
         let mut shit = if path.exists() {
             info!("Loading config");
@@ -1694,6 +2324,7 @@ impl RepakModManager {
         };
 
         if let Ok(ref mut shit) = shit {
+            shit.recover_interrupted_installs();
             shit.restart_game_path_watcher();
             shit.collect_pak_files();
             if persist_config {
@@ -1706,7 +2337,7 @@ impl RepakModManager {
         shit
     }
     fn save_state(&self) -> std::io::Result<()> {
-        let path = Self::config_path();
+        let path = config_path();
         let json = serde_json::to_string_pretty(self)?;
         info!("Saving config");
         fs::write(path, json)?;
@@ -2337,19 +2968,14 @@ impl RepakModManager {
         ctx.input(|i| {
             if !i.raw.dropped_files.is_empty() {
                 let dropped_files = i.raw.dropped_files.clone();
-                // Check if all files are either directories or have the .pak extension
+                // Check if all files are either directories, paks or archives
                 let all_valid = dropped_files.iter().all(|file| {
                     let path = file.path.clone().unwrap();
                     path.is_dir()
+                        || archive::is_archive(&path)
                         || path
                             .extension()
-                            .map(|ext| {
-                                matches!(
-                                    ext.to_str().map(|ext| ext.to_ascii_lowercase()).as_deref(),
-                                    Some("pak" | "7z" | "zip" | "rar")
-                                )
-                            })
-                            .unwrap_or(false)
+                            .is_some_and(|ext| ext.eq_ignore_ascii_case("pak"))
                 });
 
                 if all_valid {
@@ -2459,6 +3085,36 @@ impl RepakModManager {
                         &self.kawaii_physics_usmap,
                     ));
                 }
+                if ui
+                    .add_enabled(
+                        self.conflict_worker.is_none() && !self.pak_files.is_empty(),
+                        Button::new("Check mod conflicts"),
+                    )
+                    .on_hover_text("Find enabled mods that override the same assets")
+                    .clicked()
+                {
+                    ui.close_menu();
+                    self.start_conflict_check();
+                }
+                if ui
+                    .add_enabled(
+                        !self.pak_files.is_empty(),
+                        Button::new("Find duplicate mods"),
+                    )
+                    .on_hover_text("Find mods installed more than once under different names")
+                    .clicked()
+                {
+                    ui.close_menu();
+                    self.show_duplicates_window = true;
+                }
+                if ui
+                    .add_enabled(!self.pak_files.is_empty(), Button::new("Load order"))
+                    .on_hover_text("Choose which mod wins when mods override the same assets")
+                    .clicked()
+                {
+                    ui.close_menu();
+                    self.open_load_order_window();
+                }
                 ui.separator();
                 if ui.button("Select Mapping file").clicked() {
                     ui.close_menu();
@@ -2506,6 +3162,81 @@ impl RepakModManager {
                     ui.label("Show Character Details");
                     ui.add(ios_widget::toggle(&mut self.show_char_details));
                 });
+                ui.separator();
+                let db = skin_db();
+                ui.label(format!(
+                    "Skin data: revision {}{}, {} skins",
+                    db.revision,
+                    db.override_revision
+                        .map(|revision| format!(" + user revision {revision}"))
+                        .unwrap_or_default(),
+                    db.skin_count()
+                ));
+                if ui
+                    .button("Import skin data")
+                    .on_hover_text(
+                        "Add heroes and skins from a skin database, character_data.json or the MarvelRivalsCharacterIDs table",
+                    )
+                    .clicked()
+                {
+                    ui.close_menu();
+                    self.import_skin_data();
+                }
+            });
+
+            ui.menu_button("Profiles", |ui| {
+                if self.mod_profiles.is_empty() {
+                    ui.label("No profiles saved yet");
+                }
+                let mut apply = None;
+                let mut delete = None;
+                for (idx, profile) in self.mod_profiles.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        if ui
+                            .button(&profile.name)
+                            .on_hover_text(format!(
+                                "Enable {} mods and disable the rest",
+                                profile.enabled_mods.len()
+                            ))
+                            .clicked()
+                        {
+                            apply = Some(idx);
+                        }
+                        if ui.button("Delete").clicked() {
+                            delete = Some(idx);
+                        }
+                    });
+                }
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.add(
+                        TextEdit::singleline(&mut self.new_profile_name)
+                            .hint_text("Profile name")
+                            .desired_width(140.0),
+                    );
+                    if ui
+                        .add_enabled(
+                            !self.new_profile_name.trim().is_empty(),
+                            Button::new("Save current"),
+                        )
+                        .on_hover_text("Save the currently enabled mods as a profile")
+                        .clicked()
+                    {
+                        self.save_current_profile();
+                    }
+                });
+
+                if let Some(idx) = apply {
+                    ui.close_menu();
+                    self.apply_mod_profile(idx);
+                }
+                if let Some(idx) = delete {
+                    let profile = self.mod_profiles.remove(idx);
+                    info!(profile = %profile.name, "Deleted mod profile");
+                    if let Err(e) = self.save_state() {
+                        warn!(error = %e, "Failed to save profiles");
+                    }
+                }
             });
 
             if ui.button("Donate").clicked() {
@@ -2618,48 +3349,12 @@ impl RepakModManager {
             });
     }
 
-    #[instrument(skip(pak_path), fields(has_utoc = pak_path.with_extension("utoc").exists(), has_ucas = pak_path.with_extension("ucas").exists()))]
-    fn delete_mod_files(pak_path: &Path) -> std::io::Result<()> {
-        let utoc_path = pak_path.with_extension("utoc");
-        let ucas_path = pak_path.with_extension("ucas");
-        let files_to_delete = [pak_path.to_path_buf(), utoc_path, ucas_path];
-
-        info!("Deleting mod files");
-
-        for file in files_to_delete {
-            if !file.exists() {
-                debug!(
-                    file_ext = %file.extension().and_then(|ext| ext.to_str()).unwrap_or("<none>"),
-                    "Skipping missing companion file"
-                );
-                continue;
-            }
-
-            fs::remove_file(&file)?;
-            info!(
-                file_ext = %file.extension().and_then(|ext| ext.to_str()).unwrap_or("<none>"),
-                "Deleted companion file"
-            );
-        }
-
-        Ok(())
-    }
-
     #[instrument(skip(current_path), fields(enable_mod, source_ext = %current_path.extension().and_then(|ext| ext.to_str()).unwrap_or("<none>")))]
     fn toggle_mod_file(current_path: &PathBuf, enable_mod: bool) -> Option<PathBuf> {
-        let destination_path = if enable_mod {
-            current_path.with_extension("pak")
-        } else {
-            current_path.with_extension("bak_repak")
-        };
-
-        info!(
-            destination_ext = %destination_path.extension().and_then(|ext| ext.to_str()).unwrap_or("<none>"),
-            "Toggling mod"
-        );
+        info!("Toggling mod");
 
-        match std::fs::rename(current_path, &destination_path) {
-            Ok(_) => {
+        match set_mod_enabled(current_path, enable_mod) {
+            Ok(destination_path) => {
                 info!(
                     destination_ext = %destination_path.extension().and_then(|ext| ext.to_str()).unwrap_or("<none>"),
                     "Toggle complete"
@@ -2671,7 +3366,9 @@ impl RepakModManager {
                 rfd::MessageDialog::new()
                     .set_buttons(MessageButtons::Ok)
                     .set_title("Failed to toggle mod")
-                    .set_description("Failed to rename pak file. Make sure game is not running.")
+                    .set_description(format!(
+                        "Failed to move mod files: {e}\nMake sure game is not running."
+                    ))
                     .show();
                 None
             }
@@ -2690,6 +3387,10 @@ impl eframe::App for RepakModManager {
         self.process_kawaii_runtime_messages(ctx);
         self.show_kawaii_runtime_window(ctx);
         self.show_default_hidden_material_bitmap_creator(ctx);
+        self.process_conflict_worker(ctx);
+        self.show_conflicts_window(ctx);
+        self.show_duplicates_window(ctx);
+        self.show_load_order_window(ctx);
 
         if let Some(ref mut welcome) = self.welcome_screen {
             if !self.hide_welcome {
@@ -2864,128 +3565,24 @@ fn open_url(url: &str) -> Result<(), String> {
 }
 
 fn normalize_mod_display_name(name: &str) -> String {
-    name.replace("_9999999_P", "").replace("_999999_P", "")
+    mod_base_name(name).to_string()
 }
 
-fn classify_mod_metadata(job: &MetadataJob) -> Result<MetadataResult, String> {
-    let files = files_for_metadata(&job.path, job.is_iostore)?;
+fn classify_mod_metadata(job: &MetadataJob) -> Result<(MetadataResult, Vec<String>), String> {
+    let files = mod_files(&job.installed.path, job.installed.is_iostore)?;
     let file_count = Some(files.len());
-    Ok(MetadataResult {
+    let classification = classify_files(&files);
+    let result = MetadataResult {
         generation: job.generation,
         identity: job.identity.clone(),
-        signature: job.signature,
-        category: detect_mod_category(&files),
-        characteristic: get_current_pak_characteristics(files),
+        signature: job.installed.signature,
+        category: classification.category().to_string(),
+        characteristic: classification.characteristic(),
+        classification,
         obfuscated: job.obfuscated.clone(),
         file_count,
-    })
-}
-
-fn files_for_metadata(pak_path: &Path, is_iostore: bool) -> Result<Vec<String>, String> {
-    if is_iostore {
-        return read_utoc_package_names(&pak_path.with_extension("utoc"));
-    }
-
-    let mut builder = repak::PakBuilder::new();
-    builder = builder.key(AES_KEY.clone().0);
-    let file = File::open(pak_path).map_err(|e| format!("Failed to open mod: {e}"))?;
-    let pak = builder
-        .reader(&mut BufReader::new(file))
-        .map_err(|e| format!("Failed to read mod: {e}"))?;
-    Ok(pak.files())
-}
-
-fn mod_file_state(path: &Path) -> Option<(bool, bool)> {
-    match path.extension().and_then(|extension| extension.to_str()) {
-        Some("pak") => Some((true, true)),
-        Some("pak_disabled") | Some("bak_repak") => Some((false, true)),
-        _ => None,
-    }
-}
-
-fn mod_file_signature(path: &Path) -> Option<ModFileSignature> {
-    let mut size = 0u64;
-    let mut modified_secs = 0u64;
-    let companions = [
-        path.to_path_buf(),
-        path.with_extension("utoc"),
-        path.with_extension("ucas"),
-    ];
-
-    for companion in companions {
-        if !companion.exists() {
-            continue;
-        }
-        let metadata = fs::metadata(&companion).ok()?;
-        size = size.saturating_add(metadata.len());
-        let modified = metadata
-            .modified()
-            .ok()
-            .and_then(system_time_to_secs)
-            .unwrap_or_default();
-        modified_secs = modified_secs.max(modified);
-    }
-
-    Some(ModFileSignature {
-        size,
-        modified_secs,
-    })
-}
-
-fn system_time_to_secs(time: SystemTime) -> Option<u64> {
-    time.duration_since(UNIX_EPOCH)
-        .ok()
-        .map(|duration| duration.as_secs())
-}
-
-fn has_mod_suffix(name: &str) -> bool {
-    name.ends_with("_9999999_P") || name.ends_with("_999999_P")
-}
-
-fn detect_mod_category(files: &[String]) -> String {
-    if files.iter().any(|file| {
-        let name = Path::new(file)
-            .file_name()
-            .and_then(|name| name.to_str())
-            .unwrap_or_default()
-            .to_ascii_lowercase();
-        name.starts_with("sk_") || name.starts_with("sm_")
-    }) {
-        return "Mesh".to_string();
-    }
-
-    if files.iter().any(|file| file.contains("WwiseAudio")) {
-        return "Audio".to_string();
-    }
-
-    if files.iter().any(|file| {
-        file.strip_prefix("Marvel/Content/Marvel/")
-            .or_else(|| file.strip_prefix("/Game/Marvel/"))
-            .unwrap_or(file)
-            .starts_with("UI/")
-    }) {
-        return "UI".to_string();
-    }
-
-    if files.iter().any(|file| {
-        file.strip_prefix("Marvel/Content/Marvel/")
-            .or_else(|| file.strip_prefix("/Game/Marvel/"))
-            .unwrap_or(file)
-            .starts_with("Movies/")
-    }) {
-        return "Movies".to_string();
-    }
-
-    if files.iter().any(|file| {
-        file.strip_prefix("Marvel/Content/Marvel/")
-            .or_else(|| file.strip_prefix("/Game/Marvel/"))
-            .unwrap_or(file)
-            .starts_with("Characters/")
-    }) {
-        return "Texture".to_string();
-    }
-
-    "Other".to_string()
+    };
+    Ok((result, files))
 }
 
 fn filter_label(prefix: &str, selected: &[String]) -> String {
@@ -3014,6 +3611,11 @@ fn category_colors(category: &str) -> (Color32, Color32, Color32) {
         "Audio" => rgb3((213, 181, 255), (60, 42, 90), (111, 78, 164)),
         "UI" => rgb3((160, 203, 255), (31, 56, 88), (54, 105, 168)),
         "Movies" => rgb3((255, 171, 198), (82, 37, 52), (151, 68, 94)),
+        "Material" => rgb3((253, 224, 71), (74, 62, 18), (140, 118, 30)),
+        "Animation" => rgb3((134, 239, 172), (24, 70, 40), (45, 130, 75)),
+        "Physics" => rgb3((125, 211, 252), (20, 60, 84), (40, 118, 160)),
+        "Effect" => rgb3((253, 186, 116), (84, 44, 20), (160, 84, 34)),
+        "Map" => rgb3((196, 181, 253), (52, 40, 92), (100, 80, 170)),
         _ => rgb3((210, 210, 210), (54, 54, 54), (86, 86, 86)),
     }
 }
@@ -3069,10 +3671,6 @@ fn fnv1a(bytes: &[u8]) -> u64 {
     hash
 }
 
-fn same_mod_identity(left: &Path, right: &Path) -> bool {
-    normalized_mod_identity(left) == normalized_mod_identity(right)
-}
-
 fn parse_default_hidden_material_bitmaps(
     mode: DefaultHiddenMaterialMode,
     value: &str,
@@ -3143,24 +3741,3 @@ fn highest_set_bit(value: u64) -> Option<usize> {
         Some((u64::BITS - 1 - value.leading_zeros()) as usize)
     }
 }
-
-fn normalized_mod_identity(path: &Path) -> PathBuf {
-    let parent = path.parent().unwrap_or_else(|| Path::new(""));
-    let file_name = path
-        .file_name()
-        .and_then(|name| name.to_str())
-        .unwrap_or_default();
-    let stem = file_name
-        .strip_suffix(".bak_repak")
-        .or_else(|| file_name.strip_suffix(".pak_disabled"))
-        .or_else(|| file_name.strip_suffix(".pak"))
-        .unwrap_or(file_name);
-
-    parent.join(stem)
-}
-
-fn normalized_mod_identity_string(path: &Path) -> String {
-    normalized_mod_identity(path)
-        .to_string_lossy()
-        .to_ascii_lowercase()
-}
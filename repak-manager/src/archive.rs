@@ -0,0 +1,1340 @@
+//! Extraction of downloaded mod archives, shared by repak-gui and retoc-rivals-cli.
+//!
+//! Mod archives come from untrusted sources, so every entry is checked before anything is
+//! written: names must stay inside the output directory, symlinks and hard links are rejected,
+//! and the number of files and the bytes written are capped by [`ExtractLimits`].
+//!
+//! Multi-GB archives are inspected with [`list_archive`] and [`find_payloads`] first, so only the
+//! entries of the mods the user picks are extracted with [`extract_selected`].
+
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::ffi::OsStr;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Component, Path, PathBuf};
+use tempfile::TempDir;
+use tracing::{debug, info};
+
+/// Archive formats mods are distributed in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArchiveKind {
+    Zip,
+    Rar,
+    SevenZ,
+    Tar,
+    TarGz,
+    TarZst,
+}
+
+impl ArchiveKind {
+    /// The format of `path` by its extension, `None` for anything that isn't an archive.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_str()?.to_ascii_lowercase();
+        if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            return Some(ArchiveKind::TarGz);
+        }
+        if name.ends_with(".tar.zst") || name.ends_with(".tzst") {
+            return Some(ArchiveKind::TarZst);
+        }
+        match name.rsplit_once('.')?.1 {
+            "zip" => Some(ArchiveKind::Zip),
+            "rar" => Some(ArchiveKind::Rar),
+            "7z" => Some(ArchiveKind::SevenZ),
+            "tar" => Some(ArchiveKind::Tar),
+            _ => None,
+        }
+    }
+}
+
+/// Whether `path` has the extension of a supported archive.
+pub fn is_archive(path: &Path) -> bool {
+    ArchiveKind::from_path(path).is_some()
+}
+
+/// File name of `path` without its archive extension, `.tar.gz` included.
+pub fn archive_stem(path: &Path) -> Option<&str> {
+    let name = path.file_name()?.to_str()?;
+    let lower = name.to_ascii_lowercase();
+    let ext_len = [".tar.gz", ".tar.zst"]
+        .iter()
+        .find(|ext| lower.ends_with(*ext))
+        .map(|ext| ext.len());
+    match ext_len {
+        Some(len) => Some(&name[..name.len() - len]),
+        None => path.file_stem()?.to_str(),
+    }
+}
+
+/// One entry of an archive, read from its headers without extracting it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ArchiveEntry {
+    /// Path the entry extracts to, relative to the output directory.
+    pub path: PathBuf,
+    /// Uncompressed size as the archive claims it.
+    pub size: u64,
+    pub is_dir: bool,
+}
+
+/// A mod found in an archive listing, by the entries it needs.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ArchivePayload {
+    /// `.pak`, `.utoc` and `.ucas` sharing a stem in one folder.
+    IoStore {
+        pak: PathBuf,
+        utoc: PathBuf,
+        ucas: PathBuf,
+    },
+    /// A `.pak` without IoStore containers.
+    Pak(PathBuf),
+    /// Loose assets below `root`, to be packed into a pak. Containers below `root` are their own
+    /// payloads and not part of it.
+    Loose { root: PathBuf, files: Vec<PathBuf> },
+}
+
+/// Folder below the output directory of [`extract_payloads`] holding packaged payloads.
+const PACKAGES_DIR: &str = "packages";
+/// Folder below the output directory of [`extract_payloads`] holding loose payloads.
+const LOOSE_DIR: &str = "loose";
+
+impl ArchivePayload {
+    /// The archive entries of this payload.
+    pub fn entries(&self) -> Vec<&Path> {
+        match self {
+            ArchivePayload::IoStore { pak, utoc, ucas } => vec![pak, utoc, ucas],
+            ArchivePayload::Pak(pak) => vec![pak],
+            ArchivePayload::Loose { files, .. } => files.iter().map(PathBuf::as_path).collect(),
+        }
+    }
+
+    /// Stem of the payload's pak or name of its loose asset folder, `None` for loose assets at
+    /// the archive root.
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            ArchivePayload::IoStore { pak, .. } | ArchivePayload::Pak(pak) => {
+                pak.file_stem()?.to_str()
+            }
+            ArchivePayload::Loose { root, .. } => root.file_name()?.to_str(),
+        }
+    }
+
+    /// Folder of the payload's pak, or the root of its loose assets.
+    pub fn folder(&self) -> &Path {
+        match self {
+            ArchivePayload::IoStore { pak, .. } | ArchivePayload::Pak(pak) => {
+                pak.parent().unwrap_or(Path::new(""))
+            }
+            ArchivePayload::Loose { root, .. } => root,
+        }
+    }
+
+    /// The paths of a loose payload's assets below its root, for [`find_variant_groups`].
+    /// Packaged payloads list nothing here: their assets are only known from their index, once
+    /// extracted.
+    pub fn listed_assets(&self) -> Vec<String> {
+        match self {
+            ArchivePayload::IoStore { .. } | ArchivePayload::Pak(_) => Vec::new(),
+            ArchivePayload::Loose { root, files } => files
+                .iter()
+                .filter_map(|file| file.strip_prefix(root).ok())
+                .map(|file| file.to_string_lossy().into_owned())
+                .collect(),
+        }
+    }
+
+    /// Where `entry` of this payload is extracted to, relative to the output directory.
+    /// Packaged and loose payloads go to separate folders, so the folder of loose assets never
+    /// holds containers that sat next to them in the archive.
+    pub fn destination(&self, entry: &Path) -> PathBuf {
+        match self {
+            ArchivePayload::Loose { root, .. } => {
+                Path::new(LOOSE_DIR).join(entry.strip_prefix(root).unwrap_or(entry))
+            }
+            _ => Path::new(PACKAGES_DIR).join(entry),
+        }
+    }
+
+    /// Where the payload is once extracted into `output_dir`: the `.pak` of packaged payloads,
+    /// the asset folder of loose ones.
+    pub fn extracted_path(&self, output_dir: &Path) -> PathBuf {
+        match self {
+            ArchivePayload::IoStore { pak, .. } | ArchivePayload::Pak(pak) => {
+                output_dir.join(self.destination(pak))
+            }
+            ArchivePayload::Loose { .. } => output_dir.join(LOOSE_DIR),
+        }
+    }
+}
+
+/// Caps on what one archive may extract.
+#[derive(Clone, Copy, Debug)]
+pub struct ExtractLimits {
+    /// Total uncompressed bytes.
+    pub max_total_size: u64,
+    /// Number of files, directories not counted.
+    pub max_file_count: usize,
+}
+
+impl Default for ExtractLimits {
+    fn default() -> Self {
+        Self {
+            max_total_size: 16 * 1024 * 1024 * 1024,
+            max_file_count: 50_000,
+        }
+    }
+}
+
+/// Reported after each extracted file.
+#[derive(Clone, Copy, Debug)]
+pub struct ExtractProgress<'a> {
+    /// Path of the file relative to the output directory.
+    pub path: &'a Path,
+    pub size: u64,
+    /// Files extracted so far, this one included.
+    pub files: usize,
+    /// Bytes extracted so far, this file included.
+    pub bytes: u64,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ArchiveError {
+    #[error("unsupported archive: {}", .0.display())]
+    Unsupported(PathBuf),
+
+    #[error("entry {0:?} would be written outside the output directory")]
+    UnsafePath(String),
+
+    #[error("entry {0:?} is a link, which mod archives can't contain")]
+    Link(String),
+
+    #[error("archive extracts to more than {limit} bytes")]
+    TooLarge { limit: u64 },
+
+    #[error("archive contains more than {limit} files")]
+    TooManyFiles { limit: usize },
+
+    #[error(transparent)]
+    Io(#[from] io::Error),
+
+    #[error(transparent)]
+    Zip(#[from] zip::result::ZipError),
+
+    #[error(transparent)]
+    Rar(#[from] unrar::error::UnrarError),
+
+    #[error(transparent)]
+    SevenZ(#[from] sevenz_rust2::Error),
+}
+
+/// Extracts `path` into a new temporary directory.
+pub fn extract_to_temp(
+    path: &Path,
+    limits: &ExtractLimits,
+    progress: &mut dyn FnMut(ExtractProgress),
+) -> Result<TempDir, ArchiveError> {
+    let temp = tempfile::tempdir()?;
+    extract_archive(path, temp.path(), limits, progress)?;
+    Ok(temp)
+}
+
+/// Extracts `path` into `output_dir`. On error, files extracted before it are left behind.
+pub fn extract_archive(
+    path: &Path,
+    output_dir: &Path,
+    limits: &ExtractLimits,
+    progress: &mut dyn FnMut(ExtractProgress),
+) -> Result<(), ArchiveError> {
+    extract_selected(
+        path,
+        output_dir,
+        limits,
+        &|entry| Some(entry.to_path_buf()),
+        progress,
+    )
+}
+
+/// Extracts the entries of `payloads` from `path` into `output_dir`, each to its
+/// [`ArchivePayload::destination`].
+pub fn extract_payloads(
+    path: &Path,
+    output_dir: &Path,
+    payloads: &[&ArchivePayload],
+    limits: &ExtractLimits,
+    progress: &mut dyn FnMut(ExtractProgress),
+) -> Result<(), ArchiveError> {
+    let destinations = payloads
+        .iter()
+        .flat_map(|payload| {
+            payload
+                .entries()
+                .into_iter()
+                .map(|entry| (entry, payload.destination(entry)))
+        })
+        .collect::<HashMap<_, _>>();
+    extract_selected(
+        path,
+        output_dir,
+        limits,
+        &|entry| destinations.get(entry).cloned(),
+        progress,
+    )
+}
+
+/// Extracts entries of `path` into `output_dir`. `select` maps the relative path of each entry
+/// to the relative path to write it to, which must come from a checked entry path, or to `None`
+/// to skip the entry. Directories are only created when selected or as parents of files.
+pub fn extract_selected(
+    path: &Path,
+    output_dir: &Path,
+    limits: &ExtractLimits,
+    select: &dyn Fn(&Path) -> Option<PathBuf>,
+    progress: &mut dyn FnMut(ExtractProgress),
+) -> Result<(), ArchiveError> {
+    let kind =
+        ArchiveKind::from_path(path).ok_or_else(|| ArchiveError::Unsupported(path.into()))?;
+    info!(archive = %path.display(), ?kind, output = %output_dir.display(), "Extracting archive");
+    let mut extractor = Extractor {
+        output_dir,
+        limits,
+        select,
+        progress,
+        files: 0,
+        bytes: 0,
+    };
+    match kind {
+        ArchiveKind::Zip => extractor.zip(path)?,
+        ArchiveKind::Rar => extractor.rar(path)?,
+        ArchiveKind::SevenZ => extractor.seven_z(path)?,
+        ArchiveKind::Tar => extractor.tar(File::open(path)?)?,
+        ArchiveKind::TarGz => extractor.tar(flate2::read::GzDecoder::new(File::open(path)?))?,
+        ArchiveKind::TarZst => extractor.tar(zstd::Decoder::new(File::open(path)?)?)?,
+    }
+    debug!(
+        archive = %path.display(),
+        files = extractor.files,
+        bytes = extractor.bytes,
+        "Finished extracting archive"
+    );
+    Ok(())
+}
+
+/// Lists the entries of `path` from the zip central directory or the RAR, 7z or tar headers.
+/// Tar has no index, so compressed tar archives are still decompressed, but nothing is written.
+/// Entries are checked as [`extract_archive`] checks them, so a listed archive extracts cleanly.
+pub fn list_archive(path: &Path) -> Result<Vec<ArchiveEntry>, ArchiveError> {
+    let kind =
+        ArchiveKind::from_path(path).ok_or_else(|| ArchiveError::Unsupported(path.into()))?;
+    debug!(archive = %path.display(), ?kind, "Listing archive");
+    let mut entries = Vec::new();
+    match kind {
+        ArchiveKind::Zip => {
+            let mut archive = zip::ZipArchive::new(File::open(path)?)?;
+            for index in 0..archive.len() {
+                let file = archive.by_index_raw(index)?;
+                if file.is_symlink() {
+                    return Err(ArchiveError::Link(file.name().to_string()));
+                }
+                push_entry(&mut entries, file.name(), file.is_dir(), file.size())?;
+            }
+        }
+        ArchiveKind::Rar => {
+            for header in unrar::Archive::new(path).open_for_listing()? {
+                let header = header?;
+                let name = header.filename.to_string_lossy();
+                if is_rar_link(header.file_attr) {
+                    return Err(ArchiveError::Link(name.into_owned()));
+                }
+                push_entry(
+                    &mut entries,
+                    &name,
+                    header.is_directory(),
+                    header.unpacked_size,
+                )?;
+            }
+        }
+        ArchiveKind::SevenZ => {
+            let archive = sevenz_rust2::Archive::open(path)?;
+            for entry in archive.files.iter().filter(|entry| !entry.is_anti_item) {
+                if entry.has_windows_attributes && is_7z_link(entry.windows_attributes) {
+                    return Err(ArchiveError::Link(entry.name().to_string()));
+                }
+                push_entry(
+                    &mut entries,
+                    entry.name(),
+                    entry.is_directory(),
+                    entry.size(),
+                )?;
+            }
+        }
+        ArchiveKind::Tar => list_tar(File::open(path)?, &mut entries)?,
+        ArchiveKind::TarGz => list_tar(
+            flate2::read::GzDecoder::new(File::open(path)?),
+            &mut entries,
+        )?,
+        ArchiveKind::TarZst => list_tar(zstd::Decoder::new(File::open(path)?)?, &mut entries)?,
+    }
+    Ok(entries)
+}
+
+fn list_tar(reader: impl Read, entries: &mut Vec<ArchiveEntry>) -> Result<(), ArchiveError> {
+    let mut archive = tar::Archive::new(reader);
+    for entry in archive.entries()? {
+        let entry = entry?;
+        let name = String::from_utf8_lossy(&entry.path_bytes()).into_owned();
+        let entry_type = entry.header().entry_type();
+        if entry_type.is_symlink() || entry_type.is_hard_link() {
+            return Err(ArchiveError::Link(name));
+        }
+        if entry_type.is_dir() || entry_type.is_file() {
+            push_entry(entries, &name, entry_type.is_dir(), entry.header().size()?)?;
+        }
+    }
+    Ok(())
+}
+
+fn push_entry(
+    entries: &mut Vec<ArchiveEntry>,
+    name: &str,
+    is_dir: bool,
+    size: u64,
+) -> Result<(), ArchiveError> {
+    let path = safe_entry_path(name)?;
+    if path.as_os_str().is_empty() {
+        // the archive root, harmless as a directory but not as a file
+        return if is_dir {
+            Ok(())
+        } else {
+            Err(ArchiveError::UnsafePath(name.to_string()))
+        };
+    }
+    entries.push(ArchiveEntry { path, size, is_dir });
+    Ok(())
+}
+
+/// Finds the mods in an archive listing: every IoStore container set and legacy pak, and loose
+/// assets. Loose assets are packed relative to the folder holding their `Marvel/Content`, so each
+/// such folder with `.uasset` files is a mod of its own, as archives shipping options have one per
+/// option. Loose files outside of these folders belong to the payload root, the only top-level
+/// folder of archives that wrap everything in one or else the archive root, and are a mod if
+/// there are `.uasset` files among them.
+pub fn find_payloads(entries: &[ArchiveEntry]) -> Vec<ArchivePayload> {
+    let mut containers: BTreeMap<(&Path, &str), [Option<&Path>; 3]> = BTreeMap::new();
+    let mut loose = Vec::new();
+    for entry in entries.iter().filter(|entry| !entry.is_dir) {
+        let path = entry.path.as_path();
+        let (Some(parent), Some(stem)) = (path.parent(), path.file_stem().and_then(|s| s.to_str()))
+        else {
+            continue;
+        };
+        let slot = match lower_extension(path).as_deref() {
+            Some("pak") => 0,
+            Some("utoc") => 1,
+            Some("ucas") => 2,
+            _ => {
+                loose.push(path);
+                continue;
+            }
+        };
+        containers.entry((parent, stem)).or_default()[slot] = Some(path);
+    }
+
+    let root = payload_root(entries);
+    let asset_roots = loose
+        .iter()
+        .filter_map(|path| asset_root(path))
+        .collect::<BTreeSet<_>>();
+    let mut loose_payloads: BTreeMap<PathBuf, Vec<PathBuf>> = BTreeMap::new();
+    for path in loose {
+        // roots sort before the folders inside them, so the last match is the innermost one
+        let asset_root = asset_roots.iter().rev().find(|root| path.starts_with(root));
+        loose_payloads
+            .entry(asset_root.cloned().unwrap_or_else(|| root.clone()))
+            .or_default()
+            .push(path.to_path_buf());
+    }
+
+    let mut payloads = loose_payloads
+        .into_iter()
+        .filter(|(_, files)| {
+            files
+                .iter()
+                .any(|path| lower_extension(path).as_deref() == Some("uasset"))
+        })
+        .map(|(root, files)| ArchivePayload::Loose { root, files })
+        .collect::<Vec<_>>();
+    for files in containers.into_values() {
+        payloads.push(match files {
+            [Some(pak), Some(utoc), Some(ucas)] => ArchivePayload::IoStore {
+                pak: pak.to_path_buf(),
+                utoc: utoc.to_path_buf(),
+                ucas: ucas.to_path_buf(),
+            },
+            [Some(pak), ..] => ArchivePayload::Pak(pak.to_path_buf()),
+            // containers without a pak can't be installed
+            _ => continue,
+        });
+    }
+    payloads
+}
+
+/// The folder holding the `Marvel/Content` folder `path` is in.
+fn asset_root(path: &Path) -> Option<PathBuf> {
+    let components = path.components().collect::<Vec<_>>();
+    let index = components.windows(2).position(|pair| {
+        pair[0].as_os_str().eq_ignore_ascii_case("Marvel")
+            && pair[1].as_os_str().eq_ignore_ascii_case("Content")
+    })?;
+    Some(components[..index].iter().collect())
+}
+
+/// Names of the mods `payloads` of the archive at `archive_path` install as: the pak stem of
+/// packaged payloads and the archive's name for loose assets, followed by their folder's when the
+/// archive has more than one loose payload.
+pub fn payload_mod_names(archive_path: &Path, payloads: &[ArchivePayload]) -> Vec<String> {
+    let archive_name = archive_stem(archive_path).unwrap_or("mod");
+    let loose_payloads = payloads
+        .iter()
+        .filter(|payload| matches!(payload, ArchivePayload::Loose { .. }))
+        .count();
+    payloads
+        .iter()
+        .map(|payload| match (payload, payload.name()) {
+            (ArchivePayload::Loose { .. }, Some(folder)) if loose_payloads > 1 => {
+                format!("{archive_name}_{folder}")
+            }
+            (ArchivePayload::Loose { .. }, _) | (_, None) => archive_name.to_string(),
+            (_, Some(stem)) => stem.to_string(),
+        })
+        .collect()
+}
+
+/// Mutually exclusive options of a mod in one archive: sibling folders whose payloads package
+/// the same assets, like `Option A/` and `Option B/`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VariantGroup {
+    /// Folder holding the option folders, relative to the archive root.
+    pub parent: PathBuf,
+    pub variants: Vec<Variant>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Variant {
+    /// Name of the option's folder.
+    pub name: String,
+    /// Indices of the option's payloads in the list the group was found in.
+    pub payloads: Vec<usize>,
+}
+
+impl VariantGroup {
+    /// `parent` for display, `archive root` for options at the top of the archive.
+    pub fn label(&self) -> String {
+        if self.parent.as_os_str().is_empty() {
+            "archive root".to_string()
+        } else {
+            self.parent.display().to_string()
+        }
+    }
+
+    /// The variant named `name`, ignoring case.
+    pub fn variant(&self, name: &str) -> Option<&Variant> {
+        self.variants
+            .iter()
+            .find(|variant| variant.name.eq_ignore_ascii_case(name))
+    }
+}
+
+/// Groups `payloads` into options. `assets` holds the assets each payload packages, as paths
+/// in any form the payloads share: extensions and case are ignored. Two payloads in different
+/// folders sharing an asset make the folders below their closest common folder options of one
+/// group, and every payload inside those folders belongs to its option. A payload belongs to one
+/// group at most, the innermost. Payloads outside of any group are meant to be installed with
+/// whichever option is picked.
+pub fn find_variant_groups(
+    payloads: &[ArchivePayload],
+    assets: &[Vec<String>],
+) -> Vec<VariantGroup> {
+    let keys = assets
+        .iter()
+        .map(|assets| {
+            assets
+                .iter()
+                .map(|asset| asset_key(asset))
+                .collect::<HashSet<_>>()
+        })
+        .collect::<Vec<_>>();
+    let folders = payloads
+        .iter()
+        .map(ArchivePayload::folder)
+        .collect::<Vec<_>>();
+
+    let mut options: BTreeMap<PathBuf, BTreeSet<&OsStr>> = BTreeMap::new();
+    for (i, j) in (0..payloads.len()).flat_map(|i| (i + 1..payloads.len()).map(move |j| (i, j))) {
+        if keys[i].is_disjoint(&keys[j]) {
+            continue;
+        }
+        let parent = folders[i]
+            .components()
+            .zip(folders[j].components())
+            .take_while(|(left, right)| left == right)
+            .map(|(component, _)| component)
+            .collect::<PathBuf>();
+        if let (Some(left), Some(right)) = (
+            option_folder(folders[i], &parent),
+            option_folder(folders[j], &parent),
+        ) {
+            options.entry(parent).or_default().extend([left, right]);
+        }
+    }
+
+    let mut options = options.into_iter().collect::<Vec<_>>();
+    options.sort_by_key(|(parent, _)| Reverse(parent.components().count()));
+    let mut grouped = vec![false; payloads.len()];
+    let mut groups = Vec::new();
+    for (parent, names) in options {
+        let mut variants = names
+            .into_iter()
+            .map(|name| (name, Vec::new()))
+            .collect::<BTreeMap<_, _>>();
+        for (index, folder) in folders.iter().enumerate() {
+            if grouped[index] {
+                continue;
+            }
+            if let Some(payloads) =
+                option_folder(folder, &parent).and_then(|name| variants.get_mut(name))
+            {
+                payloads.push(index);
+            }
+        }
+        variants.retain(|_, payloads| !payloads.is_empty());
+        if variants.len() < 2 {
+            continue;
+        }
+        for index in variants.values().flatten() {
+            grouped[*index] = true;
+        }
+        groups.push(VariantGroup {
+            parent,
+            variants: variants
+                .into_iter()
+                .map(|(name, payloads)| Variant {
+                    name: name.to_string_lossy().into_owned(),
+                    payloads,
+                })
+                .collect(),
+        });
+    }
+    groups.sort_by(|left, right| left.parent.cmp(&right.parent));
+    groups
+}
+
+/// The folder directly below `parent` that `folder` is in, `None` for `parent` itself.
+fn option_folder<'a>(folder: &'a Path, parent: &Path) -> Option<&'a OsStr> {
+    match folder.strip_prefix(parent).ok()?.components().next()? {
+        Component::Normal(name) => Some(name),
+        _ => None,
+    }
+}
+
+fn asset_key(asset: &str) -> String {
+    let asset = asset.replace('\\', "/").to_ascii_lowercase();
+    let asset = asset.trim_start_matches('/');
+    match asset.rsplit_once('.') {
+        Some((stem, ext)) if !ext.contains('/') => stem.to_string(),
+        _ => asset.to_string(),
+    }
+}
+
+fn payload_root(entries: &[ArchiveEntry]) -> PathBuf {
+    let mut top_level = entries.iter().map(|entry| {
+        let mut components = entry.path.components();
+        let first = components.next();
+        (first, entry.is_dir || components.next().is_some())
+    });
+    let Some((Some(first), true)) = top_level.next() else {
+        return PathBuf::new();
+    };
+    if top_level.all(|(component, in_dir)| component == Some(first) && in_dir) {
+        PathBuf::from(first.as_os_str())
+    } else {
+        PathBuf::new()
+    }
+}
+
+fn lower_extension(path: &Path) -> Option<String> {
+    path.extension()?
+        .to_str()
+        .map(|ext| ext.to_ascii_lowercase())
+}
+
+/// The relative path an entry name is extracted to, empty for the output directory itself.
+/// Absolute names, drive prefixes, `:` and `..` components are rejected instead of stripped, as
+/// such archives are broken or malicious.
+pub fn safe_entry_path(name: &str) -> Result<PathBuf, ArchiveError> {
+    let unsafe_path = || ArchiveError::UnsafePath(name.to_string());
+    let normalized = name.replace('\\', "/");
+    if normalized.starts_with('/') || normalized.contains(':') {
+        return Err(unsafe_path());
+    }
+    let mut path = PathBuf::new();
+    for component in Path::new(&normalized).components() {
+        match component {
+            Component::Normal(part) => path.push(part),
+            Component::CurDir => {}
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => {
+                return Err(unsafe_path())
+            }
+        }
+    }
+    Ok(path)
+}
+
+struct Extractor<'a> {
+    output_dir: &'a Path,
+    limits: &'a ExtractLimits,
+    select: &'a dyn Fn(&Path) -> Option<PathBuf>,
+    progress: &'a mut dyn FnMut(ExtractProgress),
+    files: usize,
+    bytes: u64,
+}
+
+impl Extractor<'_> {
+    fn zip(&mut self, path: &Path) -> Result<(), ArchiveError> {
+        let mut archive = zip::ZipArchive::new(File::open(path)?)?;
+        for index in 0..archive.len() {
+            let mut file = archive.by_index(index)?;
+            let name = file.name().to_string();
+            if file.is_symlink() {
+                return Err(ArchiveError::Link(name));
+            }
+            if let Some(rel_path) = (self.select)(&safe_entry_path(&name)?) {
+                let (is_dir, size) = (file.is_dir(), file.size());
+                self.entry(&rel_path, is_dir, size, &mut file)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn rar(&mut self, path: &Path) -> Result<(), ArchiveError> {
+        let mut archive = unrar::Archive::new(path).open_for_processing()?;
+        while let Some(header) = archive.read_header()? {
+            let entry = header.entry();
+            let name = entry.filename.to_string_lossy().into_owned();
+            if is_rar_link(entry.file_attr) {
+                return Err(ArchiveError::Link(name));
+            }
+            let Some(rel_path) = (self.select)(&safe_entry_path(&name)?) else {
+                archive = header.skip()?;
+                continue;
+            };
+            let output = self.output_dir.join(&rel_path);
+            archive = if entry.is_directory() {
+                fs::create_dir_all(&output)?;
+                header.skip()?
+            } else {
+                if rel_path.as_os_str().is_empty() {
+                    return Err(ArchiveError::UnsafePath(name));
+                }
+                // unrar writes the file itself, so the limit is checked against the header size
+                // before and against the size of the written file after, which a forged header
+                // can't understate
+                self.reserve(entry.unpacked_size)?;
+                if let Some(parent) = output.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                let archive = header.extract_to(&output)?;
+                let size = fs::metadata(&output)?.len();
+                self.reserve(size)?;
+                self.report(&rel_path, size);
+                archive
+            };
+        }
+        Ok(())
+    }
+
+    fn seven_z(&mut self, path: &Path) -> Result<(), ArchiveError> {
+        // errors of the callback can't be returned through sevenz_rust2, so they stop the
+        // extraction and are returned afterwards
+        let mut failure = None;
+        sevenz_rust2::decompress_file_with_extract_fn(
+            path,
+            self.output_dir,
+            |entry, reader, _| {
+                if entry.is_anti_item {
+                    return Ok(true);
+                }
+                let is_link = entry.has_windows_attributes && is_7z_link(entry.windows_attributes);
+                let result = if is_link {
+                    Err(ArchiveError::Link(entry.name().to_string()))
+                } else {
+                    safe_entry_path(entry.name()).and_then(|rel_path| {
+                        if let Some(rel_path) = (self.select)(&rel_path) {
+                            self.entry(&rel_path, entry.is_directory(), entry.size(), reader)
+                        } else {
+                            // entries of a solid block share one stream, so a skipped entry
+                            // is still read through
+                            io::copy(reader, &mut io::sink())?;
+                            Ok(())
+                        }
+                    })
+                };
+                match result {
+                    Ok(()) => Ok(true),
+                    Err(e) => {
+                        failure = Some(e);
+                        Ok(false)
+                    }
+                }
+            },
+        )?;
+        failure.map_or(Ok(()), Err)
+    }
+
+    fn tar(&mut self, reader: impl Read) -> Result<(), ArchiveError> {
+        let mut archive = tar::Archive::new(reader);
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            let name = String::from_utf8_lossy(&entry.path_bytes()).into_owned();
+            let entry_type = entry.header().entry_type();
+            if entry_type.is_symlink() || entry_type.is_hard_link() {
+                return Err(ArchiveError::Link(name));
+            }
+            if entry_type.is_dir() || entry_type.is_file() {
+                if let Some(rel_path) = (self.select)(&safe_entry_path(&name)?) {
+                    let size = entry.header().size()?;
+                    self.entry(&rel_path, entry_type.is_dir(), size, &mut entry)?;
+                }
+            } else {
+                debug!(entry = %name, ?entry_type, "Skipping special tar entry");
+            }
+        }
+        Ok(())
+    }
+
+    /// Creates a directory entry or writes a file entry.
+    fn entry(
+        &mut self,
+        rel_path: &Path,
+        is_dir: bool,
+        size: u64,
+        reader: &mut dyn Read,
+    ) -> Result<(), ArchiveError> {
+        if is_dir {
+            fs::create_dir_all(self.output_dir.join(rel_path))?;
+            Ok(())
+        } else {
+            self.write_file(rel_path, size, reader)
+        }
+    }
+
+    /// Writes one file, stopping once the bytes written exceed the limit whatever size the
+    /// entry claims.
+    fn write_file(
+        &mut self,
+        rel_path: &Path,
+        size: u64,
+        reader: &mut dyn Read,
+    ) -> Result<(), ArchiveError> {
+        if rel_path.as_os_str().is_empty() {
+            return Err(ArchiveError::UnsafePath(String::new()));
+        }
+        self.reserve(size)?;
+        let output = self.output_dir.join(rel_path);
+        if let Some(parent) = output.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let remaining = self.limits.max_total_size - self.bytes;
+        let mut file = File::create(&output)?;
+        let written = io::copy(&mut reader.take(remaining.saturating_add(1)), &mut file)?;
+        file.flush()?;
+        if written > remaining {
+            return Err(ArchiveError::TooLarge {
+                limit: self.limits.max_total_size,
+            });
+        }
+        self.report(rel_path, written);
+        Ok(())
+    }
+
+    /// Checks that one more file of `size` bytes fits the limits.
+    fn reserve(&self, size: u64) -> Result<(), ArchiveError> {
+        if self.files >= self.limits.max_file_count {
+            return Err(ArchiveError::TooManyFiles {
+                limit: self.limits.max_file_count,
+            });
+        }
+        if self.bytes.saturating_add(size) > self.limits.max_total_size {
+            return Err(ArchiveError::TooLarge {
+                limit: self.limits.max_total_size,
+            });
+        }
+        Ok(())
+    }
+
+    fn report(&mut self, rel_path: &Path, size: u64) {
+        self.files += 1;
+        self.bytes += size;
+        (self.progress)(ExtractProgress {
+            path: rel_path,
+            size,
+            files: self.files,
+            bytes: self.bytes,
+        });
+    }
+}
+
+const FILE_ATTRIBUTE_REPARSE_POINT: u32 = 0x400;
+/// Set by 7-Zip when the high 16 bits of the attributes hold a Unix mode.
+const FILE_ATTRIBUTE_UNIX_EXTENSION: u32 = 0x8000;
+const S_IFMT: u32 = 0o170000;
+const S_IFLNK: u32 = 0o120000;
+
+/// Whether RAR file attributes, Windows attributes or a Unix mode depending on the host that
+/// made the archive, mark a link.
+fn is_rar_link(attributes: u32) -> bool {
+    attributes & FILE_ATTRIBUTE_REPARSE_POINT != 0 || attributes & S_IFMT == S_IFLNK
+}
+
+/// Whether 7z file attributes mark a link.
+fn is_7z_link(attributes: u32) -> bool {
+    attributes & FILE_ATTRIBUTE_REPARSE_POINT != 0
+        || (attributes & FILE_ATTRIBUTE_UNIX_EXTENSION != 0
+            && (attributes >> 16) & S_IFMT == S_IFLNK)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn zip_archive(dir: &Path, build: impl FnOnce(&mut zip::ZipWriter<File>)) -> PathBuf {
+        let path = dir.join("mod.zip");
+        let mut writer = zip::ZipWriter::new(File::create(&path).unwrap());
+        build(&mut writer);
+        writer.finish().unwrap();
+        path
+    }
+
+    fn tar_archive(dir: &Path, entry_type: tar::EntryType) -> PathBuf {
+        let path = dir.join("mod.tar");
+        let mut builder = tar::Builder::new(File::create(&path).unwrap());
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(entry_type);
+        header.set_size(0);
+        builder
+            .append_link(&mut header, "Mod/link.uasset", "/etc/passwd")
+            .unwrap();
+        builder.finish().unwrap();
+        path
+    }
+
+    /// Listing entries, directories marked by a trailing `/`.
+    fn listing(paths: &[&str]) -> Vec<ArchiveEntry> {
+        paths
+            .iter()
+            .map(|path| ArchiveEntry {
+                path: PathBuf::from(path.trim_end_matches('/')),
+                size: 1,
+                is_dir: path.ends_with('/'),
+            })
+            .collect()
+    }
+
+    fn loose(root: &str, files: &[&str]) -> ArchivePayload {
+        ArchivePayload::Loose {
+            root: PathBuf::from(root),
+            files: files.iter().map(PathBuf::from).collect(),
+        }
+    }
+
+    fn extract(path: &Path, output: &Path) -> Result<(), ArchiveError> {
+        extract_archive(path, output, &ExtractLimits::default(), &mut |_| {})
+    }
+
+    fn extractor<'a>(
+        output_dir: &'a Path,
+        limits: &'a ExtractLimits,
+        progress: &'a mut dyn FnMut(ExtractProgress),
+    ) -> Extractor<'a> {
+        Extractor {
+            output_dir,
+            limits,
+            select: &|entry| Some(entry.to_path_buf()),
+            progress,
+            files: 0,
+            bytes: 0,
+        }
+    }
+
+    #[test]
+    fn test_payload_root() {
+        let root = |paths: &[&str]| payload_root(&listing(paths));
+        assert_eq!(
+            root(&["Mod/", "Mod/a.pak", "Mod/Sub/b.uasset"]),
+            Path::new("Mod")
+        );
+        assert_eq!(root(&["Mod/a.pak", "Mod/Sub/"]), Path::new("Mod"));
+        assert_eq!(root(&["Mod/"]), Path::new("Mod"));
+        // anything next to the top-level folder makes the archive root the payload root
+        assert_eq!(root(&["Mod/a.pak", "readme.txt"]), Path::new(""));
+        assert_eq!(root(&["Mod/a.pak", "Other/b.pak"]), Path::new(""));
+        assert_eq!(root(&["a.pak"]), Path::new(""));
+        assert_eq!(root(&[]), Path::new(""));
+    }
+
+    #[test]
+    fn test_find_payloads() {
+        let entries = listing(&[
+            "Wrap/",
+            "Wrap/readme.txt",
+            "Wrap/Option A/",
+            "Wrap/Option A/Marvel/Content/Marvel/Characters/1014/a.uasset",
+            "Wrap/Option A/Marvel/Content/Marvel/Characters/1014/a.uexp",
+            "Wrap/Option B/Marvel/Content/Marvel/Characters/1014/a.uasset",
+            "Wrap/Option B/preview.png",
+            "Wrap/Paks/Hero_9999999_P.pak",
+            "Wrap/Paks/Hero_9999999_P.utoc",
+            "Wrap/Paks/Hero_9999999_P.UCAS",
+            "Wrap/Paks/Other.pak",
+            "Wrap/Orphan/only.utoc",
+            "Wrap/Orphan/only.ucas",
+        ]);
+        assert_eq!(
+            find_payloads(&entries),
+            [
+                loose(
+                    "Wrap/Option A",
+                    &[
+                        "Wrap/Option A/Marvel/Content/Marvel/Characters/1014/a.uasset",
+                        "Wrap/Option A/Marvel/Content/Marvel/Characters/1014/a.uexp",
+                    ]
+                ),
+                loose(
+                    "Wrap/Option B",
+                    &[
+                        "Wrap/Option B/Marvel/Content/Marvel/Characters/1014/a.uasset",
+                        "Wrap/Option B/preview.png",
+                    ]
+                ),
+                ArchivePayload::IoStore {
+                    pak: PathBuf::from("Wrap/Paks/Hero_9999999_P.pak"),
+                    utoc: PathBuf::from("Wrap/Paks/Hero_9999999_P.utoc"),
+                    ucas: PathBuf::from("Wrap/Paks/Hero_9999999_P.UCAS"),
+                },
+                ArchivePayload::Pak(PathBuf::from("Wrap/Paks/Other.pak")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_find_loose_payloads() {
+        // assets at the archive root, next to a pak that is its own payload
+        let entries = listing(&["Marvel/Content/a.uasset", "b.pak"]);
+        assert_eq!(
+            find_payloads(&entries),
+            [
+                loose("", &["Marvel/Content/a.uasset"]),
+                ArchivePayload::Pak(PathBuf::from("b.pak")),
+            ]
+        );
+
+        // loose files without `Marvel/Content` belong to the payload root
+        let entries = listing(&["Mod/a.uasset", "Mod/a.uexp"]);
+        assert_eq!(
+            find_payloads(&entries),
+            [loose("Mod", &["Mod/a.uasset", "Mod/a.uexp"])]
+        );
+
+        // nested asset roots each get their own files
+        let entries = listing(&[
+            "Mod/Marvel/Content/a.uasset",
+            "Mod/Extra/Marvel/Content/b.uasset",
+        ]);
+        assert_eq!(
+            find_payloads(&entries),
+            [
+                loose("Mod", &["Mod/Marvel/Content/a.uasset"]),
+                loose("Mod/Extra", &["Mod/Extra/Marvel/Content/b.uasset"]),
+            ]
+        );
+
+        // no assets, nothing to install
+        let entries = listing(&["Mod/readme.txt", "Mod/preview.png", "Mod/only.utoc"]);
+        assert!(find_payloads(&entries).is_empty());
+    }
+
+    #[test]
+    fn test_find_variant_groups() {
+        let pak = |path: &str| ArchivePayload::Pak(PathBuf::from(path));
+        let assets = |assets: &[&str]| assets.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+
+        // overlapping assets make options, extensions and case are ignored
+        let payloads = [
+            pak("Options/Red/Hero_P.pak"),
+            pak("Options/Blue/Hero_P.pak"),
+            pak("Common/Extra_P.pak"),
+        ];
+        let groups = find_variant_groups(
+            &payloads,
+            &[
+                assets(&["Marvel/Content/Hero/Body.uasset"]),
+                assets(&["marvel\\content\\hero\\body.uexp"]),
+                assets(&["Marvel/Content/Extra/Body.uasset"]),
+            ],
+        );
+        assert_eq!(
+            groups,
+            [VariantGroup {
+                parent: PathBuf::from("Options"),
+                variants: vec![
+                    Variant {
+                        name: "Blue".to_string(),
+                        payloads: vec![1],
+                    },
+                    Variant {
+                        name: "Red".to_string(),
+                        payloads: vec![0],
+                    },
+                ],
+            }]
+        );
+        assert_eq!(groups[0].label(), "Options");
+        assert_eq!(
+            groups[0].variant("red").map(|v| &v.payloads),
+            Some(&vec![0])
+        );
+        assert_eq!(groups[0].variant("Green"), None);
+
+        // options at the top of the archive
+        let payloads = [
+            loose("Lite", &["Lite/Marvel/Content/a.uasset"]),
+            loose("Full", &["Full/Marvel/Content/a.uasset"]),
+        ];
+        let groups = find_variant_groups(
+            &payloads,
+            &payloads
+                .iter()
+                .map(ArchivePayload::listed_assets)
+                .collect::<Vec<_>>(),
+        );
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].label(), "archive root");
+        assert_eq!(
+            groups[0].variant("full").map(|v| &v.payloads),
+            Some(&vec![1])
+        );
+
+        // disjoint assets, or overlaps in one folder, are no options
+        let payloads = [pak("Red/A_P.pak"), pak("Blue/B_P.pak")];
+        assert!(find_variant_groups(&payloads, &[assets(&["a"]), assets(&["b"])]).is_empty());
+        let payloads = [pak("Paks/A_P.pak"), pak("Paks/B_P.pak")];
+        assert!(find_variant_groups(&payloads, &[assets(&["a"]), assets(&["a"])]).is_empty());
+    }
+
+    #[test]
+    fn test_find_nested_variant_groups() {
+        // a payload belongs to the innermost group only, which leaves `Skins` with one option
+        let payloads = [
+            ArchivePayload::Pak(PathBuf::from("Skins/A/Red/Hero_P.pak")),
+            ArchivePayload::Pak(PathBuf::from("Skins/A/Blue/Hero_P.pak")),
+            ArchivePayload::Pak(PathBuf::from("Skins/B/Hero_P.pak")),
+        ];
+        let assets = vec![vec!["hero".to_string()]; 3];
+        assert_eq!(
+            find_variant_groups(&payloads, &assets),
+            [VariantGroup {
+                parent: PathBuf::from("Skins/A"),
+                variants: vec![
+                    Variant {
+                        name: "Blue".to_string(),
+                        payloads: vec![1],
+                    },
+                    Variant {
+                        name: "Red".to_string(),
+                        payloads: vec![0],
+                    },
+                ],
+            }]
+        );
+
+        // every payload inside an option folder belongs to it
+        let payloads = [
+            ArchivePayload::Pak(PathBuf::from("Red/Hero_P.pak")),
+            ArchivePayload::Pak(PathBuf::from("Red/Extra/Other_P.pak")),
+            ArchivePayload::Pak(PathBuf::from("Blue/Hero_P.pak")),
+        ];
+        let assets = [
+            vec!["hero".to_string()],
+            vec!["other".to_string()],
+            vec!["hero".to_string()],
+        ];
+        let groups = find_variant_groups(&payloads, &assets);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(
+            groups[0].variant("Red").map(|v| &v.payloads),
+            Some(&vec![0, 1])
+        );
+        assert_eq!(
+            groups[0].variant("Blue").map(|v| &v.payloads),
+            Some(&vec![2])
+        );
+    }
+
+    #[test]
+    fn test_safe_entry_path() {
+        assert_eq!(
+            safe_entry_path("Mod/Paks/a.pak").unwrap(),
+            Path::new("Mod/Paks/a.pak")
+        );
+        assert_eq!(
+            safe_entry_path("./Mod\\Paks\\a.pak").unwrap(),
+            Path::new("Mod/Paks/a.pak")
+        );
+        assert_eq!(safe_entry_path("").unwrap(), Path::new(""));
+        for name in [
+            "../a.pak",
+            "Mod/../../a.pak",
+            "Mod\\..\\..\\a.pak",
+            "/etc/passwd",
+            "\\Windows\\a.pak",
+            "C:/Windows/a.pak",
+            "C:a.pak",
+            "\\\\?\\C:\\a.pak",
+            "Mod/a.pak:stream",
+        ] {
+            assert!(
+                matches!(safe_entry_path(name), Err(ArchiveError::UnsafePath(_))),
+                "{name}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_zip_rejects_unsafe_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("out");
+        let options = zip::write::SimpleFileOptions::default();
+
+        let traversal = zip_archive(dir.path(), |zip| {
+            zip.start_file("../evil.pak", options).unwrap();
+            zip.write_all(b"evil").unwrap();
+        });
+        assert!(matches!(
+            extract(&traversal, &output),
+            Err(ArchiveError::UnsafePath(_))
+        ));
+        assert!(!dir.path().join("evil.pak").exists());
+
+        let link = zip_archive(dir.path(), |zip| {
+            zip.add_symlink("Mod/link.uasset", "/etc/passwd", options)
+                .unwrap();
+        });
+        assert!(matches!(
+            extract(&link, &output),
+            Err(ArchiveError::Link(_))
+        ));
+        assert!(!output.join("Mod/link.uasset").exists());
+    }
+
+    #[test]
+    fn test_tar_rejects_links() {
+        for entry_type in [tar::EntryType::Symlink, tar::EntryType::Link] {
+            let dir = tempfile::tempdir().unwrap();
+            let output = dir.path().join("out");
+            let tar = tar_archive(dir.path(), entry_type);
+            assert!(
+                matches!(extract(&tar, &output), Err(ArchiveError::Link(_))),
+                "{entry_type:?}"
+            );
+            assert!(!output.join("Mod/link.uasset").exists());
+        }
+    }
+
+    #[test]
+    fn test_link_attributes() {
+        // Windows reparse points and Unix symlink modes, from RAR archives made on either host
+        assert!(is_rar_link(FILE_ATTRIBUTE_REPARSE_POINT | 0x20));
+        assert!(is_rar_link(S_IFLNK | 0o777));
+        assert!(!is_rar_link(0x20));
+        assert!(!is_rar_link(0o100644));
+        assert!(!is_rar_link(0o040755));
+
+        // 7-Zip keeps the Unix mode in the high bits when it flags the Unix extension
+        assert!(is_7z_link(FILE_ATTRIBUTE_REPARSE_POINT));
+        assert!(is_7z_link(
+            FILE_ATTRIBUTE_UNIX_EXTENSION | ((S_IFLNK | 0o777) << 16)
+        ));
+        assert!(!is_7z_link(
+            FILE_ATTRIBUTE_UNIX_EXTENSION | (0o100644 << 16)
+        ));
+        assert!(!is_7z_link((S_IFLNK | 0o777) << 16));
+        assert!(!is_7z_link(0x20));
+    }
+
+    #[test]
+    fn test_extractor_file_count_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        let limits = ExtractLimits {
+            max_total_size: 1024,
+            max_file_count: 2,
+        };
+        let mut reported = Vec::new();
+        let mut progress = |progress: ExtractProgress| reported.push(progress.files);
+        let mut extractor = extractor(dir.path(), &limits, &mut progress);
+
+        extractor
+            .write_file(Path::new("a.uasset"), 1, &mut &b"a"[..])
+            .unwrap();
+        extractor
+            .write_file(Path::new("Sub/b.uasset"), 1, &mut &b"b"[..])
+            .unwrap();
+        assert!(matches!(
+            extractor.reserve(0),
+            Err(ArchiveError::TooManyFiles { limit: 2 })
+        ));
+        assert!(matches!(
+            extractor.write_file(Path::new("c.uasset"), 1, &mut &b"c"[..]),
+            Err(ArchiveError::TooManyFiles { limit: 2 })
+        ));
+        assert!(!dir.path().join("c.uasset").exists());
+        assert_eq!(reported, [1, 2]);
+        assert_eq!(fs::read(dir.path().join("Sub/b.uasset")).unwrap(), b"b");
+    }
+
+    #[test]
+    fn test_extractor_size_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        let limits = ExtractLimits {
+            max_total_size: 8,
+            max_file_count: 10,
+        };
+        let mut progress = |_: ExtractProgress| {};
+        let mut extractor = extractor(dir.path(), &limits, &mut progress);
+
+        // the claimed size is checked before anything is written
+        assert!(matches!(
+            extractor.reserve(9),
+            Err(ArchiveError::TooLarge { limit: 8 })
+        ));
+        assert!(matches!(
+            extractor.write_file(Path::new("big.uasset"), 9, &mut &[0; 9][..]),
+            Err(ArchiveError::TooLarge { limit: 8 })
+        ));
+        assert!(!dir.path().join("big.uasset").exists());
+
+        extractor
+            .write_file(Path::new("a.uasset"), 4, &mut &[0; 4][..])
+            .unwrap();
+        assert!(extractor.reserve(4).is_ok());
+        // an entry claiming less than it holds is cut off once the limit is passed
+        assert!(matches!(
+            extractor.write_file(Path::new("lying.uasset"), 1, &mut &[0; 1024][..]),
+            Err(ArchiveError::TooLarge { limit: 8 })
+        ));
+        assert!(fs::metadata(dir.path().join("lying.uasset")).unwrap().len() <= 5);
+        assert_eq!(extractor.bytes, 4);
+
+        assert!(matches!(
+            extractor.write_file(Path::new(""), 0, &mut &[][..]),
+            Err(ArchiveError::UnsafePath(_))
+        ));
+    }
+}
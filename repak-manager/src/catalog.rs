@@ -0,0 +1,914 @@
+use crate::duplicates::{mod_fingerprint, ModFingerprint};
+use crate::installed::{
+    detect_mod_category, mod_files, normalized_mod_identity_string, scan_installed_mods,
+    InstalledMod, ModFileSignature,
+};
+use crate::load_order::mod_base_name;
+use crate::state::ModTags;
+use crate::utils::mod_skins;
+use rusqlite::{params, Connection, OpenFlags, TransactionBehavior};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{info, warn};
+
+const CATALOG_FILE: &str = "mod_catalog.db";
+/// JSON catalog written before the database, imported once when the database is created.
+const LEGACY_CATALOG_FILE: &str = "mod_catalog.json";
+/// Stored in `PRAGMA user_version`. Version 1 and 2 were JSON files.
+const CATALOG_VERSION: i32 = 3;
+/// How long to wait for another process writing the catalog before giving up.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(10);
+
+const SCHEMA: &str = "
+    CREATE TABLE entries (identity TEXT PRIMARY KEY NOT NULL, entry TEXT NOT NULL);
+    CREATE VIRTUAL TABLE entry_text USING fts5(identity UNINDEXED, name, hero, skin, category, file);
+";
+
+/// Where an installed mod came from, so it can be found and reinstalled after it is removed.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ModSource {
+    pub path: PathBuf,
+    /// SHA-256 of the archive or pak the mod was installed from. Directories are not hashed.
+    pub sha256: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CatalogSkin {
+    pub skin_id: String,
+    pub hero: String,
+    pub skin_name: String,
+}
+
+/// Everything known about one mod, kept after the mod is removed from the mods folder.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CatalogEntry {
+    /// Same key as the GUI metadata cache, see [`normalized_mod_identity_string`].
+    pub identity: String,
+    pub path: PathBuf,
+    pub installed: bool,
+    pub enabled: bool,
+    pub is_iostore: bool,
+    /// Signature the file list was indexed at, `None` until the mod has been read.
+    pub signature: Option<ModFileSignature>,
+    pub category: String,
+    pub skins: Vec<CatalogSkin>,
+    pub files: Vec<String>,
+    /// Unix time of the install, or of the mod's files when it was installed outside the manager.
+    pub installed_at: Option<u64>,
+    pub source: Option<ModSource>,
+    /// `None` until indexed, or when the mod could not be fingerprinted.
+    #[serde(default)]
+    pub fingerprint: Option<ModFingerprint>,
+}
+
+impl CatalogEntry {
+    fn new(identity: String, path: &Path) -> Self {
+        Self {
+            identity,
+            path: path.to_path_buf(),
+            installed: true,
+            enabled: true,
+            is_iostore: false,
+            signature: None,
+            category: String::new(),
+            skins: Vec::new(),
+            files: Vec::new(),
+            installed_at: None,
+            source: None,
+            fingerprint: None,
+        }
+    }
+
+    /// File stem without the load order suffix.
+    pub fn name(&self) -> &str {
+        let stem = self
+            .path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .unwrap_or_default();
+        mod_base_name(stem)
+    }
+}
+
+/// Snapshot of the persistent index of every mod seen in the mods folder. The index is a SQLite
+/// database next to the GUI config with a full-text index for [`Catalog::search`].
+#[derive(Clone, Debug, Default)]
+pub struct Catalog {
+    pub entries: Vec<CatalogEntry>,
+    /// Database the snapshot was read from, searched for text terms.
+    path: PathBuf,
+    /// Full-text matches by FTS query, so redrawing the GUI does not query the database again.
+    text_matches: Arc<Mutex<HashMap<String, Arc<TextMatches>>>>,
+}
+
+/// Matching mod identities and the files that matched, see [`Catalog::text_matches`].
+type TextMatches = HashMap<String, HashSet<String>>;
+
+pub fn catalog_path() -> PathBuf {
+    crate::state::config_path().with_file_name(CATALOG_FILE)
+}
+
+impl Catalog {
+    /// Reads the catalog, creating it when it is missing. Fails when the database cannot be read
+    /// or was written by a newer version; the file is left untouched in that case.
+    pub fn load() -> Result<Self, String> {
+        Self::load_from(&catalog_path())
+    }
+
+    fn load_from(path: &Path) -> Result<Self, String> {
+        let conn = open_catalog(path)?;
+        let entries = read_entries(&conn)?
+            .into_iter()
+            .filter_map(|(_, entry)| entry)
+            .collect();
+        Ok(Self::snapshot(path, entries))
+    }
+
+    fn snapshot(path: &Path, entries: Vec<CatalogEntry>) -> Self {
+        Self {
+            entries,
+            path: path.to_path_buf(),
+            text_matches: Default::default(),
+        }
+    }
+
+    /// Applies `f` to the catalog on disk and writes the entries it changed, returning the updated
+    /// catalog. The database stays locked from the read to the write, so other threads and
+    /// processes wait instead of dropping each other's changes.
+    pub fn update(f: impl FnOnce(&mut Catalog)) -> Result<Catalog, String> {
+        Self::update_at(&catalog_path(), f)
+    }
+
+    fn update_at(path: &Path, f: impl FnOnce(&mut Catalog)) -> Result<Catalog, String> {
+        let mut conn = open_catalog(path)?;
+        let tx = conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .map_err(|e| catalog_error("lock", path, e))?;
+        let rows = read_entries(&tx)?;
+        let mut stored = HashMap::new();
+        let mut entries = Vec::new();
+        for (json, entry) in rows {
+            if let Some(entry) = entry {
+                stored.insert(entry.identity.clone(), json);
+                entries.push(entry);
+            }
+        }
+
+        let mut catalog = Self::snapshot(path, entries);
+        f(&mut catalog);
+        for entry in &catalog.entries {
+            let json = serde_json::to_string(entry)
+                .map_err(|e| format!("Failed to serialize mod catalog entry: {e}"))?;
+            if stored.get(&entry.identity) != Some(&json) {
+                write_entry(&tx, entry, &json).map_err(|e| catalog_error("write", path, e))?;
+            }
+        }
+        tx.commit().map_err(|e| catalog_error("write", path, e))?;
+        // text changed, drop matches cached before `f` ran
+        Ok(Self::snapshot(path, catalog.entries))
+    }
+
+    pub fn entry(&self, identity: &str) -> Option<&CatalogEntry> {
+        self.entries.iter().find(|entry| entry.identity == identity)
+    }
+
+    fn entry_mut(&mut self, path: &Path) -> &mut CatalogEntry {
+        let identity = normalized_mod_identity_string(path);
+        match self
+            .entries
+            .iter()
+            .position(|entry| entry.identity == identity)
+        {
+            Some(idx) => &mut self.entries[idx],
+            None => {
+                self.entries.push(CatalogEntry::new(identity, path));
+                self.entries.last_mut().unwrap()
+            }
+        }
+    }
+
+    /// Whether the indexed file list still matches the mod on disk and its fingerprint was made
+    /// by this version.
+    pub fn is_current(&self, installed: &InstalledMod) -> bool {
+        self.entry(&normalized_mod_identity_string(&installed.path))
+            .is_some_and(|entry| {
+                entry.signature == Some(installed.signature)
+                    && entry
+                        .fingerprint
+                        .as_ref()
+                        .is_none_or(ModFingerprint::is_current)
+            })
+    }
+
+    /// Stores the file list and fingerprint read from an installed mod.
+    pub fn index(
+        &mut self,
+        installed: &InstalledMod,
+        files: Vec<String>,
+        fingerprint: Option<ModFingerprint>,
+    ) {
+        let entry = self.entry_mut(&installed.path);
+        entry.path = installed.path.clone();
+        entry.installed = true;
+        entry.enabled = installed.enabled;
+        entry.is_iostore = installed.is_iostore;
+        entry.signature = Some(installed.signature);
+        entry.category = detect_mod_category(&files);
+        entry.skins = mod_skins(&files)
+            .into_iter()
+            .map(|skin| CatalogSkin {
+                skin_id: skin.skin_id.to_string(),
+                hero: skin.hero,
+                skin_name: skin.skin,
+            })
+            .collect();
+        entry.files = files;
+        entry.fingerprint = fingerprint;
+        entry
+            .installed_at
+            .get_or_insert(installed.signature.modified_secs);
+    }
+
+    /// Updates paths and enabled state from a scan and marks mods no longer in the mods folder
+    /// as removed. Returns whether anything changed.
+    pub fn sync_installed(&mut self, mods: &[InstalledMod]) -> bool {
+        let mut changed = false;
+        let mut present = HashSet::new();
+        for installed in mods {
+            let identity = normalized_mod_identity_string(&installed.path);
+            if let Some(entry) = self
+                .entries
+                .iter_mut()
+                .find(|entry| entry.identity == identity)
+            {
+                if entry.path != installed.path
+                    || entry.enabled != installed.enabled
+                    || !entry.installed
+                {
+                    entry.path = installed.path.clone();
+                    entry.enabled = installed.enabled;
+                    entry.installed = true;
+                    changed = true;
+                }
+            }
+            present.insert(identity);
+        }
+        for entry in &mut self.entries {
+            if entry.installed && !present.contains(&entry.identity) {
+                entry.installed = false;
+                entry.enabled = false;
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    /// Records when and from where a mod was installed.
+    pub fn record_install(&mut self, pak_path: &Path, source: Option<ModSource>) {
+        let entry = self.entry_mut(pak_path);
+        entry.path = pak_path.to_path_buf();
+        entry.installed = true;
+        entry.enabled = true;
+        // force the file list to be read again
+        entry.signature = None;
+        entry.installed_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .ok()
+            .map(|duration| duration.as_secs());
+        if source.is_some() {
+            entry.source = source;
+        }
+    }
+
+    /// Finds mods matching every term of `query`. Terms are matched against the start of words,
+    /// so `psy` finds `Psylocke` and `1014` finds `SK_1014_1014001`, and `"quoted terms"` must
+    /// appear as a phrase. A term can be limited to one field with `name:`, `hero:`, `skin:`,
+    /// `tag:`, `category:` or `file:`; bare terms match any of them. Tags are matched as
+    /// case-insensitive substrings.
+    pub fn search<'a>(&'a self, query: &str, tags: &ModTags) -> Vec<SearchHit<'a>> {
+        if self.entries.is_empty() {
+            return Vec::new();
+        }
+        let terms = parse_query(query)
+            .into_iter()
+            .map(|term| {
+                let text = (term.field != Field::Tag).then(|| self.text_matches(&term));
+                (term, text)
+            })
+            .collect::<Vec<_>>();
+        self.entries
+            .iter()
+            .filter_map(|entry| {
+                let entry_tags = tags.tags_for_mod(&entry.path);
+                let mut matched_files = HashSet::new();
+                for (term, text) in &terms {
+                    let text_hit = text
+                        .as_ref()
+                        .and_then(|text| text.get(&entry.identity))
+                        .inspect(|files| matched_files.extend(files.iter().map(String::as_str)));
+                    let tag_hit = matches!(term.field, Field::Any | Field::Tag)
+                        && entry_tags
+                            .iter()
+                            .any(|tag| tag.to_lowercase().contains(&term.value));
+                    if text_hit.is_none() && !tag_hit {
+                        return None;
+                    }
+                }
+                let mut matched_files = entry
+                    .files
+                    .iter()
+                    .map(String::as_str)
+                    .filter(|file| matched_files.contains(file))
+                    .collect::<Vec<_>>();
+                matched_files.sort();
+                matched_files.dedup();
+                Some(SearchHit {
+                    entry,
+                    tags: entry_tags,
+                    matched_files,
+                })
+            })
+            .collect()
+    }
+
+    /// Looks a term up in the full-text index. Errors are logged and match nothing.
+    fn text_matches(&self, term: &Term) -> Arc<TextMatches> {
+        let Some(query) = term.fts_query() else {
+            return Default::default();
+        };
+        let mut cache = self.text_matches.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(matches) = cache.get(&query) {
+            return matches.clone();
+        }
+        let matches = Arc::new(
+            query_text(&self.path, &query)
+                .inspect_err(|e| warn!(error = %e, "Failed to search mod catalog"))
+                .unwrap_or_default(),
+        );
+        cache.insert(query, matches.clone());
+        matches
+    }
+
+    /// Entries sharing at least one packaged file with `entry`, such as skins replacing the same
+    /// mesh.
+    pub fn touching<'a>(&'a self, entry: &CatalogEntry) -> Vec<&'a CatalogEntry> {
+        let files = entry.files.iter().collect::<HashSet<_>>();
+        self.entries
+            .iter()
+            .filter(|other| other.identity != entry.identity)
+            .filter(|other| other.files.iter().any(|file| files.contains(file)))
+            .collect()
+    }
+}
+
+pub struct SearchHit<'a> {
+    pub entry: &'a CatalogEntry,
+    pub tags: Vec<String>,
+    /// Files that matched `file:` or bare terms.
+    pub matched_files: Vec<&'a str>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Field {
+    Any,
+    Name,
+    Hero,
+    Skin,
+    Tag,
+    Category,
+    File,
+}
+
+struct Term {
+    field: Field,
+    value: String,
+}
+
+impl Term {
+    /// FTS5 query matching the term as a phrase whose last word is a prefix, `None` for terms
+    /// without any word to look up.
+    fn fts_query(&self) -> Option<String> {
+        let column = match self.field {
+            Field::Any => "",
+            Field::Name => "name : ",
+            Field::Hero => "hero : ",
+            Field::Skin => "skin : ",
+            Field::Category => "category : ",
+            Field::File => "file : ",
+            Field::Tag => return None,
+        };
+        self.value
+            .chars()
+            .any(char::is_alphanumeric)
+            .then(|| format!("{column}\"{}\"*", self.value.replace('"', "\"\"")))
+    }
+}
+
+fn parse_query(query: &str) -> Vec<Term> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    for c in query.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+
+    tokens
+        .into_iter()
+        .map(|token| {
+            let (field, value) = match token.split_once(':') {
+                Some((field, value)) => match field.to_ascii_lowercase().as_str() {
+                    "name" => (Field::Name, value),
+                    "hero" => (Field::Hero, value),
+                    "skin" => (Field::Skin, value),
+                    "tag" => (Field::Tag, value),
+                    "category" => (Field::Category, value),
+                    "file" => (Field::File, value),
+                    _ => (Field::Any, token.as_str()),
+                },
+                None => (Field::Any, token.as_str()),
+            };
+            Term {
+                field,
+                value: value.to_lowercase(),
+            }
+        })
+        .filter(|term| !term.value.is_empty())
+        .collect()
+}
+
+/// Opens the catalog database, creating it on first use. The file is never rewritten when it
+/// cannot be read.
+fn open_catalog(path: &Path) -> Result<Connection, String> {
+    let mut conn = Connection::open(path).map_err(|e| catalog_error("open", path, e))?;
+    conn.busy_timeout(BUSY_TIMEOUT)
+        .map_err(|e| catalog_error("open", path, e))?;
+    let mut version = catalog_version(&conn).map_err(|e| catalog_error("read", path, e))?;
+    if version == 0 {
+        version = create_catalog(&mut conn, path)?;
+    }
+    match version {
+        CATALOG_VERSION => Ok(conn),
+        newer if newer > CATALOG_VERSION => Err(format!(
+            "Mod catalog {} was written by a newer version (catalog version {newer})",
+            path.display()
+        )),
+        older => Err(format!(
+            "Mod catalog {} has unsupported catalog version {older}",
+            path.display()
+        )),
+    }
+}
+
+fn catalog_version(conn: &Connection) -> rusqlite::Result<i32> {
+    conn.pragma_query_value(None, "user_version", |row| row.get(0))
+}
+
+/// Creates the tables and imports the JSON catalog, unless another process did it first.
+/// Returns the catalog version afterwards.
+fn create_catalog(conn: &mut Connection, path: &Path) -> Result<i32, String> {
+    let tx = conn
+        .transaction_with_behavior(TransactionBehavior::Immediate)
+        .map_err(|e| catalog_error("lock", path, e))?;
+    let version = catalog_version(&tx).map_err(|e| catalog_error("read", path, e))?;
+    if version != 0 {
+        return Ok(version);
+    }
+    tx.execute_batch(SCHEMA)
+        .map_err(|e| catalog_error("create", path, e))?;
+    for entry in legacy_entries(&path.with_file_name(LEGACY_CATALOG_FILE)) {
+        let json = serde_json::to_string(&entry)
+            .map_err(|e| format!("Failed to serialize mod catalog entry: {e}"))?;
+        write_entry(&tx, &entry, &json).map_err(|e| catalog_error("create", path, e))?;
+    }
+    tx.pragma_update(None, "user_version", CATALOG_VERSION)
+        .map_err(|e| catalog_error("create", path, e))?;
+    tx.commit().map_err(|e| catalog_error("create", path, e))?;
+    Ok(CATALOG_VERSION)
+}
+
+#[derive(Deserialize)]
+struct LegacyCatalog {
+    #[serde(default)]
+    version: u32,
+    #[serde(default)]
+    entries: Vec<CatalogEntry>,
+}
+
+/// Entries of the JSON catalog. The file is left in place, also when it cannot be parsed.
+fn legacy_entries(path: &Path) -> Vec<CatalogEntry> {
+    let Ok(data) = fs::read_to_string(path) else {
+        return Vec::new();
+    };
+    match serde_json::from_str::<LegacyCatalog>(&data) {
+        Ok(mut legacy) => {
+            if legacy.version < 2 {
+                // version 1 had no fingerprints, index every mod again
+                for entry in &mut legacy.entries {
+                    entry.signature = None;
+                }
+            }
+            info!(entries = legacy.entries.len(), "Imported JSON mod catalog");
+            legacy.entries
+        }
+        Err(e) => {
+            warn!(path = %path.display(), error = %e, "Failed to import JSON mod catalog, leaving it in place");
+            Vec::new()
+        }
+    }
+}
+
+/// Every stored row with its JSON. Rows that no longer parse are logged and kept in the database
+/// until the mod is indexed again.
+fn read_entries(conn: &Connection) -> Result<Vec<(String, Option<CatalogEntry>)>, String> {
+    let read = || -> rusqlite::Result<Vec<(String, String)>> {
+        let mut stmt = conn.prepare("SELECT identity, entry FROM entries ORDER BY rowid")?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect()
+    };
+    let rows = read().map_err(|e| format!("Failed to read mod catalog: {e}"))?;
+    Ok(rows
+        .into_iter()
+        .map(|(identity, json)| {
+            let entry = serde_json::from_str::<CatalogEntry>(&json)
+                .inspect_err(
+                    |e| warn!(identity, error = %e, "Skipping unreadable mod catalog entry"),
+                )
+                .ok();
+            (json, entry)
+        })
+        .collect())
+}
+
+/// Stores an entry and replaces its row in the full-text index.
+fn write_entry(conn: &Connection, entry: &CatalogEntry, json: &str) -> rusqlite::Result<()> {
+    conn.execute(
+        "INSERT INTO entries (identity, entry) VALUES (?1, ?2)
+         ON CONFLICT(identity) DO UPDATE SET entry = excluded.entry",
+        params![entry.identity, json],
+    )?;
+    conn.execute(
+        "DELETE FROM entry_text WHERE identity = ?1",
+        [&entry.identity],
+    )?;
+
+    let mut names = vec![
+        entry.name().to_string(),
+        entry.path.to_string_lossy().into_owned(),
+    ];
+    if let Some(source) = &entry.source {
+        names.push(source.path.to_string_lossy().into_owned());
+    }
+    let heroes = entry
+        .skins
+        .iter()
+        .map(|skin| skin.hero.as_str())
+        .collect::<Vec<_>>();
+    let skins = entry
+        .skins
+        .iter()
+        .map(|skin| format!("{} {}", skin.skin_name, skin.skin_id))
+        .collect::<Vec<_>>();
+    conn.execute(
+        "INSERT INTO entry_text (identity, name, hero, skin, category, file)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            entry.identity,
+            names.join("\n"),
+            heroes.join("\n"),
+            skins.join("\n"),
+            entry.category,
+            entry.files.join("\n"),
+        ],
+    )?;
+    Ok(())
+}
+
+/// Runs an FTS5 query, returning the matching identities with the files the query hit.
+fn query_text(path: &Path, query: &str) -> Result<TextMatches, String> {
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| catalog_error("open", path, e))?;
+    conn.busy_timeout(BUSY_TIMEOUT)
+        .map_err(|e| catalog_error("open", path, e))?;
+    // column 5 is `file`, one packaged file per line
+    let mut stmt = conn
+        .prepare(
+            "SELECT identity, highlight(entry_text, 5, char(1), char(2))
+             FROM entry_text WHERE entry_text MATCH ?1",
+        )
+        .map_err(|e| catalog_error("search", path, e))?;
+    let rows = stmt
+        .query_map([query], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })
+        .map_err(|e| catalog_error("search", path, e))?;
+
+    let mut matches = TextMatches::new();
+    for row in rows {
+        let (identity, files) = row.map_err(|e| catalog_error("search", path, e))?;
+        let files = files
+            .lines()
+            .filter(|line| line.contains('\u{1}'))
+            .map(|line| line.replace(['\u{1}', '\u{2}'], ""));
+        matches.entry(identity).or_default().extend(files);
+    }
+    Ok(matches)
+}
+
+fn catalog_error(action: &str, path: &Path, e: rusqlite::Error) -> String {
+    format!("Failed to {action} mod catalog {}: {e}", path.display())
+}
+
+/// Hashes the archive or pak a mod is installed from.
+pub fn mod_source(path: &Path) -> ModSource {
+    let sha256 = path.is_file().then(|| sha256_file(path)).and_then(|hash| {
+        hash.inspect_err(|e| warn!(error = %e, "Failed to hash mod source"))
+            .ok()
+    });
+    ModSource {
+        path: path.to_path_buf(),
+        sha256,
+    }
+}
+
+fn sha256_file(path: &Path) -> std::io::Result<String> {
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut BufReader::new(File::open(path)?), &mut hasher)?;
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Records a finished install in the catalog. Failures are logged, the install itself succeeded.
+pub fn record_install(pak_path: &Path, source: Option<&Path>) {
+    let source = source.map(mod_source);
+    if let Err(e) = Catalog::update(|catalog| catalog.record_install(pak_path, source)) {
+        warn!(error = %e, "Failed to record install in mod catalog");
+    }
+}
+
+/// Fingerprints a mod for duplicate detection, logging failures so the file list is still indexed.
+pub fn fingerprint_or_warn(installed: &InstalledMod) -> Option<ModFingerprint> {
+    mod_fingerprint(&installed.path, installed.is_iostore)
+        .inspect_err(
+            |e| warn!(path = %installed.path.display(), error = %e, "Failed to fingerprint mod"),
+        )
+        .ok()
+}
+
+/// Indexes every installed mod whose files changed since the last scan and marks removed mods.
+pub fn refresh(mods_dir: &Path) -> Result<Catalog, String> {
+    let mods = scan_installed_mods(mods_dir);
+    let stale = {
+        let catalog = Catalog::load()?;
+        mods.iter()
+            .filter(|installed| !catalog.is_current(installed))
+            .cloned()
+            .collect::<Vec<_>>()
+    };
+    let indexed = stale
+        .into_iter()
+        .filter_map(
+            |installed| match mod_files(&installed.path, installed.is_iostore) {
+                Ok(files) => {
+                    let fingerprint = fingerprint_or_warn(&installed);
+                    Some((installed, files, fingerprint))
+                }
+                Err(e) => {
+                    warn!(path = %installed.path.display(), error = %e, "Failed to index mod");
+                    None
+                }
+            },
+        )
+        .collect::<Vec<_>>();
+    info!(indexed = indexed.len(), "Refreshing mod catalog");
+
+    Catalog::update(|catalog| {
+        for (installed, files, fingerprint) in indexed {
+            catalog.index(&installed, files, fingerprint);
+        }
+        catalog.sync_installed(&mods);
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn entry(path: &str, hero: &str, skin_id: &str, files: &[&str]) -> CatalogEntry {
+        let path = Path::new(path);
+        let mut entry = CatalogEntry::new(normalized_mod_identity_string(path), path);
+        entry.category = "Skin".to_string();
+        entry.skins = vec![CatalogSkin {
+            skin_id: skin_id.to_string(),
+            hero: hero.to_string(),
+            skin_name: "Default".to_string(),
+        }];
+        entry.files = files.iter().map(|file| file.to_string()).collect();
+        entry
+    }
+
+    fn psylocke() -> CatalogEntry {
+        entry(
+            "/mods/PsyBlue_9999999_P.pak",
+            "Psylocke",
+            "1048001",
+            &[
+                "Marvel/Content/Marvel/Characters/1048/1048001/Meshes/SK_1048_1048001.uasset",
+                "Marvel/Content/Marvel/UI/Textures/T_Icon.uasset",
+            ],
+        )
+    }
+
+    fn names(hits: &[SearchHit]) -> Vec<String> {
+        hits.iter()
+            .map(|hit| hit.entry.name().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_search_full_text() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(CATALOG_FILE);
+        Catalog::update_at(&path, |catalog| {
+            catalog.entries.push(psylocke());
+            catalog.entries.push(entry(
+                "/mods/Spidey_9999999_P.pak",
+                "Spider-Man",
+                "1036001",
+                &["Marvel/Content/Marvel/Characters/1036/1036001/Meshes/SK_1036_1036001.uasset"],
+            ));
+        })
+        .unwrap();
+
+        let catalog = Catalog::load_from(&path).unwrap();
+        let mut tags = ModTags::default();
+        tags.add_tag(Path::new("/mods/Spidey_9999999_P.pak"), "favourites");
+
+        assert_eq!(names(&catalog.search("psy", &tags)), ["PsyBlue"]);
+        assert_eq!(names(&catalog.search("hero:spider", &tags)), ["Spidey"]);
+        assert_eq!(names(&catalog.search("skin:1048001", &tags)), ["PsyBlue"]);
+        assert_eq!(names(&catalog.search("tag:fav", &tags)), ["Spidey"]);
+        assert_eq!(names(&catalog.search("meshes", &tags)).len(), 2);
+        assert!(catalog.search("hero:psylocke tag:fav", &tags).is_empty());
+        // word prefixes, not substrings
+        assert!(catalog.search("locke", &tags).is_empty());
+
+        let hits = catalog.search("file:meshes hero:psylocke", &tags);
+        assert_eq!(
+            hits[0].matched_files,
+            ["Marvel/Content/Marvel/Characters/1048/1048001/Meshes/SK_1048_1048001.uasset"]
+        );
+        let hits = catalog.search("\"ui textures\"", &tags);
+        assert_eq!(
+            hits[0].matched_files,
+            ["Marvel/Content/Marvel/UI/Textures/T_Icon.uasset"]
+        );
+    }
+
+    #[test]
+    fn test_update_rewrites_changed_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(CATALOG_FILE);
+        Catalog::update_at(&path, |catalog| catalog.entries.push(psylocke())).unwrap();
+        let catalog = Catalog::update_at(&path, |catalog| {
+            catalog.entries[0].skins[0].hero = "Jeff".to_string();
+        })
+        .unwrap();
+
+        assert_eq!(
+            names(&catalog.search("hero:jeff", &ModTags::default())),
+            ["PsyBlue"]
+        );
+        assert!(catalog
+            .search("hero:psylocke", &ModTags::default())
+            .is_empty());
+        assert_eq!(Catalog::load_from(&path).unwrap().entries.len(), 1);
+    }
+
+    #[test]
+    fn test_concurrent_updates_keep_every_entry() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(CATALOG_FILE);
+        Catalog::load_from(&path).unwrap();
+        std::thread::scope(|scope| {
+            for idx in 0..8 {
+                let path = &path;
+                scope.spawn(move || {
+                    Catalog::update_at(path, |catalog| {
+                        let mod_path = format!("/mods/Mod{idx}_9999999_P.pak");
+                        catalog
+                            .entries
+                            .push(entry(&mod_path, "Hero", "1000001", &[]));
+                    })
+                    .unwrap();
+                });
+            }
+        });
+        assert_eq!(Catalog::load_from(&path).unwrap().entries.len(), 8);
+    }
+
+    #[test]
+    fn test_newer_catalog_is_not_touched() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(CATALOG_FILE);
+        Catalog::update_at(&path, |catalog| catalog.entries.push(psylocke())).unwrap();
+        Connection::open(&path)
+            .unwrap()
+            .pragma_update(None, "user_version", CATALOG_VERSION + 1)
+            .unwrap();
+
+        assert!(Catalog::load_from(&path).is_err());
+        assert!(Catalog::update_at(&path, |catalog| catalog.entries.clear()).is_err());
+        let conn = Connection::open(&path).unwrap();
+        assert_eq!(catalog_version(&conn).unwrap(), CATALOG_VERSION + 1);
+        assert_eq!(read_entries(&conn).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_unreadable_rows_are_kept() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(CATALOG_FILE);
+        Catalog::load_from(&path).unwrap();
+        Connection::open(&path)
+            .unwrap()
+            .execute(
+                "INSERT INTO entries (identity, entry) VALUES ('broken', '{')",
+                [],
+            )
+            .unwrap();
+
+        let catalog =
+            Catalog::update_at(&path, |catalog| catalog.entries.push(psylocke())).unwrap();
+        assert_eq!(catalog.entries.len(), 1);
+        let rows = read_entries(&open_catalog(&path).unwrap()).unwrap();
+        assert_eq!(rows.len(), 2);
+        assert!(rows
+            .iter()
+            .any(|(json, entry)| json == "{" && entry.is_none()));
+    }
+
+    #[test]
+    fn test_imports_json_catalog() {
+        let dir = tempfile::tempdir().unwrap();
+        let legacy = dir.path().join(LEGACY_CATALOG_FILE);
+        let mut entry = psylocke();
+        entry.signature = Some(ModFileSignature {
+            size: 10,
+            modified_secs: 20,
+        });
+        let json = serde_json::json!({ "version": 1, "entries": [entry] });
+        fs::write(&legacy, json.to_string()).unwrap();
+
+        let catalog = Catalog::load_from(&dir.path().join(CATALOG_FILE)).unwrap();
+        assert_eq!(
+            names(&catalog.search("psylocke", &ModTags::default())),
+            ["PsyBlue"]
+        );
+        // version 1 had no fingerprints
+        assert_eq!(catalog.entries[0].signature, None);
+        assert!(legacy.exists());
+    }
+
+    #[test]
+    fn test_unparseable_json_catalog_is_left_in_place() {
+        let dir = tempfile::tempdir().unwrap();
+        let legacy = dir.path().join(LEGACY_CATALOG_FILE);
+        fs::write(&legacy, "{ not json").unwrap();
+
+        let catalog = Catalog::load_from(&dir.path().join(CATALOG_FILE)).unwrap();
+        assert!(catalog.entries.is_empty());
+        assert_eq!(fs::read_to_string(&legacy).unwrap(), "{ not json");
+    }
+
+    #[test]
+    fn test_not_a_database_is_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(CATALOG_FILE);
+        fs::write(
+            &path,
+            "definitely not sqlite, but long enough to have a header",
+        )
+        .unwrap();
+
+        assert!(Catalog::load_from(&path).is_err());
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "definitely not sqlite, but long enough to have a header"
+        );
+    }
+}
@@ -0,0 +1,390 @@
+//! What a mod changes, worked out from the paths of its packaged files: the heroes and skins it
+//! touches, how many assets of each kind it packages, and whether they replace game assets or
+//! add new ones.
+
+use crate::skin_db::{asset_kind, skin_db, AssetKind, SkinDb};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Folders below `Marvel/Content/` holding the game's own assets. Assets in other content folders
+/// are new and only load when another asset of the mod references them.
+const GAME_CONTENT_ROOTS: [&str; 5] = ["Marvel", "Movies", "WwiseAudio", "Localization", "Splash"];
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct ModClassification {
+    /// Heroes with assets in the mod, by ID.
+    pub heroes: Vec<HeroRef>,
+    /// Skins with assets in the mod, by ID.
+    pub skins: Vec<SkinRef>,
+    /// Number of assets of each kind. The `.uasset`, `.uexp` and `.ubulk` of one asset count once.
+    pub kinds: BTreeMap<AssetKind, usize>,
+    /// Assets in the game's own content folders.
+    pub replaced: usize,
+    /// Assets in content folders the game does not have.
+    pub added: usize,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct HeroRef {
+    pub id: u32,
+    /// `None` for heroes missing from the skin database.
+    pub name: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct SkinRef {
+    pub id: u32,
+    pub hero_id: u32,
+    pub hero: Option<String>,
+    /// `None` for skins missing from the skin database.
+    pub name: Option<String>,
+    pub default: bool,
+}
+
+impl SkinRef {
+    /// `Hero - Skin`, falling back to IDs for entries missing from the skin database.
+    pub fn label(&self) -> String {
+        let hero = self
+            .hero
+            .clone()
+            .unwrap_or_else(|| format!("Hero {}", self.hero_id));
+        match &self.name {
+            Some(name) => format!("{hero} - {name}"),
+            None => format!("{hero} - {}", self.id),
+        }
+    }
+}
+
+/// Whether a mod overrides game assets, ships new ones, or both.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, strum::Display)]
+pub enum AssetChange {
+    Replaces,
+    Adds,
+    #[strum(to_string = "Replaces and adds")]
+    Both,
+}
+
+impl ModClassification {
+    /// The kind a mod is listed under: meshes if it has any, otherwise its most common kind.
+    pub fn category(&self) -> AssetKind {
+        if self.kinds.contains_key(&AssetKind::Mesh) {
+            return AssetKind::Mesh;
+        }
+        self.kinds
+            .iter()
+            .filter(|(kind, _)| **kind != AssetKind::Other)
+            // ties go to the kind declared first
+            .max_by(|(left_kind, left), (right_kind, right)| {
+                left.cmp(right).then(right_kind.cmp(left_kind))
+            })
+            .map_or(AssetKind::Other, |(kind, _)| *kind)
+    }
+
+    pub fn change(&self) -> AssetChange {
+        match (self.replaced, self.added) {
+            (0, added) if added > 0 => AssetChange::Adds,
+            (_, 0) => AssetChange::Replaces,
+            _ => AssetChange::Both,
+        }
+    }
+
+    /// Short description of a mod: the skin it changes, preferring custom skins over default
+    /// ones, or its category for mods without hero assets.
+    pub fn characteristic(&self) -> String {
+        let skin = self
+            .skins
+            .iter()
+            .find(|skin| !skin.default)
+            .or_else(|| self.skins.first());
+        match skin {
+            Some(skin) if self.skins.len() > 1 => {
+                format!("{} (+{} more)", skin.label(), self.skins.len() - 1)
+            }
+            Some(skin) => skin.label(),
+            None => match self.category() {
+                AssetKind::Other => "Unknown".to_string(),
+                kind => kind.to_string(),
+            },
+        }
+    }
+}
+
+/// Classifies a mod from its file paths, as listed by [`crate::installed::mod_files`].
+pub fn classify_files(files: &[String]) -> ModClassification {
+    classify_files_with(&skin_db(), files)
+}
+
+fn classify_files_with(db: &SkinDb, files: &[String]) -> ModClassification {
+    let mut assets = BTreeMap::new();
+    for file in files {
+        let path = content_path(file);
+        let asset = match path.rsplit_once('.') {
+            Some((stem, ext)) if !ext.contains('/') => stem.to_string(),
+            _ => path.clone(),
+        };
+        assets.entry(asset).or_insert(path);
+    }
+
+    let mut classification = ModClassification::default();
+    for path in assets.values() {
+        *classification.kinds.entry(path_kind(path)).or_default() += 1;
+        if is_game_asset(path) {
+            classification.replaced += 1;
+        } else {
+            classification.added += 1;
+        }
+
+        let Some(asset) = db.lookup_path(path) else {
+            continue;
+        };
+        if !classification
+            .heroes
+            .iter()
+            .any(|hero| hero.id == asset.hero_id)
+        {
+            classification.heroes.push(HeroRef {
+                id: asset.hero_id,
+                name: asset.hero.clone(),
+            });
+        }
+        if !classification
+            .skins
+            .iter()
+            .any(|skin| skin.id == asset.skin_id)
+        {
+            classification.skins.push(SkinRef {
+                id: asset.skin_id,
+                hero_id: asset.hero_id,
+                hero: asset.hero,
+                default: asset.skin.as_ref().is_some_and(|skin| skin.default),
+                name: asset.skin.map(|skin| skin.skin),
+            });
+        }
+    }
+    classification.heroes.sort_by_key(|hero| hero.id);
+    classification.skins.sort_by_key(|skin| skin.id);
+    classification
+}
+
+/// Turns pak paths and IoStore package names into `Marvel/Content/...` style paths.
+fn content_path(file: &str) -> String {
+    let file = file.trim().replace('\\', "/");
+    let file = file.trim_start_matches("../../../");
+    if let Some(rest) = file.strip_prefix("/Game/") {
+        format!("Marvel/Content/{rest}")
+    } else if let Some(rest) = file.strip_prefix("/Engine/") {
+        format!("Engine/Content/{rest}")
+    } else {
+        file.trim_start_matches('/').to_string()
+    }
+}
+
+fn path_kind(path: &str) -> AssetKind {
+    let lower = path.to_ascii_lowercase();
+    let area = lower.strip_prefix("marvel/content/").unwrap_or(&lower);
+    let area = area.strip_prefix("marvel/").unwrap_or(area);
+    let ext = Path::new(&lower)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or_default();
+
+    if area.starts_with("ui/") {
+        AssetKind::Ui
+    } else if area.starts_with("movies/") || matches!(ext, "mp4" | "bk2") {
+        AssetKind::Movie
+    } else if lower.contains("wwiseaudio") || matches!(ext, "bnk" | "wem") {
+        AssetKind::Audio
+    } else if area.starts_with("maps/") || ext == "umap" {
+        AssetKind::Map
+    } else {
+        asset_kind(&path.split('/').collect::<Vec<_>>())
+    }
+}
+
+fn is_game_asset(path: &str) -> bool {
+    match path.strip_prefix("Marvel/Content/") {
+        Some(rest) => {
+            let root = rest.split('/').next().unwrap_or_default();
+            GAME_CONTENT_ROOTS
+                .iter()
+                .any(|game_root| game_root.eq_ignore_ascii_case(root))
+        }
+        // engine content, config and plugin files all override the game's
+        None => true,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::skin_db::{HeroRecord, SkinDbFile, SkinRecord, SKIN_DB_SCHEMA};
+
+    fn db() -> SkinDb {
+        let skin = |id, name: &str| SkinRecord {
+            id,
+            name: name.to_string(),
+            default: id % 1000 == 1,
+            variants: Vec::new(),
+        };
+        SkinDb::from_file(&SkinDbFile {
+            schema: SKIN_DB_SCHEMA,
+            revision: 1,
+            heroes: vec![HeroRecord {
+                id: 1014,
+                name: "The Punisher".to_string(),
+                skins: vec![skin(1014001, "Default"), skin(1014100, "Camo")],
+            }],
+        })
+    }
+
+    fn files(files: &[&str]) -> Vec<String> {
+        files.iter().map(|file| file.to_string()).collect()
+    }
+
+    #[test]
+    fn test_content_path() {
+        for (file, expected) in [
+            (
+                "../../../Marvel/Content/Marvel/T_Icon.uasset",
+                "Marvel/Content/Marvel/T_Icon.uasset",
+            ),
+            (
+                "/Game/Marvel/Characters/1014/SK_1014",
+                "Marvel/Content/Marvel/Characters/1014/SK_1014",
+            ),
+            (
+                "/Engine/EngineMaterials/M_Base",
+                "Engine/Content/EngineMaterials/M_Base",
+            ),
+            (
+                " Marvel\\Content\\Movies\\Intro.mp4 ",
+                "Marvel/Content/Movies/Intro.mp4",
+            ),
+            (
+                "/Marvel/Config/DefaultGame.ini",
+                "Marvel/Config/DefaultGame.ini",
+            ),
+        ] {
+            assert_eq!(content_path(file), expected, "{file}");
+        }
+    }
+
+    #[test]
+    fn test_is_game_asset() {
+        assert!(is_game_asset(
+            "Marvel/Content/Marvel/Characters/1014/SK_1014.uasset"
+        ));
+        assert!(is_game_asset("Marvel/Content/wwiseaudio/Media/1.wem"));
+        assert!(is_game_asset("Marvel/Content/Movies/Intro.mp4"));
+        // engine content and config override the game's files
+        assert!(is_game_asset(
+            "Engine/Content/EngineMaterials/M_Base.uasset"
+        ));
+        assert!(is_game_asset("Marvel/Config/DefaultGame.ini"));
+        assert!(!is_game_asset("Marvel/Content/MyMod/T_New.uasset"));
+        assert!(!is_game_asset("Marvel/Content/Marvelous/T_New.uasset"));
+    }
+
+    #[test]
+    fn test_path_kind() {
+        for (path, kind) in [
+            (
+                "Marvel/Content/Marvel/UI/Textures/T_Icon.uasset",
+                AssetKind::Ui,
+            ),
+            ("Marvel/Content/Movies/Intro.uasset", AssetKind::Movie),
+            ("Marvel/Content/Marvel/Intro.bk2", AssetKind::Movie),
+            ("Marvel/Content/WwiseAudio/Media/1.wem", AssetKind::Audio),
+            ("Marvel/Content/Marvel/Maps/Tokyo.umap", AssetKind::Map),
+            (
+                "Marvel/Content/Marvel/Characters/1014/1014001/Meshes/SK_1014_1014001.uasset",
+                AssetKind::Mesh,
+            ),
+            (
+                "Marvel/Content/Marvel/Misc/DT_Table.uasset",
+                AssetKind::Other,
+            ),
+        ] {
+            assert_eq!(path_kind(path), kind, "{path}");
+        }
+    }
+
+    #[test]
+    fn test_classify_files() {
+        let classification = classify_files_with(
+            &db(),
+            &files(&[
+                "../../../Marvel/Content/Marvel/Characters/1014/1014100/Meshes/SK_1014_1014100.uasset",
+                "../../../Marvel/Content/Marvel/Characters/1014/1014100/Meshes/SK_1014_1014100.uexp",
+                "../../../Marvel/Content/Marvel/Characters/1014/1014100/Meshes/SK_1014_1014100.ubulk",
+                // the IoStore package of the same asset
+                "/Game/Marvel/Characters/1014/1014100/Meshes/SK_1014_1014100",
+                "/Game/Marvel/Characters/1014/1014001/Textures/T_1014_1014001_D",
+                "/Game/Marvel/Characters/1014/1014999/Materials/MI_1014_1014999",
+                "/Game/MyMod/Textures/T_New",
+            ]),
+        );
+
+        assert_eq!(
+            classification.kinds,
+            BTreeMap::from([
+                (AssetKind::Mesh, 1),
+                (AssetKind::Texture, 2),
+                (AssetKind::Material, 1),
+            ])
+        );
+        assert_eq!((classification.replaced, classification.added), (3, 1));
+        assert_eq!(classification.change(), AssetChange::Both);
+        assert_eq!(classification.category(), AssetKind::Mesh);
+        assert_eq!(
+            classification.heroes,
+            [HeroRef {
+                id: 1014,
+                name: Some("The Punisher".to_string()),
+            }]
+        );
+        assert_eq!(
+            classification
+                .skins
+                .iter()
+                .map(|skin| (skin.id, skin.label(), skin.default))
+                .collect::<Vec<_>>(),
+            [
+                (1014001, "The Punisher - Default".to_string(), true),
+                (1014100, "The Punisher - Camo".to_string(), false),
+                // skins missing from the database fall back to their ID
+                (1014999, "The Punisher - 1014999".to_string(), false),
+            ]
+        );
+        assert_eq!(
+            classification.characteristic(),
+            "The Punisher - Camo (+2 more)"
+        );
+    }
+
+    #[test]
+    fn test_classify_without_heroes() {
+        let classification = classify_files_with(
+            &db(),
+            &files(&[
+                "Marvel/Content/Marvel/UI/Textures/T_Icon.uasset",
+                "Marvel/Content/Marvel/Misc/T_Other.uasset",
+                "Marvel/Content/Marvel/Misc/DT_Table.uasset",
+                "Marvel/Content/Marvel/Misc/DT_Other.uasset",
+                "Marvel/Content/Marvel/Misc/DT_Third.uasset",
+            ]),
+        );
+        // `Other` never wins, ties go to the kind declared first
+        assert_eq!(classification.category(), AssetKind::Texture);
+        assert_eq!(classification.change(), AssetChange::Replaces);
+        assert_eq!(classification.characteristic(), "Texture");
+
+        let empty = classify_files_with(&db(), &[]);
+        assert_eq!(empty.category(), AssetKind::Other);
+        assert_eq!(empty.characteristic(), "Unknown");
+
+        let added = classify_files_with(&db(), &files(&["Marvel/Content/MyMod/DT_New.uasset"]));
+        assert_eq!(added.change(), AssetChange::Adds);
+    }
+}
@@ -0,0 +1,346 @@
+use crate::catalog::{Catalog, CatalogEntry};
+use crate::keys::KEYS;
+use crate::load_order::load_order_key;
+use crate::utoc_utils::open_toc;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+/// Share of packaged files two mods must have in common to be reported as near duplicates.
+pub const NEAR_DUPLICATE_THRESHOLD: f32 = 0.9;
+
+/// Bumped when fingerprints are made differently, so mods indexed earlier are fingerprinted again.
+/// Version 1 hashed pak entries from the index, which has no data hashes in v10+ paks.
+pub const FINGERPRINT_VERSION: u32 = 2;
+
+/// Content of a mod independent of its file name, so copies installed under different names can
+/// be recognised.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ModFingerprint {
+    /// [`FINGERPRINT_VERSION`] the fingerprint was made with.
+    #[serde(default = "legacy_fingerprint_version")]
+    pub version: u32,
+    /// Equal for mods with identical content: a hash over the data of every packaged file, or
+    /// over the chunk hashes of an IoStore mod.
+    pub content: String,
+    /// One short digest per packaged file or IoStore chunk, sorted.
+    pub entries: Vec<String>,
+}
+
+fn legacy_fingerprint_version() -> u32 {
+    1
+}
+
+impl ModFingerprint {
+    /// Whether the fingerprint was made the way this version makes them.
+    pub fn is_current(&self) -> bool {
+        self.version == FINGERPRINT_VERSION
+    }
+}
+
+/// Fingerprints a mod from the data of every packaged file, or from the chunk hashes in the
+/// `.utoc` of an IoStore mod, which already cover the chunk data.
+pub fn mod_fingerprint(pak_path: &Path, is_iostore: bool) -> Result<ModFingerprint, String> {
+    if is_iostore {
+        let toc = open_toc(&pak_path.with_extension("utoc"))?;
+        let entries = toc
+            .chunks()
+            .iter()
+            .map(|chunk| {
+                short_digest(&[
+                    &chunk.id.as_bytes()[..],
+                    &chunk.hash[..],
+                    &chunk.length.to_le_bytes()[..],
+                ])
+            })
+            .collect();
+        return Ok(fingerprint_from_entries(entries));
+    }
+
+    let file = File::open(pak_path).map_err(|e| format!("Failed to open mod: {e}"))?;
+    let mut reader = BufReader::new(file);
+    let pak = repak::PakBuilder::new()
+        .keys(KEYS.clone())
+        .reader(&mut reader)
+        .map_err(|e| format!("Failed to read mod: {e}"))?;
+    let entries = pak
+        .files()
+        .into_iter()
+        .map(|path| {
+            // v10+ indexes carry no entry hashes, so hash the data itself
+            let mut hasher = Sha256::new();
+            pak.read_file(&path, &mut reader, &mut hasher)
+                .map_err(|e| format!("Failed to read entry {path}: {e}"))?;
+            Ok(short_digest(&[path.as_bytes(), &hasher.finalize()[..]]))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    Ok(fingerprint_from_entries(entries))
+}
+
+fn short_digest(parts: &[&[u8]]) -> String {
+    let mut hasher = Sha256::new();
+    for part in parts {
+        hasher.update((part.len() as u64).to_le_bytes());
+        hasher.update(part);
+    }
+    hex::encode(&hasher.finalize()[..8])
+}
+
+fn fingerprint_from_entries(mut entries: Vec<String>) -> ModFingerprint {
+    entries.sort();
+    entries.dedup();
+    let mut hasher = Sha256::new();
+    for entry in &entries {
+        hasher.update(entry.as_bytes());
+    }
+    ModFingerprint {
+        version: FINGERPRINT_VERSION,
+        content: hex::encode(hasher.finalize()),
+        entries,
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+pub enum DuplicateKind {
+    /// Identical content under different names.
+    Exact,
+    /// Most packaged files are the same, such as two versions of one mod. Never removed in bulk,
+    /// the user picks which version to keep.
+    Near,
+}
+
+/// Installed mods that are copies of each other.
+#[derive(Clone, Debug, Serialize)]
+pub struct DuplicateGroup {
+    pub kind: DuplicateKind,
+    /// Lowest share of packaged files any mod in the group has in common with the first one.
+    pub similarity: f32,
+    /// The mod to keep first: enabled mods before disabled ones, then the one loaded last, which
+    /// is the copy currently winning in game.
+    pub mods: Vec<PathBuf>,
+}
+
+impl DuplicateGroup {
+    pub fn keep(&self) -> &Path {
+        &self.mods[0]
+    }
+
+    pub fn extras(&self) -> &[PathBuf] {
+        &self.mods[1..]
+    }
+}
+
+fn similarity(left: &HashSet<&str>, right: &HashSet<&str>) -> f32 {
+    let union = left.union(right).count();
+    if union == 0 {
+        return 0.0;
+    }
+    left.intersection(right).count() as f32 / union as f32
+}
+
+/// Groups installed mods in the catalog with the same content, then mods sharing at least
+/// `threshold` of their packaged files. Mods without a current fingerprint are skipped.
+pub fn find_duplicates(catalog: &Catalog, threshold: f32) -> Vec<DuplicateGroup> {
+    find_duplicate_entries(&catalog.entries, threshold)
+}
+
+/// Extra copies in exact duplicate groups, the only ones safe to remove without asking.
+pub fn exact_extras(groups: &[DuplicateGroup]) -> impl Iterator<Item = &PathBuf> {
+    groups
+        .iter()
+        .filter(|group| group.kind == DuplicateKind::Exact)
+        .flat_map(|group| group.extras())
+}
+
+fn find_duplicate_entries(entries: &[CatalogEntry], threshold: f32) -> Vec<DuplicateGroup> {
+    let mut mods = entries
+        .iter()
+        .filter(|entry| {
+            entry.installed
+                && entry
+                    .fingerprint
+                    .as_ref()
+                    .is_some_and(ModFingerprint::is_current)
+        })
+        .collect::<Vec<_>>();
+    mods.sort_by_key(|entry| (Reverse(entry.enabled), Reverse(load_order_key(&entry.path))));
+
+    let mut groups = Vec::new();
+    let mut by_content: HashMap<&str, Vec<&CatalogEntry>> = HashMap::new();
+    for entry in &mods {
+        let fingerprint = entry.fingerprint.as_ref().unwrap();
+        by_content
+            .entry(fingerprint.content.as_str())
+            .or_default()
+            .push(entry);
+    }
+    let mut grouped = HashSet::new();
+    for entry in &mods {
+        let content = entry.fingerprint.as_ref().unwrap().content.as_str();
+        let same = &by_content[content];
+        if same.len() < 2 || grouped.contains(&entry.identity) {
+            continue;
+        }
+        grouped.extend(same.iter().map(|entry| entry.identity.clone()));
+        groups.push(DuplicateGroup {
+            kind: DuplicateKind::Exact,
+            similarity: 1.0,
+            mods: same.iter().map(|entry| entry.path.clone()).collect(),
+        });
+    }
+
+    // exact copies are compared through the one kept
+    let entry_sets = mods
+        .iter()
+        .filter(|entry| {
+            !grouped.contains(&entry.identity)
+                || groups
+                    .iter()
+                    .any(|group| group.keep() == entry.path.as_path())
+        })
+        .map(|entry| {
+            let set = entry
+                .fingerprint
+                .as_ref()
+                .unwrap()
+                .entries
+                .iter()
+                .map(String::as_str)
+                .collect::<HashSet<_>>();
+            (*entry, set)
+        })
+        .collect::<Vec<_>>();
+    let mut near_grouped = HashSet::new();
+    for (idx, (entry, set)) in entry_sets.iter().enumerate() {
+        if near_grouped.contains(&entry.identity) {
+            continue;
+        }
+        let mut group = DuplicateGroup {
+            kind: DuplicateKind::Near,
+            similarity: 1.0,
+            mods: vec![entry.path.clone()],
+        };
+        for (other, other_set) in &entry_sets[idx + 1..] {
+            if near_grouped.contains(&other.identity) {
+                continue;
+            }
+            let shared = similarity(set, other_set);
+            if shared >= threshold {
+                near_grouped.insert(other.identity.clone());
+                group.similarity = group.similarity.min(shared);
+                group.mods.push(other.path.clone());
+            }
+        }
+        if group.mods.len() > 1 {
+            near_grouped.insert(entry.identity.clone());
+            groups.push(group);
+        }
+    }
+    groups
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Cursor;
+
+    fn entry(path: &str, enabled: bool, fingerprint: ModFingerprint) -> CatalogEntry {
+        CatalogEntry {
+            identity: path.to_ascii_lowercase(),
+            path: PathBuf::from(path),
+            installed: true,
+            enabled,
+            is_iostore: false,
+            signature: None,
+            category: String::new(),
+            skins: Vec::new(),
+            files: Vec::new(),
+            installed_at: None,
+            source: None,
+            fingerprint: Some(fingerprint),
+        }
+    }
+
+    fn fingerprint(entries: &[&str]) -> ModFingerprint {
+        fingerprint_from_entries(entries.iter().map(|entry| entry.to_string()).collect())
+    }
+
+    fn write_pak(path: &Path, data: &[u8]) {
+        let mut writer = repak::PakBuilder::new().writer(
+            Cursor::new(vec![]),
+            repak::Version::V11,
+            "../../../".to_owned(),
+            None,
+        );
+        writer
+            .write_file("Marvel/Content/T_Test.uasset", false, data)
+            .unwrap();
+        std::fs::write(path, writer.write_index().unwrap().into_inner()).unwrap();
+    }
+
+    #[test]
+    fn test_pak_fingerprint_hashes_data() {
+        let dir = tempfile::tempdir().unwrap();
+        let original = dir.path().join("foo_9999999_P.pak");
+        let copy = dir.path().join("foo (1)_9999999_P.pak");
+        let edited = dir.path().join("bar_9999999_P.pak");
+        write_pak(&original, b"same size data A");
+        write_pak(&copy, b"same size data A");
+        write_pak(&edited, b"same size data B");
+
+        let original = mod_fingerprint(&original, false).unwrap();
+        assert_eq!(original, mod_fingerprint(&copy, false).unwrap());
+        // v11 indexes hold sizes but no hashes, so equal sizes must not look identical
+        assert_ne!(
+            original.content,
+            mod_fingerprint(&edited, false).unwrap().content
+        );
+        assert!(original.is_current());
+    }
+
+    #[test]
+    fn test_exact_and_near_groups() {
+        let shared = (0..10).map(|idx| idx.to_string()).collect::<Vec<_>>();
+        let shared = shared.iter().map(String::as_str).collect::<Vec<_>>();
+        let mut newer = shared.clone();
+        newer[9] = "changed";
+        let entries = [
+            entry("/mods/a_9999999_P.pak", true, fingerprint(&shared)),
+            entry("/mods/b_9999999_P.pak", false, fingerprint(&shared)),
+            entry("/mods/c_9999999_P.pak", true, fingerprint(&newer)),
+            entry("/mods/d_9999999_P.pak", true, fingerprint(&["other"])),
+        ];
+
+        let groups = find_duplicate_entries(&entries, 0.8);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].kind, DuplicateKind::Exact);
+        // the enabled copy is kept
+        assert_eq!(groups[0].keep(), Path::new("/mods/a_9999999_P.pak"));
+        assert_eq!(groups[0].extras(), [PathBuf::from("/mods/b_9999999_P.pak")]);
+        assert_eq!(groups[1].kind, DuplicateKind::Near);
+        assert_eq!(groups[1].mods.len(), 2);
+
+        // near duplicates are never removed in bulk
+        let extras = exact_extras(&groups).collect::<Vec<_>>();
+        assert_eq!(extras, [&PathBuf::from("/mods/b_9999999_P.pak")]);
+    }
+
+    #[test]
+    fn test_outdated_fingerprints_are_skipped() {
+        let mut outdated = fingerprint(&["a", "b"]);
+        outdated.version = 1;
+        let entries = [
+            entry("/mods/a_9999999_P.pak", true, outdated.clone()),
+            entry("/mods/b_9999999_P.pak", true, outdated),
+        ];
+        assert!(find_duplicate_entries(&entries, 0.9).is_empty());
+
+        let json = r#"{"content":"00","entries":[]}"#;
+        let legacy = serde_json::from_str::<ModFingerprint>(json).unwrap();
+        assert!(!legacy.is_current());
+    }
+}
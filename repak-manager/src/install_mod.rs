@@ -0,0 +1,484 @@
+pub mod install_mod_logic;
+
+use crate::archive::{self, ArchiveError, ArchivePayload, ExtractLimits, ExtractProgress};
+use crate::keys::KEYS;
+use crate::utils::{collect_files, get_current_pak_characteristics};
+use crate::utoc_utils::{has_directory_index, read_utoc};
+use repak::keys::{DEFAULT_GAME, RIVALS_AES_KEY};
+use repak::utils::AesKey;
+use repak::Compression::Oodle;
+use repak::{Compression, PakReader};
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, LazyLock};
+use tempfile::{tempdir, TempDir};
+use tracing::{debug, error, info, instrument};
+
+#[derive(Debug, Clone)]
+pub struct InstallableMod {
+    pub mod_name: String,
+    pub mod_type: String,
+    pub repak: bool,
+    pub fix_mesh: bool,
+    pub kawaii_porter: bool,
+    pub default_hidden_material_patch: bool,
+    pub is_dir: bool,
+    pub editing: bool,
+    pub path_hash_seed: String,
+    pub mount_point: String,
+    pub compression: Compression,
+    pub reader: Option<PakReader>,
+    pub mod_path: PathBuf,
+    pub total_files: usize,
+    pub iostore: bool,
+    // the only reason we keep this is to filter out the archives during collection
+    pub is_archived: bool,
+    pub enabled: bool,
+    pub obfuscated: bool,
+    // Keeps extracted archive files alive while the install dialog/worker still references them.
+    #[allow(dead_code)]
+    pub extracted_archive_dir: Option<Arc<TempDir>>,
+    /// Archive, pak or directory the user picked, recorded in the mod catalog on install.
+    pub source: Option<PathBuf>,
+    /// The option of its archive the mod is, if the archive ships mutually exclusive options.
+    pub variant: Option<ModVariant>,
+    /// Payload of the `source` archive still to be extracted into `extracted_archive_dir`, see
+    /// [`InstallableMod::extract_pending_archive_payload`].
+    pub pending_archive_payload: Option<ArchivePayload>,
+    // pub audio_mod: bool,
+}
+
+/// One option of a mod archive, see [`archive::find_variant_groups`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ModVariant {
+    /// The archive and folder holding the options, the same for every option of the group.
+    pub group: String,
+    /// Name of the option's folder.
+    pub name: String,
+}
+
+impl Default for InstallableMod {
+    fn default() -> Self {
+        InstallableMod {
+            obfuscated: false,
+            mod_name: "".to_string(),
+            mod_type: "".to_string(),
+            repak: false,
+            fix_mesh: false,
+            kawaii_porter: true,
+            default_hidden_material_patch: true,
+            is_dir: false,
+            editing: false,
+            path_hash_seed: "".to_string(),
+            mount_point: "".to_string(),
+            compression: Default::default(),
+            reader: None,
+            mod_path: Default::default(),
+            total_files: 0,
+            iostore: false,
+            is_archived: false,
+            enabled: true,
+            extracted_archive_dir: None,
+            source: None,
+            variant: None,
+            pending_archive_payload: None,
+        }
+    }
+}
+
+impl InstallableMod {
+    /// Extracts the [`pending_archive_payload`](Self::pending_archive_payload) of an archived
+    /// mod before it's installed, a no-op for other mods.
+    pub fn extract_pending_archive_payload(&mut self) -> Result<(), String> {
+        let Some(payload) = &self.pending_archive_payload else {
+            return Ok(());
+        };
+        let (Some(archive_path), Some(archive_dir)) = (&self.source, &self.extracted_archive_dir)
+        else {
+            return Err(format!(
+                "{} has a pending archive payload but no archive",
+                self.mod_name
+            ));
+        };
+        info!(mod_name = %self.mod_name, archive = %archive_path.display(), "Extracting archived mod");
+        archive::extract_payloads(
+            archive_path,
+            archive_dir.path(),
+            &[payload],
+            &ExtractLimits::default(),
+            &mut log_extracted_entry,
+        )
+        .map_err(|e| format!("Failed to extract {}: {e}", archive_path.display()))?;
+        self.pending_archive_payload = None;
+        Ok(())
+    }
+}
+
+/// Ticks the mods of the archive options named in `names`, ignoring case, and unticks the other
+/// options of their groups. Groups no name matches keep their first option. Returns the names
+/// matching no option.
+pub fn pick_variants(mods: &mut [InstallableMod], names: &[String]) -> Vec<String> {
+    let mut unmatched = Vec::new();
+    for name in names {
+        let groups = mods
+            .iter()
+            .filter_map(|installable_mod| installable_mod.variant.as_ref())
+            .filter(|variant| variant.name.eq_ignore_ascii_case(name))
+            .map(|variant| variant.group.clone())
+            .collect::<HashSet<_>>();
+        if groups.is_empty() {
+            unmatched.push(name.clone());
+            continue;
+        }
+        for installable_mod in mods.iter_mut() {
+            if let Some(variant) = &installable_mod.variant {
+                if groups.contains(&variant.group) {
+                    installable_mod.enabled = variant.name.eq_ignore_ascii_case(name);
+                }
+            }
+        }
+    }
+    unmatched
+}
+
+fn processable_asset_count<'a>(paths: impl IntoIterator<Item = &'a str>) -> usize {
+    paths
+        .into_iter()
+        .filter(|path| path.to_ascii_lowercase().ends_with(".uasset"))
+        .count()
+        .max(1)
+}
+
+fn processable_iostore_asset_count<'a>(paths: impl IntoIterator<Item = &'a str>) -> usize {
+    paths.into_iter().count().max(1)
+}
+
+pub fn install_progress_units(mods: &InstallableMod) -> usize {
+    let asset_units = mods.total_files.max(1);
+
+    if mods.iostore && mods.repak {
+        asset_units.saturating_mul(2)
+    } else {
+        asset_units
+    }
+}
+
+/// The game's AES key from [`KEYS`], used to write containers and by retoc, which can't pick keys
+/// by GUID. Readers use [`KEYS`] so each container gets the key of its encryption key GUID.
+pub static AES_KEY_STR: LazyLock<String> = LazyLock::new(|| {
+    KEYS.key_str(DEFAULT_GAME)
+        .unwrap_or(RIVALS_AES_KEY)
+        .to_string()
+});
+
+pub static AES_KEY: LazyLock<AesKey> =
+    LazyLock::new(|| AesKey::from_str(&AES_KEY_STR).expect("Unable to initialise AES_KEY"));
+
+/// [`AES_KEY`] parsed for retoc.
+pub fn retoc_aes_key() -> Result<retoc::AesKey, String> {
+    retoc::AesKey::from_str(&AES_KEY_STR).map_err(|e| format!("Failed to parse AES key: {e}"))
+}
+
+fn log_extracted_entry(progress: ExtractProgress) {
+    debug!(
+        entry = %progress.path.display(),
+        size = progress.size,
+        "Extracted archive entry"
+    )
+}
+
+/// Extracts `entries` of the archive at `archive_path`, each to the destination `payloads` gives
+/// it, in one pass over the archive.
+fn extract_payload_entries(
+    archive_path: &Path,
+    archive_dir: &Path,
+    entries: &[(&ArchivePayload, &Path)],
+) -> Result<(), ArchiveError> {
+    let destinations = entries
+        .iter()
+        .map(|(payload, entry)| (*entry, payload.destination(entry)))
+        .collect::<HashMap<_, _>>();
+    archive::extract_selected(
+        archive_path,
+        archive_dir,
+        &ExtractLimits::default(),
+        &|entry| destinations.get(entry).cloned(),
+        &mut log_extracted_entry,
+    )
+}
+
+/// The installable mods of an archive, found from its listing. Only what classifying them needs
+/// is extracted here, the pak of packaged mods and the utoc of IoStore mods. The rest waits in
+/// [`InstallableMod::pending_archive_payload`] until the mod is installed, so the mods a user
+/// unticks in the install dialog are never extracted.
+fn archive_mods(archive_path: &Path) -> Result<Vec<InstallableMod>, repak::Error> {
+    let archive_error = |e: ArchiveError| {
+        error!(path = ?archive_path, error = %e, "Error inspecting archive");
+        repak::Error::Other(format!("Failed to extract {}: {e}", archive_path.display()))
+    };
+    info!(path = ?archive_path, "Listing archive for inspection");
+    let entries = archive::list_archive(archive_path).map_err(archive_error)?;
+    let payloads = archive::find_payloads(&entries);
+    debug!(
+        entries = entries.len(),
+        payloads = payloads.len(),
+        "Listed archive"
+    );
+
+    let archive_dir = Arc::new(tempdir()?);
+    let inspected = payloads
+        .iter()
+        .flat_map(|payload| match payload {
+            ArchivePayload::IoStore { pak, utoc, .. } => vec![(payload, pak), (payload, utoc)],
+            ArchivePayload::Pak(pak) => vec![(payload, pak)],
+            ArchivePayload::Loose { .. } => Vec::new(),
+        })
+        .map(|(payload, entry)| (payload, entry.as_path()))
+        .collect::<Vec<_>>();
+    extract_payload_entries(archive_path, archive_dir.path(), &inspected).map_err(archive_error)?;
+
+    // containers without a directory index only list their packages in the ucas
+    let unindexed = payloads
+        .iter()
+        .filter_map(|payload| match payload {
+            ArchivePayload::IoStore { utoc, ucas, .. }
+                if !has_directory_index(&archive_dir.path().join(payload.destination(utoc))) =>
+            {
+                Some((payload, ucas.as_path()))
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    if !unindexed.is_empty() {
+        extract_payload_entries(archive_path, archive_dir.path(), &unindexed)
+            .map_err(archive_error)?;
+    }
+
+    let names = archive::payload_mod_names(archive_path, &payloads);
+    let mut mods = Vec::with_capacity(payloads.len());
+    let mut assets = Vec::with_capacity(payloads.len());
+    for (payload, name) in payloads.iter().zip(names) {
+        match archive_payload_mod(archive_path, payload, name, &archive_dir) {
+            Ok((installable_mod, payload_assets)) => {
+                debug!(mod_name = %installable_mod.mod_name, mod_type = %installable_mod.mod_type, "Discovered archived mod");
+                mods.push(Some(installable_mod));
+                assets.push(payload_assets);
+            }
+            Err(e) => {
+                error!(?payload, error = %e, "Error reading archived mod");
+                mods.push(None);
+                assets.push(Vec::new());
+            }
+        }
+    }
+
+    // only the first option of each group starts ticked, so mutually exclusive options
+    // aren't all installed by accident
+    let archive_name = archive_path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    for group in archive::find_variant_groups(&payloads, &assets) {
+        info!(group = %group.label(), variants = group.variants.len(), "Found archive options");
+        for (index, variant) in group.variants.iter().enumerate() {
+            for installable_mod in variant.payloads.iter().filter_map(|i| mods[*i].as_mut()) {
+                installable_mod.enabled = index == 0;
+                installable_mod.variant = Some(ModVariant {
+                    group: format!("{archive_name}: {}", group.label()),
+                    name: variant.name.clone(),
+                });
+            }
+        }
+    }
+    Ok(mods.into_iter().flatten().collect())
+}
+
+/// The installable mod of `payload` and the assets it packages.
+fn archive_payload_mod(
+    archive_path: &Path,
+    payload: &ArchivePayload,
+    mod_name: String,
+    archive_dir: &Arc<TempDir>,
+) -> Result<(InstallableMod, Vec<String>), repak::Error> {
+    let mod_path = payload.extracted_path(archive_dir.path());
+    let archived_mod = InstallableMod {
+        mod_name,
+        mount_point: "../../../".to_string(),
+        path_hash_seed: "00000000".to_string(),
+        extracted_archive_dir: Some(archive_dir.clone()),
+        source: Some(archive_path.to_path_buf()),
+        ..Default::default()
+    };
+    let open_pak = || {
+        repak::PakBuilder::new()
+            .keys(KEYS.clone())
+            .reader(&mut BufReader::new(File::open(&mod_path)?))
+    };
+
+    match payload {
+        ArchivePayload::Loose { files, .. } => {
+            let file_names = files
+                .iter()
+                .map(|path| path.to_string_lossy().into_owned())
+                .collect::<Vec<_>>();
+            let installable_mod = InstallableMod {
+                total_files: processable_asset_count(file_names.iter().map(String::as_str)),
+                mod_type: get_current_pak_characteristics(file_names),
+                is_dir: true,
+                pending_archive_payload: Some(payload.clone()),
+                mod_path,
+                ..archived_mod
+            };
+            Ok((installable_mod, payload.listed_assets()))
+        }
+        ArchivePayload::Pak(_) => {
+            let builder = open_pak()?;
+            let files = builder.files();
+            let installable_mod = InstallableMod {
+                total_files: processable_asset_count(files.iter().map(String::as_str)),
+                mod_type: get_current_pak_characteristics(files.clone()),
+                repak: true,
+                reader: Some(builder),
+                compression: Oodle,
+                mod_path,
+                ..archived_mod
+            };
+            Ok((installable_mod, files))
+        }
+        ArchivePayload::IoStore { utoc, ucas, .. } => {
+            let builder = open_pak()?;
+            let utoc_path = archive_dir.path().join(payload.destination(utoc));
+            let ucas_extracted = archive_dir.path().join(payload.destination(ucas)).exists();
+            let files = read_utoc(&utoc_path, &builder, &mod_path)
+                .map_err(|e| repak::Error::Io(std::io::Error::other(e)))?
+                .into_iter()
+                .map(|entry| entry.file_path)
+                .collect::<Vec<_>>();
+            let installable_mod = InstallableMod {
+                total_files: processable_iostore_asset_count(files.iter().map(String::as_str)),
+                mod_type: get_current_pak_characteristics(files.clone()),
+                reader: Some(builder),
+                iostore: true,
+                compression: Oodle,
+                pending_archive_payload: (!ucas_extracted).then(|| payload.clone()),
+                mod_path,
+                ..archived_mod
+            };
+            Ok((installable_mod, files))
+        }
+    }
+}
+
+#[instrument(skip(paths), fields(path_count = paths.len()))]
+pub fn map_to_mods_internal(paths: &[PathBuf]) -> Vec<InstallableMod> {
+    let mut extensible_vec: Vec<InstallableMod> = Vec::new();
+    info!("Mapping paths into installable mods");
+    let mut installable_mods = paths
+        .iter()
+        .map(|path| {
+            let is_dir = path.clone().is_dir();
+            let extension = path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .map(|ext| ext.to_ascii_lowercase())
+                .unwrap_or_default();
+            let is_archive = archive::is_archive(path);
+
+            let mut modtype = "Unknown".to_string();
+            let mut pak = None;
+            let mut len = 1;
+            let mut iostore = false;
+            let mut mod_path = path.clone();
+
+            if !is_dir && !is_archive {
+                debug!(?path, "Inspecting pak file");
+                let pak_path = path.with_extension("pak");
+                let utoc_path = path.with_extension("utoc");
+                let ucas_path = path.with_extension("ucas");
+                let reader_path = if extension == "utoc" || extension == "ucas" {
+                    pak_path.clone()
+                } else {
+                    path.clone()
+                };
+                mod_path = reader_path.clone();
+
+                let builder = repak::PakBuilder::new()
+                    .keys(KEYS.clone())
+                    .reader(&mut BufReader::new(File::open(reader_path).unwrap()));
+                match builder {
+                    Ok(builder) => {
+                        pak = Some(builder.clone());
+                        if pak_path.exists() && utoc_path.exists() && ucas_path.exists() {
+                            let files = read_utoc(&utoc_path, &builder, &pak_path)
+                                .map_err(|e| repak::Error::Io(std::io::Error::other(e)))?;
+                            let files = files
+                                .iter()
+                                .map(|x| x.file_path.clone())
+                                .collect::<Vec<_>>();
+                            len = processable_iostore_asset_count(files.iter().map(String::as_str));
+                            modtype = get_current_pak_characteristics(files);
+                            iostore = true;
+                        } else {
+                            let files = builder.files();
+                            len = processable_asset_count(files.iter().map(String::as_str));
+                            modtype = get_current_pak_characteristics(files);
+                        }
+                    }
+                    Err(e) => {
+                        error!(?path, error = %e, "Error reading pak file");
+                        return Err(e);
+                    }
+                }
+            }
+
+            if is_dir {
+                debug!(?path, "Inspecting directory mod");
+                let mut files = vec![];
+                collect_files(&mut files, path)?;
+                let files = files
+                    .iter()
+                    .map(|s| s.to_str().unwrap().to_string())
+                    .collect::<Vec<_>>();
+                len = processable_asset_count(files.iter().map(String::as_str));
+                modtype = get_current_pak_characteristics(files);
+            }
+
+            if is_archive {
+                modtype = "Season 2 Archives".to_string();
+                extensible_vec.extend(archive_mods(path)?);
+            }
+
+            Ok(InstallableMod {
+                mod_name: path.file_stem().unwrap().to_str().unwrap().to_string(),
+                mod_type: modtype,
+                repak: !is_dir && pak.is_some() && !iostore,
+                fix_mesh: false,
+                is_dir,
+                reader: pak,
+                mod_path,
+                mount_point: "../../../".to_string(),
+                path_hash_seed: "00000000".to_string(),
+                total_files: len,
+                iostore,
+                is_archived: is_archive,
+                source: Some(path.clone()),
+                ..Default::default()
+            })
+        })
+        .filter_map(|x: Result<InstallableMod, repak::Error>| x.ok())
+        .filter(|x| !x.is_archived)
+        .collect::<Vec<_>>();
+
+    installable_mods.extend(extensible_vec);
+
+    // debug!("Mapped installable mods: {:?}", installable_mods);
+    installable_mods
+}
+
+pub fn map_paths_to_mods(paths: &[PathBuf]) -> Vec<InstallableMod> {
+    let installable_mods = map_to_mods_internal(paths);
+    installable_mods
+}
@@ -1,9 +1,13 @@
-pub mod archives;
 pub mod iotoc;
 pub mod pak_files;
 pub mod patch_meshes;
+pub mod transaction;
 
+use crate::catalog::record_install;
 use crate::install_mod::InstallableMod;
+use crate::load_order::{
+    installed_mod_priority, mod_priority, with_mod_priority, DEFAULT_MOD_PRIORITY,
+};
 use iotoc::{convert_directory_to_iostore, to_legacy_uasset_fast_with_progress};
 use pak_files::create_repak_from_pak;
 use std::path::{Path, PathBuf};
@@ -12,8 +16,8 @@ use std::sync::mpsc::Sender;
 use std::sync::Arc;
 use std::sync::{Mutex, OnceLock};
 use tracing::{error, info, instrument, warn};
+use transaction::InstallTransaction;
 
-const MOD_NAME_SUFFIX: &str = "_9999999_P";
 static KAWAII_RUNTIME_ERROR_SENDER: OnceLock<Mutex<Option<Sender<String>>>> = OnceLock::new();
 
 pub fn register_kawaii_runtime_error_sender(sender: Sender<String>) {
@@ -24,17 +28,37 @@ pub fn register_kawaii_runtime_error_sender(sender: Sender<String>) {
 }
 
 pub(crate) fn ensure_mod_name_suffix(name: &str) -> String {
-    if name.ends_with(MOD_NAME_SUFFIX) {
+    if mod_priority(name).is_some() {
         name.to_string()
     } else {
-        format!("{name}{MOD_NAME_SUFFIX}")
+        with_mod_priority(name, DEFAULT_MOD_PRIORITY)
     }
 }
 
+/// Names a mod for installation, keeping the load-order priority of an already installed mod with
+/// the same base name so reinstalling or updating a mod does not reset its place in the order.
+fn install_mod_name(mod_directory: &Path, name: &str) -> String {
+    if mod_priority(name).is_some() {
+        return name.to_string();
+    }
+    match installed_mod_priority(mod_directory, name) {
+        Some(priority) => with_mod_priority(name, priority),
+        None => ensure_mod_name_suffix(name),
+    }
+}
+
+static TO_LEGACY_CONSOLE_HOOK: OnceLock<fn()> = OnceLock::new();
+
+/// Registers a callback run before to-legacy extraction prints its progress, which the GUI uses
+/// to route output into its release-build console.
+pub fn register_to_legacy_console_hook(hook: fn()) {
+    let _ = TO_LEGACY_CONSOLE_HOOK.set(hook);
+}
+
 #[cfg(all(windows, not(debug_assertions)))]
 fn show_to_legacy_console() {
-    if crate::has_attached_console() {
-        crate::redirect_stdio();
+    if let Some(hook) = TO_LEGACY_CONSOLE_HOOK.get() {
+        hook();
     }
 }
 
@@ -44,45 +68,9 @@ fn close_to_legacy_console() {
     // per-mod to-legacy calls causes Windows to allocate a new console each time.
 }
 
-fn backup_existing_mod_files(
-    mod_directory: &Path,
-    normalized_mod_name: &str,
-) -> std::io::Result<()> {
-    for ext in ["pak", "utoc", "ucas"] {
-        let path = mod_directory.join(format!("{normalized_mod_name}.{ext}"));
-        if !path.exists() {
-            continue;
-        }
-
-        let mut backup = mod_directory.join(format!("{normalized_mod_name}.{ext}.rebak"));
-        let mut suffix = 1usize;
-        while backup.exists() {
-            backup = mod_directory.join(format!("{normalized_mod_name}.{ext}.{suffix}.rebak"));
-            suffix += 1;
-        }
-        std::fs::rename(&path, &backup)?;
-    }
-
-    Ok(())
-}
-
-fn copy_fixed_iostore_files(
-    from_dir: &Path,
-    mod_directory: &Path,
-    normalized_mod_name: &str,
-) -> std::io::Result<()> {
-    for ext in ["pak", "utoc", "ucas"] {
-        let file_name = format!("{normalized_mod_name}.{ext}");
-        let src = from_dir.join(&file_name);
-        if src.exists() {
-            std::fs::copy(src, mod_directory.join(file_name))?;
-        }
-    }
-
-    Ok(())
-}
-
-pub fn show_kawaii_error_dialog_if_relevant(error: &str) -> bool {
+/// Forwards KawaiiPhysics runtime failures to the registered sender so the GUI can offer the .NET
+/// setup, logging them when nothing is listening. Returns whether `error` was such a failure.
+pub fn report_kawaii_runtime_error(error: &str) -> bool {
     let lower = error.to_ascii_lowercase();
     let looks_like_kawaii_runtime_error = lower.contains("kawaii")
         && (lower.contains(".net")
@@ -105,16 +93,11 @@ pub fn show_kawaii_error_dialog_if_relevant(error: &str) -> bool {
         }
     }
 
-    rfd::MessageDialog::new()
-        .set_buttons(rfd::MessageButtons::Ok)
-        .set_level(rfd::MessageLevel::Error)
-        .set_title("KawaiiPhysics needs .NET")
-        .set_description(format!(
-            "KawaiiPhysics could not start.\n\n{}\n\nOriginal error:\n{}",
-            retoc::kawaii_physics_dependency_message(),
-            error
-        ))
-        .show();
+    error!(
+        "KawaiiPhysics could not start. {} Original error: {}",
+        retoc::kawaii_physics_dependency_message(),
+        error
+    );
 
     true
 }
@@ -188,10 +171,11 @@ pub fn fix_installed_iostore_kawaii_physics(
     chunkdir: &Option<PathBuf>,
     kawaii_physics_usmap: &Option<PathBuf>,
 ) -> Result<(), repak::Error> {
-    let output_temp = tempfile::tempdir().map_err(repak::Error::Io)?;
+    let transaction = InstallTransaction::begin(mod_directory, &installable_mod.mod_name)
+        .map_err(repak::Error::Io)?;
     repack_iostore_via_fast_extract(
         installable_mod,
-        output_temp.path(),
+        transaction.staging_dir(),
         mod_directory,
         installed_mods_ptr,
         chunkdir,
@@ -201,10 +185,7 @@ pub fn fix_installed_iostore_kawaii_physics(
         None,
     )?;
 
-    backup_existing_mod_files(mod_directory, &installable_mod.mod_name)
-        .map_err(repak::Error::Io)?;
-    copy_fixed_iostore_files(output_temp.path(), mod_directory, &installable_mod.mod_name)
-        .map_err(repak::Error::Io)?;
+    transaction.commit().map_err(repak::Error::Io)?;
 
     Ok(())
 }
@@ -217,10 +198,11 @@ pub fn patch_installed_iostore_default_hidden_materials(
     kawaii_physics_usmap: &Option<PathBuf>,
     default_hidden_material_bitmaps: Option<&[u64]>,
 ) -> Result<(), repak::Error> {
-    let output_temp = tempfile::tempdir().map_err(repak::Error::Io)?;
+    let transaction = InstallTransaction::begin(mod_directory, &installable_mod.mod_name)
+        .map_err(repak::Error::Io)?;
     repack_iostore_via_fast_extract(
         installable_mod,
-        output_temp.path(),
+        transaction.staging_dir(),
         mod_directory,
         installed_mods_ptr,
         chunkdir,
@@ -230,10 +212,7 @@ pub fn patch_installed_iostore_default_hidden_materials(
         default_hidden_material_bitmaps,
     )?;
 
-    backup_existing_mod_files(mod_directory, &installable_mod.mod_name)
-        .map_err(repak::Error::Io)?;
-    copy_fixed_iostore_files(output_temp.path(), mod_directory, &installable_mod.mod_name)
-        .map_err(repak::Error::Io)?;
+    transaction.commit().map_err(repak::Error::Io)?;
 
     Ok(())
 }
@@ -267,9 +246,24 @@ pub fn install_mods_in_viewport(
             break;
         }
 
-        let normalized_mod_name = ensure_mod_name_suffix(&installable_mod.mod_name);
+        if let Err(e) = installable_mod.extract_pending_archive_payload() {
+            error!(mod_name = %installable_mod.mod_name, error = %e, "Failed to extract archived mod");
+            continue;
+        }
+
+        let normalized_mod_name = install_mod_name(mod_directory, &installable_mod.mod_name);
         installable_mod.mod_name = normalized_mod_name.clone();
 
+        // stage the install so a failure or crash never leaves a half-written mod behind
+        let transaction = match InstallTransaction::begin(mod_directory, &normalized_mod_name) {
+            Ok(transaction) => transaction,
+            Err(e) => {
+                error!(mod_name = %installable_mod.mod_name, error = %e, "Failed to create install staging folder");
+                continue;
+            }
+        };
+        let staging_dir = transaction.staging_dir().to_path_buf();
+
         if installable_mod.iostore {
             if installable_mod.repak {
                 let source_mods_dir = installable_mod
@@ -279,7 +273,7 @@ pub fn install_mods_in_viewport(
                 info!(mod_name = %installable_mod.mod_name, "Repacking IoStore mod via fast extraction");
                 if let Err(e) = repack_iostore_via_fast_extract(
                     installable_mod,
-                    mod_directory,
+                    &staging_dir,
                     source_mods_dir,
                     installed_mods_ptr.clone(),
                     chunkdir,
@@ -288,9 +282,11 @@ pub fn install_mods_in_viewport(
                     installable_mod.default_hidden_material_patch,
                     None,
                 ) {
-                    show_kawaii_error_dialog_if_relevant(&e.to_string());
+                    report_kawaii_runtime_error(&e.to_string());
                     error!(mod_name = %installable_mod.mod_name, error = %e, "Failed to repack IoStore mod");
+                    continue;
                 }
+                commit_install(installable_mod, transaction);
                 continue;
             }
 
@@ -306,15 +302,20 @@ pub fn install_mods_in_viewport(
                 (ucas_path, format!("{normalized_mod_name}.ucas")),
             ];
 
+            let mut copied = true;
             for (file, target_name) in files_to_copy {
-                if let Err(e) = std::fs::copy(&file, mod_directory.join(target_name)) {
+                if let Err(e) = std::fs::copy(&file, staging_dir.join(target_name)) {
                     error!(
                         file_name = %file.file_name().and_then(|name| name.to_str()).unwrap_or("<unknown>"),
                         error = ?e,
                         "Unable to copy file"
                     );
+                    copied = false;
                 }
             }
+            if copied {
+                commit_install(installable_mod, transaction);
+            }
             installed_mods_ptr.fetch_add(
                 installable_mod.total_files.max(1).min(i32::MAX as usize) as i32,
                 Ordering::SeqCst,
@@ -326,16 +327,18 @@ pub fn install_mods_in_viewport(
             info!(mod_name = %installable_mod.mod_name, is_repak = true, "Repacking mod");
             if let Err(e) = create_repak_from_pak(
                 installable_mod,
-                PathBuf::from(mod_directory),
+                staging_dir,
                 installed_mods_ptr.clone(),
                 kawaii_physics_usmap,
                 installable_mod.kawaii_porter,
                 installable_mod.default_hidden_material_patch,
                 None,
             ) {
-                show_kawaii_error_dialog_if_relevant(&e.to_string());
+                report_kawaii_runtime_error(&e.to_string());
                 error!(mod_name = %installable_mod.mod_name, error = %e, "Failed to create repak from pak");
+                continue;
             }
+            commit_install(installable_mod, transaction);
             continue;
         }
 
@@ -347,11 +350,15 @@ pub fn install_mods_in_viewport(
                 source_is_dir = installable_mod.mod_path.is_dir(),
                 "Copying pak mod directly"
             );
-            std::fs::copy(
+            match std::fs::copy(
                 &installable_mod.mod_path,
-                mod_directory.join(format!("{normalized_mod_name}.pak")),
-            )
-            .unwrap();
+                staging_dir.join(format!("{normalized_mod_name}.pak")),
+            ) {
+                Ok(_) => commit_install(installable_mod, transaction),
+                Err(e) => {
+                    error!(mod_name = %installable_mod.mod_name, error = %e, "Unable to copy pak mod")
+                }
+            }
             installed_mods_ptr.fetch_add(
                 installable_mod.total_files.max(1).min(i32::MAX as usize) as i32,
                 Ordering::SeqCst,
@@ -362,7 +369,7 @@ pub fn install_mods_in_viewport(
         if installable_mod.is_dir {
             let res = convert_directory_to_iostore(
                 installable_mod,
-                PathBuf::from(&mod_directory),
+                staging_dir,
                 PathBuf::from(&installable_mod.mod_path),
                 installed_mods_ptr.clone(),
                 kawaii_physics_usmap.clone(),
@@ -371,14 +378,14 @@ pub fn install_mods_in_viewport(
                 None,
             );
             if let Err(e) = res {
-                show_kawaii_error_dialog_if_relevant(&e.to_string());
+                report_kawaii_runtime_error(&e.to_string());
                 error!(
                     mod_name = %installable_mod.mod_name,
                     source_is_dir = installable_mod.mod_path.is_dir(),
                     error = %e,
                     "Failed to convert directory"
                 );
-            } else {
+            } else if commit_install(installable_mod, transaction) {
                 info!(mod_name = %installable_mod.mod_name, "Installed directory mod");
             }
         }
@@ -387,3 +394,22 @@ pub fn install_mods_in_viewport(
     AtomicI32::store(&installed_mods_ptr, -255, Ordering::SeqCst);
     info!("Install worker finished");
 }
+
+/// Moves a staged install into the mods directory. Returns whether the mod was installed.
+fn commit_install(installable_mod: &InstallableMod, transaction: InstallTransaction) -> bool {
+    let pak_path = transaction.installed_pak_path();
+    match transaction.commit() {
+        Ok(()) => {
+            record_install(&pak_path, installable_mod.source.as_deref());
+            true
+        }
+        Err(e) => {
+            error!(
+                mod_name = %installable_mod.mod_name,
+                error = %e,
+                "Failed to move installed mod into place"
+            );
+            false
+        }
+    }
+}
@@ -1,7 +1,9 @@
+use crate::install_mod::install_mod_logic::ensure_mod_name_suffix;
 use crate::install_mod::install_mod_logic::pak_files::repak_dir;
 use crate::install_mod::install_mod_logic::patch_meshes;
-use crate::install_mod::{InstallableMod, AES_KEY};
+use crate::install_mod::{retoc_aes_key, InstallableMod, AES_KEY};
 use crate::utils::collect_files;
+use crate::utoc_utils::open_toc;
 use path_slash::PathExt;
 use rayon::iter::IntoParallelRefIterator;
 use rayon::iter::ParallelIterator;
@@ -11,14 +13,11 @@ use std::collections::HashSet;
 use std::fs::File;
 use std::io::BufWriter;
 use std::path::{Path, PathBuf};
-use std::str::FromStr;
 use std::sync::atomic::{AtomicI32, Ordering};
 use std::sync::Arc;
 use tracing::{debug, info, instrument};
 use walkdir::WalkDir;
 
-const MOD_NAME_SUFFIX: &str = "_9999999_P";
-
 struct TracingRetocLogProvider {
     installed_assets: Arc<AtomicI32>,
     base_progress: i32,
@@ -84,15 +83,9 @@ fn finish_progress_phase(progress: &AtomicI32, base_progress: i32, phase_units:
     progress.fetch_max(base_progress.saturating_add(phase_units), Ordering::SeqCst);
 }
 
-fn ensure_mod_name_suffix(name: &str) -> String {
-    if name.ends_with(MOD_NAME_SUFFIX) {
-        name.to_string()
-    } else {
-        format!("{name}{MOD_NAME_SUFFIX}")
-    }
-}
-
-fn resolve_package_filter_path(package_name: &str) -> Option<String> {
+/// Maps a package name from an IoStore manifest to the mount-relative path retoc's to-legacy
+/// filters expect, e.g. `/Game/Foo` to `../../../Marvel/Content/Foo`.
+pub fn resolve_package_filter_path(package_name: &str) -> Option<String> {
     let package_name = package_name.trim().replace('\\', "/");
     if package_name.is_empty() {
         return None;
@@ -121,24 +114,33 @@ fn resolve_package_filter_path(package_name: &str) -> Option<String> {
     Some(package_name.trim_start_matches('/').to_string())
 }
 
-fn build_to_legacy_filter(utoc_path: PathBuf, config: Arc<retoc::Config>) -> Vec<String> {
-    action_manifest(ActionManifest::new(utoc_path), config)
-        .map(|ops| {
-            let mut set = HashSet::new();
-
-            for entry in &ops.oplog.entries {
-                let Some(package_data) = entry.packagedata.first() else {
-                    continue;
-                };
-                let package_path = package_data.filename.trim();
-                if let Some(filter_path) = resolve_package_filter_path(package_path) {
-                    set.insert(filter_path);
-                }
-            }
-
-            set.into_iter().collect()
-        })
-        .unwrap_or_default()
+/// Legacy paths of the packages in a `.utoc`, sorted, to limit a to-legacy conversion to them.
+/// Shared with retoc-rivals-cli.
+pub fn build_to_legacy_filter(
+    utoc_path: &Path,
+    config: Arc<retoc::Config>,
+) -> Result<Vec<String>, repak::Error> {
+    let manifest =
+        action_manifest(ActionManifest::new(utoc_path.to_path_buf()), config).map_err(|e| {
+            repak::Error::Io(std::io::Error::other(format!(
+                "Failed to read the package manifest of {}: {e}",
+                utoc_path.display()
+            )))
+        })?;
+    let mut set = HashSet::new();
+
+    for entry in &manifest.oplog.entries {
+        let Some(package_data) = entry.packagedata.first() else {
+            continue;
+        };
+        if let Some(filter_path) = resolve_package_filter_path(package_data.filename.trim()) {
+            set.insert(filter_path);
+        }
+    }
+
+    let mut filters = set.into_iter().collect::<Vec<_>>();
+    filters.sort();
+    Ok(filters)
 }
 
 fn to_legacy_config() -> Result<Arc<retoc::Config>, repak::Error> {
@@ -146,13 +148,7 @@ fn to_legacy_config() -> Result<Arc<retoc::Config>, repak::Error> {
         container_header_version_override: None,
         ..Default::default()
     };
-    let aes_toc =
-        retoc::AesKey::from_str("0C263D8C22DCB085894899C3A3796383E9BF9DE0CBFB08C9BF2DEF2E84F29D74")
-            .map_err(|e| {
-                repak::Error::Io(std::io::Error::other(format!(
-                    "Failed to parse AES key: {e}"
-                )))
-            })?;
+    let aes_toc = retoc_aes_key().map_err(|e| repak::Error::Io(std::io::Error::other(e)))?;
     config.aes_keys.insert(retoc::FGuid::default(), aes_toc);
     Ok(Arc::new(config))
 }
@@ -194,7 +190,9 @@ fn hardlink_iostore_container(src_utoc: &Path, dst_dir: &Path) -> Result<(), rep
     Ok(())
 }
 
-fn should_open_fast_game_container(path: &Path) -> bool {
+/// Whether a game container is needed to resolve mod dependencies on the fast path: `global`
+/// and the character pakchunks.
+pub fn should_open_fast_game_container(path: &Path) -> bool {
     let Some(stem) = path.file_stem().and_then(|stem| stem.to_str()) else {
         return false;
     };
@@ -335,13 +333,7 @@ pub fn convert_directory_to_iostore(
         ..Default::default()
     };
 
-    let aes_toc =
-        retoc::AesKey::from_str("0C263D8C22DCB085894899C3A3796383E9BF9DE0CBFB08C9BF2DEF2E84F29D74")
-            .map_err(|e| {
-                repak::Error::Io(std::io::Error::other(format!(
-                    "Failed to parse AES key: {e}"
-                )))
-            })?;
+    let aes_toc = retoc_aes_key().map_err(|e| repak::Error::Io(std::io::Error::other(e)))?;
 
     config.aes_keys.insert(FGuid::default(), aes_toc.clone());
     let config = Arc::new(config);
@@ -462,7 +454,7 @@ pub fn to_legacy_uasset(
     // Build filter list
     let utoc_path = pak.with_extension("utoc");
     let config = to_legacy_config()?;
-    let filter = build_to_legacy_filter(utoc_path, config.clone());
+    let filter = build_to_legacy_filter(&utoc_path, config.clone())?;
     info!(package_count = filter.len(), "Prepared to-legacy filter");
 
     let legacy_output_dir = temp_path.join(mod_stem);
@@ -542,6 +534,34 @@ pub fn to_legacy_uasset_fast(
         output_dir,
         mods_dir,
         game_paks_dir,
+        None,
+        Arc::new(TracingOnlyRetocLogProvider),
+    )
+}
+
+/// Converts one package of an IoStore mod to legacy assets in `output_dir/<mod name>`.
+/// `package_path` is the package's `.uasset` path relative to the container mount point, as
+/// listed in the `.utoc` directory index.
+#[instrument(skip_all, fields(package_path))]
+pub fn to_legacy_package(
+    pak: PathBuf,
+    package_path: &str,
+    output_dir: PathBuf,
+    mods_dir: PathBuf,
+    game_paks_dir: PathBuf,
+) -> Result<(), repak::Error> {
+    let toc = open_toc(&pak.with_extension("utoc"))
+        .map_err(|e| repak::Error::Io(std::io::Error::other(e)))?;
+    let mount_point = toc.mount_point().unwrap_or("../../../");
+    let filter = resolve_package_filter_path(&format!("{mount_point}{package_path}"))
+        .into_iter()
+        .collect();
+    to_legacy_uasset_fast_inner(
+        pak,
+        output_dir,
+        mods_dir,
+        game_paks_dir,
+        Some(filter),
         Arc::new(TracingOnlyRetocLogProvider),
     )
 }
@@ -561,6 +581,7 @@ pub fn to_legacy_uasset_fast_with_progress(
         output_dir,
         mods_dir,
         game_paks_dir,
+        None,
         Arc::new(TracingRetocLogProvider {
             installed_assets: packed_files_count.clone(),
             base_progress,
@@ -576,11 +597,14 @@ pub fn to_legacy_uasset_fast_with_progress(
     result
 }
 
+/// Converts a mod to legacy assets in `output_dir/<mod name>`. Only the packages matching
+/// `filter` are converted, every package of the mod when it is `None`.
 fn to_legacy_uasset_fast_inner(
     pak: PathBuf,
     output_dir: PathBuf,
     mods_dir: PathBuf,
     game_paks_dir: PathBuf,
+    filter: Option<Vec<String>>,
     log_provider: Arc<dyn retoc::LogProvider>,
 ) -> Result<(), repak::Error> {
     retoc::set_log_provider(log_provider);
@@ -604,7 +628,10 @@ fn to_legacy_uasset_fast_inner(
         .to_string();
 
     let config = to_legacy_config()?;
-    let filter = build_to_legacy_filter(pak.with_extension("utoc"), config.clone());
+    let filter = match filter {
+        Some(filter) => filter,
+        None => build_to_legacy_filter(&pak.with_extension("utoc"), config.clone())?,
+    };
     info!(
         package_count = filter.len(),
         "Prepared fast to-legacy filter"
@@ -674,7 +701,7 @@ pub fn to_legacy_uasset_fast_batch(
             .to_string();
         let output = output_dir.join(&mod_stem);
         std::fs::create_dir_all(&output).map_err(repak::Error::Io)?;
-        let filter = build_to_legacy_filter(pak.with_extension("utoc"), config.clone());
+        let filter = build_to_legacy_filter(&pak.with_extension("utoc"), config.clone())?;
         info!(
             mod_name = %mod_stem,
             package_count = filter.len(),
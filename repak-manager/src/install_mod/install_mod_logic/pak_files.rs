@@ -15,18 +15,9 @@ use std::sync::atomic::{AtomicI32, Ordering};
 use std::sync::Arc;
 use tempfile::tempdir;
 
+use super::ensure_mod_name_suffix;
 use super::iotoc::convert_directory_to_iostore;
 
-const MOD_NAME_SUFFIX: &str = "_9999999_P";
-
-fn ensure_mod_name_suffix(name: &str) -> String {
-    if name.ends_with(MOD_NAME_SUFFIX) {
-        name.to_string()
-    } else {
-        format!("{name}{MOD_NAME_SUFFIX}")
-    }
-}
-
 pub fn extract_pak_to_dir(pak: &InstallableMod, install_dir: PathBuf) -> Result<(), repak::Error> {
     let pak_reader = pak.clone().reader.clone().unwrap();
 
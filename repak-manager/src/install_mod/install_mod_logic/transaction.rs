@@ -0,0 +1,499 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+use tracing::{info, warn};
+
+/// Staging folder installs are written to first, see [`staging_root`]. Older versions kept it
+/// inside the mods directory, where recovery still looks for it.
+pub const STAGING_DIR: &str = ".repak_install";
+const JOURNAL_FILE: &str = "journal.json";
+const BACKUP_DIR: &str = "backup";
+const MOD_EXTENSIONS: [&str; 3] = ["pak", "utoc", "ucas"];
+
+/// Renames an install still has to perform. Written before the first rename so an interrupted
+/// commit can be completed or rolled back on the next start.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct InstallJournal {
+    pub mod_name: String,
+    /// Existing files of the mod moved aside into the staging folder.
+    pub backups: Vec<JournalRename>,
+    /// Staged files moved into the mods directory.
+    pub installs: Vec<JournalRename>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct JournalRename {
+    pub from: PathBuf,
+    pub to: PathBuf,
+}
+
+/// Where installs into `mod_directory` are staged. The game mounts every `.pak` below its `Paks`
+/// folder, so staged files and backups are kept next to `Paks` instead of inside it, on the same
+/// volume so the final renames stay atomic. Mods directories outside a `Paks` folder stage next to
+/// the mods directory.
+pub fn staging_root(mod_directory: &Path) -> PathBuf {
+    mod_directory
+        .ancestors()
+        .find(|dir| {
+            dir.file_name()
+                .is_some_and(|name| name.eq_ignore_ascii_case("paks"))
+        })
+        .unwrap_or(mod_directory)
+        .parent()
+        .unwrap_or(mod_directory)
+        .join(STAGING_DIR)
+}
+
+/// An install staged in `<staging root>/<id>`, see [`staging_root`]. Dropping it without
+/// committing deletes the staged files and leaves the installed mod untouched.
+pub struct InstallTransaction {
+    mod_directory: PathBuf,
+    mod_name: String,
+    staging: TempDir,
+}
+
+impl InstallTransaction {
+    pub fn begin(mod_directory: &Path, mod_name: &str) -> std::io::Result<Self> {
+        let root = staging_root(mod_directory);
+        fs::create_dir_all(&root)?;
+        let staging = tempfile::Builder::new()
+            .prefix(&format!("{mod_name}-"))
+            .tempdir_in(&root)?;
+        Ok(Self {
+            mod_directory: mod_directory.to_path_buf(),
+            mod_name: mod_name.to_string(),
+            staging,
+        })
+    }
+
+    /// Directory the install writes its `.pak`/`.utoc`/`.ucas` into.
+    pub fn staging_dir(&self) -> &Path {
+        self.staging.path()
+    }
+
+    /// The `.pak` the install will have once committed.
+    pub fn installed_pak_path(&self) -> PathBuf {
+        self.mod_directory.join(format!("{}.pak", self.mod_name))
+    }
+
+    /// Moves the staged files into the mods directory, replacing any installed files of the same
+    /// mod. If a rename fails the previous files are restored.
+    pub fn commit(self) -> std::io::Result<()> {
+        let staging = self.staging.path();
+        let installs = MOD_EXTENSIONS
+            .into_iter()
+            .map(|ext| format!("{}.{ext}", self.mod_name))
+            .filter(|file_name| staging.join(file_name).exists())
+            .map(|file_name| JournalRename {
+                from: staging.join(&file_name),
+                to: self.mod_directory.join(&file_name),
+            })
+            .collect::<Vec<_>>();
+        if installs.is_empty() {
+            return Err(std::io::Error::other(format!(
+                "Install of {} produced no files",
+                self.mod_name
+            )));
+        }
+        // replace the whole triple so a pak-only update does not keep a stale utoc/ucas
+        let backups = MOD_EXTENSIONS
+            .into_iter()
+            .map(|ext| format!("{}.{ext}", self.mod_name))
+            .filter(|file_name| self.mod_directory.join(file_name).exists())
+            .map(|file_name| JournalRename {
+                from: self.mod_directory.join(&file_name),
+                to: staging.join(BACKUP_DIR).join(&file_name),
+            })
+            .collect::<Vec<_>>();
+        if !backups.is_empty() {
+            fs::create_dir_all(staging.join(BACKUP_DIR))?;
+        }
+
+        let journal = InstallJournal {
+            mod_name: self.mod_name.clone(),
+            backups,
+            installs,
+        };
+        write_journal(staging, &journal)?;
+        if let Err(e) = complete(&journal) {
+            warn!(mod_name = %self.mod_name, error = %e, "Install commit failed, rolling back");
+            if let Err(rollback_error) = roll_back(&journal) {
+                // keep the staging folder and journal so recovery can retry
+                warn!(error = %rollback_error, "Install rollback failed");
+                let _ = self.staging.keep();
+            }
+            return Err(e);
+        }
+
+        info!(mod_name = %self.mod_name, files = journal.installs.len(), "Committed install");
+        // TempDir removes the journal and backups
+        Ok(())
+    }
+}
+
+fn write_journal(staging: &Path, journal: &InstallJournal) -> std::io::Result<()> {
+    let json = serde_json::to_vec_pretty(journal).map_err(std::io::Error::other)?;
+    let mut file = fs::File::create(staging.join(JOURNAL_FILE))?;
+    file.write_all(&json)?;
+    file.sync_all()
+}
+
+/// Performs the journal's renames. Renames already done are skipped so this can resume an
+/// interrupted commit. A file that showed up at an install's destination since the commit began
+/// is not the journal's and fails the commit instead of being replaced, and a backup already
+/// taken is never overwritten.
+fn complete(journal: &InstallJournal) -> std::io::Result<()> {
+    for backup in &journal.backups {
+        if backup.to.exists() {
+            continue;
+        }
+        if !backup.from.exists() {
+            return Err(missing(&backup.from));
+        }
+        move_file(&backup.from, &backup.to)?;
+    }
+    for install in &journal.installs {
+        match (install.from.exists(), install.to.exists()) {
+            (true, false) => move_file(&install.from, &install.to)?,
+            (true, true) => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::AlreadyExists,
+                    format!("{} was replaced during the install", install.to.display()),
+                ))
+            }
+            (false, true) => {}
+            (false, false) => return Err(missing(&install.from)),
+        }
+    }
+    Ok(())
+}
+
+/// Undoes the journal's renames in reverse commit order, putting the previously installed files
+/// back. Fails unless every backup is back where it was taken from.
+fn roll_back(journal: &InstallJournal) -> std::io::Result<()> {
+    for rename in journal.backups.iter().chain(&journal.installs).rev() {
+        if rename.to.exists() && !rename.from.exists() {
+            move_file(&rename.to, &rename.from)?;
+        }
+    }
+    match journal
+        .backups
+        .iter()
+        .find(|backup| backup.to.exists() || !backup.from.exists())
+    {
+        Some(backup) => Err(std::io::Error::other(format!(
+            "{} could not be restored from {}",
+            backup.from.display(),
+            backup.to.display()
+        ))),
+        None => Ok(()),
+    }
+}
+
+fn missing(path: &Path) -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::NotFound,
+        format!("{} is missing", path.display()),
+    )
+}
+
+/// Renames `from` to `to`, copying instead when the mods directory links to another volume than
+/// the staging folder.
+fn move_file(from: &Path, to: &Path) -> std::io::Result<()> {
+    match fs::rename(from, to) {
+        Err(e) if e.kind() == std::io::ErrorKind::CrossesDevices => {
+            fs::copy(from, to)?;
+            fs::remove_file(from)
+        }
+        result => result,
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct RecoveryReport {
+    /// Interrupted commits that were finished.
+    pub completed: Vec<String>,
+    /// Interrupted commits that could not be finished and were undone.
+    pub rolled_back: Vec<String>,
+    /// Installs interrupted before they were staged; nothing was installed.
+    pub discarded: usize,
+    pub errors: Vec<String>,
+}
+
+impl RecoveryReport {
+    pub fn is_empty(&self) -> bool {
+        self.completed.is_empty()
+            && self.rolled_back.is_empty()
+            && self.discarded == 0
+            && self.errors.is_empty()
+    }
+}
+
+fn read_journal(path: &Path) -> Result<InstallJournal, String> {
+    let bytes = fs::read(path)
+        .map_err(|e| format!("Failed to read install journal {}: {e}", path.display()))?;
+    serde_json::from_slice(&bytes)
+        .map_err(|e| format!("Failed to parse install journal {}: {e}", path.display()))
+}
+
+/// Finishes installs interrupted by a crash, or undoes them when they cannot be finished or
+/// `rollback` is set, then removes their staging folders.
+pub fn recover_interrupted_installs(mod_directory: &Path, rollback: bool) -> RecoveryReport {
+    let mut report = RecoveryReport::default();
+    let mut roots = vec![staging_root(mod_directory), mod_directory.join(STAGING_DIR)];
+    roots.dedup();
+    for root in roots {
+        recover_staging_root(&root, rollback, &mut report);
+    }
+    report
+}
+
+fn recover_staging_root(root: &Path, rollback: bool, report: &mut RecoveryReport) {
+    let Ok(entries) = fs::read_dir(root) else {
+        return;
+    };
+
+    for staging in entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+    {
+        let journal_path = staging.join(JOURNAL_FILE);
+        if journal_path.exists() {
+            let journal = match read_journal(&journal_path) {
+                Ok(journal) => journal,
+                Err(e) => {
+                    report.errors.push(e);
+                    continue;
+                }
+            };
+            match (!rollback).then(|| complete(&journal)) {
+                Some(Ok(())) => report.completed.push(journal.mod_name),
+                outcome => {
+                    if let Some(Err(e)) = outcome {
+                        warn!(mod_name = %journal.mod_name, error = %e, "Could not finish interrupted install");
+                    }
+                    if let Err(e) = roll_back(&journal) {
+                        report.errors.push(format!(
+                            "Failed to roll back install of {}: {e}",
+                            journal.mod_name
+                        ));
+                        continue;
+                    }
+                    report.rolled_back.push(journal.mod_name);
+                }
+            }
+        } else {
+            report.discarded += 1;
+        }
+        if let Err(e) = fs::remove_dir_all(&staging) {
+            report.errors.push(format!(
+                "Failed to remove staging folder {}: {e}",
+                staging.display()
+            ));
+        }
+    }
+    let _ = fs::remove_dir(root);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Stages `Foo.pak` over an installed one and writes the journal of an interrupted commit.
+    fn interrupted_install(mod_directory: &Path) -> PathBuf {
+        let staging = staging_root(mod_directory).join("Foo-interrupted");
+        fs::create_dir_all(staging.join(BACKUP_DIR)).unwrap();
+        fs::create_dir_all(mod_directory).unwrap();
+        fs::write(mod_directory.join("Foo.pak"), "old").unwrap();
+        fs::write(staging.join("Foo.pak"), "new").unwrap();
+        let journal = InstallJournal {
+            mod_name: "Foo".to_string(),
+            backups: vec![JournalRename {
+                from: mod_directory.join("Foo.pak"),
+                to: staging.join(BACKUP_DIR).join("Foo.pak"),
+            }],
+            installs: vec![JournalRename {
+                from: staging.join("Foo.pak"),
+                to: mod_directory.join("Foo.pak"),
+            }],
+        };
+        write_journal(&staging, &journal).unwrap();
+        staging
+    }
+
+    #[test]
+    fn test_staging_root_outside_paks() {
+        assert_eq!(
+            staging_root(Path::new("Game/Content/Paks/~mods")),
+            Path::new("Game/Content").join(STAGING_DIR)
+        );
+        assert_eq!(
+            staging_root(Path::new("Game/Content/paks/~mods/disabled")),
+            Path::new("Game/Content").join(STAGING_DIR)
+        );
+        assert_eq!(
+            staging_root(Path::new("Downloads/~mods")),
+            Path::new("Downloads").join(STAGING_DIR)
+        );
+    }
+
+    #[test]
+    fn test_commit_stages_outside_mods() {
+        let dir = tempfile::tempdir().unwrap();
+        let mods = dir.path().join("Content/Paks/~mods");
+        fs::create_dir_all(&mods).unwrap();
+        fs::write(mods.join("Foo.pak"), "old").unwrap();
+
+        let transaction = InstallTransaction::begin(&mods, "Foo").unwrap();
+        assert!(!transaction
+            .staging_dir()
+            .starts_with(dir.path().join("Content/Paks")));
+        fs::write(transaction.staging_dir().join("Foo.pak"), "new").unwrap();
+        fs::write(transaction.staging_dir().join("Foo.utoc"), "new").unwrap();
+        transaction.commit().unwrap();
+
+        assert_eq!(fs::read_to_string(mods.join("Foo.pak")).unwrap(), "new");
+        assert_eq!(fs::read_to_string(mods.join("Foo.utoc")).unwrap(), "new");
+        assert!(fs::read_dir(staging_root(&mods)).unwrap().next().is_none());
+    }
+
+    #[test]
+    fn test_recover_completes_install() {
+        let dir = tempfile::tempdir().unwrap();
+        let mods = dir.path().join("Content/Paks/~mods");
+        let staging = interrupted_install(&mods);
+
+        let report = recover_interrupted_installs(&mods, false);
+        assert_eq!(report.completed, ["Foo"]);
+        assert!(report.errors.is_empty());
+        assert_eq!(fs::read_to_string(mods.join("Foo.pak")).unwrap(), "new");
+        assert!(!staging.exists());
+    }
+
+    #[test]
+    fn test_recover_rolls_back_install() {
+        let dir = tempfile::tempdir().unwrap();
+        let mods = dir.path().join("Content/Paks/~mods");
+        interrupted_install(&mods);
+        // the commit got as far as moving the old pak aside
+        let staging = staging_root(&mods).join("Foo-interrupted");
+        fs::rename(
+            mods.join("Foo.pak"),
+            staging.join(BACKUP_DIR).join("Foo.pak"),
+        )
+        .unwrap();
+
+        let report = recover_interrupted_installs(&mods, true);
+        assert_eq!(report.rolled_back, ["Foo"]);
+        assert_eq!(fs::read_to_string(mods.join("Foo.pak")).unwrap(), "old");
+        assert!(!staging.exists());
+    }
+
+    fn read_journal_of(staging: &Path) -> InstallJournal {
+        read_journal(&staging.join(JOURNAL_FILE)).unwrap()
+    }
+
+    #[test]
+    fn test_commit_failure_after_backups() {
+        let dir = tempfile::tempdir().unwrap();
+        let mods = dir.path().join("Content/Paks/~mods");
+        let staging = interrupted_install(&mods);
+        fs::write(mods.join("Foo.utoc"), "old").unwrap();
+        let mut journal = read_journal_of(&staging);
+        journal.backups.push(JournalRename {
+            from: mods.join("Foo.utoc"),
+            to: staging.join(BACKUP_DIR).join("Foo.utoc"),
+        });
+        // the staged utoc is gone, so the commit fails after installing the pak
+        journal.installs.push(JournalRename {
+            from: staging.join("Foo.utoc"),
+            to: mods.join("Foo.utoc"),
+        });
+
+        assert!(complete(&journal).is_err());
+        assert_eq!(fs::read_to_string(mods.join("Foo.pak")).unwrap(), "new");
+        roll_back(&journal).unwrap();
+        assert_eq!(fs::read_to_string(mods.join("Foo.pak")).unwrap(), "old");
+        assert_eq!(fs::read_to_string(mods.join("Foo.utoc")).unwrap(), "old");
+        assert_eq!(fs::read_to_string(staging.join("Foo.pak")).unwrap(), "new");
+    }
+
+    #[test]
+    fn test_recover_rolls_back_completed_install() {
+        let dir = tempfile::tempdir().unwrap();
+        let mods = dir.path().join("Content/Paks/~mods");
+        let staging = interrupted_install(&mods);
+        // the commit finished, the staging folder was never removed
+        complete(&read_journal_of(&staging)).unwrap();
+        assert_eq!(fs::read_to_string(mods.join("Foo.pak")).unwrap(), "new");
+
+        let report = recover_interrupted_installs(&mods, true);
+        assert_eq!(report.rolled_back, ["Foo"]);
+        assert!(report.errors.is_empty());
+        assert_eq!(fs::read_to_string(mods.join("Foo.pak")).unwrap(), "old");
+        assert!(!staging.exists());
+    }
+
+    #[test]
+    fn test_recover_twice() {
+        let dir = tempfile::tempdir().unwrap();
+        let mods = dir.path().join("Content/Paks/~mods");
+        interrupted_install(&mods);
+
+        assert_eq!(
+            recover_interrupted_installs(&mods, false).completed,
+            ["Foo"]
+        );
+        assert!(recover_interrupted_installs(&mods, false).is_empty());
+        assert!(recover_interrupted_installs(&mods, true).is_empty());
+        assert_eq!(fs::read_to_string(mods.join("Foo.pak")).unwrap(), "new");
+    }
+
+    #[test]
+    fn test_recover_keeps_backup_of_replaced_mod() {
+        let dir = tempfile::tempdir().unwrap();
+        let mods = dir.path().join("Content/Paks/~mods");
+        let staging = interrupted_install(&mods);
+        // the old pak was moved aside, then the user put another one in its place
+        fs::rename(
+            mods.join("Foo.pak"),
+            staging.join(BACKUP_DIR).join("Foo.pak"),
+        )
+        .unwrap();
+        fs::write(mods.join("Foo.pak"), "user").unwrap();
+
+        let report = recover_interrupted_installs(&mods, false);
+        assert!(report.completed.is_empty());
+        assert!(report.rolled_back.is_empty());
+        assert_eq!(report.errors.len(), 1);
+        assert_eq!(fs::read_to_string(mods.join("Foo.pak")).unwrap(), "user");
+        assert_eq!(
+            fs::read_to_string(staging.join(BACKUP_DIR).join("Foo.pak")).unwrap(),
+            "old"
+        );
+        assert_eq!(fs::read_to_string(staging.join("Foo.pak")).unwrap(), "new");
+
+        // once the user's pak is out of the way, the next run restores the old one
+        fs::remove_file(mods.join("Foo.pak")).unwrap();
+        let report = recover_interrupted_installs(&mods, true);
+        assert_eq!(report.rolled_back, ["Foo"]);
+        assert_eq!(fs::read_to_string(mods.join("Foo.pak")).unwrap(), "old");
+        assert!(!staging.exists());
+    }
+
+    #[test]
+    fn test_recover_legacy_staging() {
+        let dir = tempfile::tempdir().unwrap();
+        let mods = dir.path().join("Content/Paks/~mods");
+        let legacy = mods.join(STAGING_DIR).join("Foo-unfinished");
+        fs::create_dir_all(&legacy).unwrap();
+        fs::write(legacy.join("Foo.pak"), "partial").unwrap();
+
+        let report = recover_interrupted_installs(&mods, false);
+        assert_eq!(report.discarded, 1);
+        assert!(!mods.join(STAGING_DIR).exists());
+    }
+}
@@ -0,0 +1,218 @@
+use crate::classification::{classify_files, ModClassification};
+use crate::install_mod::install_mod_logic::transaction::STAGING_DIR;
+use crate::keys::KEYS;
+use crate::mod_toggle::{enabled_pak_path, is_legacy_disabled, migrate_legacy_disabled};
+use crate::utoc_utils::read_utoc_package_names;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{debug, info, instrument, warn};
+use walkdir::WalkDir;
+
+/// An installed mod found by [`scan_installed_mods`], without any of its contents read.
+#[derive(Clone, Debug)]
+pub struct InstalledMod {
+    /// The `.pak`, or `.pak_disabled` when the mod is disabled.
+    pub path: PathBuf,
+    pub enabled: bool,
+    pub is_iostore: bool,
+    pub signature: ModFileSignature,
+}
+
+/// Size and modification time of a mod and its companions, used to tell when cached metadata is
+/// stale.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ModFileSignature {
+    pub size: u64,
+    pub modified_secs: u64,
+}
+
+/// Returns `(enabled, is_mod_file)` for paths the manager tracks.
+pub fn mod_file_state(path: &Path) -> Option<(bool, bool)> {
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("pak") => Some((true, true)),
+        Some("pak_disabled") | Some("bak_repak") => Some((false, true)),
+        _ => None,
+    }
+}
+
+pub fn mod_file_signature(path: &Path) -> Option<ModFileSignature> {
+    let mut size = 0u64;
+    let mut modified_secs = 0u64;
+    let companions = [
+        path.to_path_buf(),
+        path.with_extension("utoc"),
+        path.with_extension("ucas"),
+    ];
+
+    for companion in companions {
+        if !companion.exists() {
+            continue;
+        }
+        let metadata = fs::metadata(&companion).ok()?;
+        size = size.saturating_add(metadata.len());
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(system_time_to_secs)
+            .unwrap_or_default();
+        modified_secs = modified_secs.max(modified);
+    }
+
+    Some(ModFileSignature {
+        size,
+        modified_secs,
+    })
+}
+
+fn system_time_to_secs(time: SystemTime) -> Option<u64> {
+    time.duration_since(UNIX_EPOCH)
+        .ok()
+        .map(|duration| duration.as_secs())
+}
+
+/// Walks the mods directory for installed mods, skipping in-progress install staging.
+pub fn scan_installed_mods(mods_dir: &Path) -> Vec<InstalledMod> {
+    let mut mods = Vec::new();
+    for entry in WalkDir::new(mods_dir)
+        .into_iter()
+        .filter_entry(|entry| entry.file_name() != STAGING_DIR)
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+    {
+        let path = entry.path();
+        let Some((enabled, is_mod_file)) = mod_file_state(path) else {
+            continue;
+        };
+        if !is_mod_file {
+            continue;
+        }
+
+        let Some(signature) = mod_file_signature(path) else {
+            warn!(
+                file_name = %path.file_name().and_then(|name| name.to_str()).unwrap_or("<unknown>"),
+                "Skipping mod with unreadable metadata"
+            );
+            continue;
+        };
+
+        mods.push(InstalledMod {
+            path: path.to_path_buf(),
+            enabled,
+            is_iostore: path.with_extension("utoc").exists(),
+            signature,
+        });
+    }
+    mods
+}
+
+/// Moves mods disabled by older versions (a renamed `.pak` with its `.utoc`/`.ucas` left in place)
+/// into the `disabled` folder with their companions. Returns the `(old, new)` paths of every mod
+/// moved so callers can carry tags over.
+pub fn migrate_legacy_disabled_mods(mods_dir: &Path) -> Vec<(PathBuf, PathBuf)> {
+    let legacy = WalkDir::new(mods_dir)
+        .into_iter()
+        .filter_entry(|entry| entry.file_name() != STAGING_DIR)
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file() && is_legacy_disabled(entry.path()))
+        .map(|entry| entry.into_path())
+        .collect::<Vec<_>>();
+
+    let mut moved = Vec::new();
+    for path in legacy {
+        match migrate_legacy_disabled(&path) {
+            Ok(new_path) => moved.push((path, new_path)),
+            Err(e) => warn!(
+                error = %e,
+                file_name = %path.file_name().and_then(|name| name.to_str()).unwrap_or("<unknown>"),
+                "Failed to migrate disabled mod"
+            ),
+        }
+    }
+    moved
+}
+
+/// Lists the packaged paths of a mod, from the IoStore package store when it has a `.utoc`.
+pub fn mod_files(pak_path: &Path, is_iostore: bool) -> Result<Vec<String>, String> {
+    if is_iostore {
+        return read_utoc_package_names(&pak_path.with_extension("utoc"));
+    }
+
+    let mut builder = repak::PakBuilder::new();
+    builder = builder.keys(KEYS.clone());
+    let file = File::open(pak_path).map_err(|e| format!("Failed to open mod: {e}"))?;
+    let pak = builder
+        .reader(&mut BufReader::new(file))
+        .map_err(|e| format!("Failed to read mod: {e}"))?;
+    Ok(pak.files())
+}
+
+/// Category and character/skin description shown next to a mod.
+#[derive(Clone, Debug)]
+pub struct ModMetadata {
+    pub category: String,
+    pub characteristic: String,
+    pub file_count: usize,
+    pub classification: ModClassification,
+}
+
+pub fn classify_mod(pak_path: &Path, is_iostore: bool) -> Result<ModMetadata, String> {
+    let files = mod_files(pak_path, is_iostore)?;
+    let classification = classify_files(&files);
+    Ok(ModMetadata {
+        category: classification.category().to_string(),
+        characteristic: classification.characteristic(),
+        file_count: files.len(),
+        classification,
+    })
+}
+
+/// Category a mod is listed under, see [`ModClassification::category`].
+pub fn detect_mod_category(files: &[String]) -> String {
+    classify_files(files).category().to_string()
+}
+
+#[instrument(skip(pak_path), fields(has_utoc = pak_path.with_extension("utoc").exists(), has_ucas = pak_path.with_extension("ucas").exists()))]
+pub fn delete_mod_files(pak_path: &Path) -> std::io::Result<()> {
+    let utoc_path = pak_path.with_extension("utoc");
+    let ucas_path = pak_path.with_extension("ucas");
+    let files_to_delete = [pak_path.to_path_buf(), utoc_path, ucas_path];
+
+    info!("Deleting mod files");
+
+    for file in files_to_delete {
+        if !file.exists() {
+            debug!(
+                file_ext = %file.extension().and_then(|ext| ext.to_str()).unwrap_or("<none>"),
+                "Skipping missing companion file"
+            );
+            continue;
+        }
+
+        fs::remove_file(&file)?;
+        info!(
+            file_ext = %file.extension().and_then(|ext| ext.to_str()).unwrap_or("<none>"),
+            "Deleted companion file"
+        );
+    }
+
+    Ok(())
+}
+
+/// Identifies a mod by its enabled location so tags and cached metadata survive toggling it into
+/// and out of the `disabled` folder.
+pub fn normalized_mod_identity(path: &Path) -> PathBuf {
+    enabled_pak_path(path).with_extension("")
+}
+
+pub fn normalized_mod_identity_string(path: &Path) -> String {
+    normalized_mod_identity(path)
+        .to_string_lossy()
+        .to_ascii_lowercase()
+}
+
+pub fn same_mod_identity(left: &Path, right: &Path) -> bool {
+    normalized_mod_identity(left) == normalized_mod_identity(right)
+}
@@ -0,0 +1,103 @@
+//! The user's AES key overrides, merged over the built-in keys of [`repak::keys`].
+//!
+//! Later sources override earlier ones: the built-in keys, `aes_keys.txt` next to
+//! `repak_mod_manager.json`, then [`AES_KEY_ENV`] for the default game.
+
+use crate::state::config_path;
+use repak::keys::{KeyConfig, KeySource, DEFAULT_GAME};
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+use tracing::error;
+
+/// Environment variable overriding the key of [`DEFAULT_GAME`].
+pub const AES_KEY_ENV: &str = "REPAK_AES_KEY";
+/// File name of the user key overrides in the config directory.
+pub const KEYS_FILE: &str = "aes_keys.txt";
+
+/// Keys of every open container, loaded once. Errors are logged and the broken source skipped.
+pub static KEYS: LazyLock<KeyConfig> = LazyLock::new(|| {
+    let (keys, errors) = load();
+    for e in errors {
+        error!(error = %e, "Failed to load AES key configuration, skipping it");
+    }
+    keys
+});
+
+/// `aes_keys.txt` next to `repak_mod_manager.json`.
+pub fn keys_path() -> PathBuf {
+    config_path().with_file_name(KEYS_FILE)
+}
+
+/// Loads the built-in keys with the overrides of [`keys_path`] and [`AES_KEY_ENV`]. A source that
+/// fails to load is left out and its error returned next to the keys, so a broken file doesn't
+/// stop the tools from opening containers with the remaining keys.
+pub fn load() -> (KeyConfig, Vec<String>) {
+    load_from(&keys_path(), std::env::var(AES_KEY_ENV).ok())
+}
+
+fn load_from(path: &Path, env_key: Option<String>) -> (KeyConfig, Vec<String>) {
+    let mut keys = KeyConfig::default();
+    let mut errors = vec![];
+    if path.exists() {
+        let merged = std::fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| keys.merge_str(&text).map_err(|e| e.to_string()));
+        if let Err(e) = merged {
+            errors.push(format!("{}: {e}", path.display()));
+        }
+    }
+    if let Some(key) = env_key {
+        if let Err(e) = keys.insert(DEFAULT_GAME, key.trim(), KeySource::Env) {
+            errors.push(format!("{AES_KEY_ENV}: {e}"));
+        }
+    }
+    (keys, errors)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use repak::keys::RIVALS_AES_KEY;
+
+    const OTHER_KEY: &str = "lNJbw660IOC+kU7cnVQ1oeqrXyhk4J6UAZrCBbcnp94=";
+
+    #[test]
+    fn test_load_from() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(KEYS_FILE);
+
+        let (keys, errors) = load_from(&path, None);
+        assert!(errors.is_empty());
+        assert_eq!(keys.key_str(DEFAULT_GAME), Some(RIVALS_AES_KEY));
+
+        std::fs::write(
+            &path,
+            format!("{{12345678-9ABC-DEF0-0FED-CBA987654321}} = {OTHER_KEY}\n"),
+        )
+        .unwrap();
+        let (keys, errors) = load_from(&path, Some(format!(" {OTHER_KEY}\n")));
+        assert!(errors.is_empty());
+        assert_eq!(
+            keys.key_str("123456789abcdef00fedcba987654321"),
+            Some(OTHER_KEY)
+        );
+        assert_eq!(keys.key_str(DEFAULT_GAME), Some(OTHER_KEY));
+    }
+
+    #[test]
+    fn test_load_from_broken_sources() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(KEYS_FILE);
+        std::fs::write(&path, format!("a = {OTHER_KEY}\nmarvel-rivals = nope\n")).unwrap();
+
+        // a broken file is skipped as a whole, the environment still applies
+        let (keys, errors) = load_from(&path, Some(OTHER_KEY.to_string()));
+        assert_eq!(errors.len(), 1);
+        assert_eq!(keys.key_str("a"), None);
+        assert_eq!(keys.key_str(DEFAULT_GAME), Some(OTHER_KEY));
+
+        let (keys, errors) = load_from(&path, Some("nope".to_string()));
+        assert_eq!(errors.len(), 2);
+        assert_eq!(keys.key_str(DEFAULT_GAME), Some(RIVALS_AES_KEY));
+    }
+}
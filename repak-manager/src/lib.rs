@@ -0,0 +1,18 @@
+//! Mod management shared by `repak-gui` and the headless `repak-manager` CLI: scanning the mods
+//! folder, installing, enabling, disabling, tagging and removing mods.
+
+pub mod archive;
+pub mod catalog;
+pub mod classification;
+pub mod duplicates;
+pub mod install_mod;
+pub mod installed;
+pub mod keys;
+pub mod load_order;
+pub mod mod_conflicts;
+pub mod mod_profiles;
+pub mod mod_toggle;
+pub mod skin_db;
+pub mod state;
+pub mod utils;
+pub mod utoc_utils;
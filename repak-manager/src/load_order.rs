@@ -0,0 +1,283 @@
+use crate::mod_toggle::{apply_moves, DISABLED_DIR};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Priority written into every mod name unless the user picks one. The game reads the number in
+/// the `_<N>_P` suffix as the patch chunk version, so a higher number mounts later and wins
+/// conflicting assets.
+pub const DEFAULT_MOD_PRIORITY: u32 = 9999999;
+
+/// Parses the load-order priority from a mod file stem such as `Example_9999999_P`.
+pub fn mod_priority(name: &str) -> Option<u32> {
+    let rest = name.strip_suffix("_P")?;
+    let (_, digits) = rest.rsplit_once('_')?;
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    digits.parse().ok()
+}
+
+/// Strips the `_<N>_P` priority suffix from a mod file stem.
+pub fn mod_base_name(name: &str) -> &str {
+    if mod_priority(name).is_none() {
+        return name;
+    }
+    let rest = &name[..name.len() - "_P".len()];
+    rest.rsplit_once('_').map(|(base, _)| base).unwrap_or(rest)
+}
+
+/// Priority of the mod at `position` in a load order listing the winning mod first. Fails past the
+/// last position a priority below [`DEFAULT_MOD_PRIORITY`] is left for.
+pub fn load_order_priority(position: usize) -> std::io::Result<u32> {
+    u32::try_from(position)
+        .ok()
+        .and_then(|position| DEFAULT_MOD_PRIORITY.checked_sub(position))
+        .ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!(
+                    "Load order can't hold more than {} mods",
+                    u64::from(DEFAULT_MOD_PRIORITY) + 1
+                ),
+            )
+        })
+}
+
+pub fn with_mod_priority(name: &str, priority: u32) -> String {
+    format!("{}_{priority}_P", mod_base_name(name))
+}
+
+/// Sort key matching the order the game mounts mods in: `_P` paks mount after regular paks,
+/// higher priorities mount later, and equal priorities mount by name. Later mods override earlier
+/// ones.
+pub fn load_order_key(path: &Path) -> (bool, u32, String) {
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let priority = mod_priority(&stem);
+    (
+        priority.is_some() || stem.ends_with("_P"),
+        priority.unwrap_or_default(),
+        stem.to_lowercase(),
+    )
+}
+
+/// Finds the priority of an installed mod with the same base name, in any enabled or disabled
+/// state, including mods moved into the `disabled` folder.
+pub fn installed_mod_priority(mod_directory: &Path, name: &str) -> Option<u32> {
+    let base_name = mod_base_name(name).to_lowercase();
+    let disabled = fs::read_dir(mod_directory.join(DISABLED_DIR))
+        .into_iter()
+        .flatten();
+    fs::read_dir(mod_directory)
+        .ok()?
+        .chain(disabled)
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| {
+            matches!(
+                path.extension().and_then(|ext| ext.to_str()),
+                Some("pak" | "pak_disabled" | "bak_repak")
+            )
+        })
+        .find_map(|path| {
+            let stem = path.file_stem()?.to_str()?;
+            (mod_base_name(stem).to_lowercase() == base_name)
+                .then(|| mod_priority(stem))
+                .flatten()
+        })
+}
+
+/// Renames a mod's `.pak` and IoStore companions so the file name carries `priority`. Returns the
+/// new `.pak` path.
+pub fn rename_mod_priority(pak_path: &Path, priority: u32) -> std::io::Result<PathBuf> {
+    let mut renamed = apply_priorities(&[(pak_path.to_path_buf(), priority)])?;
+    Ok(renamed.remove(0))
+}
+
+/// Gives every mod in `priorities` its new priority, see [`rename_mod_priority`]. Either every mod
+/// is renamed or, when one rename fails, the renames already done are reverted. Returns the new
+/// `.pak` paths in the order of `priorities`.
+pub fn apply_priorities(priorities: &[(PathBuf, u32)]) -> std::io::Result<Vec<PathBuf>> {
+    let mut moves = Vec::new();
+    let mut renamed = Vec::with_capacity(priorities.len());
+    for (pak_path, priority) in priorities {
+        let Some(stem) = pak_path.file_stem().and_then(|stem| stem.to_str()) else {
+            return Err(std::io::Error::other(format!(
+                "Invalid mod file name: {}",
+                pak_path.display()
+            )));
+        };
+        let new_stem = with_mod_priority(stem, *priority);
+        if new_stem == stem {
+            renamed.push(pak_path.clone());
+            continue;
+        }
+
+        let pak_extension = pak_path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or("pak");
+        moves.extend(
+            [pak_extension, "utoc", "ucas"]
+                .into_iter()
+                .map(|ext| {
+                    (
+                        pak_path.with_extension(ext),
+                        pak_path.with_file_name(format!("{new_stem}.{ext}")),
+                    )
+                })
+                .filter(|(from, _)| from.exists()),
+        );
+        renamed.push(pak_path.with_file_name(format!("{new_stem}.{pak_extension}")));
+    }
+    apply_moves(&moves)?;
+    Ok(renamed)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn touch(dir: &Path, names: &[&str]) {
+        for name in names {
+            fs::write(dir.join(name), name).unwrap();
+        }
+    }
+
+    fn file_names(dir: &Path) -> Vec<String> {
+        let mut names = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_load_order_priority() {
+        assert_eq!(load_order_priority(0).unwrap(), DEFAULT_MOD_PRIORITY);
+        assert_eq!(load_order_priority(2).unwrap(), DEFAULT_MOD_PRIORITY - 2);
+        assert_eq!(
+            load_order_priority(DEFAULT_MOD_PRIORITY as usize).unwrap(),
+            0
+        );
+        let e = load_order_priority(DEFAULT_MOD_PRIORITY as usize + 1).unwrap_err();
+        assert_eq!(e.kind(), std::io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_apply_moves_restores_on_failure() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        touch(dir, &["a.pak", "a.utoc", "b.pak"]);
+        let moves = [
+            (dir.join("a.pak"), dir.join("a_1_P.pak")),
+            (dir.join("a.utoc"), dir.join("a_1_P.utoc")),
+            (dir.join("b.pak"), dir.join("disabled/b.pak")),
+            // vanished between listing and renaming
+            (dir.join("c.pak"), dir.join("c_1_P.pak")),
+        ];
+
+        let e = apply_moves(&moves).unwrap_err();
+        assert_eq!(e.kind(), std::io::ErrorKind::NotFound);
+        assert_eq!(file_names(dir), ["a.pak", "a.utoc", "b.pak", "disabled"]);
+        assert!(file_names(&dir.join("disabled")).is_empty());
+    }
+
+    #[test]
+    fn test_apply_moves_refuses_existing_target() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        touch(dir, &["a.pak", "b.pak", "b_1_P.pak"]);
+        let moves = [
+            (dir.join("a.pak"), dir.join("a_1_P.pak")),
+            (dir.join("b.pak"), dir.join("b_1_P.pak")),
+        ];
+
+        let e = apply_moves(&moves).unwrap_err();
+        assert_eq!(e.kind(), std::io::ErrorKind::AlreadyExists);
+        assert_eq!(file_names(dir), ["a.pak", "b.pak", "b_1_P.pak"]);
+        assert_eq!(fs::read(dir.join("b_1_P.pak")).unwrap(), b"b_1_P.pak");
+    }
+
+    #[test]
+    fn test_apply_priorities() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        touch(
+            dir,
+            &[
+                "Hair_9999999_P.pak",
+                "Hair_9999999_P.utoc",
+                "Hair_9999999_P.ucas",
+                "Plain.pak",
+                "Skin_7_P.pak",
+                "Old_9999999_P.pak_disabled",
+            ],
+        );
+
+        let renamed = apply_priorities(&[
+            (dir.join("Hair_9999999_P.pak"), 5),
+            (dir.join("Plain.pak"), 4),
+            (dir.join("Skin_7_P.pak"), 7),
+            (dir.join("Old_9999999_P.pak_disabled"), 3),
+        ])
+        .unwrap();
+        assert_eq!(
+            renamed,
+            [
+                dir.join("Hair_5_P.pak"),
+                dir.join("Plain_4_P.pak"),
+                dir.join("Skin_7_P.pak"),
+                dir.join("Old_3_P.pak_disabled"),
+            ]
+        );
+        assert_eq!(
+            file_names(dir),
+            [
+                "Hair_5_P.pak",
+                "Hair_5_P.ucas",
+                "Hair_5_P.utoc",
+                "Old_3_P.pak_disabled",
+                "Plain_4_P.pak",
+                "Skin_7_P.pak",
+            ]
+        );
+        assert_eq!(
+            fs::read(dir.join("Hair_5_P.utoc")).unwrap(),
+            b"Hair_9999999_P.utoc"
+        );
+    }
+
+    #[test]
+    fn test_apply_priorities_restores_on_failure() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        touch(
+            dir,
+            &[
+                "A_9_P.pak",
+                "A_9_P.utoc",
+                "A_9_P.ucas",
+                "B.pak",
+                "B_2_P.pak",
+            ],
+        );
+
+        let e =
+            apply_priorities(&[(dir.join("A_9_P.pak"), 1), (dir.join("B.pak"), 2)]).unwrap_err();
+        assert_eq!(e.kind(), std::io::ErrorKind::AlreadyExists);
+        assert_eq!(
+            file_names(dir),
+            [
+                "A_9_P.pak",
+                "A_9_P.ucas",
+                "A_9_P.utoc",
+                "B.pak",
+                "B_2_P.pak"
+            ]
+        );
+    }
+}
@@ -0,0 +1,795 @@
+use clap::{Parser, Subcommand};
+use repak_manager::catalog::{self, CatalogSkin, ModSource};
+use repak_manager::classification::ModClassification;
+use repak_manager::duplicates::{
+    exact_extras, find_duplicates, DuplicateKind, NEAR_DUPLICATE_THRESHOLD,
+};
+use repak_manager::install_mod::install_mod_logic::install_mods_in_viewport;
+use repak_manager::install_mod::install_mod_logic::transaction::recover_interrupted_installs;
+use repak_manager::install_mod::{map_paths_to_mods, pick_variants};
+use repak_manager::installed::{
+    classify_mod, delete_mod_files, migrate_legacy_disabled_mods, scan_installed_mods, InstalledMod,
+};
+use repak_manager::load_order::{load_order_key, mod_base_name, mod_priority};
+use repak_manager::mod_toggle::set_mod_enabled;
+use repak_manager::skin_db::{self, skin_db};
+use repak_manager::state::ManagerState;
+use repak_manager::utils::{
+    latest_depot_usmap_path, match_exact_paks_suffix, mods_need_kawaii_mapping,
+};
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicI32};
+use std::sync::Arc;
+use tracing::warn;
+use tracing_subscriber::filter::LevelFilter;
+
+#[derive(Parser, Debug)]
+struct ActionList {
+    /// Print mods as JSON instead of a table
+    #[arg(long, default_value = "false")]
+    json: bool,
+
+    /// Skip reading each mod to detect its category and characters
+    #[arg(long, default_value = "false")]
+    fast: bool,
+}
+
+#[derive(Parser, Debug)]
+struct ActionInstall {
+    /// Archives, .pak files or mod directories to install
+    #[arg(index = 1, required = true)]
+    input: Vec<PathBuf>,
+
+    /// Repack IoStore mods so KawaiiPhysics and hidden material fixes are applied
+    #[arg(long, default_value = "false")]
+    repak: bool,
+
+    /// Don't port KawaiiPhysics assets to the current game version
+    #[arg(long, default_value = "false")]
+    no_kawaii_porter: bool,
+
+    /// Don't patch default hidden materials
+    #[arg(long, default_value = "false")]
+    no_hidden_material_patch: bool,
+
+    /// Patch skeletal meshes of legacy mods for the current game version
+    #[arg(long, default_value = "false")]
+    fix_mesh: bool,
+
+    /// Install the mods disabled
+    #[arg(long, default_value = "false")]
+    disabled: bool,
+
+    /// Option folder to install from archives with mutually exclusive options, repeated for
+    /// several groups. Defaults to the first option of each group
+    #[arg(long)]
+    variant: Vec<String>,
+}
+
+#[derive(Parser, Debug)]
+struct ActionToggle {
+    /// Mod file name, with or without extension and load order suffix
+    #[arg(index = 1, required = true)]
+    mods: Vec<String>,
+}
+
+#[derive(Parser, Debug)]
+struct ActionTag {
+    /// Mod file name, with or without extension and load order suffix
+    #[arg(index = 1)]
+    name: String,
+
+    /// Tags to add. Can be specified multiple times
+    #[arg(action = clap::ArgAction::Append, short, long)]
+    add: Vec<String>,
+
+    /// Tags to remove. Can be specified multiple times
+    #[arg(action = clap::ArgAction::Append, short, long)]
+    remove: Vec<String>,
+}
+
+#[derive(Parser, Debug)]
+struct ActionSearch {
+    /// Search terms. Limit a term with name:, hero:, skin:, tag:, category: or file:
+    #[arg(index = 1)]
+    query: Vec<String>,
+
+    /// Only mods sharing a packaged file with this mod
+    #[arg(long)]
+    touches: Option<String>,
+
+    /// Leave out mods that are no longer installed
+    #[arg(long, default_value = "false")]
+    installed: bool,
+
+    /// Print matches as JSON instead of a table
+    #[arg(long, default_value = "false")]
+    json: bool,
+}
+
+#[derive(Parser, Debug)]
+struct ActionDuplicates {
+    /// Share of packaged files two mods must have in common to count as near duplicates
+    #[arg(long, default_value_t = NEAR_DUPLICATE_THRESHOLD)]
+    threshold: f32,
+
+    /// Delete every copy but the one kept from exact duplicate groups
+    #[arg(long, default_value = "false")]
+    remove: bool,
+
+    /// Print duplicate groups as JSON instead of a table
+    #[arg(long, default_value = "false")]
+    json: bool,
+}
+
+#[derive(Parser, Debug)]
+struct ActionSkinsLookup {
+    /// Skin IDs, hero IDs or asset paths
+    #[arg(index = 1, required = true)]
+    ids: Vec<String>,
+
+    /// Print results as JSON
+    #[arg(long, default_value = "false")]
+    json: bool,
+}
+
+#[derive(Parser, Debug)]
+struct ActionSkinsImport {
+    /// Skin database, character_data.json or MarvelRivalsCharacterIDs markdown, as a file or URL
+    #[arg(index = 1)]
+    source: String,
+}
+
+#[derive(Subcommand, Debug)]
+enum SkinsAction {
+    /// Show revisions and the user overrides file
+    Info,
+    /// Look up heroes and skins by ID or asset path
+    Lookup(ActionSkinsLookup),
+    /// Merge skin data into the user overrides
+    Import(ActionSkinsImport),
+}
+
+#[derive(Subcommand, Debug)]
+enum Action {
+    /// List installed mods
+    List(ActionList),
+    /// Install archives, paks or mod directories
+    Install(ActionInstall),
+    /// Enable installed mods
+    Enable(ActionToggle),
+    /// Disable installed mods
+    Disable(ActionToggle),
+    /// Delete installed mods and their IoStore companions
+    Remove(ActionToggle),
+    /// Show, add or remove tags on a mod
+    Tag(ActionTag),
+    /// Search installed and removed mods by name, hero, skin, tag or packaged file
+    Search(ActionSearch),
+    /// Find mods installed more than once under different names
+    Duplicates(ActionDuplicates),
+    /// Inspect and update the hero and skin database
+    #[command(subcommand)]
+    Skins(SkinsAction),
+}
+
+#[derive(Parser, Debug)]
+#[command(author, version)]
+struct Args {
+    /// Mods folder to manage. Defaults to the folder saved by repak-gui
+    #[arg(short, long)]
+    mods_dir: Option<PathBuf>,
+
+    /// Game Paks folder used when repacking IoStore mods. Defaults to the one saved by repak-gui
+    #[arg(long)]
+    game_paks_dir: Option<PathBuf>,
+
+    /// Log progress to stderr
+    #[arg(short, long, default_value = "false")]
+    verbose: bool,
+
+    #[command(subcommand)]
+    action: Action,
+}
+
+fn main() {
+    let args = Args::parse();
+    tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
+        .with_max_level(if args.verbose {
+            LevelFilter::INFO
+        } else {
+            LevelFilter::WARN
+        })
+        .init();
+
+    if let Err(e) = run(args) {
+        eprintln!("Error: {e}");
+        std::process::exit(1);
+    }
+}
+
+fn run(args: Args) -> Result<(), String> {
+    // skin data lives next to the config and needs no mods folder
+    if let Action::Skins(action) = &args.action {
+        return skins(action);
+    }
+
+    let (mut state, loaded) = match ManagerState::load() {
+        Ok(state) => (state, true),
+        Err(e) if args.mods_dir.is_some() => {
+            warn!(error = %e, "Using default state");
+            (ManagerState::default(), false)
+        }
+        Err(e) => return Err(e),
+    };
+    let saved_paths = (state.game_path.clone(), state.game_chunk_path.clone());
+    if let Some(mods_dir) = &args.mods_dir {
+        state.game_path = mods_dir.clone();
+    }
+    if let Some(game_paks_dir) = &args.game_paks_dir {
+        state.game_chunk_path = Some(game_paks_dir.clone());
+    }
+    if !state.game_path.is_dir() {
+        return Err(format!(
+            "Mods folder {} does not exist",
+            state.game_path.display()
+        ));
+    }
+
+    recover(&state.game_path);
+    let migrated = migrate_legacy_disabled_mods(&state.game_path);
+    for (old, new) in &migrated {
+        state.tags.update_path(old, new);
+    }
+    let migrated = !migrated.is_empty();
+
+    let save = match args.action {
+        Action::List(action) => list(&state, action).map(|_| false)?,
+        Action::Install(action) => install(&mut state, action)?,
+        Action::Enable(action) => toggle(&mut state, action, true)?,
+        Action::Disable(action) => toggle(&mut state, action, false)?,
+        Action::Remove(action) => remove(&mut state, action)?,
+        Action::Tag(action) => tag(&mut state, action)?,
+        Action::Search(action) => search(&state, action).map(|_| false)?,
+        Action::Duplicates(action) => duplicates(&mut state, action)?,
+        Action::Skins(_) => unreachable!("handled before loading state"),
+    };
+
+    if (save || migrated) && loaded {
+        // path overrides only apply to this run
+        (state.game_path, state.game_chunk_path) = saved_paths;
+        state.save()?;
+    }
+    Ok(())
+}
+
+fn recover(mods_dir: &Path) {
+    let report = recover_interrupted_installs(mods_dir, false);
+    for name in &report.rolled_back {
+        eprintln!("Interrupted install of {name} was undone, reinstall it");
+    }
+    for error in &report.errors {
+        eprintln!("Could not recover interrupted install: {error}");
+    }
+}
+
+fn mod_display_name(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// Finds an installed mod by file name, stem or base name without the load order suffix.
+fn find_mod(mods: &[InstalledMod], name: &str) -> Result<InstalledMod, String> {
+    let name = Path::new(name)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or(name);
+    let matches = |installed: &&InstalledMod| {
+        let stem = mod_display_name(&installed.path);
+        let file_name = installed
+            .path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        [file_name.as_str(), stem.as_str(), mod_base_name(&stem)]
+            .iter()
+            .any(|candidate| candidate.eq_ignore_ascii_case(name))
+    };
+
+    let found = mods.iter().filter(matches).collect::<Vec<_>>();
+    match found.as_slice() {
+        [installed] => Ok((*installed).clone()),
+        [] => Err(format!("No installed mod named {name}")),
+        _ => Err(format!(
+            "{name} matches more than one mod: {}",
+            found
+                .iter()
+                .map(|installed| mod_display_name(&installed.path))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+    }
+}
+
+#[derive(Serialize)]
+struct ModRow {
+    name: String,
+    path: PathBuf,
+    enabled: bool,
+    iostore: bool,
+    priority: Option<u32>,
+    category: Option<String>,
+    characteristic: Option<String>,
+    classification: Option<ModClassification>,
+    tags: Vec<String>,
+}
+
+fn list(state: &ManagerState, action: ActionList) -> Result<(), String> {
+    let mut mods = scan_installed_mods(&state.game_path);
+    mods.sort_by_cached_key(|installed| load_order_key(&installed.path));
+    let rows = mods
+        .into_iter()
+        .map(|installed| {
+            let name = mod_display_name(&installed.path);
+            let metadata = (!action.fast)
+                .then(|| classify_mod(&installed.path, installed.is_iostore))
+                .and_then(|metadata| {
+                    metadata
+                        .inspect_err(|e| warn!(mod_name = %name, error = %e, "Failed to read mod"))
+                        .ok()
+                });
+            ModRow {
+                priority: mod_priority(&name),
+                tags: state.tags.tags_for_mod(&installed.path),
+                path: installed.path,
+                enabled: installed.enabled,
+                iostore: installed.is_iostore,
+                category: metadata.as_ref().map(|metadata| metadata.category.clone()),
+                characteristic: metadata
+                    .as_ref()
+                    .map(|metadata| metadata.characteristic.clone()),
+                classification: metadata.map(|metadata| metadata.classification),
+                name,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    if action.json {
+        let json = serde_json::to_string_pretty(&rows)
+            .map_err(|e| format!("Failed to serialize mods: {e}"))?;
+        println!("{json}");
+        return Ok(());
+    }
+
+    let name_width = rows
+        .iter()
+        .map(|row| row.name.len())
+        .max()
+        .unwrap_or_default()
+        .max("Name".len());
+    println!(
+        "{:<name_width$}  {:<8}  {:<7}  {:<8}  {:<8}  Tags",
+        "Name", "Enabled", "Format", "Priority", "Category"
+    );
+    for row in &rows {
+        println!(
+            "{:<name_width$}  {:<8}  {:<7}  {:<8}  {:<8}  {}",
+            row.name,
+            if row.enabled { "yes" } else { "no" },
+            if row.iostore { "IoStore" } else { "Pak" },
+            row.priority
+                .map(|priority| priority.to_string())
+                .unwrap_or_else(|| "-".to_string()),
+            row.category.as_deref().unwrap_or("-"),
+            row.tags.join(", ")
+        );
+        if let Some(characteristic) = &row.characteristic {
+            println!("{:<name_width$}  {characteristic}", "");
+        }
+    }
+    println!("{} mods in {}", rows.len(), state.game_path.display());
+    Ok(())
+}
+
+fn install(state: &mut ManagerState, action: ActionInstall) -> Result<bool, String> {
+    for path in &action.input {
+        if !path.exists() {
+            return Err(format!("{} does not exist", path.display()));
+        }
+    }
+
+    let mut mods = map_paths_to_mods(&action.input);
+    if mods.is_empty() {
+        return Err("No installable mods found".to_string());
+    }
+    let unmatched = pick_variants(&mut mods, &action.variant);
+    if !unmatched.is_empty() {
+        return Err(format!("No archive option named {}", unmatched.join(", ")));
+    }
+    let picked = mods
+        .iter()
+        .filter(|installable_mod| installable_mod.enabled)
+        .filter_map(|installable_mod| installable_mod.variant.as_ref())
+        .map(|variant| (&variant.group, &variant.name))
+        .collect::<BTreeSet<_>>();
+    for (group, name) in picked {
+        println!("Installing option {name} of {group}");
+    }
+    mods.retain(|installable_mod| installable_mod.enabled);
+
+    for installable_mod in &mut mods {
+        installable_mod.repak |= action.repak && installable_mod.iostore;
+        installable_mod.fix_mesh = action.fix_mesh;
+        installable_mod.kawaii_porter = !action.no_kawaii_porter;
+        installable_mod.default_hidden_material_patch = !action.no_hidden_material_patch;
+    }
+
+    let chunkdir = state
+        .game_chunk_path
+        .clone()
+        .or_else(|| state.game_path.parent().and_then(match_exact_paks_suffix));
+    let mut save = false;
+    if mods_need_kawaii_mapping(&mods) {
+        match latest_depot_usmap_path(state.kawaii_physics_usmap.as_deref()) {
+            Ok(Some(path)) => {
+                state.kawaii_physics_usmap = Some(path);
+                save = true;
+            }
+            Ok(None) => {}
+            Err(e) => warn!(error = %e, "Failed to update KawaiiPhysics mapping file"),
+        }
+    }
+
+    let before = scan_installed_mods(&state.game_path);
+    println!("Installing {} mods", mods.len());
+    install_mods_in_viewport(
+        &mut mods,
+        &state.game_path,
+        Arc::new(AtomicI32::new(0)),
+        &AtomicBool::new(false),
+        &chunkdir,
+        &state.kawaii_physics_usmap,
+    );
+
+    // failures are logged per mod, anything new or rewritten was installed
+    let installed = scan_installed_mods(&state.game_path)
+        .into_iter()
+        .filter(|installed| {
+            !before.iter().any(|previous| {
+                previous.path == installed.path && previous.signature == installed.signature
+            })
+        })
+        .collect::<Vec<_>>();
+    if installed.is_empty() {
+        return Err("No mods were installed, run with --verbose for details".to_string());
+    }
+
+    if action.disabled {
+        for installed in &installed {
+            let new_path = set_mod_enabled(&installed.path, false)
+                .map_err(|e| format!("Failed to disable {}: {e}", installed.path.display()))?;
+            state.tags.update_path(&installed.path, &new_path);
+        }
+    }
+    for installed in &installed {
+        println!("Installed {}", mod_display_name(&installed.path));
+    }
+    Ok(save)
+}
+
+fn toggle(state: &mut ManagerState, action: ActionToggle, enable: bool) -> Result<bool, String> {
+    let mods = scan_installed_mods(&state.game_path);
+    for name in &action.mods {
+        let installed = find_mod(&mods, name)?;
+        if installed.enabled == enable {
+            println!(
+                "{} is already {}",
+                mod_display_name(&installed.path),
+                if enable { "enabled" } else { "disabled" }
+            );
+            continue;
+        }
+        let new_path = set_mod_enabled(&installed.path, enable).map_err(|e| {
+            format!("Failed to move mod files: {e}\nMake sure game is not running.")
+        })?;
+        state.tags.update_path(&installed.path, &new_path);
+        println!(
+            "{} {}",
+            if enable { "Enabled" } else { "Disabled" },
+            mod_display_name(&new_path)
+        );
+    }
+    Ok(true)
+}
+
+fn remove(state: &mut ManagerState, action: ActionToggle) -> Result<bool, String> {
+    let mods = scan_installed_mods(&state.game_path);
+    for name in &action.mods {
+        let installed = find_mod(&mods, name)?;
+        delete_mod_files(&installed.path).map_err(|e| {
+            format!(
+                "Failed to delete {}: {e}\nMake sure game is not running.",
+                installed.path.display()
+            )
+        })?;
+        state.tags.remove_all(&installed.path);
+        println!("Removed {}", mod_display_name(&installed.path));
+    }
+    Ok(true)
+}
+
+fn tag(state: &mut ManagerState, action: ActionTag) -> Result<bool, String> {
+    let mods = scan_installed_mods(&state.game_path);
+    let installed = find_mod(&mods, &action.name)?;
+    for tag in &action.add {
+        state.tags.add_tag(&installed.path, tag);
+    }
+    for tag in &action.remove {
+        state.tags.remove_tag(&installed.path, tag);
+    }
+
+    let tags = state.tags.tags_for_mod(&installed.path);
+    println!(
+        "{}: {}",
+        mod_display_name(&installed.path),
+        if tags.is_empty() {
+            "no tags".to_string()
+        } else {
+            tags.join(", ")
+        }
+    );
+    Ok(!action.add.is_empty() || !action.remove.is_empty())
+}
+
+#[derive(Serialize)]
+struct SearchRow<'a> {
+    name: &'a str,
+    path: &'a Path,
+    installed: bool,
+    enabled: bool,
+    category: &'a str,
+    skins: &'a [CatalogSkin],
+    tags: Vec<String>,
+    installed_at: Option<u64>,
+    source: Option<&'a ModSource>,
+    matched_files: Vec<&'a str>,
+}
+
+fn search(state: &ManagerState, action: ActionSearch) -> Result<(), String> {
+    let catalog = catalog::refresh(&state.game_path)?;
+    // the shell strips quotes, put them back around terms with spaces
+    let query = action
+        .query
+        .iter()
+        .map(|term| {
+            if term.contains(char::is_whitespace) {
+                format!("\"{term}\"")
+            } else {
+                term.clone()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let touching = match &action.touches {
+        Some(name) => {
+            let target = catalog
+                .entries
+                .iter()
+                .filter(|entry| {
+                    let stem = mod_display_name(&entry.path);
+                    stem.eq_ignore_ascii_case(name) || entry.name().eq_ignore_ascii_case(name)
+                })
+                .max_by_key(|entry| entry.installed)
+                .ok_or_else(|| format!("No catalogued mod named {name}"))?;
+            Some((
+                target.files.iter().map(String::as_str).collect::<Vec<_>>(),
+                catalog
+                    .touching(target)
+                    .into_iter()
+                    .map(|entry| entry.identity.as_str())
+                    .collect::<Vec<_>>(),
+            ))
+        }
+        None => None,
+    };
+
+    let rows = catalog
+        .search(&query, &state.tags)
+        .into_iter()
+        .filter(|hit| !action.installed || hit.entry.installed)
+        .filter_map(|hit| {
+            let mut matched_files = hit.matched_files;
+            if let Some((target_files, touching)) = &touching {
+                if !touching.contains(&hit.entry.identity.as_str()) {
+                    return None;
+                }
+                // show the shared files rather than every file the terms matched
+                matched_files = hit
+                    .entry
+                    .files
+                    .iter()
+                    .map(String::as_str)
+                    .filter(|file| target_files.contains(file))
+                    .collect();
+            }
+            Some(SearchRow {
+                name: hit.entry.name(),
+                path: &hit.entry.path,
+                installed: hit.entry.installed,
+                enabled: hit.entry.enabled,
+                category: &hit.entry.category,
+                skins: &hit.entry.skins,
+                tags: hit.tags,
+                installed_at: hit.entry.installed_at,
+                source: hit.entry.source.as_ref(),
+                matched_files,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    if action.json {
+        let json = serde_json::to_string_pretty(&rows)
+            .map_err(|e| format!("Failed to serialize search results: {e}"))?;
+        println!("{json}");
+        return Ok(());
+    }
+
+    for row in &rows {
+        let status = match (row.installed, row.enabled) {
+            (false, _) => "removed",
+            (true, true) => "enabled",
+            (true, false) => "disabled",
+        };
+        println!("{} [{status}] {}", row.name, row.category);
+        for skin in row.skins {
+            println!("    {} - {} ({})", skin.hero, skin.skin_name, skin.skin_id);
+        }
+        if !row.tags.is_empty() {
+            println!("    tags: {}", row.tags.join(", "));
+        }
+        if let Some(source) = row.source {
+            println!("    source: {}", source.path.display());
+        }
+        for file in &row.matched_files {
+            println!("    {file}");
+        }
+    }
+    println!("{} matching mods", rows.len());
+    Ok(())
+}
+
+fn duplicates(state: &mut ManagerState, action: ActionDuplicates) -> Result<bool, String> {
+    let catalog = catalog::refresh(&state.game_path)?;
+    let groups = find_duplicates(&catalog, action.threshold);
+
+    if action.json {
+        let json = serde_json::to_string_pretty(&groups)
+            .map_err(|e| format!("Failed to serialize duplicates: {e}"))?;
+        println!("{json}");
+    } else {
+        for group in &groups {
+            match group.kind {
+                DuplicateKind::Exact => println!("Exact duplicates:"),
+                DuplicateKind::Near => {
+                    println!("Near duplicates ({:.0}% shared):", group.similarity * 100.0)
+                }
+            }
+            println!("    keep   {}", mod_display_name(group.keep()));
+            let label = match group.kind {
+                DuplicateKind::Exact => "extra",
+                DuplicateKind::Near => "other",
+            };
+            for extra in group.extras() {
+                println!("    {label}  {}", mod_display_name(extra));
+            }
+        }
+        println!("{} duplicate groups", groups.len());
+        if action.remove && groups.iter().any(|group| group.kind == DuplicateKind::Near) {
+            println!(
+                "Near duplicates are not removed, delete the versions you don't want yourself"
+            );
+        }
+    }
+
+    if !action.remove {
+        return Ok(false);
+    }
+    // near duplicates are often different versions of a mod, leave the choice to the user
+    for extra in exact_extras(&groups) {
+        delete_mod_files(extra).map_err(|e| {
+            format!(
+                "Failed to delete {}: {e}\nMake sure game is not running.",
+                extra.display()
+            )
+        })?;
+        state.tags.remove_all(extra);
+        if !action.json {
+            println!("Removed {}", mod_display_name(extra));
+        }
+    }
+    Ok(true)
+}
+
+fn skins(action: &SkinsAction) -> Result<(), String> {
+    match action {
+        SkinsAction::Info => {
+            let db = skin_db();
+            println!("Built-in revision: {}", db.revision);
+            match db.override_revision {
+                Some(revision) => println!("User revision:     {revision}"),
+                None => println!("User revision:     none"),
+            }
+            println!("Heroes:            {}", db.heroes().count());
+            println!("Skins:             {}", db.skin_count());
+            println!("User overrides:    {}", skin_db::overrides_path().display());
+        }
+        SkinsAction::Lookup(action) => {
+            let db = skin_db();
+            let mut results = Vec::new();
+            for id in &action.ids {
+                let (result, text) = match id.parse::<u32>() {
+                    Ok(hero_id) if id.len() == 4 => {
+                        let hero = db.hero(hero_id);
+                        (
+                            serde_json::json!({ "hero_id": hero_id, "hero": hero }),
+                            hero.map(str::to_string),
+                        )
+                    }
+                    Ok(skin_id) => {
+                        let skin = db.skin(skin_id);
+                        (
+                            serde_json::json!({ "skin": skin }),
+                            skin.map(|skin| format!("{} - {}", skin.hero, skin.skin)),
+                        )
+                    }
+                    Err(_) => {
+                        let asset = db.lookup_path(id);
+                        let text = asset.as_ref().map(|asset| {
+                            let hero = asset.hero.as_deref().unwrap_or("unknown hero");
+                            match &asset.skin {
+                                Some(skin) => format!("{hero} - {} ({})", skin.skin, asset.kind),
+                                None => format!(
+                                    "{hero} - unknown skin {} ({})",
+                                    asset.skin_id, asset.kind
+                                ),
+                            }
+                        });
+                        (serde_json::json!({ "asset": asset }), text)
+                    }
+                };
+                if !action.json {
+                    println!("{id}: {}", text.as_deref().unwrap_or("not found"));
+                }
+                results.push(result);
+            }
+            if action.json {
+                let json = serde_json::to_string_pretty(&results)
+                    .map_err(|e| format!("Failed to serialize lookup: {e}"))?;
+                println!("{json}");
+            }
+        }
+        SkinsAction::Import(action) => {
+            let data =
+                if action.source.starts_with("http://") || action.source.starts_with("https://") {
+                    reqwest::blocking::get(&action.source)
+                        .and_then(|response| response.error_for_status())
+                        .and_then(|response| response.text())
+                        .map_err(|e| format!("Failed to download {}: {e}", action.source))?
+                } else {
+                    std::fs::read_to_string(&action.source)
+                        .map_err(|e| format!("Failed to read {}: {e}", action.source))?
+                };
+            let count = skin_db::import_overrides(&data)?;
+            println!(
+                "Imported {count} skins into {}",
+                skin_db::overrides_path().display()
+            );
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,101 @@
+use crate::load_order::load_order_key;
+use std::path::{Path, PathBuf};
+
+/// Extensions that belong to one cooked package. Legacy paks list `.uasset`/`.uexp` pairs while
+/// IoStore mods list package names, so both are reduced to the package path before comparing.
+const PACKAGE_EXTENSIONS: &[&str] = &["uasset", "uexp", "ubulk", "uptnl", "umap"];
+
+pub struct AssetConflict {
+    pub asset: String,
+    pub winner: PathBuf,
+    pub overridden: Vec<PathBuf>,
+}
+
+pub struct ModConflictSummary {
+    pub path: PathBuf,
+    pub asset_count: usize,
+    pub overridden: usize,
+    pub wins: usize,
+}
+
+impl ModConflictSummary {
+    /// Every asset of this mod is overridden by a mod later in the load order.
+    pub fn shadowed(&self) -> bool {
+        self.asset_count > 0 && self.overridden == self.asset_count
+    }
+}
+
+#[derive(Default)]
+pub struct ModConflictReport {
+    pub assets: Vec<AssetConflict>,
+    pub mods: Vec<ModConflictSummary>,
+}
+
+impl ModConflictReport {
+    pub fn summary_for(&self, path: &Path) -> Option<&ModConflictSummary> {
+        self.mods.iter().find(|summary| summary.path == path)
+    }
+}
+
+/// Compares the file lists of enabled mods and works out which mod wins every shared asset.
+pub fn analyze_mod_conflicts(mut mods: Vec<(PathBuf, Vec<String>)>) -> ModConflictReport {
+    mods.sort_by_cached_key(|(path, _)| load_order_key(path));
+    let file_lists = mods
+        .iter()
+        .map(|(_, files)| {
+            let mut keys = files
+                .iter()
+                .filter(|file| !file.is_empty() && file.as_str() != "chunknames")
+                .map(|file| conflict_asset_key(file))
+                .collect::<Vec<_>>();
+            keys.sort();
+            keys.dedup();
+            keys
+        })
+        .collect::<Vec<_>>();
+
+    let mut mod_summaries = mods
+        .iter()
+        .zip(&file_lists)
+        .map(|((path, _), files)| ModConflictSummary {
+            path: path.clone(),
+            asset_count: files.len(),
+            overridden: 0,
+            wins: 0,
+        })
+        .collect::<Vec<_>>();
+
+    let mut assets = Vec::new();
+    for (asset, owners) in simd_str_cmp::find_shared_strings(&file_lists) {
+        let Some((&winner, losers)) = owners.split_last() else {
+            continue;
+        };
+        mod_summaries[winner].wins += 1;
+        for &loser in losers {
+            mod_summaries[loser].overridden += 1;
+        }
+        assets.push(AssetConflict {
+            asset,
+            winner: mods[winner].0.clone(),
+            overridden: losers.iter().map(|&idx| mods[idx].0.clone()).collect(),
+        });
+    }
+
+    ModConflictReport {
+        assets,
+        mods: mod_summaries,
+    }
+}
+
+fn conflict_asset_key(file: &str) -> String {
+    let file = file.replace('\\', "/").to_lowercase();
+    let file = file.trim_start_matches("../").trim_start_matches('/');
+    let file = match file.strip_prefix("game/") {
+        Some(rest) => format!("marvel/content/{rest}"),
+        None => file.to_string(),
+    };
+    match file.rsplit_once('.') {
+        Some((stem, ext)) if PACKAGE_EXTENSIONS.contains(&ext) => stem.to_string(),
+        _ => file,
+    }
+}
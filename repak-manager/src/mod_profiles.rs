@@ -0,0 +1,137 @@
+use crate::load_order::mod_base_name;
+use crate::mod_toggle::{apply_moves, disabled_pak_path, enabled_pak_path, toggle_moves};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+/// A named set of enabled mods. Mods are stored by base name so a profile survives load-order
+/// renames.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ModProfile {
+    pub name: String,
+    pub enabled_mods: Vec<String>,
+}
+
+impl ModProfile {
+    pub fn contains(&self, mod_path: &Path) -> bool {
+        let name = profile_mod_name(mod_path);
+        self.enabled_mods
+            .iter()
+            .any(|enabled| enabled.eq_ignore_ascii_case(&name))
+    }
+}
+
+pub fn profile_mod_name(mod_path: &Path) -> String {
+    let stem = mod_path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or_default();
+    mod_base_name(stem).to_string()
+}
+
+/// Enables every installed mod in `profile` and disables the rest. Either every mod is moved or
+/// the moves already done are reverted, so a running game cannot leave a half-applied profile
+/// behind. Returns the `(old, new)` `.pak` path of every toggled mod.
+pub fn apply_profile(
+    installed: &[(PathBuf, bool)],
+    profile: &ModProfile,
+) -> Result<Vec<(PathBuf, PathBuf)>, String> {
+    let mut toggled = Vec::new();
+    let mut moves = Vec::new();
+    for (path, enabled) in installed {
+        let enable = profile.contains(path);
+        if enable == *enabled {
+            continue;
+        }
+        let target = if enable {
+            enabled_pak_path(path)
+        } else {
+            disabled_pak_path(path)
+        };
+        moves.extend(toggle_moves(path, enable));
+        toggled.push((path.clone(), target));
+    }
+
+    info!(profile = %profile.name, mods = toggled.len(), "Applying mod profile");
+    apply_moves(&moves).map_err(|e| {
+        warn!(error = %e, "Profile apply failed");
+        format!("Failed to move mod files: {e}. Make sure game is not running.")
+    })?;
+    Ok(toggled)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::load_order::apply_priorities;
+    use std::fs;
+
+    fn file_names(dir: &Path) -> Vec<String> {
+        let mut names = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_apply_reordered_profile() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        let disabled = dir.join("disabled");
+        fs::create_dir(&disabled).unwrap();
+        for path in [
+            dir.join("Foo_9999999_P.pak"),
+            disabled.join("Bar_9999998_P.pak_disabled"),
+            dir.join("Baz_9999997_P.pak"),
+            dir.join("Baz_9999997_P.utoc"),
+            dir.join("Baz_9999997_P.ucas"),
+        ] {
+            fs::write(path, "").unwrap();
+        }
+        // saved before the load order changed
+        let profile = ModProfile {
+            name: "competitive-safe".to_string(),
+            enabled_mods: vec!["bar".to_string(), "Baz".to_string()],
+        };
+
+        let reordered = apply_priorities(&[
+            (dir.join("Baz_9999997_P.pak"), 9999999),
+            (disabled.join("Bar_9999998_P.pak_disabled"), 9999998),
+            (dir.join("Foo_9999999_P.pak"), 9999997),
+        ])
+        .unwrap();
+        let installed = [
+            (reordered[0].clone(), true),
+            (reordered[1].clone(), false),
+            (reordered[2].clone(), true),
+        ];
+
+        let toggled = apply_profile(&installed, &profile).unwrap();
+        assert_eq!(
+            toggled,
+            [
+                (
+                    disabled.join("Bar_9999998_P.pak_disabled"),
+                    dir.join("Bar_9999998_P.pak")
+                ),
+                (
+                    dir.join("Foo_9999997_P.pak"),
+                    disabled.join("Foo_9999997_P.pak_disabled")
+                ),
+            ]
+        );
+        assert_eq!(
+            file_names(dir),
+            [
+                "Bar_9999998_P.pak",
+                "Baz_9999999_P.pak",
+                "Baz_9999999_P.ucas",
+                "Baz_9999999_P.utoc",
+                "disabled",
+            ]
+        );
+        assert_eq!(file_names(&disabled), ["Foo_9999997_P.pak_disabled"]);
+    }
+}
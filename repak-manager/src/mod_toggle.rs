@@ -0,0 +1,252 @@
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+/// Disabled mods are moved into this folder next to where they were installed. The `.pak` is
+/// renamed to `.pak_disabled` so the game's recursive `~mods` scan skips it, and the `.utoc` and
+/// `.ucas` move with it so no container is left behind without its pak.
+pub const DISABLED_DIR: &str = "disabled";
+pub const DISABLED_PAK_EXTENSION: &str = "pak_disabled";
+/// Disabled extension used before mods were moved into `disabled/`. The companions of these mods
+/// were left in place.
+pub const LEGACY_DISABLED_EXTENSION: &str = "bak_repak";
+
+const MOD_EXTENSIONS: [&str; 3] = ["pak", "utoc", "ucas"];
+
+/// Where a mod lives while enabled, e.g. `~mods/Foo_9999999_P.pak` for
+/// `~mods/disabled/Foo_9999999_P.pak_disabled`.
+pub fn enabled_pak_path(pak_path: &Path) -> PathBuf {
+    let parent = pak_path.parent().unwrap_or_else(|| Path::new(""));
+    let parent = if in_disabled_dir(pak_path) {
+        parent.parent().unwrap_or(parent)
+    } else {
+        parent
+    };
+    parent.join(
+        pak_path
+            .with_extension("pak")
+            .file_name()
+            .unwrap_or_default(),
+    )
+}
+
+pub fn disabled_pak_path(pak_path: &Path) -> PathBuf {
+    let enabled = enabled_pak_path(pak_path);
+    enabled
+        .parent()
+        .unwrap_or_else(|| Path::new(""))
+        .join(DISABLED_DIR)
+        .join(
+            enabled
+                .with_extension(DISABLED_PAK_EXTENSION)
+                .file_name()
+                .unwrap_or_default(),
+        )
+}
+
+pub fn in_disabled_dir(path: &Path) -> bool {
+    path.parent().and_then(Path::file_name) == Some(OsStr::new(DISABLED_DIR))
+}
+
+/// A disabled mod left by an older version: a `.bak_repak`, or a `.pak_disabled` outside
+/// `disabled/`.
+pub fn is_legacy_disabled(path: &Path) -> bool {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some(LEGACY_DISABLED_EXTENSION) => true,
+        Some(DISABLED_PAK_EXTENSION) => !in_disabled_dir(path),
+        _ => false,
+    }
+}
+
+/// The renames that move a mod and its IoStore companions to their enabled or disabled location.
+pub fn toggle_moves(pak_path: &Path, enable: bool) -> Vec<(PathBuf, PathBuf)> {
+    let target = if enable {
+        enabled_pak_path(pak_path)
+    } else {
+        disabled_pak_path(pak_path)
+    };
+    if target == pak_path {
+        return Vec::new();
+    }
+    MOD_EXTENSIONS
+        .into_iter()
+        .map(|ext| {
+            if ext == "pak" {
+                (pak_path.to_path_buf(), target.clone())
+            } else {
+                (pak_path.with_extension(ext), target.with_extension(ext))
+            }
+        })
+        .filter(|(from, to)| from != to && from.exists())
+        .collect()
+}
+
+/// Performs every rename or none: if one fails, the renames already done are reverted.
+pub fn apply_moves(moves: &[(PathBuf, PathBuf)]) -> std::io::Result<()> {
+    if let Some((_, to)) = moves.iter().find(|(_, to)| to.exists()) {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::AlreadyExists,
+            format!("{} already exists", to.display()),
+        ));
+    }
+
+    for (idx, (from, to)) in moves.iter().enumerate() {
+        let result = match to.parent() {
+            Some(parent) if !parent.exists() => fs::create_dir_all(parent),
+            _ => Ok(()),
+        }
+        .and_then(|_| fs::rename(from, to));
+        if let Err(e) = result {
+            warn!(error = %e, "Mod rename failed, rolling back");
+            for (from, to) in moves[..idx].iter().rev() {
+                if let Err(e) = fs::rename(to, from) {
+                    warn!(error = %e, path = %to.display(), "Failed to roll back mod rename");
+                }
+            }
+            return Err(e);
+        }
+    }
+
+    // drop disabled folders emptied by enabling their last mod
+    for (from, _) in moves {
+        if in_disabled_dir(from) {
+            if let Some(parent) = from.parent() {
+                let _ = fs::remove_dir(parent);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Enables or disables a mod as a unit. Returns the new `.pak` path.
+pub fn set_mod_enabled(pak_path: &Path, enable: bool) -> std::io::Result<PathBuf> {
+    let target = if enable {
+        enabled_pak_path(pak_path)
+    } else {
+        disabled_pak_path(pak_path)
+    };
+    apply_moves(&toggle_moves(pak_path, enable))?;
+    Ok(target)
+}
+
+/// Moves a mod disabled by an older version into `disabled/` together with its companions.
+pub fn migrate_legacy_disabled(pak_path: &Path) -> std::io::Result<PathBuf> {
+    let target = set_mod_enabled(pak_path, false)?;
+    info!(
+        file_name = %target.file_name().and_then(|name| name.to_str()).unwrap_or("<unknown>"),
+        "Migrated disabled mod into disabled folder"
+    );
+    Ok(target)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn touch(dir: &Path, names: &[&str]) {
+        for name in names {
+            fs::write(dir.join(name), name).unwrap();
+        }
+    }
+
+    fn file_names(dir: &Path) -> Vec<String> {
+        let mut names = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_toggle_iostore_mod() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        touch(
+            dir,
+            &[
+                "Foo_9999999_P.pak",
+                "Foo_9999999_P.utoc",
+                "Foo_9999999_P.ucas",
+                "Bar.pak",
+            ],
+        );
+
+        let disabled = dir.join("disabled");
+        assert_eq!(
+            toggle_moves(&dir.join("Foo_9999999_P.pak"), false),
+            [
+                (
+                    dir.join("Foo_9999999_P.pak"),
+                    disabled.join("Foo_9999999_P.pak_disabled")
+                ),
+                (
+                    dir.join("Foo_9999999_P.utoc"),
+                    disabled.join("Foo_9999999_P.utoc")
+                ),
+                (
+                    dir.join("Foo_9999999_P.ucas"),
+                    disabled.join("Foo_9999999_P.ucas")
+                ),
+            ]
+        );
+        let target = set_mod_enabled(&dir.join("Foo_9999999_P.pak"), false).unwrap();
+        assert_eq!(target, disabled.join("Foo_9999999_P.pak_disabled"));
+        assert_eq!(file_names(dir), ["Bar.pak", "disabled"]);
+        assert_eq!(
+            file_names(&disabled),
+            [
+                "Foo_9999999_P.pak_disabled",
+                "Foo_9999999_P.ucas",
+                "Foo_9999999_P.utoc"
+            ]
+        );
+        // disabling again changes nothing
+        assert!(toggle_moves(&target, false).is_empty());
+
+        let target = set_mod_enabled(&target, true).unwrap();
+        assert_eq!(target, dir.join("Foo_9999999_P.pak"));
+        // the emptied disabled folder is removed
+        assert_eq!(
+            file_names(dir),
+            [
+                "Bar.pak",
+                "Foo_9999999_P.pak",
+                "Foo_9999999_P.ucas",
+                "Foo_9999999_P.utoc"
+            ]
+        );
+        assert_eq!(
+            fs::read(dir.join("Foo_9999999_P.ucas")).unwrap(),
+            b"Foo_9999999_P.ucas"
+        );
+    }
+
+    #[test]
+    fn test_migrate_legacy_disabled() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        touch(dir, &["Foo_P.bak_repak", "Foo_P.utoc", "Foo_P.ucas"]);
+        assert!(is_legacy_disabled(&dir.join("Foo_P.bak_repak")));
+
+        let target = migrate_legacy_disabled(&dir.join("Foo_P.bak_repak")).unwrap();
+        let disabled = dir.join("disabled");
+        assert_eq!(target, disabled.join("Foo_P.pak_disabled"));
+        assert_eq!(file_names(dir), ["disabled"]);
+        assert_eq!(
+            file_names(&disabled),
+            ["Foo_P.pak_disabled", "Foo_P.ucas", "Foo_P.utoc"]
+        );
+        assert_eq!(fs::read(&target).unwrap(), b"Foo_P.bak_repak");
+
+        // the migrated mod isn't picked up again and a repeated migration is a no-op
+        assert!(!is_legacy_disabled(&target));
+        assert_eq!(migrate_legacy_disabled(&target).unwrap(), target);
+        assert_eq!(file_names(dir), ["disabled"]);
+        assert_eq!(
+            file_names(&disabled),
+            ["Foo_P.pak_disabled", "Foo_P.ucas", "Foo_P.utoc"]
+        );
+    }
+}
@@ -0,0 +1,738 @@
+//! Hero and skin names keyed by the IDs in Marvel Rivals asset paths such as
+//! `Characters/1014/1014001/Meshes/SK_1014_1014001.uasset`.
+//!
+//! The built-in database ships with the binary. Users can add or correct entries in
+//! `skin_db.json` next to the GUI config, which is merged over the built-in data, and
+//! [`import_overrides`] converts other formats into that file.
+
+use crate::state::config_path;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, RwLock};
+use tracing::{debug, info, warn};
+
+/// Version of the file layout. Files with another schema are rejected instead of misread.
+pub const SKIN_DB_SCHEMA: u32 = 1;
+const OVERRIDES_FILE: &str = "skin_db.json";
+
+static BUILT_IN: &str = include_str!("data/skin_db.json");
+static SKIN_DB: LazyLock<RwLock<Arc<SkinDb>>> = LazyLock::new(|| RwLock::new(Arc::new(load())));
+
+/// On-disk skin database, used for the built-in data and user overrides.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct SkinDbFile {
+    pub schema: u32,
+    /// Bumped whenever the data changes.
+    #[serde(default)]
+    pub revision: u32,
+    #[serde(default)]
+    pub heroes: Vec<HeroRecord>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct HeroRecord {
+    /// Four digit hero ID, the first four digits of every skin ID of the hero.
+    pub id: u32,
+    pub name: String,
+    #[serde(default)]
+    pub skins: Vec<SkinRecord>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SkinRecord {
+    /// Seven digit skin ID.
+    pub id: u32,
+    pub name: String,
+    /// The hero's default look, usually `<hero>001`.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub default: bool,
+    /// Colour variants sharing the skin's assets.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub variants: Vec<String>,
+}
+
+impl SkinDbFile {
+    /// Parses a skin database and checks it against the schema.
+    pub fn parse(data: &str) -> Result<Self, String> {
+        let file = serde_json::from_str::<SkinDbFile>(data)
+            .map_err(|e| format!("Invalid skin database: {e}"))?;
+        file.validate()?;
+        Ok(file)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.schema != SKIN_DB_SCHEMA {
+            return Err(format!(
+                "Unsupported skin database schema {}, expected {SKIN_DB_SCHEMA}",
+                self.schema
+            ));
+        }
+
+        let mut errors = Vec::new();
+        let mut hero_ids = HashSet::new();
+        let mut skin_ids = HashSet::new();
+        for hero in &self.heroes {
+            if !(1000..=9999).contains(&hero.id) {
+                errors.push(format!("hero ID {} is not four digits", hero.id));
+            }
+            if !hero_ids.insert(hero.id) {
+                errors.push(format!("hero {} is listed twice", hero.id));
+            }
+            if hero.name.trim().is_empty() {
+                errors.push(format!("hero {} has no name", hero.id));
+            }
+            if hero.skins.iter().filter(|skin| skin.default).count() > 1 {
+                errors.push(format!("hero {} has more than one default skin", hero.id));
+            }
+            for skin in &hero.skins {
+                if skin.id / 1000 != hero.id {
+                    errors.push(format!(
+                        "skin {} does not belong to hero {}",
+                        skin.id, hero.id
+                    ));
+                }
+                if !skin_ids.insert(skin.id) {
+                    errors.push(format!("skin {} is listed twice", skin.id));
+                }
+                if skin.name.trim().is_empty() {
+                    errors.push(format!("skin {} has no name", skin.id));
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("Invalid skin database:\n{}", errors.join("\n")))
+        }
+    }
+
+    /// Adds the heroes and skins of `other`, replacing entries with the same ID.
+    pub fn merge(&mut self, other: SkinDbFile) {
+        self.revision = self.revision.max(other.revision);
+        for hero in other.heroes {
+            let Some(existing) = self
+                .heroes
+                .iter_mut()
+                .find(|existing| existing.id == hero.id)
+            else {
+                self.heroes.push(hero);
+                continue;
+            };
+            existing.name = hero.name;
+            for skin in hero.skins {
+                if skin.default {
+                    existing
+                        .skins
+                        .iter_mut()
+                        .for_each(|skin| skin.default = false);
+                }
+                match existing
+                    .skins
+                    .iter_mut()
+                    .find(|existing| existing.id == skin.id)
+                {
+                    Some(existing) => *existing = skin,
+                    None => existing.skins.push(skin),
+                }
+            }
+            existing.skins.sort_by_key(|skin| skin.id);
+        }
+        self.heroes.sort_by_key(|hero| hero.id);
+    }
+}
+
+/// A skin with its hero, as returned by the lookups.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct SkinInfo {
+    pub hero_id: u32,
+    pub hero: String,
+    pub skin_id: u32,
+    pub skin: String,
+    pub default: bool,
+    pub variants: Vec<String>,
+}
+
+/// What an asset is, from its folder or file name prefix.
+#[derive(
+    Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize, strum::Display,
+)]
+pub enum AssetKind {
+    Mesh,
+    Texture,
+    Material,
+    Animation,
+    Physics,
+    Effect,
+    Audio,
+    #[strum(to_string = "UI")]
+    Ui,
+    Map,
+    #[strum(to_string = "Movies")]
+    Movie,
+    Other,
+}
+
+/// The hero and skin an asset path belongs to. `skin` is `None` for skin IDs missing from the
+/// database, such as skins released after it was last updated.
+#[derive(Clone, Debug, Serialize)]
+pub struct AssetSkin {
+    pub hero_id: u32,
+    pub hero: Option<String>,
+    pub skin_id: u32,
+    pub skin: Option<SkinInfo>,
+    pub kind: AssetKind,
+}
+
+/// Merged built-in and user skin data.
+#[derive(Debug, Default)]
+pub struct SkinDb {
+    pub revision: u32,
+    /// Revision of the user overrides, `None` when there are none.
+    pub override_revision: Option<u32>,
+    heroes: BTreeMap<u32, String>,
+    skins: HashMap<u32, SkinInfo>,
+}
+
+impl SkinDb {
+    pub(crate) fn from_file(file: &SkinDbFile) -> Self {
+        let mut db = SkinDb {
+            revision: file.revision,
+            ..SkinDb::default()
+        };
+        for hero in &file.heroes {
+            db.heroes.insert(hero.id, hero.name.clone());
+            for skin in &hero.skins {
+                db.skins.insert(
+                    skin.id,
+                    SkinInfo {
+                        hero_id: hero.id,
+                        hero: hero.name.clone(),
+                        skin_id: skin.id,
+                        skin: skin.name.clone(),
+                        default: skin.default,
+                        variants: skin.variants.clone(),
+                    },
+                );
+            }
+        }
+        db
+    }
+
+    pub fn hero(&self, hero_id: u32) -> Option<&str> {
+        self.heroes.get(&hero_id).map(String::as_str)
+    }
+
+    pub fn heroes(&self) -> impl Iterator<Item = (u32, &str)> {
+        self.heroes.iter().map(|(id, name)| (*id, name.as_str()))
+    }
+
+    pub fn skin(&self, skin_id: u32) -> Option<&SkinInfo> {
+        self.skins.get(&skin_id)
+    }
+
+    pub fn skin_count(&self) -> usize {
+        self.skins.len()
+    }
+
+    /// Finds the hero and skin of an asset from the `<hero>/<skin>` folders in its path.
+    pub fn lookup_path(&self, path: &str) -> Option<AssetSkin> {
+        let parts = path.split('/').collect::<Vec<_>>();
+        let (idx, hero_id, skin_id) = parts.windows(2).enumerate().find_map(|(idx, pair)| {
+            let hero_id = parse_id(pair[0], 4)?;
+            let skin_id = parse_id(pair[1], 7)?;
+            (skin_id / 1000 == hero_id).then_some((idx, hero_id, skin_id))
+        })?;
+        Some(AssetSkin {
+            hero_id,
+            hero: self.hero(hero_id).map(str::to_string),
+            skin_id,
+            skin: self.skin(skin_id).cloned(),
+            kind: asset_kind(&parts[idx + 2..]),
+        })
+    }
+}
+
+fn parse_id(part: &str, digits: usize) -> Option<u32> {
+    (part.len() == digits && part.bytes().all(|b| b.is_ascii_digit()))
+        .then(|| part.parse().ok())
+        .flatten()
+}
+
+/// Classifies an asset from the folders and file name of its path, such as the part below its
+/// skin folder.
+pub(crate) fn asset_kind(rest: &[&str]) -> AssetKind {
+    let file_name = rest
+        .last()
+        .copied()
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    let folders = rest[..rest.len().saturating_sub(1)]
+        .iter()
+        .map(|folder| folder.to_ascii_lowercase())
+        .collect::<Vec<_>>();
+    let in_folder = |names: &[&str]| {
+        folders
+            .iter()
+            .any(|folder| names.iter().any(|name| folder.starts_with(name)))
+    };
+    let prefixed = |prefixes: &[&str]| prefixes.iter().any(|prefix| file_name.starts_with(prefix));
+
+    if prefixed(&["sk_", "sm_"]) || in_folder(&["mesh"]) {
+        AssetKind::Mesh
+    } else if prefixed(&["pa_", "phy_"]) || in_folder(&["physic"]) {
+        AssetKind::Physics
+    } else if prefixed(&["a_", "am_", "abp_", "bs_"]) || in_folder(&["anim"]) {
+        AssetKind::Animation
+    } else if prefixed(&["mi_", "m_", "mf_"]) || in_folder(&["material"]) {
+        AssetKind::Material
+    } else if prefixed(&["t_"]) || in_folder(&["texture"]) {
+        AssetKind::Texture
+    } else if prefixed(&["ns_", "fx_", "p_"]) || in_folder(&["vfx", "fx", "effect"]) {
+        AssetKind::Effect
+    } else if in_folder(&["audio", "sound", "wwise"]) {
+        AssetKind::Audio
+    } else if in_folder(&["ui"]) {
+        AssetKind::Ui
+    } else {
+        AssetKind::Other
+    }
+}
+
+/// Location of the user skin database, next to the GUI config.
+pub fn overrides_path() -> PathBuf {
+    config_path().with_file_name(OVERRIDES_FILE)
+}
+
+fn read_overrides(path: &Path) -> Option<SkinDbFile> {
+    let data = fs::read_to_string(path).ok()?;
+    SkinDbFile::parse(&data)
+        .inspect_err(
+            |e| warn!(path = %path.display(), error = %e, "Ignoring skin database overrides"),
+        )
+        .ok()
+}
+
+fn load() -> SkinDb {
+    let mut file = SkinDbFile::parse(BUILT_IN).expect("built-in skin database is invalid");
+    let overrides = read_overrides(&overrides_path());
+    let override_revision = overrides.as_ref().map(|overrides| overrides.revision);
+    if let Some(overrides) = overrides {
+        let revision = file.revision;
+        file.merge(overrides);
+        file.revision = revision;
+    }
+    let mut db = SkinDb::from_file(&file);
+    db.override_revision = override_revision;
+    debug!(
+        heroes = db.heroes.len(),
+        skins = db.skins.len(),
+        "Loaded skin database"
+    );
+    db
+}
+
+/// The merged skin database, loaded on first use.
+pub fn skin_db() -> Arc<SkinDb> {
+    SKIN_DB.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// Reads the built-in data and user overrides again.
+pub fn reload() {
+    *SKIN_DB.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(load());
+}
+
+/// One row of the flat `character_data.json` list used by older versions.
+#[derive(Debug, Deserialize)]
+struct LegacySkinEntry {
+    skinid: String,
+    skin_name: String,
+    name: String,
+}
+
+/// Converts skin data to the database format. Accepts a skin database, the flat
+/// `character_data.json` list of older versions, or the markdown table of the
+/// MarvelRivalsCharacterIDs project.
+pub fn import(data: &str) -> Result<SkinDbFile, String> {
+    let trimmed = data.trim_start();
+    if trimmed.starts_with('{') {
+        return SkinDbFile::parse(data);
+    }
+    let entries = if trimmed.starts_with('[') {
+        serde_json::from_str::<Vec<LegacySkinEntry>>(data)
+            .map_err(|e| format!("Invalid skin list: {e}"))?
+    } else {
+        parse_markdown_table(data)
+    };
+    if entries.is_empty() {
+        return Err("No skins found in import".to_string());
+    }
+
+    let mut file = SkinDbFile {
+        schema: SKIN_DB_SCHEMA,
+        revision: 0,
+        heroes: Vec::new(),
+    };
+    for entry in entries {
+        let Ok(skin_id) = entry.skinid.trim().parse::<u32>() else {
+            continue;
+        };
+        let hero_id = skin_id / 1000;
+        if !(1000..=9999).contains(&hero_id) {
+            continue;
+        }
+        let default = skin_id % 1000 == 1;
+        let hero_index = match file.heroes.iter().position(|hero| hero.id == hero_id) {
+            Some(idx) => idx,
+            None => {
+                file.heroes.push(HeroRecord {
+                    id: hero_id,
+                    name: entry.name.clone(),
+                    skins: Vec::new(),
+                });
+                file.heroes.len() - 1
+            }
+        };
+        let hero = &mut file.heroes[hero_index];
+        // the default skin row carries the hero's real name, other rows can be mislabeled
+        if default {
+            hero.name = entry.name.clone();
+        }
+        match hero.skins.iter_mut().find(|skin| skin.id == skin_id) {
+            Some(skin) => {
+                if skin.name != entry.skin_name && !skin.variants.contains(&entry.skin_name) {
+                    skin.variants.push(entry.skin_name);
+                }
+            }
+            None => hero.skins.push(SkinRecord {
+                id: skin_id,
+                name: if default {
+                    "Default".to_string()
+                } else {
+                    entry.skin_name
+                },
+                default,
+                variants: Vec::new(),
+            }),
+        }
+    }
+    for hero in &mut file.heroes {
+        hero.skins.sort_by_key(|skin| skin.id);
+    }
+    file.heroes.sort_by_key(|hero| hero.id);
+    file.validate()?;
+    Ok(file)
+}
+
+/// Imports skin data into the user overrides and reloads the database. Returns the number of
+/// skins imported.
+pub fn import_overrides(data: &str) -> Result<usize, String> {
+    let imported = import(data)?;
+    let count = imported.heroes.iter().map(|hero| hero.skins.len()).sum();
+    let path = overrides_path();
+    let mut overrides = read_overrides(&path).unwrap_or(SkinDbFile {
+        schema: SKIN_DB_SCHEMA,
+        ..SkinDbFile::default()
+    });
+    let revision = overrides.revision;
+    overrides.merge(imported);
+    overrides.revision = revision + 1;
+    overrides.validate()?;
+
+    let json = serde_json::to_string_pretty(&overrides)
+        .map_err(|e| format!("Failed to serialize skin database: {e}"))?;
+    fs::write(&path, json).map_err(|e| format!("Failed to write {}: {e}", path.display()))?;
+    info!(skins = count, path = %path.display(), "Imported skin database overrides");
+    reload();
+    Ok(count)
+}
+
+/// Reads the MarvelRivalsCharacterIDs markdown table, skipping bots, placeholders and rows the
+/// page marks as mislabeled.
+fn parse_markdown_table(markdown: &str) -> Vec<LegacySkinEntry> {
+    let mut entries = Vec::new();
+    let mut current_char_name: Option<String> = None;
+
+    for line in markdown.lines() {
+        let line = line.trim();
+        if !line.starts_with('|') || line.contains("NAME") || line.contains(":--:") {
+            continue;
+        }
+
+        let cols: Vec<&str> = line.split('|').map(|s| s.trim()).collect();
+        if cols.len() < 5 {
+            continue;
+        }
+        let (id_col, name_col, skin_id_col, skin_name_col) = (cols[1], cols[2], cols[3], cols[4]);
+
+        // a row with a hero ID starts a new hero
+        if !id_col.is_empty() && id_col != "????" {
+            let is_placeholder = !id_col.chars().all(|c| c.is_ascii_digit())
+                || name_col.is_empty()
+                || name_col.contains("Old)")
+                || name_col.ends_with("Bot")
+                || name_col.contains("Bot (")
+                || name_col.starts_with("Zombie")
+                || name_col.starts_with("No Data")
+                || name_col.contains("Mislabeled");
+            if is_placeholder {
+                current_char_name = None;
+                continue;
+            }
+
+            current_char_name = Some(name_col.to_string());
+            entries.push(LegacySkinEntry {
+                skinid: format!("{id_col}001"),
+                skin_name: "Default".to_string(),
+                name: name_col.to_string(),
+            });
+        }
+
+        if skin_id_col.is_empty() || !skin_id_col.chars().all(|c| c.is_ascii_digit()) {
+            continue;
+        }
+        let Some(char_name) = &current_char_name else {
+            continue;
+        };
+        entries.push(LegacySkinEntry {
+            skinid: skin_id_col.to_string(),
+            skin_name: if skin_name_col.is_empty() {
+                char_name.clone()
+            } else {
+                skin_name_col.to_string()
+            },
+            name: char_name.clone(),
+        });
+    }
+
+    entries
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn skin(id: u32, name: &str) -> SkinRecord {
+        SkinRecord {
+            id,
+            name: name.to_string(),
+            default: id % 1000 == 1,
+            variants: Vec::new(),
+        }
+    }
+
+    fn hero(id: u32, name: &str, skins: Vec<SkinRecord>) -> HeroRecord {
+        HeroRecord {
+            id,
+            name: name.to_string(),
+            skins,
+        }
+    }
+
+    fn db(revision: u32, heroes: Vec<HeroRecord>) -> SkinDbFile {
+        SkinDbFile {
+            schema: SKIN_DB_SCHEMA,
+            revision,
+            heroes,
+        }
+    }
+
+    #[test]
+    fn test_built_in_is_valid() {
+        let file = SkinDbFile::parse(BUILT_IN).unwrap();
+        assert!(!file.heroes.is_empty());
+    }
+
+    #[test]
+    fn test_validate() {
+        let valid = db(
+            1,
+            vec![hero(1014, "Punisher", vec![skin(1014001, "Default")])],
+        );
+        assert!(valid.validate().is_ok());
+
+        let mut schema = valid.clone();
+        schema.schema = SKIN_DB_SCHEMA + 1;
+        assert!(schema.validate().unwrap_err().contains("schema"));
+
+        let invalid = db(
+            1,
+            vec![
+                hero(
+                    1014,
+                    "Punisher",
+                    vec![
+                        skin(1014001, "Default"),
+                        skin(1014001, "Again"),
+                        skin(1015002, "Wrong hero"),
+                        SkinRecord {
+                            default: true,
+                            ..skin(1014003, " ")
+                        },
+                    ],
+                ),
+                hero(1014, "Twice", Vec::new()),
+                hero(101, "", Vec::new()),
+            ],
+        );
+        let errors = invalid.validate().unwrap_err();
+        for expected in [
+            "skin 1014001 is listed twice",
+            "skin 1015002 does not belong to hero 1014",
+            "skin 1014003 has no name",
+            "hero 1014 has more than one default skin",
+            "hero 1014 is listed twice",
+            "hero ID 101 is not four digits",
+            "hero 101 has no name",
+        ] {
+            assert!(
+                errors.contains(expected),
+                "{expected} missing from {errors}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut base = db(
+            3,
+            vec![
+                hero(
+                    1014,
+                    "Punisher",
+                    vec![skin(1014001, "Default"), skin(1014002, "Old name")],
+                ),
+                hero(1048, "Psylocke", vec![skin(1048001, "Default")]),
+            ],
+        );
+        let new_default = SkinRecord {
+            default: true,
+            ..skin(1014010, "New default")
+        };
+        base.merge(db(
+            2,
+            vec![
+                hero(1048, "Psylocke", vec![skin(1048005, "New skin")]),
+                hero(
+                    1014,
+                    "The Punisher",
+                    vec![skin(1014002, "New name"), new_default],
+                ),
+                hero(1011, "Hulk", vec![skin(1011001, "Default")]),
+            ],
+        ));
+
+        assert_eq!(base.revision, 3);
+        assert_eq!(
+            base.heroes.iter().map(|hero| hero.id).collect::<Vec<_>>(),
+            [1011, 1014, 1048]
+        );
+        let punisher = &base.heroes[1];
+        assert_eq!(punisher.name, "The Punisher");
+        assert_eq!(
+            punisher
+                .skins
+                .iter()
+                .map(|skin| (skin.id, skin.name.as_str(), skin.default))
+                .collect::<Vec<_>>(),
+            [
+                (1014001, "Default", false),
+                (1014002, "New name", false),
+                (1014010, "New default", true),
+            ]
+        );
+        let psylocke = &base.heroes[2];
+        assert_eq!(
+            psylocke
+                .skins
+                .iter()
+                .map(|skin| skin.name.as_str())
+                .collect::<Vec<_>>(),
+            ["Default", "New skin"]
+        );
+        assert!(base.validate().is_ok());
+    }
+
+    #[test]
+    fn test_import_legacy_list() {
+        let json = r#"[
+            {"skinid": "1014001", "skin_name": "Default", "name": "Punisher"},
+            {"skinid": "1014002", "skin_name": "Cosmic Gold", "name": "Mislabeled"},
+            {"skinid": "1014002", "skin_name": "Cosmic Silver", "name": "Punisher"},
+            {"skinid": "abc", "skin_name": "Broken", "name": "Nobody"},
+            {"skinid": "101001", "skin_name": "Too short", "name": "Nobody"}
+        ]"#;
+        let file = import(json).unwrap();
+        assert_eq!(file.heroes.len(), 1);
+        let punisher = &file.heroes[0];
+        assert_eq!(punisher.name, "Punisher");
+        assert_eq!(punisher.skins.len(), 2);
+        assert!(punisher.skins[0].default);
+        assert_eq!(punisher.skins[1].name, "Cosmic Gold");
+        assert_eq!(punisher.skins[1].variants, ["Cosmic Silver"]);
+    }
+
+    #[test]
+    fn test_import_database() {
+        let json = serde_json::to_string(&db(
+            7,
+            vec![hero(1014, "Punisher", vec![skin(1014001, "Default")])],
+        ))
+        .unwrap();
+        assert_eq!(import(&json).unwrap().revision, 7);
+
+        let wrong_schema = json.replace(&format!("\"schema\":{SKIN_DB_SCHEMA}"), "\"schema\":99");
+        assert!(import(&wrong_schema).is_err());
+        assert!(import("nothing to see here").is_err());
+        assert!(import("[]").is_err());
+    }
+
+    const MARKDOWN: &str = "
+| ID | NAME | SKIN ID | SKIN NAME |
+|:--:|:--:|:--:|:--:|
+| 1014 | Punisher | 1014001 | |
+| | | 1014002 | Cosmic Gold |
+| ???? | | 1014003 | Mystery |
+| 1015 | Punisher Bot | 1015001 | Bot skin |
+| | | 1015002 | Skipped too |
+| 1016 | Storm (Old) | 1016002 | Old skin |
+| 1017 | Storm | 1017002 | |
+| 10x8 | Broken | 1018001 | |
+";
+
+    #[test]
+    fn test_parse_markdown_table() {
+        let entries = parse_markdown_table(MARKDOWN)
+            .into_iter()
+            .map(|entry| (entry.skinid, entry.skin_name, entry.name))
+            .collect::<Vec<_>>();
+        let expected = [
+            ("1014001", "Default", "Punisher"),
+            ("1014001", "Punisher", "Punisher"),
+            ("1014002", "Cosmic Gold", "Punisher"),
+            // rows without a hero ID keep the current hero
+            ("1014003", "Mystery", "Punisher"),
+            ("1017001", "Default", "Storm"),
+            ("1017002", "Storm", "Storm"),
+        ]
+        .map(|(id, skin, name)| (id.to_string(), skin.to_string(), name.to_string()));
+        assert_eq!(entries, expected);
+    }
+
+    #[test]
+    fn test_import_markdown() {
+        let file = import(MARKDOWN).unwrap();
+        assert_eq!(
+            file.heroes
+                .iter()
+                .map(|hero| (hero.id, hero.name.as_str(), hero.skins.len()))
+                .collect::<Vec<_>>(),
+            [(1014, "Punisher", 3), (1017, "Storm", 2)]
+        );
+        let default = &file.heroes[0].skins[0];
+        assert_eq!(default.name, "Default");
+        assert_eq!(default.variants, ["Punisher"]);
+    }
+}
@@ -0,0 +1,150 @@
+use crate::installed::same_mod_identity;
+use crate::mod_profiles::ModProfile;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{debug, info, warn};
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ModTagAssignment {
+    pub path: PathBuf,
+    pub tags: Vec<String>,
+}
+
+/// User tags and the mods they are assigned to, keyed by mod identity so toggling a mod keeps its
+/// tags.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct ModTags {
+    #[serde(default)]
+    pub tag_catalog: Vec<String>,
+    #[serde(default)]
+    pub mod_tags: Vec<ModTagAssignment>,
+}
+
+impl ModTags {
+    pub fn tags_for_mod(&self, mod_path: &Path) -> Vec<String> {
+        self.mod_tags
+            .iter()
+            .find(|assignment| same_mod_identity(&assignment.path, mod_path))
+            .map(|assignment| assignment.tags.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn add_tag(&mut self, mod_path: &Path, tag: &str) {
+        let tag = tag.trim();
+        if tag.is_empty() {
+            return;
+        }
+
+        if !self.tag_catalog.iter().any(|existing| existing == tag) {
+            self.tag_catalog.push(tag.to_string());
+            self.tag_catalog.sort();
+        }
+
+        if let Some(assignment) = self
+            .mod_tags
+            .iter_mut()
+            .find(|assignment| same_mod_identity(&assignment.path, mod_path))
+        {
+            if !assignment.tags.iter().any(|existing| existing == tag) {
+                assignment.tags.push(tag.to_string());
+                assignment.tags.sort();
+            }
+            return;
+        }
+
+        self.mod_tags.push(ModTagAssignment {
+            path: mod_path.to_path_buf(),
+            tags: vec![tag.to_string()],
+        });
+    }
+
+    pub fn remove_tag(&mut self, mod_path: &Path, tag: &str) {
+        if let Some(assignment) = self
+            .mod_tags
+            .iter_mut()
+            .find(|assignment| same_mod_identity(&assignment.path, mod_path))
+        {
+            assignment.tags.retain(|existing| existing != tag);
+        }
+        self.mod_tags
+            .retain(|assignment| !assignment.tags.is_empty());
+    }
+
+    pub fn remove_all(&mut self, mod_path: &Path) {
+        self.mod_tags
+            .retain(|assignment| !same_mod_identity(&assignment.path, mod_path));
+    }
+
+    pub fn update_path(&mut self, old_path: &Path, new_path: &Path) {
+        if let Some(assignment) = self
+            .mod_tags
+            .iter_mut()
+            .find(|assignment| same_mod_identity(&assignment.path, old_path))
+        {
+            assignment.path = new_path.to_path_buf();
+        }
+    }
+}
+
+/// The parts of `repak_mod_manager.json` the headless manager reads and writes. Settings only
+/// the GUI understands are kept in `other` so saving from the CLI doesn't drop them.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct ManagerState {
+    pub game_path: PathBuf,
+    #[serde(default)]
+    pub game_chunk_path: Option<PathBuf>,
+    #[serde(default)]
+    pub kawaii_physics_usmap: Option<PathBuf>,
+    #[serde(flatten)]
+    pub tags: ModTags,
+    /// Mod base names from highest to lowest priority, as last applied in the load order window.
+    #[serde(default)]
+    pub load_order: Vec<String>,
+    #[serde(default)]
+    pub mod_profiles: Vec<ModProfile>,
+    #[serde(flatten)]
+    pub other: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Location of the state file shared by the GUI and the CLI, creating its directory if needed.
+pub fn config_path() -> PathBuf {
+    let mut path = dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("repak_manager");
+    if !path.exists() {
+        if let Err(e) = fs::create_dir_all(&path) {
+            warn!(
+                error = %e,
+                "Failed to create config directory"
+            );
+        } else {
+            debug!("Created config directory");
+        }
+    }
+
+    path.push("repak_mod_manager.json");
+
+    path
+}
+
+impl ManagerState {
+    pub fn load() -> Result<Self, String> {
+        let path = config_path();
+        let data = fs::read_to_string(&path).map_err(|e| {
+            format!(
+                "Failed to read {}: {e}\nSet up the mods folder in the GUI or pass --mods-dir",
+                path.display()
+            )
+        })?;
+        serde_json::from_str(&data).map_err(|e| format!("Failed to parse {}: {e}", path.display()))
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let path = config_path();
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize state: {e}"))?;
+        info!("Saving config");
+        fs::write(&path, json).map_err(|e| format!("Failed to write {}: {e}", path.display()))
+    }
+}
@@ -1,39 +1,10 @@
+use crate::classification::classify_files;
 use crate::install_mod::InstallableMod;
+use crate::skin_db::{skin_db, SkinInfo};
 use path_clean::PathClean;
-use std::collections::HashMap;
-use std::option::Option;
 use std::path::{Path, PathBuf};
-use std::sync::LazyLock;
 use std::{fs, io};
 
-#[derive(Debug, Deserialize, Serialize, Hash)]
-pub struct SkinEntry {
-    pub skinid: String,
-    #[serde(rename = "skin_name")]
-    pub skin_name: String,
-    pub name: String,
-}
-// we grab the locally found character_data.json otherwise we let the program use the build time
-// provided one
-static SKIN_ENTRIES: LazyLock<HashMap<u32, SkinEntry>> = LazyLock::new(|| {
-    let json_data = match fs::read_to_string("data/character_data.json") {
-        Ok(s) => s,
-        Err(_) => include_str!("data/character_data.json").to_owned(),
-    };
-
-    let skins: Vec<SkinEntry> =
-        serde_json::from_str(&json_data).expect("Invalid character_data.json");
-    skins
-        .into_iter()
-        .map(|entry| {
-            let id: u32 = entry.skinid.parse().expect("Invalid skinid");
-            (id, entry)
-        })
-        .collect()
-});
-
-static SKIN_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"[0-9]{4}\/[0-9]{7}").unwrap());
-
 pub fn collect_files(paths: &mut Vec<PathBuf>, dir: &Path) -> io::Result<()> {
     for entry in fs::read_dir(dir)? {
         let entry = entry?;
@@ -47,62 +18,31 @@ pub fn collect_files(paths: &mut Vec<PathBuf>, dir: &Path) -> io::Result<()> {
     Ok(())
 }
 
-pub enum ModType {
-    Default(String),
-    Custom(String),
-}
-pub fn get_character_mod_skin(file: &str) -> Option<ModType> {
-    let skin_id = SKIN_REGEX.clone().captures(file);
-    if let Some(skin_id) = skin_id {
-        let skin_id = skin_id[0].to_string();
-        let skin_id = &skin_id[5..];
-        let skin = SKIN_ENTRIES.get(&(skin_id.parse().unwrap()));
-        if let Some(skin) = skin {
-            if skin.skin_name == "Default" {
-                return Some(ModType::Default(format!(
-                    "{} - {}",
-                    &skin.name, &skin.skin_name
-                )));
-            }
-            return Some(ModType::Custom(format!(
-                "{} - {}",
-                &skin.name, &skin.skin_name
-            )));
+/// Skins referenced anywhere in a mod's files, each listed once.
+pub fn mod_skins(mod_contents: &[String]) -> Vec<SkinInfo> {
+    let db = skin_db();
+    let mut skins: Vec<SkinInfo> = Vec::new();
+    for file in mod_contents {
+        let Some(skin) = db.lookup_path(file).and_then(|asset| asset.skin) else {
+            continue;
+        };
+        if !skins
+            .iter()
+            .any(|existing| existing.skin_id == skin.skin_id)
+        {
+            skins.push(skin);
         }
-        None
-    } else {
-        None
     }
+    skins
 }
-pub fn get_current_pak_characteristics(mod_contents: Vec<String>) -> String {
-    let mut fallback: Option<String> = None;
-
-    for file in &mod_contents {
-        let path = file
-            .strip_prefix("Marvel/Content/Marvel/")
-            .or_else(|| file.strip_prefix("/Game/Marvel/"))
-            .unwrap_or(file);
-
-        let category = path.split('/').next().unwrap_or_default();
-
-        match category {
-            "Characters" => match get_character_mod_skin(path) {
-                Some(ModType::Custom(skin)) => return skin,
-                Some(ModType::Default(name)) => fallback = Some(name),
-                None => {}
-            },
-            "UI" => return "UI".to_string(),
-            "Movies" => return "Movies".to_string(),
-            _ if path.contains("WwiseAudio") => return "Audio".to_string(),
-            _ => {}
-        }
-    }
 
-    fallback.unwrap_or_else(|| "Character (Unknown)".to_string())
+/// Short description of a mod from its file list, see
+/// [`ModClassification::characteristic`](crate::classification::ModClassification::characteristic)..
+pub fn get_current_pak_characteristics(mod_contents: Vec<String>) -> String {
+    classify_files(&mod_contents).characteristic()
 }
 
-use regex_lite::Regex;
-use serde::{Deserialize, Serialize};
+use serde::Deserialize;
 use tracing::{debug, info, instrument, warn};
 
 const RIVALS_USMAP_API_URL: &str =
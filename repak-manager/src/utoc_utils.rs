@@ -0,0 +1,245 @@
+use crate::install_mod::retoc_aes_key;
+use crate::keys::KEYS;
+use repak::iostore::{ChunkType, TocFile, TocReader};
+use repak::PakReader;
+use retoc::{action_manifest, ActionManifest, Config, FGuid};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// One row of a mod's file listing, from a pak index or an IoStore package store.
+#[derive(Clone, Debug)]
+pub struct FileEntry {
+    /// This is the path of the internal file mounting path
+    pub file_path: String,
+    pub pak_path: PathBuf,
+    pub pak_reader: PakReader,
+    pub compressed: String,
+    pub uncompressed: String,
+    pub offset: String,
+    /// Compression methods of an IoStore package's chunks, empty for pak entries.
+    pub compression: String,
+    pub bulkdata: Option<usize>,
+    pub package_data: Option<usize>,
+    /// The `.utoc` directory index files holding an IoStore package, empty for pak entries and
+    /// containers without a directory index.
+    pub toc_files: Vec<TocFile>,
+}
+
+/// The packages of the container at `utoc_path`, one row each.
+pub fn read_utoc(
+    utoc_path: &Path,
+    pak_reader: &PakReader,
+    pak_path: &Path,
+) -> Result<Vec<FileEntry>, String> {
+    let toc = open_toc(utoc_path)?;
+    let packages = match toc_packages(&toc) {
+        Some(packages) => packages,
+        // containers without a directory index only list their packages in the package store
+        None => manifest_packages(utoc_path)?,
+    };
+    let unavailable = || "Unavailable".to_string();
+    Ok(packages
+        .into_iter()
+        .map(|package| {
+            let sizes = package_sizes(&toc, &package.files);
+            FileEntry {
+                file_path: package.name,
+                pak_path: PathBuf::from(pak_path),
+                pak_reader: pak_reader.clone(),
+                compressed: sizes
+                    .as_ref()
+                    .map_or_else(unavailable, |sizes| sizes.compressed.to_string()),
+                uncompressed: sizes
+                    .as_ref()
+                    .map_or_else(unavailable, |sizes| sizes.uncompressed.to_string()),
+                offset: sizes
+                    .as_ref()
+                    .and_then(|sizes| sizes.offset)
+                    .map_or_else(unavailable, |offset| format!("{offset:#x}")),
+                compression: sizes.map_or_else(unavailable, |sizes| sizes.methods.join(", ")),
+                bulkdata: Some(package.bulkdata),
+                package_data: Some(package.package_data),
+                toc_files: package.files,
+            }
+        })
+        .collect::<Vec<_>>())
+}
+
+pub fn read_utoc_package_names(utoc_path: &Path) -> Result<Vec<String>, String> {
+    let packages = match toc_packages(&open_toc(utoc_path)?) {
+        Some(packages) => packages,
+        None => manifest_packages(utoc_path)?,
+    };
+    Ok(packages.into_iter().map(|package| package.name).collect())
+}
+
+pub fn is_iostore_obfuscated(utoc_path: &Path) -> Result<bool, String> {
+    Ok(open_toc(utoc_path)?.flags().encrypted())
+}
+
+/// Opens a `.utoc` with the game's AES key.
+pub fn open_toc(utoc_path: &Path) -> Result<TocReader, String> {
+    let file = File::open(utoc_path)
+        .map_err(|e| format!("Failed to open {}: {e}", utoc_path.display()))?;
+    repak::PakBuilder::new()
+        .keys(KEYS.clone())
+        .toc_reader(&mut BufReader::new(file))
+        .map_err(|e| format!("Failed to read {}: {e}", utoc_path.display()))
+}
+
+/// Whether the container at `utoc_path` has a directory index, so [`read_utoc`] can list its
+/// packages without the `.ucas`.
+pub fn has_directory_index(utoc_path: &Path) -> bool {
+    open_toc(utoc_path).is_ok_and(|toc| toc.flags().indexed())
+}
+
+/// Writes the raw chunk data of directory index files into `output_dir`, one file per chunk
+/// named after its file. Export bundles are written as stored in the container, not as legacy
+/// `.uasset`/`.uexp` pairs.
+pub fn extract_toc_files(
+    utoc_path: &Path,
+    files: &[TocFile],
+    output_dir: &Path,
+) -> Result<Vec<PathBuf>, String> {
+    let toc = open_toc(utoc_path)?;
+    let ucas_path = utoc_path.with_extension("ucas");
+    let mut ucas = BufReader::new(
+        File::open(&ucas_path)
+            .map_err(|e| format!("Failed to open {}: {e}", ucas_path.display()))?,
+    );
+    fs::create_dir_all(output_dir)
+        .map_err(|e| format!("Failed to create {}: {e}", output_dir.display()))?;
+
+    let mut written = Vec::with_capacity(files.len());
+    for file in files {
+        let data = toc
+            .read_chunk(file.chunk, &mut ucas)
+            .map_err(|e| format!("Failed to read {}: {e}", file.path))?;
+        let name = Path::new(&file.path)
+            .file_name()
+            .ok_or_else(|| format!("Invalid file path in directory index: {}", file.path))?;
+        let output = output_dir.join(name);
+        fs::write(&output, data)
+            .map_err(|e| format!("Failed to write {}: {e}", output.display()))?;
+        written.push(output);
+    }
+    Ok(written)
+}
+
+struct TocPackage {
+    name: String,
+    bulkdata: usize,
+    package_data: usize,
+    files: Vec<TocFile>,
+}
+
+/// Lists the packages of a container from its directory index, or `None` if it has none.
+fn toc_packages(toc: &TocReader) -> Option<Vec<TocPackage>> {
+    if !toc.flags().indexed() {
+        return None;
+    }
+    let mut packages: BTreeMap<&str, TocPackage> = BTreeMap::new();
+    for file in toc.files() {
+        let Some((stem, _)) = file.path.rsplit_once('.') else {
+            continue;
+        };
+        let package = packages.entry(stem).or_insert_with(|| TocPackage {
+            name: package_name(stem),
+            bulkdata: 0,
+            package_data: 0,
+            files: Vec::new(),
+        });
+        match toc.chunks()[file.chunk].id.chunk_type() {
+            Some(ChunkType::ExportBundleData) => package.package_data += 1,
+            Some(
+                ChunkType::BulkData | ChunkType::OptionalBulkData | ChunkType::MemoryMappedBulkData,
+            ) => package.bulkdata += 1,
+            _ => {}
+        }
+        package.files.push(file.clone());
+    }
+    Some(
+        packages
+            .into_values()
+            .filter(|package| package.package_data > 0)
+            .collect(),
+    )
+}
+
+struct PackageSizes {
+    /// Offset of the first stored block in the `.ucas`, `None` for empty packages.
+    offset: Option<u64>,
+    compressed: u64,
+    uncompressed: u64,
+    methods: Vec<String>,
+}
+
+/// Adds up the stored and uncompressed sizes of a package's chunks.
+fn package_sizes(toc: &TocReader, files: &[TocFile]) -> Option<PackageSizes> {
+    if files.is_empty() {
+        return None;
+    }
+    let mut sizes = PackageSizes {
+        offset: None,
+        compressed: 0,
+        uncompressed: 0,
+        methods: Vec::new(),
+    };
+    for file in files {
+        sizes.uncompressed += toc.chunks()[file.chunk].length;
+        for block in toc.chunk_blocks(file.chunk).ok()? {
+            sizes.offset = Some(
+                sizes
+                    .offset
+                    .map_or(block.offset, |offset| offset.min(block.offset)),
+            );
+            sizes.compressed += block.compressed_size as u64;
+            let method = match block.method {
+                0 => "None",
+                method => toc.compression_methods().get(method as usize - 1)?.as_str(),
+            };
+            if !sizes.methods.iter().any(|known| known == method) {
+                sizes.methods.push(method.to_string());
+            }
+        }
+    }
+    Some(sizes)
+}
+
+/// Turns a mount relative path like `Marvel/Content/UI/Foo` into the package name `/Game/UI/Foo`.
+fn package_name(stem: &str) -> String {
+    if let Some(rest) = stem.strip_prefix("Marvel/Content/") {
+        format!("/Game/{rest}")
+    } else if let Some(rest) = stem.strip_prefix("Engine/Content/") {
+        format!("/Engine/{rest}")
+    } else {
+        format!("/{stem}")
+    }
+}
+
+fn manifest_packages(utoc_path: &Path) -> Result<Vec<TocPackage>, String> {
+    let action_mn = ActionManifest::new(PathBuf::from(utoc_path));
+    let mut config = Config {
+        container_header_version_override: None,
+        ..Default::default()
+    };
+    config.aes_keys.insert(FGuid::default(), retoc_aes_key()?);
+    let config = Arc::new(config);
+
+    let ops = action_manifest(action_mn, config)
+        .map_err(|e| format!("Failed to read utoc manifest: {e}"))?;
+    Ok(ops
+        .oplog
+        .entries
+        .iter()
+        .map(|entry| TocPackage {
+            name: entry.packagestoreentry.packagename.clone(),
+            bulkdata: entry.bulkdata.len(),
+            package_data: entry.packagedata.len(),
+            files: Vec::new(),
+        })
+        .collect())
+}
@@ -0,0 +1,291 @@
+use std::fmt;
+use std::sync::Arc;
+
+use crate::{Compression, Error};
+
+/// A block compression algorithm referenced by name from the pak footer.
+///
+/// Paks since version 8 store compression method names in the footer and entries refer to them by
+/// slot. Built-in codecs cover the names in [`Compression`]; register others on
+/// [`PakBuilder`](crate::PakBuilder) to read or write paks using custom compressors.
+pub trait Codec: Send + Sync {
+    /// Name written into the pak footer. Matched case-insensitively and limited to 32 bytes.
+    fn name(&self) -> &str;
+
+    /// Compresses one block.
+    fn compress(&self, data: &[u8]) -> Result<Vec<u8>, Error>;
+
+    /// Compresses one block and appends it to `out`. Writers call this for every block, override
+    /// it to compress straight into `out` instead of allocating a buffer per block.
+    fn compress_into(&self, data: &[u8], out: &mut Vec<u8>) -> Result<(), Error> {
+        out.extend_from_slice(&self.compress(data)?);
+        Ok(())
+    }
+
+    /// Decompresses one block into `out`, which is sized to the block's uncompressed length.
+    fn decompress_into(&self, data: &[u8], out: &mut [u8]) -> Result<(), Error>;
+}
+
+impl fmt::Debug for dyn Codec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Codec({})", self.name())
+    }
+}
+
+/// Codecs available to a reader or writer, looked up by footer name.
+#[derive(Clone, Debug)]
+pub struct CodecRegistry {
+    codecs: Vec<Arc<dyn Codec>>,
+}
+
+impl Default for CodecRegistry {
+    fn default() -> Self {
+        #[allow(unused_mut)]
+        let mut codecs: Vec<Arc<dyn Codec>> = vec![];
+        #[cfg(feature = "compression")]
+        codecs.extend([
+            Arc::new(builtin::Zlib) as Arc<dyn Codec>,
+            Arc::new(builtin::Gzip),
+            Arc::new(builtin::Zstd),
+            Arc::new(builtin::Lz4),
+        ]);
+        #[cfg(any(feature = "oodle", feature = "oodle-rs"))]
+        codecs.push(Arc::new(builtin::Oodle));
+        Self { codecs }
+    }
+}
+
+impl CodecRegistry {
+    /// Adds a codec, replacing any registered codec with the same name.
+    pub fn register(&mut self, codec: Arc<dyn Codec>) {
+        self.codecs
+            .retain(|existing| !existing.name().eq_ignore_ascii_case(codec.name()));
+        self.codecs.push(codec);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Arc<dyn Codec>> {
+        self.codecs
+            .iter()
+            .find(|codec| codec.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Looks up the codec for a footer name, explaining why it is missing if it is not registered.
+    pub(crate) fn resolve(&self, name: &str) -> Result<&Arc<dyn Codec>, Error> {
+        self.get(name).ok_or_else(|| {
+            // built-in names are only missing when their feature is disabled
+            match name.parse::<Compression>() {
+                Ok(Compression::Oodle) => Error::Oodle,
+                Ok(_) => Error::Compression,
+                Err(_) => Error::UnregisteredCodec(name.to_owned()),
+            }
+        })
+    }
+}
+
+#[cfg(feature = "compression")]
+mod builtin {
+    use std::io::{Read, Write};
+
+    use super::Codec;
+    use crate::Error;
+
+    fn read_exact_into(mut decoder: impl Read, out: &mut [u8], name: &str) -> Result<(), Error> {
+        decoder
+            .read_exact(out)
+            .map_err(|_| Error::DecompressionFailed(name.to_owned()))
+    }
+
+    pub(super) struct Zlib;
+    impl Codec for Zlib {
+        fn name(&self) -> &str {
+            "Zlib"
+        }
+        fn compress(&self, data: &[u8]) -> Result<Vec<u8>, Error> {
+            let mut out = vec![];
+            self.compress_into(data, &mut out)?;
+            Ok(out)
+        }
+        fn compress_into(&self, data: &[u8], out: &mut Vec<u8>) -> Result<(), Error> {
+            let mut compress = flate2::write::ZlibEncoder::new(out, flate2::Compression::fast());
+            compress.write_all(data)?;
+            compress.finish()?;
+            Ok(())
+        }
+        fn decompress_into(&self, data: &[u8], out: &mut [u8]) -> Result<(), Error> {
+            read_exact_into(flate2::read::ZlibDecoder::new(data), out, self.name())
+        }
+    }
+
+    pub(super) struct Gzip;
+    impl Codec for Gzip {
+        fn name(&self) -> &str {
+            "Gzip"
+        }
+        fn compress(&self, data: &[u8]) -> Result<Vec<u8>, Error> {
+            let mut out = vec![];
+            self.compress_into(data, &mut out)?;
+            Ok(out)
+        }
+        fn compress_into(&self, data: &[u8], out: &mut Vec<u8>) -> Result<(), Error> {
+            let mut compress = flate2::write::GzEncoder::new(out, flate2::Compression::fast());
+            compress.write_all(data)?;
+            compress.finish()?;
+            Ok(())
+        }
+        fn decompress_into(&self, data: &[u8], out: &mut [u8]) -> Result<(), Error> {
+            read_exact_into(flate2::read::GzDecoder::new(data), out, self.name())
+        }
+    }
+
+    pub(super) struct Zstd;
+    impl Codec for Zstd {
+        fn name(&self) -> &str {
+            "Zstd"
+        }
+        fn compress(&self, data: &[u8]) -> Result<Vec<u8>, Error> {
+            Ok(zstd::stream::encode_all(data, 0)?)
+        }
+        fn compress_into(&self, data: &[u8], out: &mut Vec<u8>) -> Result<(), Error> {
+            Ok(zstd::stream::copy_encode(data, out, 0)?)
+        }
+        fn decompress_into(&self, data: &[u8], out: &mut [u8]) -> Result<(), Error> {
+            read_exact_into(zstd::stream::read::Decoder::new(data)?, out, self.name())
+        }
+    }
+
+    pub(super) struct Lz4;
+    impl Codec for Lz4 {
+        fn name(&self) -> &str {
+            "LZ4"
+        }
+        fn compress(&self, data: &[u8]) -> Result<Vec<u8>, Error> {
+            Ok(lz4_flex::block::compress(data))
+        }
+        fn decompress_into(&self, data: &[u8], out: &mut [u8]) -> Result<(), Error> {
+            lz4_flex::block::decompress_into(data, out)
+                .map(|_| ())
+                .map_err(|_| Error::DecompressionFailed(self.name().to_owned()))
+        }
+    }
+
+    /// Native Oodle through `oodle_loader`, falling back to the pure-Rust decoder for reading when
+    /// the `oodle-rs` feature is enabled and the library can't be loaded.
+    #[cfg(any(feature = "oodle", feature = "oodle-rs"))]
+    pub(super) struct Oodle;
+    #[cfg(any(feature = "oodle", feature = "oodle-rs"))]
+    impl Codec for Oodle {
+        fn name(&self) -> &str {
+            "Oodle"
+        }
+        fn compress(&self, data: &[u8]) -> Result<Vec<u8>, Error> {
+            let mut out = vec![];
+            self.compress_into(data, &mut out)?;
+            Ok(out)
+        }
+        fn compress_into(&self, data: &[u8], out: &mut Vec<u8>) -> Result<(), Error> {
+            #[cfg(feature = "oodle")]
+            {
+                native::with_compressor(|oodle| Ok(oodle.compress_append(data, out)?))
+            }
+            // the pure-Rust decoder can't compress
+            #[cfg(not(feature = "oodle"))]
+            {
+                let _ = (data, out);
+                Err(Error::Other(
+                    "Oodle compression needs the native library, enable the oodle feature".into(),
+                ))
+            }
+        }
+        fn decompress_into(&self, data: &[u8], out: &mut [u8]) -> Result<(), Error> {
+            #[cfg(not(feature = "oodle-rs"))]
+            {
+                native::with_compressor(|oodle| Ok(oodle.decompress_into(data, out)?))
+            }
+            #[cfg(all(feature = "oodle", feature = "oodle-rs"))]
+            {
+                match oodle_loader::oodle() {
+                    Ok(_) => native::with_compressor(|oodle| Ok(oodle.decompress_into(data, out)?)),
+                    Err(_) => oodle_rs::decompress_into(data, out),
+                }
+            }
+            #[cfg(not(feature = "oodle"))]
+            {
+                oodle_rs::decompress_into(data, out)
+            }
+        }
+    }
+
+    #[cfg(feature = "oodle")]
+    mod native {
+        use std::cell::RefCell;
+
+        use oodle_loader::{CompressionLevel, Compressor, OodleCompressor};
+
+        use crate::Error;
+
+        thread_local! {
+            // scratch and decoder memory are reused for every block compressed on this thread
+            static COMPRESSOR: RefCell<Option<OodleCompressor>> = const { RefCell::new(None) };
+        }
+
+        pub fn with_compressor<T>(
+            f: impl FnOnce(&mut OodleCompressor) -> Result<T, Error>,
+        ) -> Result<T, Error> {
+            COMPRESSOR.with_borrow_mut(|compressor| {
+                let compressor = match compressor {
+                    Some(compressor) => compressor,
+                    None => compressor.insert(OodleCompressor::new(
+                        Compressor::Kraken,
+                        CompressionLevel::Normal,
+                    )?),
+                };
+                f(compressor)
+            })
+        }
+    }
+
+    #[cfg(feature = "oodle-rs")]
+    pub(super) mod oodle_rs {
+        use std::cell::RefCell;
+
+        use crate::Error;
+
+        thread_local! {
+            // the extractor owns ~768KiB of scratch buffers so keep one per thread
+            static EXTRACTOR: RefCell<oozextract::Extractor> =
+                RefCell::new(oozextract::Extractor::new());
+        }
+
+        pub fn decompress_into(data: &[u8], out: &mut [u8]) -> Result<(), Error> {
+            EXTRACTOR
+                .with_borrow_mut(|extractor| extractor.read_from_slice(data, out))
+                .map(|_| ())
+                .map_err(|err| Error::DecompressionFailed(format!("Oodle: {err}")))
+        }
+    }
+}
+
+#[cfg(all(test, feature = "oodle", feature = "oodle-rs"))]
+mod test {
+    #[test]
+    fn test_oodle_rs_matches_native() {
+        let data = (0..0x10000u32)
+            .flat_map(|i| (i / 7).to_le_bytes())
+            .take(0x10000)
+            .collect::<Vec<_>>();
+        let oodle = oodle_loader::oodle().unwrap();
+        for compressor in [
+            oodle_loader::Compressor::Kraken,
+            oodle_loader::Compressor::Mermaid,
+            oodle_loader::Compressor::Selkie,
+            oodle_loader::Compressor::Leviathan,
+        ] {
+            let compressed = oodle
+                .compress(&data, compressor, oodle_loader::CompressionLevel::Normal)
+                .unwrap();
+            let mut out = vec![0; data.len()];
+            super::builtin::oodle_rs::decompress_into(&compressed, &mut out).unwrap();
+            assert_eq!(data, out, "{compressor:?}");
+        }
+    }
+}
@@ -1,8 +1,9 @@
 use std::io::Write;
+use std::sync::Arc;
 
 use crate::{
     entry::{Block, Entry},
-    Compression, Error, Hash, Version, VersionMajor,
+    Codec, Error, Hash, Version, VersionMajor,
 };
 
 type Result<T, E = Error> = std::result::Result<T, E>;
@@ -56,7 +57,7 @@ pub(crate) fn decrypt(key: &super::Key, bytes: &mut [u8]) -> Result<(), super::E
 }
 
 pub struct PartialEntry<D: AsRef<[u8]>> {
-    compression: Option<Compression>,
+    compression: Option<String>,
     compressed_size: u64,
     uncompressed_size: u64,
     compression_block_size: u32,
@@ -84,16 +85,15 @@ impl<D: AsRef<[u8]>> AsRef<[u8]> for PartialEntryData<D> {
     }
 }
 
-#[cfg(feature = "compression")]
 fn get_compression_slot(
     version: Version,
-    compression_slots: &mut Vec<Option<Compression>>,
-    compression: Compression,
+    compression_slots: &mut Vec<Option<String>>,
+    compression: &str,
 ) -> Result<u32> {
-    let slot = compression_slots
-        .iter()
-        .enumerate()
-        .find(|(_, s)| **s == Some(compression));
+    let slot = compression_slots.iter().enumerate().find(|(_, s)| {
+        s.as_deref()
+            .is_some_and(|s| s.eq_ignore_ascii_case(compression))
+    });
     Ok(if let Some((i, _)) = slot {
         // existing found
         i
@@ -111,11 +111,11 @@ fn get_compression_slot(
             .find(|(_, s)| s.is_none())
         {
             // empty found, set it to used compression type
-            *empty_slot = Some(compression);
+            *empty_slot = Some(compression.to_owned());
             i
         } else {
             // no empty slot found, add a new one
-            compression_slots.push(Some(compression));
+            compression_slots.push(Some(compression.to_owned()));
             compression_slots.len() - 1
         }
     } as u32)
@@ -125,16 +125,14 @@ impl<D: AsRef<[u8]>> PartialEntry<D> {
     pub(crate) fn build_entry(
         &self,
         version: Version,
-        #[allow(unused)] compression_slots: &mut Vec<Option<Compression>>,
+        compression_slots: &mut Vec<Option<String>>,
         file_offset: u64,
     ) -> Result<Entry> {
-        #[cfg(feature = "compression")]
         let compression_slot = self
             .compression
+            .as_deref()
             .map(|c| get_compression_slot(version, compression_slots, c))
             .transpose()?;
-        #[cfg(not(feature = "compression"))]
-        let compression_slot = None;
 
         let blocks = match &self.data {
             PartialEntryData::Slice(_) => None,
@@ -191,7 +189,7 @@ impl<D: AsRef<[u8]>> PartialEntry<D> {
 }
 
 pub(crate) fn build_partial_entry<D>(
-    allowed_compression: &[Compression],
+    allowed_compression: &[Arc<dyn Codec>],
     data: D,
     #[allow(unused)] key: &super::Key,
     path: &str,
@@ -210,32 +208,33 @@ where
     }
 
     // TODO possibly select best compression based on some criteria instead of picking first
-    let mut compression = allowed_compression.first().cloned();
     let uncompressed_size = data.as_ref().len() as u64;
+    let compression = allowed_compression
+        .first()
+        .filter(|_| uncompressed_size > 0);
     let compression_block_size;
 
     let mut data = match compression {
-        #[cfg(not(feature = "compression"))]
-        Some(_) => {
-            unreachable!("should not be able to reach this point without compression feature")
-        }
-        #[cfg(feature = "compression")]
-        Some(compression) if uncompressed_size > 0 => {
+        Some(codec) => {
             // https://github.com/EpicGames/UnrealEngine/commit/3aad0ff7976be1073005dca2c1282af548b45d89
             // Block size must fit into flags field or it may cause unreadable paks for earlier Unreal Engine versions
             compression_block_size = 0x10000;
             let mut compressed_data = vec![];
             let mut blocks = vec![];
             for chunk in data.as_ref().chunks(compression_block_size as usize) {
-                let mut data = compress(compression, chunk)?;
-                if encrypted {
-                    pad_zeros_to_alignment(&mut data, 16);
-                }
-                compressed_data.extend_from_slice(&data);
-                hasher.update(&data);
+                // compress straight into the entry buffer, padding each block in place
+                let start = compressed_data.len();
+                codec.compress_into(chunk, &mut compressed_data)?;
+                let compressed_size = if encrypted {
+                    pad_length(compressed_data.len() - start, 16)
+                } else {
+                    compressed_data.len() - start
+                };
+                compressed_data.resize(start + compressed_size, 0);
+                hasher.update(&compressed_data[start..]);
                 blocks.push(PartialBlock {
                     uncompressed_size: chunk.len(),
-                    compressed_size: data.len(),
+                    compressed_size,
                 })
             }
 
@@ -244,8 +243,7 @@ where
                 blocks,
             }
         }
-        _ => {
-            compression = None;
+        None => {
             compression_block_size = 0;
             hasher.update(data.as_ref());
             PartialEntryData::Slice(data)
@@ -281,7 +279,7 @@ where
     }
 
     Ok(PartialEntry {
-        compression,
+        compression: compression.map(|codec| codec.name().to_owned()),
         compressed_size: data.as_ref().len() as u64,
         uncompressed_size,
         compression_block_size,
@@ -290,39 +288,3 @@ where
         encrypted,
     })
 }
-
-#[cfg(feature = "compression")]
-fn compress(compression: Compression, data: &[u8]) -> Result<Vec<u8>> {
-    use std::io::Write;
-
-    let compressed = match compression {
-        Compression::Zlib => {
-            let mut compress =
-                flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::fast());
-            compress.write_all(data.as_ref())?;
-            compress.finish()?
-        }
-        Compression::Gzip => {
-            let mut compress =
-                flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
-            compress.write_all(data.as_ref())?;
-            compress.finish()?
-        }
-        Compression::Zstd => zstd::stream::encode_all(data, 0)?,
-        Compression::LZ4 => lz4_flex::block::compress(data),
-        Compression::Oodle => {
-            #[cfg(not(feature = "oodle"))]
-            return Err(super::Error::Oodle);
-            #[cfg(feature = "oodle")]
-            {
-                oodle_loader::oodle().unwrap().compress(
-                    data.as_ref(),
-                    oodle_loader::Compressor::Kraken,
-                    oodle_loader::CompressionLevel::Normal,
-                )?
-            }
-        }
-    };
-
-    Ok(compressed)
-}
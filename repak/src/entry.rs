@@ -1,8 +1,9 @@
-use crate::{data::build_partial_entry, Error, Hash};
+use crate::{data::build_partial_entry, Codec, CodecRegistry, Error, Hash};
 
-use super::{ext::BoolExt, ext::ReadExt, Compression, Version, VersionMajor};
+use super::{ext::BoolExt, ext::ReadExt, Version, VersionMajor};
 use byteorder::{ReadBytesExt, WriteBytesExt, LE};
 use std::io;
+use std::sync::Arc;
 
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum EntryLocation {
@@ -101,8 +102,8 @@ impl Entry {
     pub(crate) fn write_file<W: io::Write + io::Seek>(
         writer: &mut W,
         version: Version,
-        compression_slots: &mut Vec<Option<Compression>>,
-        allowed_compression: &[Compression],
+        compression_slots: &mut Vec<Option<String>>,
+        allowed_compression: &[Arc<dyn Codec>],
         data: &[u8],
         #[allow(unused)] key: &super::Key,
         path: &str,
@@ -330,18 +331,19 @@ impl Entry {
         Ok(())
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn read_file<R: io::Read + io::Seek, W: io::Write>(
         &self,
         reader: &mut R,
         version: Version,
-        compression: &[Option<Compression>],
+        compression: &[Option<String>],
+        codecs: &CodecRegistry,
         #[allow(unused)] key: &super::Key,
         buf: &mut W,
         path: &str,
     ) -> Result<(), super::Error> {
         reader.seek(io::SeekFrom::Start(self.offset))?;
         Entry::read(reader, version)?;
-        #[cfg(any(feature = "compression", feature = "oodle"))]
         let data_offset = reader.stream_position()?;
 
         #[allow(unused_mut)]
@@ -362,83 +364,43 @@ impl Entry {
             }
         }
 
-        #[cfg(feature = "compression")]
-        let ranges = {
-            let offset = |index: u64| -> usize {
-                (match version.version_major() >= VersionMajor::RelativeChunkOffsets {
-                    true => index - (data_offset - self.offset),
-                    false => index - data_offset,
-                }) as usize
-            };
-
-            match &self.blocks {
-                Some(blocks) => blocks
-                    .iter()
-                    .map(|block| offset(block.start)..offset(block.end))
-                    .collect::<Vec<_>>(),
-                #[allow(clippy::single_range_in_vec_init)]
-                None => vec![0..data.len()],
+        let codec = match self
+            .compression_slot
+            .and_then(|c| compression.get(c as usize).cloned().flatten())
+        {
+            None => {
+                buf.write_all(&data)?;
+                buf.flush()?;
+                return Ok(());
             }
+            Some(name) => codecs.resolve(&name)?,
         };
 
-        #[cfg(feature = "compression")]
-        macro_rules! decompress {
-            ($decompressor: ty) => {
-                for range in ranges {
-                    io::copy(&mut <$decompressor>::new(&data[range]), buf)?;
-                }
-            };
-        }
+        let offset = |index: u64| -> usize {
+            (match version.version_major() >= VersionMajor::RelativeChunkOffsets {
+                true => index - (data_offset - self.offset),
+                false => index - data_offset,
+            }) as usize
+        };
+        let ranges = match &self.blocks {
+            Some(blocks) => blocks
+                .iter()
+                .map(|block| offset(block.start)..offset(block.end))
+                .collect::<Vec<_>>(),
+            #[allow(clippy::single_range_in_vec_init)]
+            None => vec![0..data.len()],
+        };
+        let chunk_size = if ranges.len() == 1 {
+            self.uncompressed as usize
+        } else {
+            self.compression_block_size as usize
+        };
 
-        match self.compression_slot.and_then(|c| compression[c as usize]) {
-            None => buf.write_all(&data)?,
-            #[cfg(not(feature = "compression"))]
-            _ => return Err(super::Error::Compression),
-            #[cfg(feature = "compression")]
-            Some(comp) => {
-                let chunk_size = if ranges.len() == 1 {
-                    self.uncompressed as usize
-                } else {
-                    self.compression_block_size as usize
-                };
-
-                match comp {
-                    Compression::Zlib => decompress!(flate2::read::ZlibDecoder<&[u8]>),
-                    Compression::Gzip => decompress!(flate2::read::GzDecoder<&[u8]>),
-                    Compression::Zstd => {
-                        for range in ranges {
-                            io::copy(&mut zstd::stream::read::Decoder::new(&data[range])?, buf)?;
-                        }
-                    }
-                    Compression::LZ4 => {
-                        let mut decompressed = vec![0; self.uncompressed as usize];
-                        for (decomp_chunk, comp_range) in
-                            decompressed.chunks_mut(chunk_size).zip(ranges)
-                        {
-                            lz4_flex::block::decompress_into(&data[comp_range], decomp_chunk)
-                                .map_err(|_| Error::DecompressionFailed(Compression::LZ4))?;
-                        }
-                        buf.write_all(&decompressed)?;
-                    }
-                    #[cfg(feature = "oodle")]
-                    Compression::Oodle => {
-                        let mut decompressed = vec![0; self.uncompressed as usize];
-                        for (decomp_chunk, comp_range) in
-                            decompressed.chunks_mut(chunk_size).zip(ranges)
-                        {
-                            let out =
-                                oodle_loader::oodle()?.decompress(&data[comp_range], decomp_chunk);
-                            if out == 0 {
-                                return Err(Error::DecompressionFailed(Compression::Oodle));
-                            }
-                        }
-                        buf.write_all(&decompressed)?;
-                    }
-                    #[cfg(not(feature = "oodle"))]
-                    Compression::Oodle => return Err(super::Error::Oodle),
-                }
-            }
+        let mut decompressed = vec![0; self.uncompressed as usize];
+        for (decomp_chunk, comp_range) in decompressed.chunks_mut(chunk_size).zip(ranges) {
+            codec.decompress_into(&data[comp_range], decomp_chunk)?;
         }
+        buf.write_all(&decompressed)?;
         buf.flush()?;
         Ok(())
     }
@@ -1,5 +1,3 @@
-use crate::Compression;
-
 #[derive(thiserror::Error)]
 pub enum Error {
     // dependency errors
@@ -16,7 +14,7 @@ pub enum Error {
     #[error("enable the encryption feature to read encrypted paks")]
     Encryption,
 
-    #[error("enable the oodle feature to read Oodle compressed paks")]
+    #[error("enable the oodle or oodle-rs feature to read Oodle compressed paks")]
     Oodle,
 
     // std errors
@@ -62,7 +60,10 @@ pub enum Error {
     InputNotADirectory(String),
 
     #[error("{0} decompression failed")]
-    DecompressionFailed(Compression),
+    DecompressionFailed(String),
+
+    #[error("unregistered codec {0}")]
+    UnregisteredCodec(String),
 
     #[error("used version {used} but pak is version {version}")]
     Version {
@@ -79,6 +80,15 @@ pub enum Error {
     #[error("{0}version unsupported or is encrypted (possibly missing --aes-key?)")]
     UnsupportedOrEncrypted(String),
 
+    #[error("not an IoStore table of contents")]
+    TocMagic,
+
+    #[error("unsupported IoStore table of contents version {0}")]
+    TocVersion(u8),
+
+    #[error("invalid IoStore table of contents: {0}")]
+    InvalidToc(String),
+
     #[error("{0}")]
     Other(String),
 }
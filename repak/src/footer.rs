@@ -5,7 +5,6 @@ use crate::{
 
 use super::{ext::ReadExt, Compression, Version, VersionMajor};
 use byteorder::{ReadBytesExt, WriteBytesExt, LE};
-use std::str::FromStr;
 
 #[derive(Debug)]
 pub struct Footer {
@@ -18,7 +17,9 @@ pub struct Footer {
     pub index_size: u64,
     pub hash: Hash,
     pub frozen: bool,
-    pub compression: Vec<Option<Compression>>,
+    /// Compression method names by slot, resolved against the [`CodecRegistry`](crate::CodecRegistry)
+    /// when entries are read.
+    pub compression: Vec<Option<String>>,
 }
 
 impl Footer {
@@ -41,22 +42,19 @@ impl Footer {
                 _ => 5,
             });
             for _ in 0..compression.capacity() {
-                compression.push(
-                    Compression::from_str(
-                        &reader
-                            .read_len(32)?
-                            .iter()
-                            // filter out whitespace and convert to char
-                            .filter_map(|&ch| (ch != 0).then_some(ch as char))
-                            .collect::<String>(),
-                    )
-                    .ok(),
-                )
+                let name = reader
+                    .read_len(32)?
+                    .iter()
+                    // filter out whitespace and convert to char
+                    .filter_map(|&ch| (ch != 0).then_some(ch as char))
+                    .collect::<String>();
+                compression.push((!name.is_empty()).then_some(name))
             }
             if version.version_major() < VersionMajor::FNameBasedCompression {
-                compression.push(Some(Compression::Zlib));
-                compression.push(Some(Compression::Gzip));
-                compression.push(Some(Compression::Oodle));
+                compression.extend(
+                    [Compression::Zlib, Compression::Gzip, Compression::Oodle]
+                        .map(|compression| Some(compression.to_string())),
+                );
             }
             compression
         };
@@ -107,9 +105,12 @@ impl Footer {
         for i in 0..algo_size {
             let mut name = [0; 32];
             if let Some(algo) = self.compression.get(i).cloned().flatten() {
-                for (i, b) in algo.to_string().as_bytes().iter().enumerate() {
-                    name[i] = *b;
+                if algo.len() > name.len() {
+                    return Err(super::Error::Other(format!(
+                        "compression name {algo:?} is longer than 32 bytes"
+                    )));
                 }
+                name[..algo.len()].copy_from_slice(algo.as_bytes());
             }
             writer.write_all(&name)?;
         }
@@ -0,0 +1,569 @@
+//! Reader for IoStore containers: the `.utoc` table of contents and the chunk data in its `.ucas`.
+//!
+//! Only the container layout is parsed, packages are returned as raw chunks. Containers are
+//! opened with [`PakBuilder::toc_reader`](crate::PakBuilder::toc_reader), which supplies the AES
+//! key and the codecs used to decompress blocks.
+
+use crate::ext::ReadExt;
+use crate::{CodecRegistry, Error};
+use byteorder::{ReadBytesExt, LE};
+use std::io::{Cursor, Read, Seek, SeekFrom};
+
+pub const TOC_MAGIC: &[u8; 16] = b"-==--==--==--==-";
+
+/// Index used by the directory index for "no entry".
+const NO_ENTRY: u32 = u32::MAX;
+
+#[repr(u8)]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Debug, strum::Display, strum::FromRepr)]
+pub enum TocVersion {
+    Invalid,
+    Initial,
+    DirectoryIndex,
+    PartitionSize,
+    PerfectHash,
+    PerfectHashWithOverflow,
+    OnDemandMetaData,
+    RemovedOnDemandMetaData,
+    ReplaceIoChunkHashWithIoHash,
+}
+
+/// Flags from the `.utoc` header.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ContainerFlags(pub u8);
+
+impl ContainerFlags {
+    pub const COMPRESSED: u8 = 1 << 0;
+    pub const ENCRYPTED: u8 = 1 << 1;
+    pub const SIGNED: u8 = 1 << 2;
+    pub const INDEXED: u8 = 1 << 3;
+    pub const ON_DEMAND: u8 = 1 << 4;
+
+    pub fn compressed(self) -> bool {
+        self.0 & Self::COMPRESSED != 0
+    }
+    pub fn encrypted(self) -> bool {
+        self.0 & Self::ENCRYPTED != 0
+    }
+    pub fn signed(self) -> bool {
+        self.0 & Self::SIGNED != 0
+    }
+    pub fn indexed(self) -> bool {
+        self.0 & Self::INDEXED != 0
+    }
+}
+
+/// What a chunk holds, stored in the last byte of its ID.
+#[repr(u8)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, strum::Display, strum::FromRepr)]
+pub enum ChunkType {
+    Invalid,
+    ExportBundleData,
+    BulkData,
+    OptionalBulkData,
+    MemoryMappedBulkData,
+    ScriptObjects,
+    ContainerHeader,
+    ExternalFile,
+    ShaderCodeLibrary,
+    ShaderCode,
+    PackageStoreEntry,
+    DerivedData,
+    EditorDerivedData,
+    PackageResource,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ChunkId(pub [u8; 12]);
+
+impl std::fmt::Debug for ChunkId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ChunkId({})", hex::encode(self.0))
+    }
+}
+
+impl ChunkId {
+    pub fn as_bytes(&self) -> &[u8; 12] {
+        &self.0
+    }
+    /// Package ID for package chunks, a hash of the chunk's name otherwise.
+    pub fn id(&self) -> u64 {
+        u64::from_le_bytes(self.0[..8].try_into().unwrap())
+    }
+    /// `None` for types newer than this reader.
+    pub fn chunk_type(&self) -> Option<ChunkType> {
+        ChunkType::from_repr(self.0[11])
+    }
+}
+
+/// A chunk listed in the table of contents.
+#[derive(Clone, Debug)]
+pub struct TocChunk {
+    pub id: ChunkId,
+    /// Offset in the uncompressed address space of the container.
+    pub offset: u64,
+    /// Uncompressed length.
+    pub length: u64,
+    /// Hash of the uncompressed data as written by the packager, truncated to 20 bytes in
+    /// containers older than [`TocVersion::ReplaceIoChunkHashWithIoHash`].
+    pub hash: [u8; 20],
+    pub flags: u8,
+}
+
+/// One compression block in the `.ucas`. Every block but a chunk's last covers
+/// [`TocReader::compression_block_size`] uncompressed bytes.
+#[derive(Clone, Copy, Debug)]
+pub struct CompressionBlock {
+    /// Offset of the stored block in the `.ucas`.
+    pub offset: u64,
+    pub compressed_size: u32,
+    pub uncompressed_size: u32,
+    /// Index into [`TocReader::compression_methods`] plus one, `0` for stored blocks.
+    pub method: u8,
+}
+
+/// A file from the directory index and the chunk holding its data.
+#[derive(Clone, Debug)]
+pub struct TocFile {
+    /// Path relative to the mount point.
+    pub path: String,
+    pub chunk: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct TocReader {
+    version: TocVersion,
+    container_id: u64,
+    encryption_key_guid: [u8; 16],
+    flags: ContainerFlags,
+    compression_block_size: u32,
+    partition_count: u32,
+    partition_size: u64,
+    chunks: Vec<TocChunk>,
+    blocks: Vec<CompressionBlock>,
+    compression_methods: Vec<String>,
+    mount_point: Option<String>,
+    files: Vec<TocFile>,
+    key: super::Key,
+    codecs: CodecRegistry,
+}
+
+impl TocReader {
+    pub(crate) fn new<R: Read + Seek>(
+        reader: &mut R,
+        key: super::Key,
+        keys: Option<&crate::keys::KeyConfig>,
+        codecs: CodecRegistry,
+    ) -> Result<Self, Error> {
+        let mut magic = [0; 16];
+        reader.read_exact(&mut magic)?;
+        if &magic != TOC_MAGIC {
+            return Err(Error::TocMagic);
+        }
+        let version = reader.read_u8()?;
+        let version = TocVersion::from_repr(version)
+            .filter(|version| *version != TocVersion::Invalid)
+            .ok_or(Error::TocVersion(version))?;
+        reader.read_u8()?;
+        reader.read_u16::<LE>()?;
+        let header_size = reader.read_u32::<LE>()?;
+        let entry_count = reader.read_u32::<LE>()? as usize;
+        let block_count = reader.read_u32::<LE>()? as usize;
+        let block_entry_size = reader.read_u32::<LE>()? as usize;
+        let method_name_count = reader.read_u32::<LE>()? as usize;
+        let method_name_length = reader.read_u32::<LE>()? as usize;
+        let compression_block_size = reader.read_u32::<LE>()?;
+        let directory_index_size = reader.read_u32::<LE>()? as usize;
+        let partition_count = reader.read_u32::<LE>()?;
+        let container_id = reader.read_u64::<LE>()?;
+        let mut encryption_key_guid = [0; 16];
+        reader.read_exact(&mut encryption_key_guid)?;
+        let flags = ContainerFlags(reader.read_u8()?);
+        reader.read_u8()?;
+        reader.read_u16::<LE>()?;
+        let perfect_hash_seed_count = reader.read_u32::<LE>()? as i64;
+        let partition_size = reader.read_u64::<LE>()?;
+        let without_perfect_hash_count = reader.read_u32::<LE>()? as i64;
+        let key = match keys {
+            Some(keys) => keys
+                .key_for_guid(u128::from_le_bytes(encryption_key_guid))?
+                .0
+                .into(),
+            None => key,
+        };
+
+        if block_entry_size < 12 || compression_block_size == 0 {
+            return Err(Error::InvalidToc("bad compression block layout".to_owned()));
+        }
+        // every table is read from the .utoc, so sizes the file can't hold mean it is corrupt and
+        // must be rejected before anything is allocated for them
+        let toc_size = reader.seek(SeekFrom::End(0))?;
+        let tables_size = [
+            (entry_count, 22),
+            (block_count, block_entry_size),
+            (method_name_count, method_name_length),
+            (directory_index_size, 1),
+        ]
+        .into_iter()
+        .fold(header_size as u64, |size, (count, element_size)| {
+            size.saturating_add((count as u64).saturating_mul(element_size as u64))
+        });
+        if tables_size > toc_size {
+            return Err(Error::InvalidToc(
+                "tables are larger than the file".to_owned(),
+            ));
+        }
+        reader.seek(SeekFrom::Start(header_size as u64))?;
+
+        let ids = reader.read_array_len(entry_count, |r| {
+            let mut id = [0; 12];
+            r.read_exact(&mut id)?;
+            Ok(ChunkId(id))
+        })?;
+        // five byte big endian offset followed by a five byte big endian length
+        let offset_lengths = reader.read_array_len(entry_count, |r| {
+            let mut bytes = [0; 10];
+            r.read_exact(&mut bytes)?;
+            Ok((read_u40_be(&bytes[..5]), read_u40_be(&bytes[5..])))
+        })?;
+        if version >= TocVersion::PerfectHash {
+            reader.seek(SeekFrom::Current(perfect_hash_seed_count * 4))?;
+        }
+        if version >= TocVersion::PerfectHashWithOverflow {
+            reader.seek(SeekFrom::Current(without_perfect_hash_count * 4))?;
+        }
+        let blocks = reader.read_array_len(block_count, |r| {
+            let bytes = r.read_len(block_entry_size)?;
+            Ok(CompressionBlock {
+                offset: read_uint_le(&bytes[..5]),
+                compressed_size: read_uint_le(&bytes[5..8]) as u32,
+                uncompressed_size: read_uint_le(&bytes[8..11]) as u32,
+                method: bytes[11],
+            })
+        })?;
+        let compression_methods = reader.read_array_len(method_name_count, |r| {
+            let name = r.read_len(method_name_length)?;
+            let len = name.iter().position(|&b| b == 0).unwrap_or(name.len());
+            Ok(String::from_utf8_lossy(&name[..len]).into_owned())
+        })?;
+        if flags.signed() {
+            let hash_size = reader.read_i32::<LE>()? as i64;
+            reader.seek(SeekFrom::Current(hash_size * 2 + block_count as i64 * 20))?;
+        }
+
+        let mut mount_point = None;
+        let mut files = vec![];
+        if flags.indexed() && directory_index_size > 0 {
+            let mut index = reader.read_len(directory_index_size)?;
+            if flags.encrypted() {
+                #[cfg(not(feature = "encryption"))]
+                return Err(Error::Encryption);
+                #[cfg(feature = "encryption")]
+                super::data::decrypt(&key, &mut index)?;
+            }
+            let (mount, index_files) = read_directory_index(&index)?;
+            mount_point = Some(mount);
+            files = index_files;
+        } else {
+            reader.seek(SeekFrom::Current(directory_index_size as i64))?;
+        }
+
+        let metas = reader.read_array_len(entry_count, |r| {
+            let mut hash = [0; 20];
+            r.read_exact(&mut hash)?;
+            if version >= TocVersion::ReplaceIoChunkHashWithIoHash {
+                let flags = r.read_u8()?;
+                r.read_len(3)?;
+                Ok((hash, flags))
+            } else {
+                r.read_len(12)?;
+                Ok((hash, r.read_u8()?))
+            }
+        })?;
+
+        let chunks = ids
+            .into_iter()
+            .zip(offset_lengths)
+            .zip(metas)
+            .map(|((id, (offset, length)), (hash, flags))| TocChunk {
+                id,
+                offset,
+                length,
+                hash,
+                flags,
+            })
+            .collect::<Vec<_>>();
+        if let Some(file) = files.iter().find(|file| file.chunk >= chunks.len()) {
+            return Err(Error::InvalidToc(format!(
+                "{} points at missing chunk {}",
+                file.path, file.chunk
+            )));
+        }
+
+        Ok(Self {
+            version,
+            container_id,
+            encryption_key_guid,
+            flags,
+            compression_block_size,
+            partition_count,
+            partition_size,
+            chunks,
+            blocks,
+            compression_methods,
+            mount_point,
+            files,
+            key,
+            codecs,
+        })
+    }
+
+    pub fn version(&self) -> TocVersion {
+        self.version
+    }
+
+    pub fn container_id(&self) -> u64 {
+        self.container_id
+    }
+
+    pub fn encryption_key_guid(&self) -> Option<u128> {
+        let guid = u128::from_le_bytes(self.encryption_key_guid);
+        (guid != 0).then_some(guid)
+    }
+
+    pub fn flags(&self) -> ContainerFlags {
+        self.flags
+    }
+
+    pub fn compression_block_size(&self) -> u32 {
+        self.compression_block_size
+    }
+
+    pub fn compression_methods(&self) -> &[String] {
+        &self.compression_methods
+    }
+
+    pub fn compression_blocks(&self) -> &[CompressionBlock] {
+        &self.blocks
+    }
+
+    pub fn chunks(&self) -> &[TocChunk] {
+        &self.chunks
+    }
+
+    /// `None` when the container has no directory index.
+    pub fn mount_point(&self) -> Option<&str> {
+        self.mount_point.as_deref()
+    }
+
+    /// Files from the directory index, sorted by path. Empty when the container has none.
+    pub fn files(&self) -> &[TocFile] {
+        &self.files
+    }
+
+    /// The compression blocks holding a chunk.
+    pub fn chunk_blocks(&self, index: usize) -> Result<&[CompressionBlock], Error> {
+        let chunk = self.chunk(index)?;
+        if chunk.length == 0 {
+            return Ok(&[]);
+        }
+        let block_size = self.compression_block_size as u64;
+        let first = (chunk.offset / block_size) as usize;
+        let last = ((chunk.offset + chunk.length - 1) / block_size) as usize;
+        self.blocks.get(first..=last).ok_or_else(|| {
+            Error::InvalidToc(format!("chunk {index} extends past the compression blocks"))
+        })
+    }
+
+    /// Reads, decrypts and decompresses a chunk from the `.ucas`. Containers split into several
+    /// partitions are read from the first `.ucas` only.
+    pub fn read_chunk<R: Read + Seek>(&self, index: usize, ucas: &mut R) -> Result<Vec<u8>, Error> {
+        let chunk = self.chunk(index)?;
+        let blocks = self.chunk_blocks(index)?;
+        let block_size = self.compression_block_size as u64;
+        let first_block = chunk.offset / block_size;
+        // the length comes from the .utoc, check it against the blocks before allocating for it
+        let covered = blocks
+            .iter()
+            .map(|block| block.uncompressed_size as u64)
+            .sum::<u64>();
+        if chunk.offset - first_block * block_size + chunk.length > covered {
+            return Err(Error::InvalidToc(format!(
+                "chunk {index} is longer than its blocks"
+            )));
+        }
+        let mut data = Vec::with_capacity(chunk.length as usize);
+        for (block_index, block) in (first_block..).zip(blocks) {
+            let block_data = self.read_block(block, ucas)?;
+            let block_start = block_index * block_size;
+            let start = chunk.offset.saturating_sub(block_start) as usize;
+            let end = ((chunk.offset + chunk.length - block_start) as usize).min(block_data.len());
+            data.extend_from_slice(block_data.get(start..end).ok_or_else(|| {
+                Error::InvalidToc(format!("chunk {index} is longer than its blocks"))
+            })?);
+        }
+        Ok(data)
+    }
+
+    /// Reads a file from the directory index by its path relative to the mount point.
+    pub fn read_file<R: Read + Seek>(&self, path: &str, ucas: &mut R) -> Result<Vec<u8>, Error> {
+        let file = self
+            .files
+            .iter()
+            .find(|file| file.path == path)
+            .ok_or_else(|| Error::MissingEntry(path.to_owned()))?;
+        self.read_chunk(file.chunk, ucas)
+    }
+
+    fn chunk(&self, index: usize) -> Result<&TocChunk, Error> {
+        self.chunks
+            .get(index)
+            .ok_or_else(|| Error::MissingEntry(format!("chunk {index}")))
+    }
+
+    fn read_block<R: Read + Seek>(
+        &self,
+        block: &CompressionBlock,
+        ucas: &mut R,
+    ) -> Result<Vec<u8>, Error> {
+        let mut offset = block.offset;
+        if self.partition_count > 1 && self.partition_size > 0 {
+            let partition = offset / self.partition_size;
+            if partition > 0 {
+                return Err(Error::InvalidToc(format!(
+                    "block is in .ucas partition {partition}"
+                )));
+            }
+            offset %= self.partition_size;
+        }
+        ucas.seek(SeekFrom::Start(offset))?;
+        let compressed_size = block.compressed_size as usize;
+        let mut data = if self.flags.encrypted() {
+            #[cfg(not(feature = "encryption"))]
+            return Err(Error::Encryption);
+            #[cfg(feature = "encryption")]
+            {
+                let mut data = ucas.read_len(super::data::pad_length(compressed_size, 16))?;
+                super::data::decrypt(&self.key, &mut data)?;
+                data
+            }
+        } else {
+            ucas.read_len(compressed_size)?
+        };
+        data.truncate(compressed_size);
+
+        if block.method == 0 {
+            return Ok(data);
+        }
+        let name = self
+            .compression_methods
+            .get(block.method as usize - 1)
+            .ok_or_else(|| {
+                Error::InvalidToc(format!("unknown compression method {}", block.method))
+            })?;
+        let mut out = vec![0; block.uncompressed_size as usize];
+        self.codecs
+            .resolve(name)?
+            .decompress_into(&data, &mut out)?;
+        Ok(out)
+    }
+}
+
+fn read_u40_be(bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .fold(0, |value, byte| value << 8 | *byte as u64)
+}
+
+fn read_uint_le(bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .rev()
+        .fold(0, |value, byte| value << 8 | *byte as u64)
+}
+
+/// Reads an array length from the directory index, rejecting lengths the remaining data can't
+/// hold so a corrupt index can't trigger huge allocations.
+fn read_index_len(reader: &mut Cursor<&[u8]>, element_size: usize) -> Result<usize, Error> {
+    let len = reader.read_u32::<LE>()? as usize;
+    let remaining = reader.get_ref().len() - reader.position() as usize;
+    if len.saturating_mul(element_size) > remaining {
+        return Err(Error::InvalidToc(
+            "directory index array is truncated".to_owned(),
+        ));
+    }
+    Ok(len)
+}
+
+fn read_directory_index(index: &[u8]) -> Result<(String, Vec<TocFile>), Error> {
+    let mut reader = Cursor::new(index);
+    let mount_point = reader.read_string()?;
+    let len = read_index_len(&mut reader, 16)?;
+    // name, first child, next sibling, first file
+    let directories = reader.read_array_len(len, |r| {
+        Ok([
+            r.read_u32::<LE>()?,
+            r.read_u32::<LE>()?,
+            r.read_u32::<LE>()?,
+            r.read_u32::<LE>()?,
+        ])
+    })?;
+    let len = read_index_len(&mut reader, 12)?;
+    // name, next file, chunk index
+    let entries = reader.read_array_len(len, |r| {
+        Ok([
+            r.read_u32::<LE>()?,
+            r.read_u32::<LE>()?,
+            r.read_u32::<LE>()?,
+        ])
+    })?;
+    let len = read_index_len(&mut reader, 4)?;
+    let names = reader.read_array_len(len, |r| r.read_string())?;
+
+    let invalid = |what: &str| Error::InvalidToc(format!("directory index {what}"));
+    let name = |index: u32| names.get(index as usize).ok_or_else(|| invalid("name"));
+    let mut files = vec![];
+    let mut visited = 0;
+    let mut stack = vec![];
+    if !directories.is_empty() {
+        stack.push((0u32, String::new()));
+    }
+    while let Some((directory, prefix)) = stack.pop() {
+        // every directory is reached once, more means the index loops
+        visited += 1;
+        if visited > directories.len() {
+            return Err(invalid("has a cycle"));
+        }
+        let [_, first_child, _, first_file] = *directories
+            .get(directory as usize)
+            .ok_or_else(|| invalid("directory"))?;
+
+        let mut file = first_file;
+        while file != NO_ENTRY {
+            if files.len() >= entries.len() {
+                return Err(invalid("has a cycle"));
+            }
+            let [name_index, next, chunk] =
+                *entries.get(file as usize).ok_or_else(|| invalid("file"))?;
+            files.push(TocFile {
+                path: format!("{prefix}{}", name(name_index)?),
+                chunk: chunk as usize,
+            });
+            file = next;
+        }
+
+        let mut child = first_child;
+        while child != NO_ENTRY {
+            let [name_index, _, next_sibling, _] = *directories
+                .get(child as usize)
+                .ok_or_else(|| invalid("directory"))?;
+            stack.push((child, format!("{prefix}{}/", name(name_index)?)));
+            child = next_sibling;
+            if stack.len() > directories.len() {
+                return Err(invalid("has a cycle"));
+            }
+        }
+    }
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok((mount_point, files))
+}
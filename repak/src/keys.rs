@@ -0,0 +1,228 @@
+//! AES keys known to the tools, so a rotated game key can be updated without a new release.
+//!
+//! A [`KeyConfig`] starts from the built-in defaults and takes overrides by game name or by the
+//! encryption key GUID of a container. [`PakBuilder::keys`](crate::PakBuilder::keys) picks the key
+//! of each opened container by its GUID. Loading overrides from files or the environment is left
+//! to the tools, see `repak_manager::keys`.
+//!
+//! Override text holds one `<name> = <key>` line per key, where the name is a game or a GUID
+//! written as `{AAAAAAAA-BBBB-BBBB-CCCC-CCCCDDDDDDDD}` or 32 hex digits, and the key is hex or
+//! base64. Lines starting with `#` are comments.
+
+use crate::utils::AesKey;
+use crate::Error;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+/// Name of the game the tools are built for, used when no other game is asked for.
+pub const DEFAULT_GAME: &str = "marvel-rivals";
+/// Built-in Marvel Rivals key, used for both paks and IoStore containers.
+pub const RIVALS_AES_KEY: &str = "0C263D8C22DCB085894899C3A3796383E9BF9DE0CBFB08C9BF2DEF2E84F29D74";
+
+/// Where a key came from, for error messages and listings.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, strum::Display)]
+pub enum KeySource {
+    #[strum(to_string = "built-in")]
+    BuiltIn,
+    #[strum(to_string = "config file")]
+    File,
+    #[strum(to_string = "environment")]
+    Env,
+}
+
+#[derive(Clone, Debug)]
+pub struct KeyConfig {
+    /// Keys by lowercase game name or GUID.
+    keys: BTreeMap<String, (String, KeySource)>,
+}
+
+impl Default for KeyConfig {
+    /// The built-in keys only.
+    fn default() -> Self {
+        let mut keys = BTreeMap::new();
+        keys.insert(
+            DEFAULT_GAME.to_string(),
+            (RIVALS_AES_KEY.to_string(), KeySource::BuiltIn),
+        );
+        Self { keys }
+    }
+}
+
+impl KeyConfig {
+    /// Adds the keys of override text. Every key is checked before any is added.
+    pub fn merge_str(&mut self, text: &str) -> Result<(), Error> {
+        let mut parsed = Vec::new();
+        for (line_number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (name, key) = line
+                .split_once('=')
+                .map(|(name, key)| (name.trim(), key.trim().trim_matches('"')))
+                .filter(|(name, key)| !name.is_empty() && !key.is_empty())
+                .ok_or_else(|| {
+                    Error::Other(format!(
+                        "line {}: expected `<name> = <key>`",
+                        line_number + 1
+                    ))
+                })?;
+            AesKey::from_str(key)
+                .map_err(|e| Error::Other(format!("line {}: {name}: {e}", line_number + 1)))?;
+            parsed.push((name, key));
+        }
+        for (name, key) in parsed {
+            self.insert(name, key, KeySource::File)?;
+        }
+        Ok(())
+    }
+
+    /// Sets the key of a game or GUID, replacing any earlier key.
+    pub fn insert(&mut self, name: &str, key: &str, source: KeySource) -> Result<(), Error> {
+        AesKey::from_str(key)?;
+        self.keys
+            .insert(normalize_name(name), (key.to_string(), source));
+        Ok(())
+    }
+
+    /// The key of a game or GUID as configured, for tools parsing keys themselves.
+    pub fn key_str(&self, name: &str) -> Option<&str> {
+        self.keys
+            .get(&normalize_name(name))
+            .map(|(key, _)| key.as_str())
+    }
+
+    pub fn key(&self, name: &str) -> Result<AesKey, Error> {
+        let (key, source) = self
+            .keys
+            .get(&normalize_name(name))
+            .ok_or_else(|| Error::Other(format!("no AES key configured for {name}")))?;
+        AesKey::from_str(key).map_err(|e| Error::Other(format!("{source} AES key for {name}: {e}")))
+    }
+
+    /// The key of a container's encryption key GUID, falling back to the default game's key
+    /// for containers without their own entry.
+    pub fn key_for_guid(&self, guid: u128) -> Result<AesKey, Error> {
+        let name = guid_name(guid);
+        if self.keys.contains_key(&name) {
+            self.key(&name)
+        } else {
+            self.key(DEFAULT_GAME)
+        }
+    }
+
+    /// Configured keys with their source, sorted by name.
+    pub fn entries(&self) -> impl Iterator<Item = (&str, &str, KeySource)> {
+        self.keys
+            .iter()
+            .map(|(name, (key, source))| (name.as_str(), key.as_str(), *source))
+    }
+}
+
+/// The name of an encryption key GUID as read by repak, a little endian `u128` of the four
+/// FGuid words, in the word order of its `{A-B-C-D}` text form.
+pub fn guid_name(guid: u128) -> String {
+    format!(
+        "{:08x}{:08x}{:08x}{:08x}",
+        guid as u32,
+        (guid >> 32) as u32,
+        (guid >> 64) as u32,
+        (guid >> 96) as u32
+    )
+}
+
+fn normalize_name(name: &str) -> String {
+    let name = name.trim().to_ascii_lowercase();
+    // GUIDs may be written with braces and dashes
+    let guid = name.trim_matches(|c| c == '{' || c == '}').replace('-', "");
+    if guid.len() == 32 && guid.bytes().all(|b| b.is_ascii_hexdigit()) {
+        guid
+    } else {
+        name
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const OTHER_KEY: &str = "lNJbw660IOC+kU7cnVQ1oeqrXyhk4J6UAZrCBbcnp94=";
+
+    #[test]
+    fn test_builtin_key() {
+        let config = KeyConfig::default();
+        assert_eq!(config.key_str(DEFAULT_GAME), Some(RIVALS_AES_KEY));
+        assert!(config.key(DEFAULT_GAME).is_ok());
+        assert!(config.key("other-game").is_err());
+    }
+
+    #[test]
+    fn test_merge_str() {
+        let mut config = KeyConfig::default();
+        config
+            .merge_str(&format!(
+                "# comment\n\nMarvel-Rivals = {OTHER_KEY}\n{{0000000A-0000-0000-0000-000000000000}} = \"{RIVALS_AES_KEY}\"\n"
+            ))
+            .unwrap();
+        assert_eq!(config.key_str(DEFAULT_GAME), Some(OTHER_KEY));
+        assert_eq!(
+            config.key_str("0000000a000000000000000000000000"),
+            Some(RIVALS_AES_KEY)
+        );
+        let sources = config
+            .entries()
+            .map(|(_, _, source)| source)
+            .collect::<Vec<_>>();
+        assert_eq!(sources, [KeySource::File, KeySource::File]);
+        assert!(config.key_for_guid(1).is_ok());
+    }
+
+    #[test]
+    fn test_key_for_guid() {
+        // {12345678-9ABC-DEF0-0FED-CBA987654321} as stored in a pak footer or .utoc header
+        let bytes = [0x12345678u32, 0x9ABCDEF0, 0x0FEDCBA9, 0x87654321]
+            .map(u32::to_le_bytes)
+            .concat();
+        let guid = u128::from_le_bytes(bytes.try_into().unwrap());
+        assert_eq!(guid_name(guid), "123456789abcdef00fedcba987654321");
+
+        let mut config = KeyConfig::default();
+        config
+            .merge_str(&format!(
+                "{{12345678-9ABC-DEF0-0FED-CBA987654321}} = {OTHER_KEY}"
+            ))
+            .unwrap();
+        // keys are told apart by what they encrypt
+        let key = |key: AesKey| {
+            use aes::cipher::BlockEncrypt;
+            let mut block = aes::Block::default();
+            key.0.encrypt_block(&mut block);
+            block
+        };
+        assert_eq!(
+            key(config.key_for_guid(guid).unwrap()),
+            key(AesKey::from_str(OTHER_KEY).unwrap())
+        );
+        // unknown GUIDs and containers without one use the game key
+        assert_eq!(
+            key(config.key_for_guid(guid.swap_bytes()).unwrap()),
+            key(config.key(DEFAULT_GAME).unwrap())
+        );
+        assert_eq!(
+            key(config.key_for_guid(0).unwrap()),
+            key(AesKey::from_str(RIVALS_AES_KEY).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_merge_str_rejects_bad_keys() {
+        let mut config = KeyConfig::default();
+        assert!(config.merge_str("marvel-rivals").is_err());
+        assert!(config
+            .merge_str(&format!("a = {OTHER_KEY}\nmarvel-rivals = nope"))
+            .is_err());
+        // nothing from a rejected file is kept
+        assert_eq!(config.key_str("a"), None);
+        assert_eq!(config.key_str(DEFAULT_GAME), Some(RIVALS_AES_KEY));
+    }
+}
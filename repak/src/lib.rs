@@ -1,13 +1,21 @@
 #![allow(dead_code)]
+mod codec;
 mod data;
 pub mod entry;
 mod error;
 mod ext;
 mod footer;
+pub mod iostore;
+pub mod keys;
 mod pak;
 pub mod utils;
 
-pub use {data::PartialEntry, error::*, pak::*};
+pub use {
+    codec::{Codec, CodecRegistry},
+    data::PartialEntry,
+    error::*,
+    pak::*,
+};
 
 pub const MAGIC: u32 = 0x5A6F12E1;
 
@@ -1,12 +1,13 @@
 use crate::data::{build_partial_entry, pad_length};
 use crate::entry::Entry;
-use crate::{Compression, Error, PartialEntry};
+use crate::{Codec, CodecRegistry, Compression, Error, PartialEntry};
 
 use super::ext::{ReadExt, WriteExt};
 use super::{Version, VersionMajor};
 use byteorder::{ReadBytesExt, WriteBytesExt, LE};
 use std::collections::BTreeMap;
 use std::io::{self, Read, Seek, Write};
+use std::sync::Arc;
 
 #[derive(Default, Clone, Copy, PartialEq)]
 pub struct Hash(pub(crate) [u8; 20]);
@@ -15,11 +16,18 @@ impl std::fmt::Debug for Hash {
         write!(f, "Hash({})", hex::encode(self.0))
     }
 }
+impl Hash {
+    pub fn as_bytes(&self) -> &[u8; 20] {
+        &self.0
+    }
+}
 
 #[derive(Debug)]
 pub struct PakBuilder {
     key: super::Key,
-    allowed_compression: Vec<Compression>,
+    keys: Option<crate::keys::KeyConfig>,
+    codecs: CodecRegistry,
+    allowed_compression: Vec<String>,
 }
 
 impl Default for PakBuilder {
@@ -32,6 +40,8 @@ impl PakBuilder {
     pub fn new() -> Self {
         Self {
             key: Default::default(),
+            keys: None,
+            codecs: Default::default(),
             allowed_compression: Default::default(),
         }
     }
@@ -40,20 +50,55 @@ impl PakBuilder {
         self.key = super::Key::Some(key);
         self
     }
+    /// Reads each pak or `.utoc` with the key of the encryption key GUID it names, see
+    /// [`KeyConfig::key_for_guid`](crate::keys::KeyConfig::key_for_guid). Takes precedence over
+    /// [`PakBuilder::key`] when reading; written paks use the key set with that.
+    #[cfg(feature = "encryption")]
+    pub fn keys(mut self, keys: crate::keys::KeyConfig) -> Self {
+        self.keys = Some(keys);
+        self
+    }
     #[cfg(feature = "compression")]
     pub fn compression(mut self, compression: impl IntoIterator<Item = Compression>) -> Self {
-        self.allowed_compression = compression.into_iter().collect();
+        self.allowed_compression = compression
+            .into_iter()
+            .map(|compression| compression.to_string())
+            .collect();
+        self
+    }
+    /// Compresses written files with registered codecs by name, e.g. a custom [`Codec`].
+    pub fn compression_codecs(
+        mut self,
+        names: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        self.allowed_compression = names.into_iter().map(Into::into).collect();
+        self
+    }
+    /// Registers a codec for reading and writing, replacing a built-in codec of the same name.
+    pub fn codec(mut self, codec: impl Codec + 'static) -> Self {
+        self.codecs.register(Arc::new(codec));
         self
     }
     pub fn reader<R: Read + Seek>(self, reader: &mut R) -> Result<PakReader, super::Error> {
-        PakReader::new_any_inner(reader, self.key)
+        let key = self.pak_key(reader, Version::iter())?;
+        PakReader::new_any_inner(reader, key, self.codecs)
+    }
+    /// Opens an IoStore `.utoc`, decrypting its directory index and later chunk reads with the
+    /// builder's key, or the key of its GUID when [`PakBuilder::keys`] is set, and decompressing
+    /// with its codecs.
+    pub fn toc_reader<R: Read + Seek>(
+        self,
+        reader: &mut R,
+    ) -> Result<crate::iostore::TocReader, super::Error> {
+        crate::iostore::TocReader::new(reader, self.key, self.keys.as_ref(), self.codecs)
     }
     pub fn reader_with_version<R: Read + Seek>(
         self,
         reader: &mut R,
         version: super::Version,
     ) -> Result<PakReader, super::Error> {
-        PakReader::new_inner(reader, version, self.key)
+        let key = self.pak_key(reader, [version])?;
+        PakReader::new_inner(reader, version, key, self.codecs)
     }
     pub fn writer<W: Write + Seek>(
         self,
@@ -68,15 +113,34 @@ impl PakBuilder {
             version,
             mount_point,
             path_hash_seed,
+            self.codecs,
             self.allowed_compression,
         )
     }
+    /// The key to read a pak with: by the encryption key GUID in the first footer of `versions`
+    /// that parses when [`PakBuilder::keys`] is set, otherwise the builder's key.
+    fn pak_key<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+        versions: impl IntoIterator<Item = Version>,
+    ) -> Result<super::Key, super::Error> {
+        let Some(keys) = &self.keys else {
+            return Ok(self.key.clone());
+        };
+        let guid = versions.into_iter().find_map(|version| {
+            reader.seek(io::SeekFrom::End(-version.size())).ok()?;
+            super::footer::Footer::read(reader, version).ok()
+        });
+        let key = keys.key_for_guid(guid.and_then(|footer| footer.encryption_uuid).unwrap_or(0))?;
+        Ok(key.0.into())
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct PakReader {
     pak: Pak,
     key: super::Key,
+    codecs: CodecRegistry,
 }
 
 #[derive(Debug)]
@@ -84,7 +148,8 @@ pub struct PakWriter<W: Write + Seek> {
     pak: Pak,
     writer: W,
     key: super::Key,
-    allowed_compression: Vec<Compression>,
+    codecs: CodecRegistry,
+    allowed_compression: Vec<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -95,7 +160,9 @@ pub(crate) struct Pak {
     index: Index,
     encrypted_index: bool,
     encryption_guid: Option<u128>,
-    compression: Vec<Option<Compression>>,
+    /// SHA-1 of the primary index as recorded in the footer.
+    index_hash: Hash,
+    compression: Vec<Option<String>>,
 }
 
 impl Pak {
@@ -107,12 +174,11 @@ impl Pak {
             index: Index::new(path_hash_seed),
             encrypted_index: false,
             encryption_guid: None,
+            index_hash: Hash::default(),
             compression: (if version.version_major() < VersionMajor::FNameBasedCompression {
-                vec![
-                    Some(Compression::Zlib),
-                    Some(Compression::Gzip),
-                    Some(Compression::Oodle),
-                ]
+                [Compression::Zlib, Compression::Gzip, Compression::Oodle]
+                    .map(|compression| Some(compression.to_string()))
+                    .to_vec()
             } else {
                 vec![]
             }),
@@ -151,13 +217,14 @@ impl PakReader {
     fn new_any_inner<R: Read + Seek>(
         reader: &mut R,
         key: super::Key,
+        codecs: CodecRegistry,
     ) -> Result<Self, super::Error> {
         use std::fmt::Write;
         let mut log = "\n".to_owned();
 
         for ver in Version::iter() {
             match Pak::read(&mut *reader, ver, &key) {
-                Ok(pak) => return Ok(Self { pak, key }),
+                Ok(pak) => return Ok(Self { pak, key, codecs }),
                 Err(err) => writeln!(log, "trying version {} failed: {}", ver, err)?,
             }
         }
@@ -168,8 +235,9 @@ impl PakReader {
         reader: &mut R,
         version: super::Version,
         key: super::Key,
+        codecs: CodecRegistry,
     ) -> Result<Self, super::Error> {
-        Pak::read(reader, version, &key).map(|pak| Self { pak, key })
+        Pak::read(reader, version, &key).map(|pak| Self { pak, key, codecs })
     }
 
     pub fn version(&self) -> super::Version {
@@ -192,6 +260,14 @@ impl PakReader {
         self.pak.index.path_hash_seed
     }
 
+    /// Hash of the index read from the footer. Paks with the same mount point and entries have
+    /// the same index hash regardless of their file name. From v10 on the index holds only
+    /// offsets and sizes of the entries, so paks with different data of the same size can share
+    /// an index hash.
+    pub fn index_hash(&self) -> Hash {
+        self.pak.index_hash
+    }
+
     pub fn get<R: Read + Seek>(&self, path: &str, reader: &mut R) -> Result<Vec<u8>, super::Error> {
         let mut data = Vec::new();
         self.read_file(path, reader, &mut data)?;
@@ -209,6 +285,7 @@ impl PakReader {
                 reader,
                 self.pak.version,
                 &self.pak.compression,
+                &self.codecs,
                 &self.key,
                 writer,
                 &root_path(self.mount_point(), path),
@@ -233,9 +310,17 @@ impl PakReader {
     ) -> Result<PakWriter<W>, super::Error> {
         writer.seek(io::SeekFrom::Start(self.pak.index_offset.unwrap()))?;
         Ok(PakWriter {
-            allowed_compression: self.pak.compression.iter().filter_map(|c| *c).collect(),
+            allowed_compression: self
+                .pak
+                .compression
+                .iter()
+                .flatten()
+                .filter(|name| self.codecs.get(name).is_some())
+                .cloned()
+                .collect(),
             pak: self.pak,
             key: self.key,
+            codecs: self.codecs,
             writer,
         })
     }
@@ -249,12 +334,14 @@ impl<W: Write + Seek> PakWriter<W> {
         version: Version,
         mount_point: String,
         path_hash_seed: Option<u64>,
-        allowed_compression: Vec<Compression>,
+        codecs: CodecRegistry,
+        allowed_compression: Vec<String>,
     ) -> Self {
         PakWriter {
             pak: Pak::new(version, mount_point, path_hash_seed),
             writer,
             key,
+            codecs,
             allowed_compression,
         }
     }
@@ -269,17 +356,18 @@ impl<W: Write + Seek> PakWriter<W> {
         allow_compress: bool,
         data: impl AsRef<[u8]>,
     ) -> Result<(), super::Error> {
+        let allowed_compression = if allow_compress {
+            resolve_codecs(&self.codecs, &self.allowed_compression)?
+        } else {
+            vec![]
+        };
         self.pak.index.add_entry(
             path.to_string(),
             Entry::write_file(
                 &mut self.writer,
                 self.pak.version,
                 &mut self.pak.compression,
-                if allow_compress {
-                    &self.allowed_compression
-                } else {
-                    &[]
-                },
+                &allowed_compression,
                 data.as_ref(),
                 &self.key,
                 &root_path(&self.pak.mount_point, path),
@@ -291,6 +379,7 @@ impl<W: Write + Seek> PakWriter<W> {
 
     pub fn entry_builder(&self) -> EntryBuilder {
         EntryBuilder {
+            codecs: self.codecs.clone(),
             allowed_compression: self.allowed_compression.clone(),
             key: self.key.clone(),
             mount_point: self.pak.mount_point.clone(),
@@ -342,6 +431,13 @@ fn root_path(mount_point: &str, path: &str) -> String {
     path.strip_prefix("../../../").unwrap_or(&path).to_string()
 }
 
+fn resolve_codecs(codecs: &CodecRegistry, names: &[String]) -> Result<Vec<Arc<dyn Codec>>, Error> {
+    names
+        .iter()
+        .map(|name| codecs.resolve(name).cloned())
+        .collect()
+}
+
 struct Data<'d>(Box<dyn AsRef<[u8]> + Send + Sync + 'd>);
 impl AsRef<[u8]> for Data<'_> {
     fn as_ref(&self) -> &[u8] {
@@ -351,7 +447,8 @@ impl AsRef<[u8]> for Data<'_> {
 
 #[derive(Clone)]
 pub struct EntryBuilder {
-    allowed_compression: Vec<Compression>,
+    codecs: CodecRegistry,
+    allowed_compression: Vec<String>,
     #[allow(unused)]
     key: super::Key,
     mount_point: String,
@@ -364,11 +461,13 @@ impl EntryBuilder {
         data: D,
         path: &str,
     ) -> Result<PartialEntry<D>, Error> {
-        let compression = compress
-            .then_some(self.allowed_compression.as_slice())
-            .unwrap_or_default();
+        let compression = if compress {
+            resolve_codecs(&self.codecs, &self.allowed_compression)?
+        } else {
+            vec![]
+        };
         build_partial_entry(
-            compression,
+            &compression,
             data,
             &self.key,
             &root_path(&self.mount_point, path),
@@ -524,6 +623,7 @@ impl Pak {
             index,
             encrypted_index: footer.encrypted,
             encryption_guid: footer.encryption_uuid,
+            index_hash: footer.hash,
             compression: footer.compression,
         })
     }
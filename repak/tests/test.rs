@@ -299,3 +299,479 @@ matrix_test!(
     ("", /*"_encryptindex"*/),
     test_rewrite_index
 );
+
+/// Toy codec standing in for a game-specific compressor named in the pak footer.
+struct XorCodec;
+
+impl repak::Codec for XorCodec {
+    fn name(&self) -> &str {
+        "XorTest"
+    }
+    fn compress(&self, data: &[u8]) -> Result<Vec<u8>, repak::Error> {
+        Ok(data.iter().map(|b| b ^ 0x5A).collect())
+    }
+    fn decompress_into(&self, data: &[u8], out: &mut [u8]) -> Result<(), repak::Error> {
+        if data.len() != out.len() {
+            return Err(repak::Error::DecompressionFailed(self.name().to_owned()));
+        }
+        for (out, b) in out.iter_mut().zip(data) {
+            *out = b ^ 0x5A;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_custom_codec() {
+    let data = include_bytes!("pack/root/test.png");
+    let mut pak_writer = repak::PakBuilder::new()
+        .codec(XorCodec)
+        .compression_codecs(["XorTest"])
+        .writer(
+            Cursor::new(vec![]),
+            repak::Version::V11,
+            "../mount/point/root/".to_owned(),
+            None,
+        );
+    pak_writer.write_file("test.png", true, data).unwrap();
+    let mut pak = Cursor::new(pak_writer.write_index().unwrap().into_inner());
+
+    let reader = repak::PakBuilder::new()
+        .codec(XorCodec)
+        .reader(&mut pak)
+        .unwrap();
+    assert_eq!(reader.get("test.png", &mut pak).unwrap(), data);
+
+    let reader = repak::PakBuilder::new().reader(&mut pak).unwrap();
+    match reader.get("test.png", &mut pak) {
+        Err(repak::Error::UnregisteredCodec(name)) => assert_eq!(name, "XorTest"),
+        other => panic!("expected unregistered codec error, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_index_hash() {
+    let write = |version: repak::Version, data: &[u8]| {
+        let mut pak_writer = repak::PakBuilder::new().writer(
+            Cursor::new(vec![]),
+            version,
+            "../mount/point/root/".to_owned(),
+            None,
+        );
+        pak_writer.write_file("test.png", false, data).unwrap();
+        let mut pak = Cursor::new(pak_writer.write_index().unwrap().into_inner());
+        repak::PakBuilder::new()
+            .reader(&mut pak)
+            .unwrap()
+            .index_hash()
+    };
+    let data = include_bytes!("pack/root/test.png");
+    let mut edited = data.to_vec();
+    edited[0] ^= 0xff;
+
+    // older indexes store a hash of every entry
+    assert_eq!(
+        write(repak::Version::V8B, data),
+        write(repak::Version::V8B, data)
+    );
+    assert_ne!(
+        write(repak::Version::V8B, data),
+        write(repak::Version::V8B, &edited)
+    );
+    // encoded v10+ entries only hold offsets and sizes, so the data is not covered
+    assert_eq!(
+        write(repak::Version::V11, data),
+        write(repak::Version::V11, data)
+    );
+    assert_eq!(
+        write(repak::Version::V11, data),
+        write(repak::Version::V11, &edited)
+    );
+}
+
+#[cfg(feature = "oodle")]
+#[test]
+fn test_oodle_round_trip() {
+    // larger than one block so the reused per-thread buffers are exercised
+    let data = include_bytes!("pack/root/test.png").repeat(8);
+    let mut pak_writer = repak::PakBuilder::new()
+        .compression([repak::Compression::Oodle])
+        .writer(
+            Cursor::new(vec![]),
+            repak::Version::V11,
+            "../mount/point/root/".to_owned(),
+            None,
+        );
+    pak_writer.write_file("test.png", true, &data).unwrap();
+    let mut pak = Cursor::new(pak_writer.write_index().unwrap().into_inner());
+
+    let reader = repak::PakBuilder::new().reader(&mut pak).unwrap();
+    assert_eq!(reader.get("test.png", &mut pak).unwrap(), data);
+}
+
+/// Encrypts the way [`repak::PakBuilder::key`] decrypts, for building encrypted test containers.
+fn encrypt_blocks(key: &aes::Aes256, bytes: &mut [u8]) {
+    use aes::cipher::BlockEncrypt;
+    for chunk in bytes.chunks_mut(16) {
+        chunk.chunks_mut(4).for_each(|c| c.reverse());
+        key.encrypt_block(aes::Block::from_mut_slice(chunk));
+        chunk.chunks_mut(4).for_each(|c| c.reverse());
+    }
+}
+
+fn test_key() -> aes::Aes256 {
+    use aes::cipher::KeyInit;
+    use base64::{engine::general_purpose, Engine as _};
+    aes::Aes256::new_from_slice(&general_purpose::STANDARD.decode(AES_KEY).unwrap()).unwrap()
+}
+
+/// Builds a `.utoc`/`.ucas` pair holding `Marvel/Content/A.uasset` and `Marvel/Content/Sub/B.ubulk`.
+fn build_container(
+    a: &[u8],
+    b: &[u8],
+    compress: bool,
+    key: Option<&aes::Aes256>,
+) -> (Vec<u8>, Vec<u8>) {
+    use byteorder::LE;
+
+    const BLOCK_SIZE: usize = 0x40;
+    let write_string = |out: &mut Vec<u8>, value: &str| {
+        out.write_u32::<LE>(value.len() as u32 + 1).unwrap();
+        out.extend_from_slice(value.as_bytes());
+        out.push(0);
+    };
+    let pad16 = |data: &mut Vec<u8>| data.resize(data.len().div_ceil(16) * 16, 0);
+
+    // chunks start on block boundaries of the uncompressed address space
+    let mut blocks = vec![];
+    let mut offsets = vec![];
+    let mut ucas = vec![];
+    for data in [a, b] {
+        offsets.push((blocks.len() * BLOCK_SIZE) as u64);
+        for block in data.chunks(BLOCK_SIZE) {
+            let (mut stored, method) = if compress {
+                let zlib = repak::CodecRegistry::default();
+                (zlib.get("Zlib").unwrap().compress(block).unwrap(), 1u8)
+            } else {
+                (block.to_vec(), 0u8)
+            };
+            let compressed_size = stored.len();
+            if let Some(key) = key {
+                pad16(&mut stored);
+                encrypt_blocks(key, &mut stored);
+            }
+            blocks.push((ucas.len() as u64, compressed_size, block.len(), method));
+            ucas.extend_from_slice(&stored);
+        }
+    }
+
+    let mut index = vec![];
+    write_string(&mut index, "../../../");
+    let none = u32::MAX;
+    // name, first child, next sibling, first file
+    let directories = [
+        [none, 1, none, none],
+        [0, 2, none, none],
+        [1, 3, none, 0],
+        [3, none, none, 1],
+    ];
+    index.write_u32::<LE>(directories.len() as u32).unwrap();
+    for field in directories.iter().flatten() {
+        index.write_u32::<LE>(*field).unwrap();
+    }
+    // name, next file, chunk
+    let files = [[2, none, 0], [4, none, 1]];
+    index.write_u32::<LE>(files.len() as u32).unwrap();
+    for field in files.iter().flatten() {
+        index.write_u32::<LE>(*field).unwrap();
+    }
+    let names = ["Marvel", "Content", "A.uasset", "Sub", "B.ubulk"];
+    index.write_u32::<LE>(names.len() as u32).unwrap();
+    for name in names {
+        write_string(&mut index, name);
+    }
+    if let Some(key) = key {
+        pad16(&mut index);
+        encrypt_blocks(key, &mut index);
+    }
+
+    let mut flags = 8u8;
+    if compress {
+        flags |= 1;
+    }
+    if key.is_some() {
+        flags |= 2;
+    }
+    let mut toc = b"-==--==--==--==-".to_vec();
+    toc.extend_from_slice(&[8, 0, 0, 0]);
+    for value in [
+        144,
+        2,
+        blocks.len() as u32,
+        12,
+        compress as u32,
+        32,
+        BLOCK_SIZE as u32,
+        index.len() as u32,
+        1,
+    ] {
+        toc.write_u32::<LE>(value).unwrap();
+    }
+    toc.write_u64::<LE>(0x1234).unwrap();
+    toc.extend_from_slice(&[0; 16]);
+    toc.extend_from_slice(&[flags, 0, 0, 0]);
+    toc.write_u32::<LE>(0).unwrap();
+    toc.write_u64::<LE>(u64::MAX).unwrap();
+    toc.write_u32::<LE>(0).unwrap();
+    toc.resize(144, 0);
+
+    for (idx, chunk_type) in [1u8, 2].into_iter().enumerate() {
+        toc.write_u64::<LE>(idx as u64 + 100).unwrap();
+        toc.extend_from_slice(&[0, 0, 0, chunk_type]);
+    }
+    for (offset, len) in offsets.iter().zip([a.len(), b.len()]) {
+        toc.extend_from_slice(&offset.to_be_bytes()[3..]);
+        toc.extend_from_slice(&(len as u64).to_be_bytes()[3..]);
+    }
+    for (offset, compressed, uncompressed, method) in &blocks {
+        toc.extend_from_slice(&offset.to_le_bytes()[..5]);
+        toc.extend_from_slice(&(*compressed as u32).to_le_bytes()[..3]);
+        toc.extend_from_slice(&(*uncompressed as u32).to_le_bytes()[..3]);
+        toc.push(*method);
+    }
+    if compress {
+        let mut name = b"Zlib".to_vec();
+        name.resize(32, 0);
+        toc.extend_from_slice(&name);
+    }
+    toc.extend_from_slice(&index);
+    for hash in [[0xaa; 20], [0xbb; 20]] {
+        toc.extend_from_slice(&hash);
+        toc.extend_from_slice(&[0; 4]);
+    }
+    (toc, ucas)
+}
+
+#[test]
+fn test_toc_reader() {
+    let a = include_bytes!("pack/root/test.png");
+    let b = b"bulk data";
+    for (compress, encrypt) in [(false, false), (true, false), (false, true), (true, true)] {
+        let key = test_key();
+        let (toc, ucas) = build_container(a, b, compress, encrypt.then_some(&key));
+        let mut builder = repak::PakBuilder::new();
+        if encrypt {
+            builder = builder.key(key);
+        }
+        let toc = builder.toc_reader(&mut Cursor::new(toc)).unwrap();
+
+        assert_eq!(
+            toc.version(),
+            repak::iostore::TocVersion::ReplaceIoChunkHashWithIoHash
+        );
+        assert_eq!(toc.container_id(), 0x1234);
+        assert_eq!(toc.flags().encrypted(), encrypt);
+        assert_eq!(toc.mount_point(), Some("../../../"));
+        let files = toc
+            .files()
+            .iter()
+            .map(|file| (file.path.as_str(), file.chunk))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            files,
+            [
+                ("Marvel/Content/A.uasset", 0),
+                ("Marvel/Content/Sub/B.ubulk", 1)
+            ]
+        );
+
+        let chunks = toc.chunks();
+        assert_eq!(
+            chunks[0].id.chunk_type(),
+            Some(repak::iostore::ChunkType::ExportBundleData)
+        );
+        assert_eq!(
+            chunks[1].id.chunk_type(),
+            Some(repak::iostore::ChunkType::BulkData)
+        );
+        assert_eq!(chunks[0].length, a.len() as u64);
+        assert_eq!(chunks[1].hash, [0xbb; 20]);
+        assert_eq!(toc.chunk_blocks(0).unwrap().len(), a.len().div_ceil(0x40));
+
+        let mut ucas = Cursor::new(ucas);
+        assert_eq!(toc.read_chunk(0, &mut ucas).unwrap(), a);
+        assert_eq!(
+            toc.read_file("Marvel/Content/Sub/B.ubulk", &mut ucas)
+                .unwrap(),
+            b
+        );
+        assert!(matches!(
+            toc.read_file("missing", &mut ucas),
+            Err(repak::Error::MissingEntry(_))
+        ));
+    }
+}
+
+#[test]
+fn test_toc_reader_errors() {
+    let (toc, _) = build_container(b"a", b"b", false, Some(&test_key()));
+    assert!(matches!(
+        repak::PakBuilder::new().toc_reader(&mut Cursor::new(&toc)),
+        Err(repak::Error::Encrypted)
+    ));
+
+    let mut bad_magic = toc.clone();
+    bad_magic[0] = b'x';
+    assert!(matches!(
+        repak::PakBuilder::new().toc_reader(&mut Cursor::new(bad_magic)),
+        Err(repak::Error::TocMagic)
+    ));
+
+    let mut bad_version = toc;
+    bad_version[16] = 200;
+    assert!(matches!(
+        repak::PakBuilder::new().toc_reader(&mut Cursor::new(bad_version)),
+        Err(repak::Error::TocVersion(200))
+    ));
+}
+
+#[test]
+fn test_keys_by_guid() {
+    // {12345678-9ABC-DEF0-0FED-CBA987654321} as stored in a pak footer or .utoc header
+    let guid = [0x12345678u32, 0x9ABCDEF0, 0x0FEDCBA9, 0x87654321]
+        .map(u32::to_le_bytes)
+        .concat();
+    // the built-in game key can't open these containers, only the key of their GUID can
+    let mut keys = repak::keys::KeyConfig::default();
+    keys.merge_str(&format!(
+        "{{12345678-9ABC-DEF0-0FED-CBA987654321}} = {AES_KEY}"
+    ))
+    .unwrap();
+    let other_guid = {
+        let mut keys = repak::keys::KeyConfig::default();
+        keys.merge_str(&format!(
+            "{{78563412-9ABC-DEF0-0FED-CBA987654321}} = {AES_KEY}"
+        ))
+        .unwrap();
+        keys
+    };
+
+    // the configured key as the tools parse it
+    let key = <repak::utils::AesKey as std::str::FromStr>::from_str(AES_KEY)
+        .unwrap()
+        .0;
+
+    let mut pak_writer = repak::PakBuilder::new().key(key.clone()).writer(
+        Cursor::new(vec![]),
+        repak::Version::V11,
+        "../mount/point/root/".to_owned(),
+        None,
+    );
+    pak_writer.write_file("test.txt", false, b"data").unwrap();
+    let mut pak = pak_writer.write_index().unwrap().into_inner();
+    let footer = pak.len() - repak::Version::V11.size() as usize;
+    pak[footer..footer + 16].copy_from_slice(&guid);
+    let pak_reader = repak::PakBuilder::new()
+        .keys(keys.clone())
+        .reader(&mut Cursor::new(&pak))
+        .unwrap();
+    assert_eq!(pak_reader.files(), ["test.txt"]);
+    assert!(repak::PakBuilder::new()
+        .keys(other_guid.clone())
+        .reader(&mut Cursor::new(&pak))
+        .is_err());
+
+    let (mut toc, ucas) = build_container(b"a", b"b", false, Some(&key));
+    toc[64..80].copy_from_slice(&guid);
+    let toc_reader = repak::PakBuilder::new()
+        .keys(keys)
+        .toc_reader(&mut Cursor::new(&toc))
+        .unwrap();
+    assert_eq!(
+        toc_reader.read_chunk(1, &mut Cursor::new(ucas)).unwrap(),
+        b"b"
+    );
+    assert!(repak::PakBuilder::new()
+        .keys(other_guid)
+        .toc_reader(&mut Cursor::new(&toc))
+        .is_err());
+}
+
+#[test]
+fn test_toc_reader_malformed() {
+    use byteorder::LE;
+
+    let a = include_bytes!("pack/root/test.png");
+    let (toc, ucas) = build_container(a, b"b", false, None);
+    let open = |toc: &[u8]| repak::PakBuilder::new().toc_reader(&mut Cursor::new(toc));
+    let set_u32 = |toc: &mut Vec<u8>, offset: usize, value: u32| {
+        (&mut toc[offset..offset + 4])
+            .write_u32::<LE>(value)
+            .unwrap()
+    };
+
+    // header fields: entry count at 24, method name count and length at 36 and 40, directory
+    // index size at 48
+    for (offset, value) in [(24, u32::MAX), (40, u32::MAX), (48, u32::MAX)] {
+        let mut toc = toc.clone();
+        if offset == 40 {
+            set_u32(&mut toc, 36, 1);
+        }
+        set_u32(&mut toc, offset, value);
+        assert!(
+            matches!(open(&toc), Err(repak::Error::InvalidToc(_))),
+            "header field at {offset}"
+        );
+    }
+
+    // chunk lengths are five byte big endian values after the two chunk IDs
+    let set_length = |toc: &mut Vec<u8>, length: u64| {
+        toc[144 + 24 + 5..144 + 24 + 10].copy_from_slice(&length.to_be_bytes()[3..]);
+    };
+    let mut huge = toc.clone();
+    set_length(&mut huge, 0xff_ffff_ffff);
+    let reader = open(&huge).unwrap();
+    assert!(matches!(
+        reader.read_chunk(0, &mut Cursor::new(&ucas)),
+        Err(repak::Error::InvalidToc(_))
+    ));
+
+    // still within the last block's range, but longer than the data it holds
+    assert_ne!(a.len() % 0x40, 0);
+    let mut longer = toc.clone();
+    set_length(&mut longer, a.len() as u64 + 1);
+    let reader = open(&longer).unwrap();
+    assert!(matches!(
+        reader.read_chunk(0, &mut Cursor::new(&ucas)),
+        Err(repak::Error::InvalidToc(_))
+    ));
+
+    // the directory index entry for B.ubulk points at chunk 1, make it chunk 7
+    let mut missing_chunk = toc.clone();
+    let needle = [4u32, u32::MAX, 1]
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .collect::<Vec<_>>();
+    let pos = missing_chunk
+        .windows(needle.len())
+        .position(|window| window == needle)
+        .unwrap();
+    set_u32(&mut missing_chunk, pos + 8, 7);
+    assert!(matches!(
+        open(&missing_chunk),
+        Err(repak::Error::InvalidToc(_))
+    ));
+
+    // a directory whose sibling is itself
+    let mut cycle = toc;
+    let needle = [3u32, u32::MAX, u32::MAX, 1]
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .collect::<Vec<_>>();
+    let pos = cycle
+        .windows(needle.len())
+        .position(|window| window == needle)
+        .unwrap();
+    set_u32(&mut cycle, pos + 8, 3);
+    assert!(matches!(open(&cycle), Err(repak::Error::InvalidToc(_))));
+}
@@ -4,7 +4,9 @@ use colored::Colorize;
 use path_clean::PathClean;
 use path_slash::PathExt;
 use rayon::prelude::*;
+use repak::keys::{KeyConfig, DEFAULT_GAME};
 use repak::utils::AesKey;
+use repak_manager::mod_conflicts::analyze_mod_conflicts;
 use std::collections::{BTreeMap, HashSet};
 use std::fs::{self, File, OpenOptions};
 use std::io::{self, BufRead, BufReader, BufWriter, ErrorKind, Write};
@@ -139,6 +141,21 @@ struct ActionGet {
     strip_prefix: String,
 }
 
+#[derive(Parser, Debug)]
+struct ActionConflicts {
+    /// Mods directory containing installed .pak files
+    #[arg(index = 1)]
+    input: String,
+
+    /// Prefix to strip from entry path
+    #[arg(short, long, default_value = "../../../")]
+    strip_prefix: String,
+
+    /// Print every conflicting asset path with its winner
+    #[arg(short, long, default_value = "false")]
+    verbose: bool,
+}
+
 #[derive(Subcommand, Debug)]
 enum Action {
     /// Print .pak info
@@ -153,43 +170,79 @@ enum Action {
     Pack(ActionPack),
     /// Reads a single file to stdout
     Get(ActionGet),
+    /// Report asset paths overridden by more than one mod in a mods directory
+    Conflicts(ActionConflicts),
 }
 
 #[derive(Parser, Debug)]
 #[command(author, version)]
 struct Args {
-    /// 256 bit AES encryption key as base64 or hex string if the pak is encrypted
-    #[arg(
-        short,
-        long,
-        default_value = "0C263D8C22DCB085894899C3A3796383E9BF9DE0CBFB08C9BF2DEF2E84F29D74"
-    )]
+    /// 256 bit AES encryption key as base64 or hex string if the pak is encrypted. Defaults to
+    /// the key of the pak's encryption key GUID from `aes_keys.txt`, `REPAK_AES_KEY` or the
+    /// built-in Marvel Rivals key
+    #[arg(short, long)]
     aes_key: Option<AesKey>,
 
     #[command(subcommand)]
     action: Action,
 }
 
+/// Keys to read and write paks with.
+#[derive(Clone)]
+struct Keys {
+    /// `--aes-key` or the configured game key, used to write paks
+    key: aes::Aes256,
+    /// picks the key of each pak read by its encryption key GUID when there is no `--aes-key`
+    config: Option<KeyConfig>,
+}
+
+impl Keys {
+    fn load(aes_key: Option<AesKey>) -> Result<Self, repak::Error> {
+        if let Some(key) = aes_key {
+            return Ok(Self {
+                key: key.0,
+                config: None,
+            });
+        }
+        // a broken override is skipped so paks using the other keys can still be read
+        let (config, errors) = repak_manager::keys::load();
+        for e in errors {
+            eprintln!("{} {e}", "skipping AES keys from".yellow());
+        }
+        Ok(Self {
+            key: config.key(DEFAULT_GAME)?.0,
+            config: Some(config),
+        })
+    }
+
+    fn builder(&self) -> repak::PakBuilder {
+        let builder = repak::PakBuilder::new().key(self.key.clone());
+        match &self.config {
+            Some(config) => builder.keys(config.clone()),
+            None => builder,
+        }
+    }
+}
+
 fn main() -> Result<(), repak::Error> {
     let args = Args::parse();
-    let aes_key = args.aes_key.map(|k| k.0);
+    let keys = Keys::load(args.aes_key)?;
 
     match args.action {
-        Action::Info(action) => info(aes_key, action),
-        Action::List(action) => list(aes_key, action),
-        Action::HashList(action) => hash_list(aes_key, action),
-        Action::Unpack(action) => unpack(aes_key, action),
-        Action::Pack(action) => pack(aes_key, action),
-        Action::Get(action) => get(aes_key, action),
+        Action::Info(action) => info(&keys, action),
+        Action::List(action) => list(&keys, action),
+        Action::HashList(action) => hash_list(&keys, action),
+        Action::Unpack(action) => unpack(&keys, action),
+        Action::Pack(action) => pack(&keys, action),
+        Action::Get(action) => get(&keys, action),
+        Action::Conflicts(action) => conflicts(&keys, action),
     }
 }
 
-fn info(aes_key: Option<aes::Aes256>, action: ActionInfo) -> Result<(), repak::Error> {
-    let mut builder = repak::PakBuilder::new();
-    if let Some(aes_key) = aes_key {
-        builder = builder.key(aes_key);
-    }
-    let pak = builder.reader(&mut BufReader::new(File::open(action.input)?))?;
+fn info(keys: &Keys, action: ActionInfo) -> Result<(), repak::Error> {
+    let pak = keys
+        .builder()
+        .reader(&mut BufReader::new(File::open(action.input)?))?;
     println!("mount point: {}", pak.mount_point());
     println!("version: {}", pak.version());
     println!("version major: {}", pak.version().version_major());
@@ -200,12 +253,10 @@ fn info(aes_key: Option<aes::Aes256>, action: ActionInfo) -> Result<(), repak::E
     Ok(())
 }
 
-fn list(aes_key: Option<aes::Aes256>, action: ActionList) -> Result<(), repak::Error> {
-    let mut builder = repak::PakBuilder::new();
-    if let Some(aes_key) = aes_key {
-        builder = builder.key(aes_key);
-    }
-    let pak = builder.reader(&mut BufReader::new(File::open(action.input)?))?;
+fn list(keys: &Keys, action: ActionList) -> Result<(), repak::Error> {
+    let pak = keys
+        .builder()
+        .reader(&mut BufReader::new(File::open(action.input)?))?;
 
     let mount_point = PathBuf::from(pak.mount_point());
     let prefix = Path::new(&action.strip_prefix);
@@ -233,12 +284,10 @@ fn list(aes_key: Option<aes::Aes256>, action: ActionList) -> Result<(), repak::E
     Ok(())
 }
 
-fn hash_list(aes_key: Option<aes::Aes256>, action: ActionHashList) -> Result<(), repak::Error> {
-    let mut builder = repak::PakBuilder::new();
-    if let Some(aes_key) = aes_key {
-        builder = builder.key(aes_key);
-    }
-    let pak = builder.reader(&mut BufReader::new(File::open(&action.input)?))?;
+fn hash_list(keys: &Keys, action: ActionHashList) -> Result<(), repak::Error> {
+    let pak = keys
+        .builder()
+        .reader(&mut BufReader::new(File::open(&action.input)?))?;
 
     let mount_point = PathBuf::from(pak.mount_point());
     let prefix = Path::new(&action.strip_prefix);
@@ -305,13 +354,11 @@ impl Output {
     }
 }
 
-fn unpack(aes_key: Option<aes::Aes256>, action: ActionUnpack) -> Result<(), repak::Error> {
+fn unpack(keys: &Keys, action: ActionUnpack) -> Result<(), repak::Error> {
     for input in &action.input {
-        let mut builder = repak::PakBuilder::new();
-        if let Some(aes_key) = aes_key.clone() {
-            builder = builder.key(aes_key);
-        }
-        let pak = builder.reader(&mut BufReader::new(File::open(input)?))?;
+        let pak = keys
+            .builder()
+            .reader(&mut BufReader::new(File::open(input)?))?;
         let output = action
             .output
             .as_ref()
@@ -481,7 +528,7 @@ static MESH_DIRECTORY_NAMES: LazyLock<Vec<String>> = LazyLock::new(|| {
     vec
 });
 
-fn pack(aes_key: Option<aes::Aes256>, args: ActionPack) -> Result<(), repak::Error> {
+fn pack(keys: &Keys, args: ActionPack) -> Result<(), repak::Error> {
     let output = args.output.map(PathBuf::from).unwrap_or_else(|| {
         // NOTE: don't use `with_extension` here because it will replace e.g. the `.1` in
         // `test_v1.1`.
@@ -694,10 +741,7 @@ fn pack(aes_key: Option<aes::Aes256>, args: ActionPack) -> Result<(), repak::Err
 
     paths.sort();
 
-    let mut builder = repak::PakBuilder::new().compression(args.compression.iter().cloned());
-    if let Some(aes_key) = aes_key {
-        builder = builder.key(aes_key);
-    }
+    let builder = keys.builder().compression(args.compression.iter().cloned());
     let mut pak = builder.writer(
         BufWriter::new(File::create(&output)?),
         args.version,
@@ -762,13 +806,9 @@ fn pack(aes_key: Option<aes::Aes256>, args: ActionPack) -> Result<(), repak::Err
     Ok(())
 }
 
-fn get(aes_key: Option<aes::Aes256>, args: ActionGet) -> Result<(), repak::Error> {
+fn get(keys: &Keys, args: ActionGet) -> Result<(), repak::Error> {
     let mut reader = BufReader::new(File::open(&args.input)?);
-    let mut builder = repak::PakBuilder::new();
-    if let Some(aes_key) = aes_key {
-        builder = builder.key(aes_key);
-    }
-    let pak = builder.reader(&mut reader)?;
+    let pak = keys.builder().reader(&mut reader)?;
     let mount_point = PathBuf::from(pak.mount_point());
     let prefix = Path::new(&args.strip_prefix);
 
@@ -784,3 +824,150 @@ fn get(aes_key: Option<aes::Aes256>, args: ActionGet) -> Result<(), repak::Error
     std::io::stdout().write_all(&pak.get(&file.to_slash_lossy(), &mut reader)?)?;
     Ok(())
 }
+
+fn conflicts(keys: &Keys, args: ActionConflicts) -> Result<(), repak::Error> {
+    fn collect_paks(paths: &mut Vec<PathBuf>, dir: &Path) -> io::Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                collect_paks(paths, &path)?;
+            } else if path
+                .extension()
+                .and_then(|e| e.to_str())
+                .is_some_and(|e| e.eq_ignore_ascii_case("pak"))
+            {
+                paths.push(path);
+            }
+        }
+        Ok(())
+    }
+    fn mod_name(path: &Path) -> String {
+        path.file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default()
+    }
+    let input_path = Path::new(&args.input);
+    if !input_path.is_dir() {
+        return Err(repak::Error::InputNotADirectory(
+            input_path.to_string_lossy().to_string(),
+        ));
+    }
+    let mut paths = vec![];
+    collect_paks(&mut paths, input_path)?;
+
+    let prefix = Path::new(&args.strip_prefix);
+    let mut mods = vec![];
+    for path in paths {
+        match conflict_asset_paths(keys, &path, prefix) {
+            Ok(files) => mods.push((path, files)),
+            Err(e) => eprintln!("{} {}: {e}", "skipping".yellow(), path.display()),
+        }
+    }
+
+    // the report lists mods in load order and names the last owner of each asset the winner
+    let report = analyze_mod_conflicts(mods);
+    println!(
+        "Scanned {} mods, {} assets provided by more than one mod",
+        report.mods.len(),
+        report.assets.len()
+    );
+    if report.assets.is_empty() {
+        return Ok(());
+    }
+
+    let mut overridden_by = BTreeMap::<&Path, BTreeMap<&Path, usize>>::new();
+    for conflict in &report.assets {
+        for loser in &conflict.overridden {
+            *overridden_by
+                .entry(loser)
+                .or_default()
+                .entry(&conflict.winner)
+                .or_default() += 1;
+        }
+        if args.verbose {
+            println!("{}", conflict.asset);
+            println!("  winner: {}", mod_name(&conflict.winner).green());
+            for loser in &conflict.overridden {
+                println!("  overridden: {}", mod_name(loser).red());
+            }
+        }
+    }
+
+    println!();
+    for summary in report.mods.iter().filter(|summary| summary.overridden > 0) {
+        let winners = report
+            .mods
+            .iter()
+            .filter_map(|winner| {
+                let count = overridden_by
+                    .get(summary.path.as_path())?
+                    .get(winner.path.as_path())?;
+                Some(format!("{} ({count})", mod_name(&winner.path)))
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        println!(
+            "{}: {} of {} assets overridden by {winners}",
+            mod_name(&summary.path).yellow(),
+            summary.overridden,
+            summary.asset_count
+        );
+    }
+
+    let shadowed = report
+        .mods
+        .iter()
+        .filter(|summary| summary.shadowed())
+        .map(|summary| mod_name(&summary.path))
+        .collect::<Vec<_>>();
+    if !shadowed.is_empty() {
+        println!();
+        println!("Shadowed entirely (no effect in game):");
+        for name in shadowed {
+            println!("  {}", name.red());
+        }
+    }
+    Ok(())
+}
+
+/// Lists the asset paths a mod provides, relative to `prefix`. IoStore mods are read from the
+/// `chunknames` entry of their companion pak, plus the loose files the companion pak holds.
+fn conflict_asset_paths(
+    keys: &Keys,
+    path: &Path,
+    prefix: &Path,
+) -> Result<Vec<String>, repak::Error> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let pak = keys.builder().reader(&mut reader)?;
+    let mount_point = PathBuf::from(pak.mount_point());
+    let mut files = pak
+        .files()
+        .into_iter()
+        .filter(|f| f != "chunknames")
+        .map(|f| {
+            let full_path = mount_point.join(f);
+            full_path
+                .strip_prefix(prefix)
+                .unwrap_or(&full_path)
+                .to_slash_lossy()
+                .to_string()
+        })
+        .collect::<Vec<_>>();
+
+    if path.with_extension("utoc").exists() {
+        if !pak.files().iter().any(|f| f == "chunknames") {
+            return Err(repak::Error::Other(
+                "IoStore mod has no chunknames entry to read asset paths from".to_string(),
+            ));
+        }
+        files.extend(
+            String::from_utf8_lossy(&pak.get("chunknames", &mut reader)?)
+                .lines()
+                .map(str::to_string),
+        );
+        files.sort();
+        files.dedup();
+    }
+
+    Ok(files)
+}
@@ -1,83 +1,150 @@
+use crate::error::{Error, Result};
+use crate::util::pak_reader_builder;
+use repak_manager::archive::{self, ArchiveError, ArchivePayload, ExtractLimits, ExtractProgress};
+use repak_manager::utoc_utils::read_utoc_package_names;
+use std::collections::HashSet;
 use std::fs::File;
-use std::io;
-use std::path::{Path, PathBuf};
+use std::io::BufReader;
+use std::path::Path;
 use tempfile::TempDir;
-use unrar::Archive;
-use zip::ZipArchive;
 
-pub fn extract_archive(path: &Path) -> Result<TempDir, String> {
-    let temp = tempfile::tempdir().map_err(|e| format!("Failed to create temp dir: {e}"))?;
-    let size = std::fs::metadata(path).map(|metadata| metadata.len()).ok();
-    match size {
-        Some(size) => tracing::info!(
-            archive = %path.display(),
-            size,
-            output = %temp.path().display(),
-            "Extracting archive"
-        ),
-        None => tracing::info!(
-            archive = %path.display(),
-            output = %temp.path().display(),
-            "Extracting archive"
-        ),
+/// Extracts a mod archive into a temporary directory with the shared extractor's safety
+/// limits.
+pub fn extract_archive(path: &Path) -> Result<TempDir> {
+    if !path.exists() {
+        return Err(Error::InputNotFound(path.to_path_buf()));
     }
-    match path
-        .extension()
-        .and_then(|ext| ext.to_str())
-        .map(|ext| ext.to_ascii_lowercase())
-        .as_deref()
-    {
-        Some("zip") => extract_zip(path, temp.path())
-            .map_err(|e| format!("Failed to extract {}: {e}", path.display()))?,
-        Some("rar") => extract_rar(path, temp.path())
-            .map_err(|e| format!("Failed to extract {}: {e}", path.display()))?,
-        Some("7z") => sevenz_rust2::decompress_file(path, temp.path())
-            .map_err(|e| format!("Failed to extract {}: {e}", path.display()))?,
-        _ => return Err(format!("Unsupported archive: {}", path.display())),
+    let size = std::fs::metadata(path).map(|metadata| metadata.len()).ok();
+    tracing::info!(archive = %path.display(), size, "Extracting archive");
+    archive::extract_to_temp(path, &ExtractLimits::default(), &mut log_extracted_entry)
+        .map_err(|e| archive_error(path, e))
+}
+
+/// Lists a mod archive and extracts only the mods found in it into a temporary directory, each
+/// at its [`ArchivePayload::extracted_path`]. Entries [`archive::find_payloads`] doesn't count
+/// as part of a mod are never extracted. The options `variants` doesn't pick are left out of
+/// the returned payloads, see [`pick_variants`]; `None` keeps every option.
+pub fn extract_archive_payloads(
+    path: &Path,
+    variants: Option<&[String]>,
+) -> Result<(TempDir, Vec<ArchivePayload>)> {
+    if !path.exists() {
+        return Err(Error::InputNotFound(path.to_path_buf()));
     }
-    tracing::debug!(
+    let entries = archive::list_archive(path).map_err(|e| archive_error(path, e))?;
+    let mut payloads = archive::find_payloads(&entries);
+    tracing::info!(
         archive = %path.display(),
-        output = %temp.path().display(),
-        "Finished extracting archive"
+        entries = entries.len(),
+        payloads = payloads.len(),
+        "Listed archive"
     );
-    Ok(temp)
+    let temp =
+        tempfile::tempdir().map_err(|e| Error::io("Failed to create a temporary directory", e))?;
+    archive::extract_payloads(
+        path,
+        temp.path(),
+        &payloads.iter().collect::<Vec<_>>(),
+        &ExtractLimits::default(),
+        &mut log_extracted_entry,
+    )
+    .map_err(|e| archive_error(path, e))?;
+    if let Some(variants) = variants {
+        // options are told apart by what their containers package, only known from their index
+        let assets = payloads
+            .iter()
+            .map(|payload| payload_assets(temp.path(), payload))
+            .collect::<Result<Vec<_>>>()?;
+        payloads = pick_variants(path, payloads, &assets, variants)?;
+    }
+    Ok((temp, payloads))
 }
 
-fn extract_zip(zip_path: &Path, output_dir: &Path) -> io::Result<()> {
-    let file = File::open(zip_path)?;
-    let mut archive = ZipArchive::new(file)?;
-
-    for i in 0..archive.len() {
-        let mut file = archive.by_index(i)?;
-        let outpath = output_dir.join(file.mangled_name());
-
-        if file.name().ends_with('/') {
-            std::fs::create_dir_all(&outpath)?;
-        } else {
-            if let Some(parent) = outpath.parent() {
-                std::fs::create_dir_all(parent)?;
-            }
-            let mut outfile = File::create(&outpath)?;
-            io::copy(&mut file, &mut outfile)?;
+/// The assets `payload`, extracted into `output_dir`, packages: the files in the index of a
+/// pak, the packages of an IoStore container, the paths of loose assets below their root.
+fn payload_assets(output_dir: &Path, payload: &ArchivePayload) -> Result<Vec<String>> {
+    let extracted_path = payload.extracted_path(output_dir);
+    match payload {
+        ArchivePayload::IoStore { utoc, .. } => {
+            let utoc_path = output_dir.join(payload.destination(utoc));
+            read_utoc_package_names(&utoc_path)
+                .map_err(|e| Error::container(format!("Failed to read {}", utoc_path.display()), e))
+        }
+        ArchivePayload::Pak(_) => {
+            let file = File::open(&extracted_path).map_err(|e| {
+                Error::io(format!("Failed to open {}", extracted_path.display()), e)
+            })?;
+            let pak = pak_reader_builder()
+                .reader(&mut BufReader::new(file))
+                .map_err(|e| {
+                    Error::pak(format!("Failed to read {}", extracted_path.display()), e)
+                })?;
+            Ok(pak.files())
         }
+        ArchivePayload::Loose { .. } => Ok(payload.listed_assets()),
     }
-
-    Ok(())
 }
 
-fn extract_rar(rar_path: &Path, output_dir: &Path) -> Result<(), unrar::error::UnrarError> {
-    let mut archive = Archive::new(rar_path).open_for_processing()?;
-    while let Some(header) = archive.read_header()? {
-        let filename: PathBuf = header.entry().filename.clone();
-        archive = if header.entry().is_file() {
-            let output = output_dir.join(filename);
-            if let Some(parent) = output.parent() {
-                let _ = std::fs::create_dir_all(parent);
-            }
-            header.extract_to(output)?
-        } else {
-            header.skip()?
+/// Keeps one option of each group of mutually exclusive options in `payloads`, the one named
+/// in `variants`, ignoring case. `assets` holds the assets of each payload, see
+/// [`archive::find_variant_groups`]. Fails for groups `variants` names none or several options
+/// of.
+pub fn pick_variants(
+    path: &Path,
+    payloads: Vec<ArchivePayload>,
+    assets: &[Vec<String>],
+    variants: &[String],
+) -> Result<Vec<ArchivePayload>> {
+    let mut dropped = HashSet::new();
+    for group in archive::find_variant_groups(&payloads, assets) {
+        let picked = group
+            .variants
+            .iter()
+            .filter(|variant| {
+                variants
+                    .iter()
+                    .any(|name| variant.name.eq_ignore_ascii_case(name))
+            })
+            .collect::<Vec<_>>();
+        let [picked] = picked.as_slice() else {
+            return Err(Error::Variant {
+                path: path.to_path_buf(),
+                group: group.label(),
+                options: group
+                    .variants
+                    .iter()
+                    .map(|variant| variant.name.clone())
+                    .collect(),
+            });
         };
+        println!("  picked option {} of {}", picked.name, group.label());
+        dropped.extend(
+            group
+                .variants
+                .iter()
+                .filter(|variant| variant.name != picked.name)
+                .flat_map(|variant| variant.payloads.iter().copied()),
+        );
+    }
+    Ok(payloads
+        .into_iter()
+        .enumerate()
+        .filter(|(index, _)| !dropped.contains(index))
+        .map(|(_, payload)| payload)
+        .collect())
+}
+
+fn log_extracted_entry(progress: ExtractProgress) {
+    tracing::debug!(
+        entry = %progress.path.display(),
+        size = progress.size,
+        "Extracted archive entry"
+    );
+}
+
+fn archive_error(path: &Path, e: ArchiveError) -> Error {
+    match e {
+        ArchiveError::Unsupported(path) => Error::UnsupportedArchive(path),
+        e => Error::archive(path, e),
     }
-    Ok(())
 }
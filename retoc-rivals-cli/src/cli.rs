@@ -1,7 +1,11 @@
 use clap::{Parser, Subcommand, ValueEnum};
+use std::num::NonZeroUsize;
 use std::path::PathBuf;
 
-use crate::RIVALS_AES_KEY;
+use crate::legacy::FixKawaiiOptions;
+use crate::pack::{PackDirOptions, PackOptions};
+use crate::unpack::UnpackOptions;
+use crate::MAX_MOD_PRIORITY;
 
 pub fn parse_u64_bitmap(value: &str) -> Result<u64, String> {
     let trimmed = value.trim();
@@ -24,14 +28,20 @@ pub fn parse_u64_bitmap(value: &str) -> Result<u64, String> {
 #[derive(Parser, Debug)]
 #[command(author, version, about)]
 pub struct Args {
-    /// 256-bit AES key used for Marvel Rivals IoStore containers.
-    #[arg(short, long, default_value = RIVALS_AES_KEY)]
-    pub aes_key: retoc::AesKey,
+    /// 256-bit AES key used for Marvel Rivals IoStore containers. Defaults to the configured
+    /// key, see `keys`.
+    #[arg(short, long)]
+    pub aes_key: Option<retoc::AesKey>,
 
     /// Increase log verbosity.
     #[arg(short, long, global = true)]
     pub verbose: bool,
 
+    /// Print errors to stderr as one line of JSON with the error kind, exit code, message and
+    /// causes.
+    #[arg(long, global = true)]
+    pub json_errors: bool,
+
     #[command(subcommand)]
     pub command: Command,
 }
@@ -54,6 +64,14 @@ pub enum Command {
     PackDir(PackDirArgs),
     /// Patch raw assets in-place or rebuild installed IoStore mods with KawaiiPhysics porting.
     FixKawaiiPhysics(FixKawaiiPhysicsArgs),
+    /// List installed mods in load order or change a mod's load-order priority.
+    LoadOrder(LoadOrderArgs),
+    /// List or apply mod profiles saved by repak-gui.
+    Profile(ProfileArgs),
+    /// Complete or roll back repak-gui installs interrupted by a crash.
+    Recover(RecoverArgs),
+    /// Show the configured AES keys and where they come from.
+    Keys,
 }
 
 #[derive(Parser, Debug)]
@@ -84,6 +102,18 @@ pub struct UnpackArgs {
     pub full_iostore_check: bool,
 }
 
+impl UnpackArgs {
+    pub fn options(&self) -> UnpackOptions {
+        UnpackOptions {
+            output: self.output.clone(),
+            filters: self.filter.clone(),
+            game_paks_dir: self.game_paks_dir.clone(),
+            full_iostore_check: self.full_iostore_check,
+            verbose: self.verbose,
+        }
+    }
+}
+
 #[derive(Parser, Debug)]
 pub struct UnpackDirArgs {
     /// Directory to search for IoStore triples and legacy paks.
@@ -116,65 +146,51 @@ pub struct PackArgs {
     #[arg(required = true)]
     pub input: Vec<PathBuf>,
 
-    /// Output directory. Defaults to each input directory's parent.
-    #[arg(short, long)]
-    pub output: Option<PathBuf>,
-
-    /// Put each packed mod into a separate child directory below --output.
-    #[arg(long, alias = "per-mod-output-dir")]
-    pub separate_output_dirs: bool,
-
-    /// Mount point to write into the generated fake .pak.
-    #[arg(long, default_value = "../../../")]
-    pub mount_point: String,
-
-    /// Path hash seed to write into the generated fake .pak.
-    #[arg(long, default_value = "00000000")]
-    pub path_hash_seed: String,
-
-    /// Do not append the Marvel Rivals mod suffix to output names.
-    #[arg(long)]
-    pub no_mod_suffix: bool,
+    #[command(flatten)]
+    pub pack: PackOptionArgs,
+}
 
-    /// Obfuscate generated IoStore containers.
-    #[arg(long)]
-    pub obfuscate: bool,
+impl PackArgs {
+    pub fn options(&self) -> PackOptions {
+        self.pack.options()
+    }
+}
 
-    /// IoStore compression method.
-    #[arg(long, value_enum, default_value_t = CompressionArg::Oodle)]
-    pub compression: CompressionArg,
+#[derive(Parser, Debug)]
+pub struct PackDirArgs {
+    /// Directory containing raw mod folders, legacy paks, archives, or IoStore package triples.
+    pub input: PathBuf,
 
-    /// Port KawaiiPhysics assets while converting to IoStore.
-    #[arg(long)]
-    pub kawaii_physics: bool,
+    #[command(flatten)]
+    pub pack: PackOptionArgs,
 
-    /// USMAP used by KawaiiPhysics porting. If omitted, saved config is used, then the latest mapping is downloaded.
-    #[arg(long)]
-    pub kawaii_physics_usmap: Option<PathBuf>,
+    /// Number of mods to pack at the same time.
+    #[arg(short, long, default_value = "1")]
+    pub jobs: NonZeroUsize,
 
-    /// Patch LODInfo.DefaultHiddenMaterials from carrier data.
+    /// Keep packing the remaining mods after one fails instead of stopping at the first failure.
     #[arg(long)]
-    pub patch_default_hidden_mats: bool,
+    pub keep_going: bool,
 
-    /// Override LODInfo.DefaultHiddenMaterials using per-LOD integer bitmaps. Accepts comma-separated hex/decimal masks.
-    #[arg(long, value_parser = parse_u64_bitmap, value_delimiter = ',')]
-    pub default_hidden_material_bitmaps: Vec<u64>,
-
-    /// Game Paks directory used when repacking IoStore with dependencies.
-    #[arg(long)]
-    pub game_paks_dir: Option<PathBuf>,
-
-    /// Open all game IoStore containers instead of only selected fast-path containers.
-    #[arg(long)]
-    pub full_iostore_check: bool,
+    /// Also write the final report as JSON to this file.
+    #[arg(long, value_name = "FILE")]
+    pub report_json: Option<PathBuf>,
 }
 
-#[derive(Parser, Debug)]
-pub struct PackDirArgs {
-    /// Directory containing raw mod folders, legacy paks, archives, or IoStore package triples.
-    pub input: PathBuf,
+impl PackDirArgs {
+    pub fn options(&self) -> PackDirOptions {
+        PackDirOptions {
+            pack: self.pack.options(),
+            jobs: self.jobs,
+            keep_going: self.keep_going,
+        }
+    }
+}
 
-    /// Output directory. Defaults to the input directory's parent.
+/// Packing options shared by `pack` and `pack-dir`.
+#[derive(clap::Args, Debug)]
+pub struct PackOptionArgs {
+    /// Output directory. Defaults to each input's parent directory.
     #[arg(short, long)]
     pub output: Option<PathBuf>,
 
@@ -194,6 +210,10 @@ pub struct PackDirArgs {
     #[arg(long)]
     pub no_mod_suffix: bool,
 
+    /// Load-order priority written into output names as `_<N>_P`. Higher loads later and wins conflicting assets. Defaults to 9999999.
+    #[arg(long, conflicts_with = "no_mod_suffix", value_parser = clap::value_parser!(u32).range(..=MAX_MOD_PRIORITY as i64))]
+    pub priority: Option<u32>,
+
     /// Obfuscate generated IoStore containers.
     #[arg(long)]
     pub obfuscate: bool,
@@ -218,6 +238,11 @@ pub struct PackDirArgs {
     #[arg(long, value_parser = parse_u64_bitmap, value_delimiter = ',')]
     pub default_hidden_material_bitmaps: Vec<u64>,
 
+    /// Pack loose files IoStore can't carry (.ini, .json, .locres, .locmeta, .bnk, .wem) as real
+    /// entries of the companion .pak instead of converting them with the packages.
+    #[arg(long)]
+    pub loose_files_in_pak: bool,
+
     /// Game Paks directory used when repacking IoStore with dependencies. If omitted, saved GUI config is used.
     #[arg(long)]
     pub game_paks_dir: Option<PathBuf>,
@@ -225,6 +250,39 @@ pub struct PackDirArgs {
     /// Open all game IoStore containers instead of only selected fast-path containers.
     #[arg(long)]
     pub full_iostore_check: bool,
+
+    /// Option folder to pack from archives shipping mutually exclusive options, repeated for
+    /// several groups. Archives with options fail without one.
+    #[arg(long, value_name = "NAME")]
+    pub variant: Vec<String>,
+
+    /// Pack every option of archives shipping mutually exclusive options.
+    #[arg(long, conflicts_with = "variant")]
+    pub all_variants: bool,
+}
+
+impl PackOptionArgs {
+    pub fn options(&self) -> PackOptions {
+        PackOptions {
+            output: self.output.clone(),
+            separate_output_dirs: self.separate_output_dirs,
+            mount_point: self.mount_point.clone(),
+            path_hash_seed: self.path_hash_seed.clone(),
+            no_mod_suffix: self.no_mod_suffix,
+            priority: self.priority,
+            obfuscate: self.obfuscate,
+            compression: self.compression,
+            kawaii_physics: self.kawaii_physics,
+            kawaii_physics_usmap: self.kawaii_physics_usmap.clone(),
+            patch_default_hidden_mats: self.patch_default_hidden_mats,
+            default_hidden_material_bitmaps: self.default_hidden_material_bitmaps.clone(),
+            loose_files_in_pak: self.loose_files_in_pak,
+            game_paks_dir: self.game_paks_dir.clone(),
+            full_iostore_check: self.full_iostore_check,
+            variants: self.variant.clone(),
+            all_variants: self.all_variants,
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
@@ -240,6 +298,10 @@ pub enum CompressionArg {
 pub struct InfoArgs {
     /// Package path, archive, or directory.
     pub input: PathBuf,
+
+    /// Print info and mod classification as JSON.
+    #[arg(long)]
+    pub json: bool,
 }
 
 #[derive(Parser, Debug)]
@@ -277,3 +339,63 @@ pub struct FixKawaiiPhysicsArgs {
     #[arg(long, value_parser = parse_u64_bitmap, value_delimiter = ',')]
     pub default_hidden_material_bitmaps: Vec<u64>,
 }
+
+impl FixKawaiiPhysicsArgs {
+    pub fn options(&self) -> FixKawaiiOptions {
+        FixKawaiiOptions {
+            output: self.output.clone(),
+            usmap: self.usmap.clone(),
+            patch_default_hidden_mats: self.patch_default_hidden_mats,
+            default_hidden_material_bitmaps: self.default_hidden_material_bitmaps.clone(),
+        }
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct LoadOrderArgs {
+    /// Installed mods directory. If omitted, the saved GUI mods directory is used.
+    pub input: Option<PathBuf>,
+
+    /// Mod to re-prioritize, by file name with or without its `_<N>_P` suffix.
+    #[arg(long, requires = "priority")]
+    pub set: Option<String>,
+
+    /// New load-order priority for --set. Higher loads later and wins conflicting assets.
+    #[arg(long, requires = "set", value_parser = clap::value_parser!(u32).range(..=MAX_MOD_PRIORITY as i64))]
+    pub priority: Option<u32>,
+}
+
+#[derive(Parser, Debug)]
+pub struct ProfileArgs {
+    #[command(subcommand)]
+    pub command: ProfileCommand,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ProfileCommand {
+    /// List saved profiles.
+    List,
+    /// Enable every mod in a profile and disable the rest. Nothing changes if any rename fails.
+    Apply(ProfileApplyArgs),
+}
+
+#[derive(Parser, Debug)]
+pub struct ProfileApplyArgs {
+    /// Profile name.
+    pub name: String,
+
+    /// Installed mods directory. If omitted, the saved GUI mods directory is used.
+    #[arg(long)]
+    pub mods_dir: Option<PathBuf>,
+}
+
+#[derive(Parser, Debug)]
+pub struct RecoverArgs {
+    /// Installed mods directory. If omitted, the saved GUI mods directory is used.
+    #[arg(long)]
+    pub mods_dir: Option<PathBuf>,
+
+    /// Restore the previously installed files instead of completing interrupted installs.
+    #[arg(long)]
+    pub rollback: bool,
+}
@@ -1,3 +1,5 @@
+use crate::error::{Error, Result};
+use repak_manager::mod_profiles::ModProfile;
 use retoc::{Config, FGuid};
 use std::fs;
 use std::path::PathBuf;
@@ -8,6 +10,8 @@ pub struct CliState {
     pub game_path: PathBuf,
     pub game_chunk_path: Option<PathBuf>,
     pub kawaii_physics_usmap: Option<PathBuf>,
+    #[serde(default)]
+    pub mod_profiles: Vec<ModProfile>,
 }
 
 pub fn retoc_config(aes_key: retoc::AesKey) -> Arc<Config> {
@@ -19,15 +23,15 @@ pub fn retoc_config(aes_key: retoc::AesKey) -> Arc<Config> {
     Arc::new(config)
 }
 
-pub fn read_saved_state() -> Result<CliState, String> {
+pub fn read_saved_state() -> Result<CliState> {
     let config_path = cli_config_paths()
         .into_iter()
         .find(|path| path.exists())
         .ok_or_else(|| "Could not find saved repak-rivals state".to_string())?;
     let state = fs::read_to_string(&config_path)
-        .map_err(|e| format!("Failed to read {}: {e}", config_path.display()))?;
+        .map_err(|e| Error::io(format!("Failed to read {}", config_path.display()), e))?;
     serde_json::from_str(&state)
-        .map_err(|e| format!("Failed to parse {}: {e}", config_path.display()))
+        .map_err(|e| Error::Other(format!("Failed to parse {}: {e}", config_path.display())))
 }
 
 fn cli_config_paths() -> [PathBuf; 2] {
@@ -0,0 +1,297 @@
+use serde_json::{json, Value};
+use std::path::PathBuf;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Errors of every command. Each variant belongs to a category with its own process exit code,
+/// see [`Error::exit_code`]. Messages without a category of their own end up in
+/// [`Error::Other`].
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Input not found: {}", .0.display())]
+    InputNotFound(PathBuf),
+
+    #[error("Unsupported input: {}", .0.display())]
+    UnsupportedInput(PathBuf),
+
+    #[error(
+        "Missing IoStore companions for {}. Need .pak, .utoc, and .ucas with same stem",
+        .0.display()
+    )]
+    MissingCompanion(PathBuf),
+
+    #[error("Failed to load AES key")]
+    AesKey(#[source] BoxError),
+
+    #[error("Unsupported archive: {}", .0.display())]
+    UnsupportedArchive(PathBuf),
+
+    #[error("Failed to extract {}", path.display())]
+    Archive {
+        path: PathBuf,
+        #[source]
+        source: BoxError,
+    },
+
+    #[error("No packable mods found below {}", .0.display())]
+    NoMods(PathBuf),
+
+    /// An archive ships mutually exclusive options and `--variant` doesn't pick exactly one of
+    /// a group.
+    #[error(
+        "{} has options in {group}, pick one with --variant: {}",
+        path.display(),
+        options.join(", ")
+    )]
+    Variant {
+        path: PathBuf,
+        group: String,
+        options: Vec<String>,
+    },
+
+    #[error("{context}")]
+    Io {
+        context: String,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("{context}")]
+    Pak {
+        context: String,
+        #[source]
+        source: repak::Error,
+    },
+
+    /// retoc failed to read or write an IoStore container.
+    #[error("{context}")]
+    Container {
+        context: String,
+        #[source]
+        source: BoxError,
+    },
+
+    /// Downloading a mapping file failed.
+    #[error("{context}")]
+    Network {
+        context: String,
+        #[source]
+        source: reqwest::Error,
+    },
+
+    #[error("{failed} of {total} mods failed")]
+    BatchFailed { failed: usize, total: usize },
+
+    #[error("{0}")]
+    Other(String),
+}
+
+impl Error {
+    pub fn io(context: impl Into<String>, source: std::io::Error) -> Self {
+        Self::Io {
+            context: context.into(),
+            source,
+        }
+    }
+
+    pub fn pak(context: impl Into<String>, source: repak::Error) -> Self {
+        Self::Pak {
+            context: context.into(),
+            source,
+        }
+    }
+
+    pub fn container(context: impl Into<String>, source: impl Into<BoxError>) -> Self {
+        Self::Container {
+            context: context.into(),
+            source: source.into(),
+        }
+    }
+
+    pub fn network(context: impl Into<String>, source: reqwest::Error) -> Self {
+        Self::Network {
+            context: context.into(),
+            source,
+        }
+    }
+
+    pub fn archive(path: impl Into<PathBuf>, source: impl Into<BoxError>) -> Self {
+        Self::Archive {
+            path: path.into(),
+            source: source.into(),
+        }
+    }
+
+    /// Category name used in `--json-errors` output.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Error::InputNotFound(_) => "input-not-found",
+            Error::UnsupportedInput(_) => "unsupported-input",
+            Error::MissingCompanion(_) => "missing-companion",
+            Error::AesKey(_) => "aes-key",
+            Error::UnsupportedArchive(_) => "unsupported-archive",
+            Error::Archive { .. } => "archive",
+            Error::NoMods(_) => "no-mods",
+            Error::Variant { .. } => "variant",
+            Error::Io { .. } => "io",
+            Error::Pak { .. } => "pak",
+            Error::Container { .. } => "container",
+            Error::Network { .. } => "network",
+            Error::BatchFailed { .. } => "batch-failed",
+            Error::Other(_) => "other",
+        }
+    }
+
+    /// Process exit code of the error's category. 2 is left to clap's usage errors.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::Other(_) => 1,
+            Error::InputNotFound(_) | Error::UnsupportedInput(_) | Error::MissingCompanion(_) => 3,
+            Error::AesKey(_) => 4,
+            Error::UnsupportedArchive(_) | Error::Archive { .. } => 5,
+            Error::NoMods(_) => 6,
+            Error::Io { .. } => 7,
+            Error::Pak { .. } | Error::Container { .. } => 8,
+            Error::BatchFailed { .. } => 9,
+            Error::Network { .. } => 10,
+            Error::Variant { .. } => 11,
+        }
+    }
+
+    /// The messages of the error's sources, outermost first.
+    pub fn causes(&self) -> Vec<String> {
+        let mut causes = Vec::new();
+        let mut source = std::error::Error::source(self);
+        while let Some(error) = source {
+            causes.push(error.to_string());
+            source = error.source();
+        }
+        causes
+    }
+
+    /// The error and its sources on one line.
+    pub fn report(&self) -> String {
+        std::iter::once(self.to_string())
+            .chain(self.causes())
+            .collect::<Vec<_>>()
+            .join(": ")
+    }
+
+    pub fn to_json(&self) -> Value {
+        let path = match self {
+            Error::InputNotFound(path)
+            | Error::UnsupportedInput(path)
+            | Error::MissingCompanion(path)
+            | Error::UnsupportedArchive(path)
+            | Error::Archive { path, .. }
+            | Error::NoMods(path)
+            | Error::Variant { path, .. } => Some(path.display().to_string()),
+            _ => None,
+        };
+        json!({
+            "kind": self.kind(),
+            "exit_code": self.exit_code(),
+            "message": self.to_string(),
+            "causes": self.causes(),
+            "path": path,
+        })
+    }
+}
+
+impl From<String> for Error {
+    fn from(message: String) -> Self {
+        Self::Other(message)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn io_error() -> std::io::Error {
+        std::io::Error::new(std::io::ErrorKind::NotFound, "no such file")
+    }
+
+    #[test]
+    fn test_exit_codes() {
+        let path = || PathBuf::from("mods/Foo");
+        let cases = [
+            (Error::Other("failed".into()), "other", 1),
+            (Error::InputNotFound(path()), "input-not-found", 3),
+            (Error::UnsupportedInput(path()), "unsupported-input", 3),
+            (Error::MissingCompanion(path()), "missing-companion", 3),
+            (Error::AesKey("bad key".into()), "aes-key", 4),
+            (Error::UnsupportedArchive(path()), "unsupported-archive", 5),
+            (Error::archive(path(), io_error()), "archive", 5),
+            (Error::NoMods(path()), "no-mods", 6),
+            (Error::io("Failed to read", io_error()), "io", 7),
+            (
+                Error::container("Failed to write", "bad toc"),
+                "container",
+                8,
+            ),
+            (
+                Error::pak("Failed to open", repak::Error::Io(io_error())),
+                "pak",
+                8,
+            ),
+            (
+                Error::BatchFailed {
+                    failed: 1,
+                    total: 2,
+                },
+                "batch-failed",
+                9,
+            ),
+            (
+                Error::Variant {
+                    path: path(),
+                    group: "Hair".into(),
+                    options: vec!["Long".into(), "Short".into()],
+                },
+                "variant",
+                11,
+            ),
+        ];
+        for (error, kind, exit_code) in cases {
+            assert_eq!(error.kind(), kind, "{error:?}");
+            assert_eq!(error.exit_code(), exit_code, "{error:?}");
+        }
+    }
+
+    #[test]
+    fn test_to_json() {
+        let error = Error::archive("mods/Foo.zip", io_error());
+        assert_eq!(
+            error.to_json(),
+            json!({
+                "kind": "archive",
+                "exit_code": 5,
+                "message": "Failed to extract mods/Foo.zip",
+                "causes": ["no such file"],
+                "path": "mods/Foo.zip",
+            })
+        );
+        assert_eq!(
+            error.report(),
+            "Failed to extract mods/Foo.zip: no such file"
+        );
+
+        let error = Error::BatchFailed {
+            failed: 1,
+            total: 3,
+        };
+        assert_eq!(
+            error.to_json(),
+            json!({
+                "kind": "batch-failed",
+                "exit_code": 9,
+                "message": "1 of 3 mods failed",
+                "causes": [],
+                "path": null,
+            })
+        );
+    }
+}
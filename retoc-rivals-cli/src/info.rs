@@ -1,17 +1,30 @@
 use crate::archive;
 use crate::cli::InfoArgs;
 use crate::config::retoc_config;
+use crate::error::{Error, Result};
 use crate::source::{classify_path, IoStorePackage, PackageSource};
-use crate::util::pak_aes_key;
+use crate::util::pak_reader_builder;
+use repak_manager::classification::{classify_files, ModClassification};
+use retoc::{action_manifest, ActionManifest};
+use serde_json::{json, Value};
 use std::fs::File;
 use std::io::BufReader;
 use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
 
-pub fn info(aes_key: retoc::AesKey, args: InfoArgs) -> Result<(), String> {
-    print_source_info(&aes_key, classify_path(&args.input)?)
+pub fn info(aes_key: retoc::AesKey, args: InfoArgs) -> Result<()> {
+    let source = classify_path(&args.input)?;
+    if args.json {
+        let value = source_json(&aes_key, source)?;
+        let json = serde_json::to_string_pretty(&value)
+            .map_err(|e| Error::Other(format!("Failed to serialize info JSON: {e}")))?;
+        println!("{json}");
+        return Ok(());
+    }
+    print_source_info(&aes_key, source)
 }
 
-fn print_source_info(aes_key: &retoc::AesKey, source: PackageSource) -> Result<(), String> {
+fn print_source_info(aes_key: &retoc::AesKey, source: PackageSource) -> Result<()> {
     match source {
         PackageSource::IoStore(package) => print_iostore_info(aes_key, &package),
         PackageSource::LegacyPak(path) => print_legacy_pak_info(&path),
@@ -53,24 +66,20 @@ fn print_source_info(aes_key: &retoc::AesKey, source: PackageSource) -> Result<(
     }
 }
 
-fn print_iostore_info(aes_key: &retoc::AesKey, package: &IoStorePackage) -> Result<(), String> {
+fn print_iostore_info(aes_key: &retoc::AesKey, package: &IoStorePackage) -> Result<()> {
     println!("type: iostore");
     println!("pak: {}", package.pak.display());
     println!("utoc: {}", package.utoc.display());
     println!("ucas: {}", package.ucas.display());
     let store = retoc::open_iostore(&package.utoc, retoc_config(aes_key.clone()))
-        .map_err(|e| format!("Failed to open {}: {e}", package.utoc.display()))?;
+        .map_err(|e| Error::container(format!("Failed to open {}", package.utoc.display()), e))?;
     store.print_info(0);
+    print_classification(&classify_files(&iostore_package_names(aes_key, package)?));
     Ok(())
 }
 
-fn print_legacy_pak_info(path: &Path) -> Result<(), String> {
-    let file = File::open(path).map_err(|e| format!("Failed to open {}: {e}", path.display()))?;
-    let mut reader = BufReader::new(file);
-    let pak = repak::PakBuilder::new()
-        .key(pak_aes_key()?.0)
-        .reader(&mut reader)
-        .map_err(|e| format!("Failed to read legacy pak {}: {e}", path.display()))?;
+fn print_legacy_pak_info(path: &Path) -> Result<()> {
+    let pak = open_legacy_pak(path)?;
 
     println!("type: legacy-pak");
     println!("path: {}", path.display());
@@ -90,9 +99,132 @@ fn print_legacy_pak_info(path: &Path) -> Result<(), String> {
             .unwrap_or_else(|| "<none>".to_string())
     );
     println!("files: {}", pak.files().len());
+    print_classification(&classify_files(&pak.files()));
     Ok(())
 }
 
+fn print_classification(classification: &ModClassification) {
+    println!("category: {}", classification.category());
+    println!("mod type: {}", classification.characteristic());
+    println!("changes: {}", classification.change());
+}
+
+fn open_legacy_pak(path: &Path) -> Result<repak::PakReader> {
+    let file =
+        File::open(path).map_err(|e| Error::io(format!("Failed to open {}", path.display()), e))?;
+    pak_reader_builder()
+        .reader(&mut BufReader::new(file))
+        .map_err(|e| Error::pak(format!("Failed to read legacy pak {}", path.display()), e))
+}
+
+fn iostore_package_names(aes_key: &retoc::AesKey, package: &IoStorePackage) -> Result<Vec<String>> {
+    let manifest = action_manifest(
+        ActionManifest::new(package.utoc.clone()),
+        retoc_config(aes_key.clone()),
+    )
+    .map_err(|e| Error::container(format!("Manifest failed for {}", package.utoc.display()), e))?;
+    Ok(manifest
+        .oplog
+        .entries
+        .iter()
+        .map(|entry| entry.packagestoreentry.packagename.clone())
+        .collect())
+}
+
+/// Paths of the files below a raw directory, relative to it.
+fn raw_directory_files(root: &Path) -> Vec<String> {
+    WalkDir::new(root)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| {
+            let relative = entry.path().strip_prefix(root).ok()?;
+            Some(relative.to_string_lossy().replace('\\', "/"))
+        })
+        .collect()
+}
+
+fn classification_json(files: &[String]) -> Value {
+    let classification = classify_files(files);
+    json!({
+        "category": classification.category().to_string(),
+        "characteristic": classification.characteristic(),
+        "change": classification.change(),
+        "heroes": classification.heroes,
+        "skins": classification.skins,
+        "kinds": classification.kinds,
+        "replaced": classification.replaced,
+        "added": classification.added,
+    })
+}
+
+fn source_json(aes_key: &retoc::AesKey, source: PackageSource) -> Result<Value> {
+    match source {
+        PackageSource::IoStore(package) => {
+            let packages = iostore_package_names(aes_key, &package)?;
+            Ok(json!({
+                "type": "iostore",
+                "pak": package.pak,
+                "utoc": package.utoc,
+                "ucas": package.ucas,
+                "packages": packages.len(),
+                "classification": classification_json(&packages),
+            }))
+        }
+        PackageSource::LegacyPak(path) => {
+            let pak = open_legacy_pak(&path)?;
+            let files = pak.files();
+            Ok(json!({
+                "type": "legacy-pak",
+                "path": path,
+                "version": format!("{:?}", pak.version()),
+                "mount_point": pak.mount_point(),
+                "encrypted_index": pak.encrypted_index(),
+                "files": files.len(),
+                "classification": classification_json(&files),
+            }))
+        }
+        PackageSource::RawDirectory(path) => {
+            let files = raw_directory_files(&path);
+            Ok(json!({
+                "type": "raw-directory",
+                "path": path,
+                "files": files.len(),
+                "classification": classification_json(&files),
+            }))
+        }
+        PackageSource::Archive(path) => {
+            let temp = archive::extract_archive(&path)?;
+            let root = archive_payload_root(temp.path());
+            Ok(json!({
+                "type": "archive",
+                "path": path,
+                "payload": source_json(aes_key, classify_path(&root)?)?,
+            }))
+        }
+        PackageSource::DirectoryPackages {
+            root,
+            iostore,
+            legacy_paks,
+            archives,
+        } => {
+            let mut packages = Vec::new();
+            for package in iostore {
+                packages.push(source_json(aes_key, PackageSource::IoStore(package))?);
+            }
+            for pak in legacy_paks {
+                packages.push(source_json(aes_key, PackageSource::LegacyPak(pak))?);
+            }
+            Ok(json!({
+                "type": "package-directory",
+                "path": root,
+                "packages": packages,
+                "archives": archives,
+            }))
+        }
+    }
+}
+
 fn archive_payload_root(root: &Path) -> PathBuf {
     let Ok(entries) = std::fs::read_dir(root) else {
         return root.to_path_buf();
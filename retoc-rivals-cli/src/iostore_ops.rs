@@ -1,6 +1,10 @@
 use crate::config::{read_saved_state, retoc_config};
+use crate::error::{Error, Result};
 use crate::source::IoStorePackage;
-use crate::util::ensure_mod_name_suffix;
+use crate::util::output_mod_name;
+use repak_manager::install_mod::install_mod_logic::iotoc::{
+    build_to_legacy_filter, should_open_fast_game_container,
+};
 use retoc::{action_manifest, action_to_legacy_batch, ActionManifest, ActionToLegacyBatch};
 use retoc::{ActionToLegacyBatchItem, Config};
 use std::collections::HashSet;
@@ -14,18 +18,22 @@ pub struct ExtractedPackage {
     pub output: PathBuf,
 }
 
-pub fn manifest_filter(aes_key: &retoc::AesKey, utoc: &Path) -> Result<Vec<String>, String> {
-    build_to_legacy_filter(utoc.to_path_buf(), retoc_config(aes_key.clone()))
+pub fn manifest_filter(aes_key: &retoc::AesKey, utoc: &Path) -> Result<Vec<String>> {
+    to_legacy_filter(utoc, retoc_config(aes_key.clone()))
 }
 
-pub fn manifest_value(aes_key: &retoc::AesKey, utoc: &Path) -> Result<serde_json::Value, String> {
+pub fn manifest_value(aes_key: &retoc::AesKey, utoc: &Path) -> Result<serde_json::Value> {
     let manifest = action_manifest(
         ActionManifest::new(utoc.to_path_buf()),
         retoc_config(aes_key.clone()),
     )
-    .map_err(|e| format!("Manifest failed for {}: {e}", utoc.display()))?;
-    serde_json::to_value(manifest)
-        .map_err(|e| format!("Failed to serialize manifest for {}: {e}", utoc.display()))
+    .map_err(|e| Error::container(format!("Manifest failed for {}", utoc.display()), e))?;
+    serde_json::to_value(manifest).map_err(|e| {
+        Error::Other(format!(
+            "Failed to serialize manifest for {}: {e}",
+            utoc.display()
+        ))
+    })
 }
 
 pub fn to_legacy_single(
@@ -36,7 +44,7 @@ pub fn to_legacy_single(
     game_paks_dir: Option<&Path>,
     full_iostore_check: bool,
     verbose: bool,
-) -> Result<ExtractedPackage, String> {
+) -> Result<ExtractedPackage> {
     let mut extracted = to_legacy_outputs(
         aes_key,
         std::slice::from_ref(package),
@@ -48,7 +56,7 @@ pub fn to_legacy_single(
     )?;
     extracted
         .pop()
-        .ok_or_else(|| "IoStore extraction produced no output".to_string())
+        .ok_or_else(|| Error::Other("IoStore extraction produced no output".to_string()))
 }
 
 pub fn to_legacy_prefixed(
@@ -59,7 +67,7 @@ pub fn to_legacy_prefixed(
     game_paks_dir: Option<&Path>,
     full_iostore_check: bool,
     verbose: bool,
-) -> Result<Vec<ExtractedPackage>, String> {
+) -> Result<Vec<ExtractedPackage>> {
     let outputs = packages
         .iter()
         .map(|package| {
@@ -90,7 +98,7 @@ pub fn to_legacy_under_root(
     game_paks_dir: Option<&Path>,
     full_iostore_check: bool,
     verbose: bool,
-) -> Result<Vec<ExtractedPackage>, String> {
+) -> Result<Vec<ExtractedPackage>> {
     let outputs = packages
         .iter()
         .map(|package| output_root.join(package.stem()))
@@ -115,9 +123,11 @@ pub fn to_legacy_outputs(
     game_paks_dir: Option<&Path>,
     full_iostore_check: bool,
     verbose: bool,
-) -> Result<Vec<ExtractedPackage>, String> {
+) -> Result<Vec<ExtractedPackage>> {
     if packages.len() != outputs.len() {
-        return Err("IoStore package/output count mismatch".to_string());
+        return Err(Error::Other(
+            "IoStore package/output count mismatch".to_string(),
+        ));
     }
     if packages.is_empty() {
         return Ok(Vec::new());
@@ -128,12 +138,12 @@ pub fn to_legacy_outputs(
     let mut extracted = Vec::with_capacity(packages.len());
     for (package, output) in packages.iter().zip(outputs) {
         let item_filter = if filters.is_empty() {
-            build_to_legacy_filter(package.utoc.clone(), config.clone())?
+            to_legacy_filter(&package.utoc, config.clone())?
         } else {
             filters.to_vec()
         };
         fs::create_dir_all(&output)
-            .map_err(|e| format!("Failed to create {}: {e}", output.display()))?;
+            .map_err(|e| Error::io(format!("Failed to create {}", output.display()), e))?;
         items.push(ActionToLegacyBatchItem {
             inputs: vec![package.utoc.clone()],
             output: output.clone(),
@@ -168,7 +178,7 @@ pub fn to_legacy_outputs(
         },
         config,
     )
-    .map_err(|e| format!("IoStore to-legacy extraction failed: {e}"))?;
+    .map_err(|e| Error::container("IoStore to-legacy extraction failed", e))?;
 
     Ok(extracted)
 }
@@ -177,14 +187,11 @@ pub fn copy_iostore_package(
     package: &IoStorePackage,
     output_dir: &Path,
     no_mod_suffix: bool,
-) -> Result<PathBuf, String> {
+    priority: Option<u32>,
+) -> Result<PathBuf> {
     fs::create_dir_all(output_dir)
-        .map_err(|e| format!("Failed to create {}: {e}", output_dir.display()))?;
-    let name = if no_mod_suffix {
-        package.stem()
-    } else {
-        ensure_mod_name_suffix(&package.stem())
-    };
+        .map_err(|e| Error::io(format!("Failed to create {}", output_dir.display()), e))?;
+    let name = output_mod_name(&package.stem(), no_mod_suffix, priority);
 
     for (src, ext) in [
         (&package.pak, "pak"),
@@ -195,14 +202,18 @@ pub fn copy_iostore_package(
         if same_file(src, &dst) {
             continue;
         }
-        fs::copy(src, &dst)
-            .map_err(|e| format!("Failed to copy {} to {}: {e}", src.display(), dst.display()))?;
+        fs::copy(src, &dst).map_err(|e| {
+            Error::io(
+                format!("Failed to copy {} to {}", src.display(), dst.display()),
+                e,
+            )
+        })?;
     }
 
     Ok(output_dir.join(format!("{name}.utoc")))
 }
 
-pub fn resolve_game_paks_dir(arg: &Option<PathBuf>) -> Result<Option<PathBuf>, String> {
+pub fn resolve_game_paks_dir(arg: &Option<PathBuf>) -> Result<Option<PathBuf>> {
     if let Some(path) = arg {
         return Ok(Some(path.clone()));
     }
@@ -213,53 +224,16 @@ pub fn resolve_game_paks_dir(arg: &Option<PathBuf>) -> Result<Option<PathBuf>, S
     }
 }
 
-fn build_to_legacy_filter(utoc_path: PathBuf, config: Arc<Config>) -> Result<Vec<String>, String> {
-    let manifest = action_manifest(ActionManifest::new(utoc_path.clone()), config)
-        .map_err(|e| format!("Failed to build filter for {}: {e}", utoc_path.display()))?;
-    let mut set = HashSet::new();
-
-    for entry in &manifest.oplog.entries {
-        let Some(package_data) = entry.packagedata.first() else {
-            continue;
-        };
-        if let Some(filter_path) = resolve_package_filter_path(package_data.filename.trim()) {
-            set.insert(filter_path);
-        }
-    }
-
-    let mut filters = set.into_iter().collect::<Vec<_>>();
-    filters.sort();
-    Ok(filters)
-}
-
-fn resolve_package_filter_path(package_name: &str) -> Option<String> {
-    let package_name = package_name.trim().replace('\\', "/");
-    if package_name.is_empty() {
-        return None;
-    }
-    if package_name.starts_with("../../../") {
-        return Some(package_name);
-    }
-    if let Some(rest) = package_name.strip_prefix("/Game/") {
-        return Some(format!("../../../Marvel/Content/{rest}"));
-    }
-    if package_name == "/Game" {
-        return Some("../../../Marvel/Content".to_string());
-    }
-    if let Some(rest) = package_name.strip_prefix("/Engine/") {
-        return Some(format!("../../../Engine/Content/{rest}"));
-    }
-    if package_name == "/Engine" {
-        return Some("../../../Engine/Content".to_string());
-    }
-    Some(package_name.trim_start_matches('/').to_string())
+fn to_legacy_filter(utoc_path: &Path, config: Arc<Config>) -> Result<Vec<String>> {
+    build_to_legacy_filter(utoc_path, config)
+        .map_err(|e| Error::container("Failed to build to-legacy filter", e))
 }
 
 fn collect_to_legacy_inputs(
     selected_utocs: impl IntoIterator<Item = PathBuf>,
     game_paks_dir: Option<&Path>,
     full_iostore_check: bool,
-) -> Result<Vec<PathBuf>, String> {
+) -> Result<Vec<PathBuf>> {
     let mut inputs = Vec::new();
     let mut seen = HashSet::new();
 
@@ -270,42 +244,44 @@ fn collect_to_legacy_inputs(
     }
 
     if full_iostore_check && game_paks_dir.is_none() {
-        return Err(
+        return Err(Error::Other(
             "--full-iostore-check requires --game-paks-dir or saved GUI config".to_string(),
-        );
+        ));
     }
 
     let Some(game_paks_dir) = game_paks_dir else {
         return Ok(inputs);
     };
 
+    for path in game_containers(game_paks_dir, full_iostore_check)? {
+        if seen.insert(path.clone()) {
+            inputs.push(path);
+        }
+    }
+
+    Ok(inputs)
+}
+
+/// Game `.utoc` containers to open: every container with `full_iostore_check`, otherwise only
+/// `global` and the character pakchunks.
+pub fn game_containers(game_paks_dir: &Path, full_iostore_check: bool) -> Result<Vec<PathBuf>> {
+    let mut containers = Vec::new();
     for entry in fs::read_dir(game_paks_dir)
-        .map_err(|e| format!("Failed to scan {}: {e}", game_paks_dir.display()))?
+        .map_err(|e| Error::io(format!("Failed to scan {}", game_paks_dir.display()), e))?
     {
         let path = entry
-            .map_err(|e| format!("Failed to read {}: {e}", game_paks_dir.display()))?
+            .map_err(|e| Error::io(format!("Failed to read {}", game_paks_dir.display()), e))?
             .path();
         let is_utoc = path
             .extension()
             .and_then(|ext| ext.to_str())
             .is_some_and(|ext| ext.eq_ignore_ascii_case("utoc"));
-        if is_utoc
-            && (full_iostore_check || should_open_fast_game_container(&path))
-            && seen.insert(path.clone())
-        {
-            inputs.push(path);
+        if is_utoc && (full_iostore_check || should_open_fast_game_container(&path)) {
+            containers.push(path);
         }
     }
-
-    Ok(inputs)
-}
-
-fn should_open_fast_game_container(path: &Path) -> bool {
-    let Some(stem) = path.file_stem().and_then(|stem| stem.to_str()) else {
-        return false;
-    };
-    let stem = stem.to_ascii_lowercase();
-    stem == "global" || (stem.starts_with("pakchunk") && stem.contains("character"))
+    containers.sort();
+    Ok(containers)
 }
 
 fn same_file(left: &Path, right: &Path) -> bool {
@@ -3,6 +3,7 @@ use std::{
     path::{Path, PathBuf},
 };
 
+use crate::error::{Error, Result};
 use path_clean::PathClean;
 use serde::Deserialize;
 use tracing::info;
@@ -50,17 +51,17 @@ fn usmap_build_from_path(path: &Path) -> Option<u64> {
         .and_then(usmap_build_from_name)
 }
 
-fn query_latest_depot_usmap() -> Result<DepotUsmap, String> {
+fn query_latest_depot_usmap() -> Result<DepotUsmap> {
     let client = reqwest::blocking::Client::new();
     let entries = client
         .get(RIVALS_USMAP_API_URL)
         .header("User-Agent", RIVALS_USMAP_USER_AGENT)
         .send()
-        .map_err(|e| format!("Failed to query rivals-depot usmaps: {e}"))?
+        .map_err(|e| Error::network("Failed to query rivals-depot usmaps", e))?
         .error_for_status()
-        .map_err(|e| format!("rivals-depot usmap query failed: {e}"))?
+        .map_err(|e| Error::network("rivals-depot usmap query failed", e))?
         .text()
-        .map_err(|e| format!("Failed to read rivals-depot usmap response: {e}"))?;
+        .map_err(|e| Error::network("Failed to read rivals-depot usmap response", e))?;
 
     let entries = serde_json::from_str::<Vec<GithubContentEntry>>(&entries)
         .map_err(|e| format!("Failed to parse rivals-depot usmap response: {e}"))?;
@@ -78,13 +79,15 @@ fn query_latest_depot_usmap() -> Result<DepotUsmap, String> {
             })
         })
         .max_by_key(|entry| entry.build)
-        .ok_or_else(|| "rivals-depot did not return any usable .usmap files".to_string())
+        .ok_or_else(|| {
+            Error::Other("rivals-depot did not return any usable .usmap files".to_string())
+        })
 }
 
-fn download_depot_usmap(usmap: &DepotUsmap) -> Result<PathBuf, String> {
+fn download_depot_usmap(usmap: &DepotUsmap) -> Result<PathBuf> {
     let usmap_dir = PathBuf::from("usmap");
     fs::create_dir_all(&usmap_dir)
-        .map_err(|e| format!("Failed to create {}: {e}", usmap_dir.display()))?;
+        .map_err(|e| Error::io(format!("Failed to create {}", usmap_dir.display()), e))?;
 
     let output_path = usmap_dir.join(&usmap.name);
     if output_path.exists() {
@@ -96,18 +99,18 @@ fn download_depot_usmap(usmap: &DepotUsmap) -> Result<PathBuf, String> {
         .get(&usmap.download_url)
         .header("User-Agent", RIVALS_USMAP_USER_AGENT)
         .send()
-        .map_err(|e| format!("Failed to download {}: {e}", usmap.name))?
+        .map_err(|e| Error::network(format!("Failed to download {}", usmap.name), e))?
         .error_for_status()
-        .map_err(|e| format!("Download failed for {}: {e}", usmap.name))?
+        .map_err(|e| Error::network(format!("Download failed for {}", usmap.name), e))?
         .bytes()
-        .map_err(|e| format!("Failed to read {} download: {e}", usmap.name))?;
+        .map_err(|e| Error::network(format!("Failed to read {} download", usmap.name), e))?;
 
     fs::write(&output_path, bytes)
-        .map_err(|e| format!("Failed to write {}: {e}", output_path.display()))?;
+        .map_err(|e| Error::io(format!("Failed to write {}", output_path.display()), e))?;
     Ok(output_path.clean())
 }
 
-fn latest_depot_usmap_path(current: Option<&Path>) -> Result<Option<PathBuf>, String> {
+fn latest_depot_usmap_path(current: Option<&Path>) -> Result<Option<PathBuf>> {
     let latest = query_latest_depot_usmap()?;
     let current_build = current.and_then(usmap_build_from_path);
     if current.is_some_and(Path::exists) && current_build.is_some_and(|build| build >= latest.build)
@@ -125,12 +128,12 @@ fn latest_depot_usmap_path(current: Option<&Path>) -> Result<Option<PathBuf>, St
     Ok(Some(path))
 }
 
-pub fn resolve_kawaii_usmap(current: Option<&Path>) -> Result<PathBuf, String> {
+pub fn resolve_kawaii_usmap(current: Option<&Path>) -> Result<PathBuf> {
     if let Some(path) = latest_depot_usmap_path(current)? {
         return Ok(path);
     }
 
-    current
-        .map(Path::to_path_buf)
-        .ok_or_else(|| "No KawaiiPhysics USMAP was provided or downloadable".to_string())
+    current.map(Path::to_path_buf).ok_or_else(|| {
+        Error::Other("No KawaiiPhysics USMAP was provided or downloadable".to_string())
+    })
 }
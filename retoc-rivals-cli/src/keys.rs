@@ -0,0 +1,23 @@
+use crate::error::Result;
+use repak_manager::keys::{keys_path, load, AES_KEY_ENV};
+
+pub fn keys(aes_key_arg: bool) -> Result<()> {
+    let (config, errors) = load();
+    let path = keys_path();
+    if path.exists() {
+        println!("Config file: {}", path.display());
+    } else {
+        println!("Config file: {} (not created)", path.display());
+    }
+    println!("Environment override: {AES_KEY_ENV}");
+    for error in &errors {
+        println!("  skipped {error}");
+    }
+    for (name, key, source) in config.entries() {
+        println!("  {name} = {key} ({source})");
+    }
+    if aes_key_arg {
+        println!("--aes-key replaces the IoStore key for this run");
+    }
+    Ok(())
+}
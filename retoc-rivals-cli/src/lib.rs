@@ -0,0 +1,39 @@
+//! Extracting and building Marvel Rivals IoStore mods, shared by the `retoc-rivals-cli` binary
+//! and repak-gui.
+//!
+//! [`classify_path`] tells what an input is, [`pack_inputs`], [`pack_directory`] and
+//! [`unpack_inputs`] convert it with the settings of an option struct, reporting per-item
+//! [`Progress`] to a callback. The remaining modules hold the other CLI commands, each taking
+//! its parsed [`cli`] arguments.
+
+pub mod archive;
+pub mod cli;
+pub mod config;
+pub mod error;
+pub mod info;
+pub mod iostore_ops;
+pub mod kawaii_utils;
+pub mod keys;
+pub mod legacy;
+pub mod load_order;
+pub mod manifest;
+pub mod pack;
+pub mod pack_report;
+pub mod profile;
+pub mod progress;
+pub mod recover;
+pub mod source;
+pub mod unpack;
+pub mod util;
+
+pub use error::{Error, Result};
+pub use legacy::{installed_iostore_packages, rebuild_installed_mods, FixKawaiiOptions};
+pub use pack::{pack_directory, pack_inputs, PackDirOptions, PackOptions};
+pub use pack_report::PackReport;
+pub use progress::{Progress, ProgressFn};
+pub use repak_manager::load_order::DEFAULT_MOD_PRIORITY;
+pub use source::{classify_path, IoStorePackage, PackageSource};
+pub use unpack::{unpack_inputs, UnpackOptions};
+
+/// Largest priority the game accepts before its pak order overflows.
+pub const MAX_MOD_PRIORITY: u32 = 21_000_000;
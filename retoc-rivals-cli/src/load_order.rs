@@ -0,0 +1,70 @@
+use crate::cli::LoadOrderArgs;
+use crate::config::read_saved_state;
+use crate::error::{Error, Result};
+use crate::util::migrate_legacy_disabled_mods;
+use repak_manager::installed::scan_installed_mods;
+use repak_manager::load_order::{load_order_key, mod_base_name, mod_priority, rename_mod_priority};
+use std::path::{Path, PathBuf};
+
+pub fn load_order(args: LoadOrderArgs) -> Result<()> {
+    let mods_dir = match args.input {
+        Some(dir) => dir,
+        None => read_saved_state()?.game_path,
+    };
+    if !mods_dir.is_dir() {
+        return Err(Error::InputNotFound(mods_dir));
+    }
+    migrate_legacy_disabled_mods(&mods_dir);
+
+    if let (Some(name), Some(priority)) = (args.set.as_deref(), args.priority) {
+        let pak = find_installed_mod(&mods_dir, name)?;
+        let renamed = rename_mod_priority(&pak, priority)
+            .map_err(|e| Error::io(format!("Failed to rename {}", pak.display()), e))?;
+        println!("Renamed {} to {}", pak.display(), renamed.display());
+    }
+
+    let mut mods = scan_installed_mods(&mods_dir);
+    mods.sort_by_cached_key(|installed| load_order_key(&installed.path));
+    println!(
+        "Load order for {} (later entries win conflicting assets):",
+        mods_dir.display()
+    );
+    for (idx, installed) in mods.iter().enumerate() {
+        let stem = file_stem(&installed.path);
+        let priority = mod_priority(stem)
+            .map(|priority| priority.to_string())
+            .unwrap_or_else(|| "-".to_string());
+        let state = if installed.enabled { "" } else { " (disabled)" };
+        println!(
+            "{:>4}. {priority:>10}  {}{state}",
+            idx + 1,
+            mod_base_name(stem)
+        );
+    }
+    Ok(())
+}
+
+fn find_installed_mod(mods_dir: &Path, name: &str) -> Result<PathBuf> {
+    let base_name = mod_base_name(name);
+    let matches = scan_installed_mods(mods_dir)
+        .into_iter()
+        .map(|installed| installed.path)
+        .filter(|path| mod_base_name(file_stem(path)).eq_ignore_ascii_case(base_name))
+        .collect::<Vec<_>>();
+    match matches.as_slice() {
+        [path] => Ok(path.clone()),
+        [] => Err(Error::Other(format!(
+            "No installed mod named {base_name} in {}",
+            mods_dir.display()
+        ))),
+        _ => Err(Error::Other(format!(
+            "More than one installed mod is named {base_name}; rename one of them first"
+        ))),
+    }
+}
+
+fn file_stem(path: &Path) -> &str {
+    path.file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or_default()
+}
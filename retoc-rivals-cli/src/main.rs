@@ -1,21 +1,8 @@
-mod archive;
-mod cli;
-mod config;
-mod info;
-mod iostore_ops;
-mod kawaii_utils;
-mod legacy;
-mod manifest;
-mod pack;
-mod source;
-mod unpack;
-mod util;
-
 use clap::Parser;
-use cli::{Args, Command};
-
-pub const RIVALS_AES_KEY: &str = "0C263D8C22DCB085894899C3A3796383E9BF9DE0CBFB08C9BF2DEF2E84F29D74";
-pub const MOD_NAME_SUFFIX: &str = "_9999999_P";
+use retoc_rivals_cli::cli::{Args, Command};
+use retoc_rivals_cli::{
+    info, keys, legacy, load_order, manifest, pack, profile, recover, unpack, util, Result,
+};
 
 fn main() {
     let args = Args::parse();
@@ -26,22 +13,33 @@ fn main() {
     };
     init_tracing(verbosity);
 
+    let json_errors = args.json_errors;
     if let Err(error) = run(args) {
-        tracing::error!("{error}");
-        eprintln!("{error}");
-        std::process::exit(1);
+        tracing::error!("{}", error.report());
+        if json_errors {
+            eprintln!("{}", error.to_json());
+        } else {
+            eprintln!("{}", error.report());
+        }
+        std::process::exit(error.exit_code());
     }
 }
 
-fn run(args: Args) -> Result<(), String> {
+fn run(args: Args) -> Result<()> {
+    // commands without containers to read work even when the key configuration is broken
+    let aes_key = || util::iostore_aes_key(args.aes_key.clone());
     match args.command {
-        Command::Info(command) => info::info(args.aes_key, command),
-        Command::Manifest(command) => manifest::manifest(args.aes_key, command),
-        Command::Unpack(command) => unpack::unpack(args.aes_key, command),
-        Command::UnpackDir(command) => unpack::unpack_dir(args.aes_key, command),
-        Command::Pack(command) => pack::pack(args.aes_key, command),
-        Command::PackDir(command) => pack::pack_dir(args.aes_key, command),
-        Command::FixKawaiiPhysics(command) => legacy::fix_kawaii_physics(args.aes_key, command),
+        Command::Info(command) => info::info(aes_key()?, command),
+        Command::Manifest(command) => manifest::manifest(aes_key()?, command),
+        Command::Unpack(command) => unpack::unpack(aes_key()?, command),
+        Command::UnpackDir(command) => unpack::unpack_dir(aes_key()?, command),
+        Command::Pack(command) => pack::pack(aes_key()?, command),
+        Command::PackDir(command) => pack::pack_dir(aes_key()?, command),
+        Command::FixKawaiiPhysics(command) => legacy::fix_kawaii_physics(aes_key()?, command),
+        Command::LoadOrder(command) => load_order::load_order(command),
+        Command::Profile(command) => profile::profile(command),
+        Command::Recover(command) => recover::recover(command),
+        Command::Keys => keys::keys(args.aes_key.is_some()),
     }
 }
 
@@ -1,12 +1,13 @@
 use crate::archive;
 use crate::cli::ManifestArgs;
+use crate::error::{Error, Result};
 use crate::iostore_ops;
 use crate::source::{classify_path, IoStorePackage, PackageSource};
 use serde_json::json;
 use std::fs;
 use std::path::Path;
 
-pub fn manifest(aes_key: retoc::AesKey, args: ManifestArgs) -> Result<(), String> {
+pub fn manifest(aes_key: retoc::AesKey, args: ManifestArgs) -> Result<()> {
     let content = match classify_path(&args.input)? {
         PackageSource::IoStore(package) => emit_manifest(&aes_key, &[package], args.filters)?,
         PackageSource::DirectoryPackages { iostore, .. } => {
@@ -23,24 +24,24 @@ pub fn manifest(aes_key: retoc::AesKey, args: ManifestArgs) -> Result<(), String
                     emit_manifest(&aes_key, &iostore, args.filters)?
                 }
                 other => {
-                    return Err(format!(
+                    return Err(Error::Other(format!(
                         "Archive did not contain IoStore packages: {}",
                         source_kind(&other)
-                    ));
+                    )));
                 }
             }
         }
         other => {
-            return Err(format!(
+            return Err(Error::Other(format!(
                 "Manifest requires IoStore package input, got {}",
                 source_kind(&other)
-            ));
+            )));
         }
     };
 
     if let Some(output) = args.output {
         fs::write(&output, content)
-            .map_err(|e| format!("Failed to write {}: {e}", output.display()))?;
+            .map_err(|e| Error::io(format!("Failed to write {}", output.display()), e))?;
     } else {
         println!("{content}");
     }
@@ -52,9 +53,9 @@ fn emit_manifest(
     aes_key: &retoc::AesKey,
     packages: &[IoStorePackage],
     filters: bool,
-) -> Result<String, String> {
+) -> Result<String> {
     if packages.is_empty() {
-        return Err("No IoStore packages found".to_string());
+        return Err(Error::Other("No IoStore packages found".to_string()));
     }
 
     if filters {
@@ -71,7 +72,7 @@ fn emit_manifest(
     if packages.len() == 1 {
         let value = iostore_ops::manifest_value(aes_key, &packages[0].utoc)?;
         return serde_json::to_string_pretty(&value)
-            .map_err(|e| format!("Failed to serialize manifest JSON: {e}"));
+            .map_err(|e| Error::Other(format!("Failed to serialize manifest JSON: {e}")));
     }
 
     let mut manifests = Vec::new();
@@ -82,7 +83,7 @@ fn emit_manifest(
         }));
     }
     serde_json::to_string_pretty(&manifests)
-        .map_err(|e| format!("Failed to serialize manifest JSON: {e}"))
+        .map_err(|e| Error::Other(format!("Failed to serialize manifest JSON: {e}")))
 }
 
 fn archive_payload_root(root: &Path) -> std::path::PathBuf {
@@ -1,97 +1,218 @@
 use crate::archive;
-use crate::cli::{PackArgs, PackDirArgs};
+use crate::cli::{CompressionArg, PackArgs, PackDirArgs};
 use crate::config::read_saved_state;
+use crate::error::{Error, Result};
 use crate::iostore_ops;
 use crate::kawaii_utils;
+use crate::pack_report::{JobReport, JobStatus, PackReport};
+use crate::progress::{no_progress, print_started, Progress, ProgressFn};
 use crate::source::{classify_path, scan_directory_packages, IoStorePackage, PackageSource};
 use crate::unpack::unpack_legacy_pak_to_dir;
 use crate::util::{
-    collect_files, ensure_mod_name_suffix, pak_aes_key, parse_path_hash_seed, repak_compression,
+    collect_files, output_mod_name, pak_aes_key, parse_path_hash_seed, repak_compression,
     retoc_compression,
 };
+use repak_manager::archive::ArchivePayload;
 use retoc::{action_to_zen, ActionToZen, Config, EngineVersion, FGuid};
+use std::any::Any;
+use std::collections::HashMap;
 use std::fs::{self, File};
 use std::io::BufWriter;
+use std::num::NonZeroUsize;
+use std::panic::{self, AssertUnwindSafe};
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Instant;
 use tempfile::TempDir;
 
+/// Settings shared by every mod of a pack run. The defaults match the CLI's.
+#[derive(Clone, Debug)]
+pub struct PackOptions {
+    /// Output directory. Defaults to the parent of each input.
+    pub output: Option<PathBuf>,
+    /// Put each packed mod into its own child directory of the output directory.
+    pub separate_output_dirs: bool,
+    /// Mount point of the generated `.pak`.
+    pub mount_point: String,
+    /// Path hash seed of the generated `.pak`, as hex.
+    pub path_hash_seed: String,
+    /// Leave the `_<N>_P` mod suffix off output names.
+    pub no_mod_suffix: bool,
+    /// Load-order priority written into output names, [`crate::DEFAULT_MOD_PRIORITY`] if unset.
+    pub priority: Option<u32>,
+    pub obfuscate: bool,
+    pub compression: CompressionArg,
+    /// Port KawaiiPhysics assets while converting to IoStore.
+    pub kawaii_physics: bool,
+    /// USMAP for KawaiiPhysics porting. Falls back to the saved config, then the latest
+    /// downloaded mapping.
+    pub kawaii_physics_usmap: Option<PathBuf>,
+    pub patch_default_hidden_mats: bool,
+    pub default_hidden_material_bitmaps: Vec<u64>,
+    /// Write loose files IoStore can't carry as real entries of the companion `.pak`.
+    pub loose_files_in_pak: bool,
+    /// Game Paks directory for repacking IoStore mods. Falls back to the saved config.
+    pub game_paks_dir: Option<PathBuf>,
+    /// Open every game container instead of the fast-path ones.
+    pub full_iostore_check: bool,
+    /// Option folders to pack from archives shipping mutually exclusive options, one per group.
+    pub variants: Vec<String>,
+    /// Pack every option of such archives instead.
+    pub all_variants: bool,
+}
+
+impl Default for PackOptions {
+    fn default() -> Self {
+        Self {
+            output: None,
+            separate_output_dirs: false,
+            mount_point: "../../../".to_string(),
+            path_hash_seed: "00000000".to_string(),
+            no_mod_suffix: false,
+            priority: None,
+            obfuscate: false,
+            compression: CompressionArg::Oodle,
+            kawaii_physics: false,
+            kawaii_physics_usmap: None,
+            patch_default_hidden_mats: false,
+            default_hidden_material_bitmaps: Vec::new(),
+            loose_files_in_pak: false,
+            game_paks_dir: None,
+            full_iostore_check: false,
+            variants: Vec::new(),
+            all_variants: false,
+        }
+    }
+}
+
+/// Settings of [`pack_directory`].
+#[derive(Clone, Debug)]
+pub struct PackDirOptions {
+    pub pack: PackOptions,
+    /// Number of mods packed at the same time.
+    pub jobs: NonZeroUsize,
+    /// Keep packing the remaining mods after one fails instead of skipping them.
+    pub keep_going: bool,
+}
+
+impl Default for PackDirOptions {
+    fn default() -> Self {
+        Self {
+            pack: PackOptions::default(),
+            jobs: NonZeroUsize::MIN,
+            keep_going: false,
+        }
+    }
+}
+
 struct ExtractedArchive {
     _temp: TempDir,
-    source: PackageSource,
-    source_name: String,
+    iostore: Vec<IoStorePackage>,
+    legacy_paks: Vec<PathBuf>,
+    raw_dirs: Vec<(PathBuf, String)>,
+}
+
+pub fn pack(aes_key: retoc::AesKey, args: PackArgs) -> Result<()> {
+    pack_inputs(&aes_key, &args.input, &args.options(), &no_progress)?;
+    Ok(())
 }
 
-pub fn pack(aes_key: retoc::AesKey, mut args: PackArgs) -> Result<(), String> {
-    if args.kawaii_physics || should_patch_default_hidden_mats(&args) {
-        args.kawaii_physics_usmap = Some(resolve_pack_usmap(args.kawaii_physics_usmap.as_deref())?);
+pub fn pack_dir(aes_key: retoc::AesKey, args: PackDirArgs) -> Result<()> {
+    let report = pack_directory(
+        &aes_key,
+        &args.input,
+        &args.options(),
+        &print_started("Packing"),
+    )?;
+    report.print_table();
+    if let Some(path) = &args.report_json {
+        report.write_json(path)?;
     }
 
-    let game_paks_dir = iostore_ops::resolve_game_paks_dir(&args.game_paks_dir)?;
-    for input in &args.input {
+    if report.failed > 0 {
+        return Err(Error::BatchFailed {
+            failed: report.failed,
+            total: report.items.len(),
+        });
+    }
+    Ok(())
+}
+
+/// Packs each input, whatever [`classify_path`] finds it to be, and returns the written
+/// `.utoc` files, or `.pak` files for mods without IoStore containers.
+pub fn pack_inputs(
+    aes_key: &retoc::AesKey,
+    inputs: &[PathBuf],
+    options: &PackOptions,
+    progress: ProgressFn,
+) -> Result<Vec<PathBuf>> {
+    let options = &with_resolved_usmap(options)?;
+    let game_paks_dir = iostore_ops::resolve_game_paks_dir(&options.game_paks_dir)?;
+    let mut outputs = Vec::new();
+    for (index, input) in inputs.iter().enumerate() {
+        let total = inputs.len();
+        progress(Progress::Started {
+            index,
+            total,
+            input,
+        });
         tracing::info!(input = %input.display(), "Classifying pack input");
-        let source = classify_path(input)?;
         let default_output = input
             .parent()
             .unwrap_or_else(|| Path::new("."))
             .to_path_buf();
-        pack_source(
-            &aes_key,
-            &args,
-            source,
-            input_stem(input),
-            &default_output,
-            game_paks_dir.as_deref(),
-        )?;
+        let result = classify_path(input).and_then(|source| {
+            pack_source(
+                aes_key,
+                options,
+                source,
+                input_stem(input),
+                &default_output,
+                game_paks_dir.as_deref(),
+            )
+        });
+        progress(Progress::Finished {
+            index,
+            total,
+            input,
+            ok: result.is_ok(),
+        });
+        outputs.extend(result?);
     }
-    Ok(())
+    Ok(outputs)
 }
 
-pub fn pack_dir(aes_key: retoc::AesKey, args: PackDirArgs) -> Result<(), String> {
-    if !args.input.is_dir() {
-        return Err(format!(
-            "Input is not a directory: {}",
-            args.input.display()
-        ));
+/// Packs every mod found below `root` independently, see `pack-dir`. Failed items are
+/// recorded in the report rather than returned as errors.
+pub fn pack_directory(
+    aes_key: &retoc::AesKey,
+    root: &Path,
+    options: &PackDirOptions,
+    progress: ProgressFn,
+) -> Result<PackReport> {
+    if !root.is_dir() {
+        return Err(Error::UnsupportedInput(root.to_path_buf()));
     }
 
-    let mut pack_args = PackArgs {
-        input: Vec::new(),
-        output: args.output.clone(),
-        separate_output_dirs: args.separate_output_dirs,
-        mount_point: args.mount_point,
-        path_hash_seed: args.path_hash_seed,
-        no_mod_suffix: args.no_mod_suffix,
-        obfuscate: args.obfuscate,
-        compression: args.compression,
-        kawaii_physics: args.kawaii_physics,
-        kawaii_physics_usmap: args.kawaii_physics_usmap,
-        patch_default_hidden_mats: args.patch_default_hidden_mats,
-        default_hidden_material_bitmaps: args.default_hidden_material_bitmaps,
-        game_paks_dir: args.game_paks_dir,
-        full_iostore_check: args.full_iostore_check,
+    let options = &PackDirOptions {
+        pack: with_resolved_usmap(&options.pack)?,
+        ..options.clone()
     };
-    if pack_args.kawaii_physics || should_patch_default_hidden_mats(&pack_args) {
-        pack_args.kawaii_physics_usmap = Some(resolve_pack_usmap(
-            pack_args.kawaii_physics_usmap.as_deref(),
-        )?);
-    }
-
-    let output_dir = pack_args.output.clone().unwrap_or_else(|| {
-        args.input
-            .parent()
+    let default_output = options.pack.output.clone().unwrap_or_else(|| {
+        root.parent()
             .unwrap_or_else(|| Path::new("."))
             .to_path_buf()
     });
-    let default_output = output_dir.clone();
-    let game_paks_dir = iostore_ops::resolve_game_paks_dir(&pack_args.game_paks_dir)?;
-    tracing::info!(input = %args.input.display(), "Scanning mixed pack directory");
+    let game_paks_dir = iostore_ops::resolve_game_paks_dir(&options.pack.game_paks_dir)?;
+    tracing::info!(input = %root.display(), "Scanning mixed pack directory");
     println!(
         "Scanning {} for mods, packages, and archives",
-        args.input.display()
+        root.display()
     );
-    let (iostore, legacy_paks, archives) = scan_directory_packages(&args.input);
-    let raw_dirs = scan_raw_mod_dirs(&args.input);
+    let (iostore, legacy_paks, archives) = scan_directory_packages(root);
+    let raw_dirs = scan_raw_mod_dirs(root);
     let raw_roots = raw_dirs
         .iter()
         .map(|path| path.as_path())
@@ -119,39 +240,247 @@ pub fn pack_dir(aes_key: retoc::AesKey, args: PackDirArgs) -> Result<(), String>
     );
     let item_count = raw_dirs.len() + iostore.len() + legacy_paks.len() + archives.len();
     if item_count == 0 {
-        return Err(format!(
-            "No packable mods found below {}",
-            args.input.display()
-        ));
+        return Err(Error::NoMods(root.to_path_buf()));
     }
 
-    println!(
-        "Found {item_count} packable mods below {}",
-        args.input.display()
-    );
-    for raw_dir in &raw_dirs {
-        pack_raw_dir(
-            &aes_key,
-            &pack_args,
-            raw_dir,
-            &input_stem(raw_dir),
-            &default_output,
-        )?;
+    println!("Found {item_count} packable mods below {}", root.display());
+    let jobs = raw_dirs
+        .into_iter()
+        .map(PackJob::RawDir)
+        .chain(iostore.into_iter().map(PackJob::IoStore))
+        .chain(legacy_paks.into_iter().map(PackJob::LegacyPak))
+        .chain(archives.into_iter().map(PackJob::Archive))
+        .collect::<Vec<_>>();
+    let workers = options.jobs.get().min(jobs.len());
+    if workers > 1 {
+        println!("Packing with {workers} jobs");
     }
-    pack_discovered_items(
-        &aes_key,
-        &pack_args,
-        iostore,
-        legacy_paks,
-        archives,
+
+    let started = Instant::now();
+    let reports = run_pack_jobs(
+        aes_key,
+        options,
+        &jobs,
+        workers,
         &default_output,
         game_paks_dir.as_deref(),
-    )?;
+        progress,
+    );
+    Ok(PackReport::new(root, workers, started.elapsed(), reports))
+}
 
-    Ok(())
+/// One item found by `pack-dir`, packed independently of the others.
+enum PackJob {
+    RawDir(PathBuf),
+    IoStore(IoStorePackage),
+    LegacyPak(PathBuf),
+    Archive(PathBuf),
+}
+
+impl PackJob {
+    fn input(&self) -> &Path {
+        match self {
+            PackJob::RawDir(path) | PackJob::LegacyPak(path) | PackJob::Archive(path) => path,
+            PackJob::IoStore(package) => &package.utoc,
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        match self {
+            PackJob::RawDir(_) => "raw-directory",
+            PackJob::IoStore(_) => "iostore",
+            PackJob::LegacyPak(_) => "legacy-pak",
+            PackJob::Archive(_) => "archive",
+        }
+    }
+}
+
+/// Packs `jobs` on `workers` threads. Items writing the same output run one at a time in their
+/// listed order, see [`job_chains`]. Without `keep_going`, items not yet started when one fails
+/// are reported as skipped.
+fn run_pack_jobs(
+    aes_key: &retoc::AesKey,
+    options: &PackDirOptions,
+    jobs: &[PackJob],
+    workers: usize,
+    default_output: &Path,
+    game_paks_dir: Option<&Path>,
+    progress: ProgressFn,
+) -> Vec<JobReport> {
+    let stop = AtomicBool::new(false);
+    let reports = Mutex::new(
+        jobs.iter()
+            .map(|job| JobReport::new(job.input(), job.kind()))
+            .collect::<Vec<_>>(),
+    );
+    let run_chains = |chains: &[Vec<usize>], workers: usize| {
+        let next = AtomicUsize::new(0);
+        thread::scope(|scope| {
+            for _ in 0..workers.max(1) {
+                scope.spawn(|| {
+                    while let Some(chain) = chains.get(next.fetch_add(1, Ordering::SeqCst)) {
+                        for &index in chain {
+                            if stop.load(Ordering::SeqCst) {
+                                break;
+                            }
+                            let job = &jobs[index];
+                            let (total, input) = (jobs.len(), job.input());
+                            progress(Progress::Started {
+                                index,
+                                total,
+                                input,
+                            });
+                            let report = run_pack_job(
+                                aes_key,
+                                &options.pack,
+                                job,
+                                default_output,
+                                game_paks_dir,
+                            );
+                            progress(Progress::Finished {
+                                index,
+                                total,
+                                input,
+                                ok: report.status == JobStatus::Ok,
+                            });
+                            if report.status == JobStatus::Failed && !options.keep_going {
+                                stop.store(true, Ordering::SeqCst);
+                            }
+                            reports.lock().unwrap()[index] = report;
+                        }
+                    }
+                });
+            }
+        });
+    };
+
+    let (chains, archives) = job_chains(&options.pack, jobs, default_output);
+    run_chains(&chains, workers);
+    // archive outputs are only known once extracted, so archives run after everything else
+    if !archives.is_empty() {
+        run_chains(&[archives], 1);
+    }
+    reports.into_inner().unwrap()
+}
+
+/// Groups the indices of `jobs` into chains whose items write the same output, each in listed
+/// order, so a chain packs one item at a time and the last one listed is the one kept. Archives
+/// are returned separately since their outputs are unknown before extraction.
+fn job_chains(
+    options: &PackOptions,
+    jobs: &[PackJob],
+    default_output: &Path,
+) -> (Vec<Vec<usize>>, Vec<usize>) {
+    let mut chains = Vec::<Vec<usize>>::new();
+    let mut chain_of_output = HashMap::<String, usize>::new();
+    let mut archives = Vec::new();
+    for (index, job) in jobs.iter().enumerate() {
+        let name = match job {
+            PackJob::RawDir(path) | PackJob::LegacyPak(path) => input_stem(path),
+            PackJob::IoStore(package) => package.stem(),
+            PackJob::Archive(_) => {
+                archives.push(index);
+                continue;
+            }
+        };
+        let output = output_dir_for(options, default_output, &name).join(output_mod_name(
+            &name,
+            options.no_mod_suffix,
+            options.priority,
+        ));
+        // Windows treats names differing only in case as the same file
+        let output = output.to_string_lossy().to_lowercase();
+        match chain_of_output.get(&output) {
+            Some(&chain) => chains[chain].push(index),
+            None => {
+                chain_of_output.insert(output, chains.len());
+                chains.push(vec![index]);
+            }
+        }
+    }
+    (chains, archives)
+}
+
+fn run_pack_job(
+    aes_key: &retoc::AesKey,
+    options: &PackOptions,
+    job: &PackJob,
+    default_output: &Path,
+    game_paks_dir: Option<&Path>,
+) -> JobReport {
+    let mut report = JobReport::new(job.input(), job.kind());
+    tracing::info!(input = %job.input().display(), kind = job.kind(), "Packing item");
+    let started = Instant::now();
+    // retoc panics on some malformed packages, which must not take the other items down
+    let result = panic::catch_unwind(AssertUnwindSafe(|| match job {
+        PackJob::RawDir(path) => {
+            pack_raw_dir(aes_key, options, path, &input_stem(path), default_output)
+                .map(|out| vec![out])
+        }
+        PackJob::IoStore(package) => {
+            pack_iostore_package(aes_key, options, package, default_output, game_paks_dir)
+                .map(|out| vec![out])
+        }
+        PackJob::LegacyPak(path) => {
+            repack_legacy_pak(aes_key, options, path, default_output).map(|out| vec![out])
+        }
+        PackJob::Archive(path) => pack_discovered_items(
+            aes_key,
+            options,
+            Vec::new(),
+            Vec::new(),
+            vec![path.clone()],
+            default_output,
+            game_paks_dir,
+        ),
+    }))
+    .unwrap_or_else(|payload| {
+        Err(Error::Other(format!(
+            "panicked: {}",
+            panic_message(&*payload)
+        )))
+    });
+    report.duration_ms = started.elapsed().as_millis() as u64;
+
+    match result {
+        Ok(outputs) => {
+            if outputs.is_empty() {
+                report
+                    .warnings
+                    .push("nothing packable found in archive".to_string());
+            }
+            report.status = JobStatus::Ok;
+            report.outputs = outputs;
+        }
+        Err(e) => {
+            eprintln!("Failed to pack {}: {}", job.input().display(), e.report());
+            report.status = JobStatus::Failed;
+            report.error_kind = Some(e.kind());
+            report.error = Some(e.report());
+        }
+    }
+    report
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string())
 }
 
-fn resolve_pack_usmap(current: Option<&Path>) -> Result<PathBuf, String> {
+/// `options` with the KawaiiPhysics USMAP resolved when porting or patching needs one.
+fn with_resolved_usmap(options: &PackOptions) -> Result<PackOptions> {
+    let mut options = options.clone();
+    if options.kawaii_physics || should_patch_default_hidden_mats(&options) {
+        options.kawaii_physics_usmap =
+            Some(resolve_pack_usmap(options.kawaii_physics_usmap.as_deref())?);
+    }
+    Ok(options)
+}
+
+fn resolve_pack_usmap(current: Option<&Path>) -> Result<PathBuf> {
     let saved_usmap = if current.is_none() {
         read_saved_state()
             .ok()
@@ -164,19 +493,22 @@ fn resolve_pack_usmap(current: Option<&Path>) -> Result<PathBuf, String> {
 
 fn pack_source(
     aes_key: &retoc::AesKey,
-    args: &PackArgs,
+    options: &PackOptions,
     source: PackageSource,
     source_name: String,
     default_output: &Path,
     game_paks_dir: Option<&Path>,
-) -> Result<(), String> {
+) -> Result<Vec<PathBuf>> {
     match source {
         PackageSource::RawDirectory(path) => {
-            pack_raw_dir(aes_key, args, &path, &source_name, default_output)
+            pack_raw_dir(aes_key, options, &path, &source_name, default_output).map(|out| vec![out])
+        }
+        PackageSource::LegacyPak(path) => {
+            repack_legacy_pak(aes_key, options, &path, default_output).map(|out| vec![out])
         }
-        PackageSource::LegacyPak(path) => repack_legacy_pak(aes_key, args, &path, default_output),
         PackageSource::IoStore(package) => {
-            pack_iostore_package(aes_key, args, &package, default_output, game_paks_dir)
+            pack_iostore_package(aes_key, options, &package, default_output, game_paks_dir)
+                .map(|out| vec![out])
         }
         PackageSource::DirectoryPackages {
             root,
@@ -191,14 +523,14 @@ fn pack_source(
                 archives = archives.len(),
                 "Packing discovered directory packages"
             );
-            let output_dir = args.output.clone().unwrap_or_else(|| {
+            let output_dir = options.output.clone().unwrap_or_else(|| {
                 root.parent()
                     .unwrap_or_else(|| Path::new("."))
                     .to_path_buf()
             });
             pack_discovered_items(
                 aes_key,
-                args,
+                options,
                 iostore,
                 legacy_paks,
                 archives,
@@ -208,13 +540,12 @@ fn pack_source(
         }
         PackageSource::Archive(path) => {
             tracing::info!(archive = %path.display(), "Packing archive input");
-            let temp = archive::extract_archive(&path)?;
-            let root = archive_payload_root(temp.path());
-            pack_source(
+            pack_discovered_items(
                 aes_key,
-                args,
-                classify_path(&root)?,
-                input_stem(&path),
+                options,
+                Vec::new(),
+                Vec::new(),
+                vec![path],
                 default_output,
                 game_paks_dir,
             )
@@ -224,13 +555,13 @@ fn pack_source(
 
 fn pack_discovered_items(
     aes_key: &retoc::AesKey,
-    args: &PackArgs,
+    options: &PackOptions,
     mut iostore: Vec<IoStorePackage>,
     mut legacy_paks: Vec<PathBuf>,
     archives: Vec<PathBuf>,
     default_output: &Path,
     game_paks_dir: Option<&Path>,
-) -> Result<(), String> {
+) -> Result<Vec<PathBuf>> {
     let mut archive_sources = Vec::new();
     let mut archive_raw_dirs = Vec::new();
     if !archives.is_empty() {
@@ -242,14 +573,10 @@ fn pack_discovered_items(
     }
     for archive in archives {
         println!("Extracting archive {}", archive.display());
-        let extracted = extract_archive_source(&archive)?;
-        collect_archive_source(
-            &extracted.source,
-            &extracted.source_name,
-            &mut iostore,
-            &mut legacy_paks,
-            &mut archive_raw_dirs,
-        );
+        let extracted = extract_archive_source(&archive, options)?;
+        iostore.extend(extracted.iostore.iter().cloned());
+        legacy_paks.extend(extracted.legacy_paks.iter().cloned());
+        archive_raw_dirs.extend(extracted.raw_dirs.iter().cloned());
         tracing::debug!(
             archive = %archive.display(),
             iostore = iostore.len(),
@@ -260,83 +587,94 @@ fn pack_discovered_items(
         archive_sources.push(extracted);
     }
 
-    pack_iostore_packages(aes_key, args, &iostore, default_output, game_paks_dir)?;
+    let mut outputs =
+        pack_iostore_packages(aes_key, options, &iostore, default_output, game_paks_dir)?;
     for pak in &legacy_paks {
-        repack_legacy_pak(aes_key, args, pak, default_output)?;
+        outputs.push(repack_legacy_pak(aes_key, options, pak, default_output)?);
     }
     for (path, name) in &archive_raw_dirs {
-        pack_raw_dir(aes_key, args, path, name, default_output)?;
+        outputs.push(pack_raw_dir(aes_key, options, path, name, default_output)?);
     }
 
     drop(archive_sources);
-    Ok(())
+    Ok(outputs)
 }
 
-fn extract_archive_source(path: &Path) -> Result<ExtractedArchive, String> {
-    let temp = archive::extract_archive(path)?;
-    let root = archive_payload_root(temp.path());
-    let source = classify_path(&root)?;
+/// Extracts the mods found in an archive's listing, see [`archive::extract_archive_payloads`].
+fn extract_archive_source(path: &Path, options: &PackOptions) -> Result<ExtractedArchive> {
+    let variants = (!options.all_variants).then_some(options.variants.as_slice());
+    let (temp, payloads) = archive::extract_archive_payloads(path, variants)?;
+    let mut iostore = Vec::new();
+    let mut legacy_paks = Vec::new();
+    let mut raw_dirs = Vec::new();
+    let names = repak_manager::archive::payload_mod_names(path, &payloads);
+    for (payload, name) in payloads.iter().zip(names) {
+        println!("  found {}", payload_label(payload));
+        let extracted_path = payload.extracted_path(temp.path());
+        match payload {
+            ArchivePayload::IoStore { utoc, ucas, .. } => iostore.push(IoStorePackage {
+                pak: extracted_path,
+                utoc: temp.path().join(payload.destination(utoc)),
+                ucas: temp.path().join(payload.destination(ucas)),
+            }),
+            ArchivePayload::Pak(_) => legacy_paks.push(extracted_path),
+            ArchivePayload::Loose { .. } => raw_dirs.push((extracted_path, name)),
+        }
+    }
     Ok(ExtractedArchive {
         _temp: temp,
-        source,
-        source_name: input_stem(path),
+        iostore,
+        legacy_paks,
+        raw_dirs,
     })
 }
 
-fn collect_archive_source(
-    source: &PackageSource,
-    source_name: &str,
-    iostore: &mut Vec<IoStorePackage>,
-    legacy_paks: &mut Vec<PathBuf>,
-    raw_dirs: &mut Vec<(PathBuf, String)>,
-) {
-    match source {
-        PackageSource::IoStore(package) => iostore.push(package.clone()),
-        PackageSource::LegacyPak(path) => legacy_paks.push(path.clone()),
-        PackageSource::RawDirectory(path) => raw_dirs.push((path.clone(), source_name.to_string())),
-        PackageSource::DirectoryPackages {
-            iostore: packages,
-            legacy_paks: paks,
-            archives: _,
-            ..
-        } => {
-            iostore.extend(packages.iter().cloned());
-            legacy_paks.extend(paks.iter().cloned());
+fn payload_label(payload: &ArchivePayload) -> String {
+    match payload {
+        ArchivePayload::IoStore { pak, .. } => format!("IoStore mod {}", pak.display()),
+        ArchivePayload::Pak(pak) => format!("pak mod {}", pak.display()),
+        ArchivePayload::Loose { root, files } => {
+            format!("{} loose files in {}/", files.len(), root.display())
         }
-        PackageSource::Archive(_) => {}
     }
 }
 
 fn pack_iostore_packages(
     aes_key: &retoc::AesKey,
-    args: &PackArgs,
+    options: &PackOptions,
     packages: &[IoStorePackage],
     default_output: &Path,
     game_paks_dir: Option<&Path>,
-) -> Result<(), String> {
+) -> Result<Vec<PathBuf>> {
     if packages.is_empty() {
-        return Ok(());
+        return Ok(Vec::new());
     }
 
-    let base_output_dir = args
+    let base_output_dir = options
         .output
         .clone()
         .unwrap_or_else(|| default_output.to_path_buf());
 
-    if !should_repack_iostore(args) {
+    if !should_repack_iostore(options) {
+        let mut outputs = Vec::with_capacity(packages.len());
         for package in packages {
-            let output_dir = output_dir_for(args, default_output, &package.stem());
-            let output =
-                iostore_ops::copy_iostore_package(package, &output_dir, args.no_mod_suffix)?;
+            let output_dir = output_dir_for(options, default_output, &package.stem());
+            let output = iostore_ops::copy_iostore_package(
+                package,
+                &output_dir,
+                options.no_mod_suffix,
+                options.priority,
+            )?;
             println!("Installed IoStore package to {}", output.display());
+            outputs.push(output);
         }
-        return Ok(());
+        return Ok(outputs);
     }
 
     let game_paks_dir = game_paks_dir.ok_or_else(|| {
-        "Game Paks directory is required when repacking IoStore mods with --obfuscate, non-default --compression, or --kawaii-physics. Pass --game-paks-dir or open repak-gui once so its saved config can be used.".to_string()
+        Error::Other("Game Paks directory is required when repacking IoStore mods with --obfuscate, non-default --compression, or --kawaii-physics. Pass --game-paks-dir or open repak-gui once so its saved config can be used.".to_string())
     })?;
-    let temp = tempfile::tempdir().map_err(|e| format!("Failed to create temp dir: {e}"))?;
+    let temp = tempfile::tempdir().map_err(|e| Error::io("Failed to create temp dir", e))?;
     let outputs = packages
         .iter()
         .map(|package| temp.path().join(package.stem()))
@@ -347,41 +685,49 @@ fn pack_iostore_packages(
         outputs,
         &[],
         Some(game_paks_dir),
-        args.full_iostore_check,
+        options.full_iostore_check,
         true,
     )?;
 
-    for (package, extracted) in packages.iter().zip(extracted) {
-        pack_raw_dir(
-            aes_key,
-            args,
-            &extracted.output,
-            &package.stem(),
-            &base_output_dir,
-        )?;
-    }
-    Ok(())
+    packages
+        .iter()
+        .zip(extracted)
+        .map(|(package, extracted)| {
+            pack_raw_dir(
+                aes_key,
+                options,
+                &extracted.output,
+                &package.stem(),
+                &base_output_dir,
+            )
+        })
+        .collect()
 }
 
 fn pack_iostore_package(
     aes_key: &retoc::AesKey,
-    args: &PackArgs,
+    options: &PackOptions,
     package: &IoStorePackage,
     default_output: &Path,
     game_paks_dir: Option<&Path>,
-) -> Result<(), String> {
-    if !should_repack_iostore(args) {
-        let output_dir = output_dir_for(args, default_output, &package.stem());
-        let output = iostore_ops::copy_iostore_package(package, &output_dir, args.no_mod_suffix)?;
+) -> Result<PathBuf> {
+    if !should_repack_iostore(options) {
+        let output_dir = output_dir_for(options, default_output, &package.stem());
+        let output = iostore_ops::copy_iostore_package(
+            package,
+            &output_dir,
+            options.no_mod_suffix,
+            options.priority,
+        )?;
         println!("Installed IoStore package to {}", output.display());
-        return Ok(());
+        return Ok(output);
     }
 
     let game_paks_dir = game_paks_dir.ok_or_else(|| {
-        "Game Paks directory is required when repacking IoStore mods with --obfuscate, non-default --compression, or --kawaii-physics. Pass --game-paks-dir or open repak-gui once so its saved config can be used.".to_string()
+        Error::Other("Game Paks directory is required when repacking IoStore mods with --obfuscate, non-default --compression, or --kawaii-physics. Pass --game-paks-dir or open repak-gui once so its saved config can be used.".to_string())
     })?;
 
-    let temp = tempfile::tempdir().map_err(|e| format!("Failed to create temp dir: {e}"))?;
+    let temp = tempfile::tempdir().map_err(|e| Error::io("Failed to create temp dir", e))?;
     let extracted_dir = temp.path().join(package.stem());
     iostore_ops::to_legacy_single(
         aes_key,
@@ -389,12 +735,12 @@ fn pack_iostore_package(
         &extracted_dir,
         &[],
         Some(game_paks_dir),
-        args.full_iostore_check,
+        options.full_iostore_check,
         true,
     )?;
     pack_raw_dir(
         aes_key,
-        args,
+        options,
         &extracted_dir,
         &package.stem(),
         default_output,
@@ -403,15 +749,15 @@ fn pack_iostore_package(
 
 fn repack_legacy_pak(
     aes_key: &retoc::AesKey,
-    args: &PackArgs,
+    options: &PackOptions,
     pak_path: &Path,
     default_output: &Path,
-) -> Result<(), String> {
-    let temp = tempfile::tempdir().map_err(|e| format!("Failed to create temp dir: {e}"))?;
+) -> Result<PathBuf> {
+    let temp = tempfile::tempdir().map_err(|e| Error::io("Failed to create temp dir", e))?;
     unpack_legacy_pak_to_dir(pak_path, temp.path())?;
     pack_raw_dir(
         aes_key,
-        args,
+        options,
         temp.path(),
         &input_stem(pak_path),
         default_output,
@@ -420,40 +766,64 @@ fn repack_legacy_pak(
 
 fn pack_raw_dir(
     aes_key: &retoc::AesKey,
-    args: &PackArgs,
+    options: &PackOptions,
     input: &Path,
     raw_name: &str,
     default_output: &Path,
-) -> Result<(), String> {
+) -> Result<PathBuf> {
     if !input.is_dir() {
-        return Err(format!("Input is not a directory: {}", input.display()));
+        return Err(Error::UnsupportedInput(input.to_path_buf()));
     }
 
-    let output_dir = output_dir_for(args, default_output, raw_name);
+    let output_dir = output_dir_for(options, default_output, raw_name);
     fs::create_dir_all(&output_dir)
-        .map_err(|e| format!("Failed to create {}: {e}", output_dir.display()))?;
+        .map_err(|e| Error::io(format!("Failed to create {}", output_dir.display()), e))?;
 
-    let mod_name = if args.no_mod_suffix {
-        raw_name.to_string()
+    let mod_name = output_mod_name(raw_name, options.no_mod_suffix, options.priority);
+    let utoc = output_dir.join(format!("{mod_name}.utoc"));
+
+    let rel_paths = relative_files(input)?;
+    let loose = if options.loose_files_in_pak {
+        rel_paths
+            .iter()
+            .filter(|path| is_loose_file(path))
+            .cloned()
+            .collect::<Vec<_>>()
     } else {
-        ensure_mod_name_suffix(raw_name)
+        Vec::new()
     };
-    let utoc = output_dir.join(format!("{mod_name}.utoc"));
+    let pak = output_dir.join(format!("{mod_name}.pak"));
+    if !loose.is_empty() && loose.len() == rel_paths.len() {
+        println!(
+            "{} only has loose files, writing a legacy pak without IoStore containers",
+            input.display()
+        );
+        write_companion_pak(input, &pak, &[], &loose, options)?;
+        return Ok(pak);
+    }
+    // IoStore only carries packages, so loose files are left out of the containers
+    let iostore_input = if loose.is_empty() {
+        None
+    } else {
+        println!("Packing {} loose files into {}", loose.len(), pak.display());
+        Some(iostore_only_dir(input, &rel_paths)?)
+    };
+    let zen_input = iostore_input.as_ref().map_or(input, |temp| temp.path());
 
     let mut action = ActionToZen::new(
-        input.to_path_buf(),
-        utoc,
+        zen_input.to_path_buf(),
+        utoc.clone(),
         EngineVersion::UE5_3,
-        retoc_compression(args.compression),
+        retoc_compression(options.compression),
     )
-    .with_obfuscation(args.obfuscate);
-    if let Some(usmap) = args.kawaii_physics_usmap.clone() {
+    .with_obfuscation(options.obfuscate);
+    if let Some(usmap) = options.kawaii_physics_usmap.clone() {
         action = action.with_kawaii_physics_port(usmap);
     }
-    if args.patch_default_hidden_mats {
+    if options.patch_default_hidden_mats {
         action = action.with_default_hidden_material_patch();
     }
-    if let Some(default_hidden_material_bitmaps) = default_hidden_material_bitmaps(args) {
+    if let Some(default_hidden_material_bitmaps) = default_hidden_material_bitmaps(options) {
         action = action
             .with_kawaii_physics_default_hidden_material_bitmaps(default_hidden_material_bitmaps);
     }
@@ -465,97 +835,157 @@ fn pack_raw_dir(
         ..Default::default()
     };
     config.aes_keys.insert(FGuid::default(), aes_key.clone());
-    config.port_kawaii_physics = args.kawaii_physics;
-    config.kawaii_physics_usmap = args.kawaii_physics_usmap.clone();
+    config.port_kawaii_physics = options.kawaii_physics;
+    config.kawaii_physics_usmap = options.kawaii_physics_usmap.clone();
     config.kawaii_physics_force_rebuild = true;
-    config.patch_default_hidden_materials = args.patch_default_hidden_mats;
-    config.kawaii_physics_default_hidden_material_bitmaps = default_hidden_material_bitmaps(args);
-    action_to_zen(action, Arc::new(config)).map_err(|e| format!("Pack failed: {e:#}"))?;
-
-    write_chunknames_pak(
-        input,
-        &output_dir.join(format!("{mod_name}.pak")),
-        &args.mount_point,
-        &args.path_hash_seed,
-        args.compression,
-    )
+    config.patch_default_hidden_materials = options.patch_default_hidden_mats;
+    config.kawaii_physics_default_hidden_material_bitmaps =
+        default_hidden_material_bitmaps(options);
+    action_to_zen(action, Arc::new(config)).map_err(|e| Error::container("Pack failed", e))?;
+
+    write_companion_pak(input, &pak, &rel_paths, &loose, options)?;
+    Ok(utoc)
 }
 
-fn write_chunknames_pak(
-    input: &Path,
-    output: &Path,
-    mount_point: &str,
-    path_hash_seed: &str,
-    compression: crate::cli::CompressionArg,
-) -> Result<(), String> {
+/// Extensions of loose files that IoStore containers can't carry: config, localization and
+/// Wwise audio.
+const LOOSE_FILE_EXTENSIONS: [&str; 6] = ["ini", "json", "locres", "locmeta", "bnk", "wem"];
+
+fn is_loose_file(rel_path: &str) -> bool {
+    Path::new(rel_path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| {
+            LOOSE_FILE_EXTENSIONS
+                .iter()
+                .any(|loose| loose.eq_ignore_ascii_case(ext))
+        })
+}
+
+/// Sorted paths of every file below `input`, relative to it with `/` separators.
+fn relative_files(input: &Path) -> Result<Vec<String>> {
     let mut paths = Vec::new();
-    collect_files(&mut paths, input).map_err(|e| format!("Failed to scan input files: {e}"))?;
+    collect_files(&mut paths, input).map_err(|e| Error::io("Failed to scan input files", e))?;
     let mut rel_paths = paths
         .iter()
         .map(|path| {
             path.strip_prefix(input)
                 .map(|path| path.to_string_lossy().replace('\\', "/"))
-                .map_err(|e| format!("File is not in input directory: {} ({e})", path.display()))
+                .map_err(|e| {
+                    Error::Other(format!(
+                        "File is not in input directory: {} ({e})",
+                        path.display()
+                    ))
+                })
         })
-        .collect::<Result<Vec<_>, _>>()?;
+        .collect::<Result<Vec<_>>>()?;
     rel_paths.sort();
+    Ok(rel_paths)
+}
 
-    let seed = parse_path_hash_seed(path_hash_seed)?;
+/// Copies everything but the loose files of `input` into a temporary directory for the
+/// IoStore conversion, hard linking where the file system allows it.
+fn iostore_only_dir(input: &Path, rel_paths: &[String]) -> Result<TempDir> {
+    let temp = tempfile::tempdir().map_err(|e| Error::io("Failed to create temp dir", e))?;
+    for rel_path in rel_paths.iter().filter(|path| !is_loose_file(path)) {
+        let src = input.join(rel_path);
+        let dst = temp.path().join(rel_path);
+        if let Some(parent) = dst.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| Error::io(format!("Failed to create {}", parent.display()), e))?;
+        }
+        if fs::hard_link(&src, &dst).is_err() {
+            fs::copy(&src, &dst).map_err(|e| {
+                Error::io(
+                    format!("Failed to copy {} to {}", src.display(), dst.display()),
+                    e,
+                )
+            })?;
+        }
+    }
+    Ok(temp)
+}
+
+/// Writes the companion pak of an IoStore mod: a `chunknames` entry with the relative paths of
+/// the mod's files in the IoStore containers, plus the `loose` files of `input` as real entries.
+/// Loose files are left out of `chunknames`, which lists IoStore chunks only. With no
+/// `chunknames` paths the result is a plain legacy pak of the loose files.
+fn write_companion_pak(
+    input: &Path,
+    output: &Path,
+    chunknames: &[String],
+    loose: &[String],
+    options: &PackOptions,
+) -> Result<()> {
+    let seed = parse_path_hash_seed(&options.path_hash_seed)?;
     let builder = repak::PakBuilder::new()
-        .compression(repak_compression(compression))
+        .compression(repak_compression(options.compression))
         .key(pak_aes_key()?.0);
-    let file =
-        File::create(output).map_err(|e| format!("Failed to create {}: {e}", output.display()))?;
+    let file = File::create(output)
+        .map_err(|e| Error::io(format!("Failed to create {}", output.display()), e))?;
     let mut pak = builder.writer(
         BufWriter::new(file),
         repak::Version::V11,
-        mount_point.to_string(),
+        options.mount_point.clone(),
         Some(seed),
     );
-    let entry = pak
-        .entry_builder()
-        .build_entry(true, rel_paths.join("\n").into_bytes(), "chunknames")
-        .map_err(|e| format!("Failed to build chunknames entry: {e}"))?;
-    pak.write_entry("chunknames".to_string(), entry)
-        .map_err(|e| format!("Failed to write chunknames entry: {e}"))?;
+    let chunknames = chunknames
+        .iter()
+        .filter(|path| !loose.contains(path))
+        .map(String::as_str)
+        .collect::<Vec<_>>();
+    if !chunknames.is_empty() {
+        let entry = pak
+            .entry_builder()
+            .build_entry(true, chunknames.join("\n").into_bytes(), "chunknames")
+            .map_err(|e| Error::pak("Failed to build chunknames entry", e))?;
+        pak.write_entry("chunknames".to_string(), entry)
+            .map_err(|e| Error::pak("Failed to write chunknames entry", e))?;
+    }
+    for rel_path in loose {
+        let src = input.join(rel_path);
+        let data = fs::read(&src)
+            .map_err(|e| Error::io(format!("Failed to read {}", src.display()), e))?;
+        pak.write_file(rel_path, true, data)
+            .map_err(|e| Error::pak(format!("Failed to write {rel_path} into pak"), e))?;
+    }
     pak.write_index()
-        .map_err(|e| format!("Failed to write pak index: {e}"))?;
+        .map_err(|e| Error::pak("Failed to write pak index", e))?;
     println!("Wrote {}", output.display());
     Ok(())
 }
 
 fn input_stem(path: &Path) -> String {
-    path.file_stem()
-        .and_then(|stem| stem.to_str())
+    repak_manager::archive::archive_stem(path)
         .unwrap_or("mod")
         .to_string()
 }
 
-fn should_repack_iostore(args: &PackArgs) -> bool {
-    args.kawaii_physics
-        || should_patch_default_hidden_mats(args)
-        || args.obfuscate
-        || args.compression != crate::cli::CompressionArg::Oodle
+fn should_repack_iostore(options: &PackOptions) -> bool {
+    options.kawaii_physics
+        || should_patch_default_hidden_mats(options)
+        || options.obfuscate
+        || options.compression != CompressionArg::Oodle
 }
 
-fn should_patch_default_hidden_mats(args: &PackArgs) -> bool {
-    args.patch_default_hidden_mats || !args.default_hidden_material_bitmaps.is_empty()
+fn should_patch_default_hidden_mats(options: &PackOptions) -> bool {
+    options.patch_default_hidden_mats || !options.default_hidden_material_bitmaps.is_empty()
 }
 
-fn default_hidden_material_bitmaps(args: &PackArgs) -> Option<Vec<u64>> {
-    if !args.default_hidden_material_bitmaps.is_empty() {
-        Some(args.default_hidden_material_bitmaps.clone())
+fn default_hidden_material_bitmaps(options: &PackOptions) -> Option<Vec<u64>> {
+    if !options.default_hidden_material_bitmaps.is_empty() {
+        Some(options.default_hidden_material_bitmaps.clone())
     } else {
         None
     }
 }
 
-fn output_dir_for(args: &PackArgs, default_output: &Path, item_name: &str) -> PathBuf {
-    let base = args
+fn output_dir_for(options: &PackOptions, default_output: &Path, item_name: &str) -> PathBuf {
+    let base = options
         .output
         .clone()
         .unwrap_or_else(|| default_output.to_path_buf());
-    if args.separate_output_dirs {
+    if options.separate_output_dirs {
         base.join(item_name)
     } else {
         base
@@ -594,23 +1024,133 @@ fn is_under_any(path: &Path, roots: &[&Path]) -> bool {
     roots.iter().any(|root| path.starts_with(root))
 }
 
-fn archive_payload_root(root: &Path) -> PathBuf {
-    let Ok(entries) = fs::read_dir(root) else {
-        return root.to_path_buf();
-    };
-    let mut dirs = Vec::new();
-    let mut files = 0usize;
-    for entry in entries.flatten() {
-        let path = entry.path();
-        if path.is_dir() {
-            dirs.push(path);
-        } else {
-            files += 1;
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::util::{iostore_aes_key, pak_reader_builder};
+    use std::io::BufReader;
+
+    fn pack_dir_options(keep_going: bool) -> PackDirOptions {
+        PackDirOptions {
+            pack: PackOptions {
+                output: Some(PathBuf::from("out")),
+                ..PackOptions::default()
+            },
+            keep_going,
+            ..PackDirOptions::default()
         }
     }
-    if files == 0 && dirs.len() == 1 {
-        dirs.pop().unwrap()
-    } else {
-        root.to_path_buf()
+
+    #[test]
+    fn test_job_chains() {
+        let jobs = [
+            PackJob::RawDir(PathBuf::from("a/Foo")),
+            PackJob::Archive(PathBuf::from("a/Options.zip")),
+            PackJob::RawDir(PathBuf::from("a/Bar")),
+            PackJob::LegacyPak(PathBuf::from("b/FOO.pak")),
+            PackJob::RawDir(PathBuf::from("c/foo")),
+            PackJob::Archive(PathBuf::from("b/More.7z")),
+        ];
+        let options = pack_dir_options(false).pack;
+        let (chains, archives) = job_chains(&options, &jobs, Path::new("default"));
+        // outputs differing only in case are one file on Windows
+        assert_eq!(chains, [vec![0, 3, 4], vec![2]]);
+        assert_eq!(archives, [1, 5]);
+
+        let options = PackOptions {
+            separate_output_dirs: true,
+            priority: Some(5),
+            ..options
+        };
+        let (chains, _) = job_chains(&options, &jobs, Path::new("default"));
+        assert_eq!(chains, [vec![0, 3, 4], vec![2]]);
+    }
+
+    #[test]
+    fn test_run_pack_jobs_stops_after_failure() {
+        let dir = tempfile::tempdir().unwrap();
+        let jobs = ["a", "b", "c"].map(|name| PackJob::RawDir(dir.path().join(name)));
+        let aes_key = iostore_aes_key(None).unwrap();
+        let run = |keep_going| {
+            run_pack_jobs(
+                &aes_key,
+                &pack_dir_options(keep_going),
+                &jobs,
+                1,
+                dir.path(),
+                None,
+                &no_progress,
+            )
+            .into_iter()
+            .map(|report| report.status)
+            .collect::<Vec<_>>()
+        };
+
+        // the missing input directories fail to pack
+        assert_eq!(
+            run(false),
+            [JobStatus::Failed, JobStatus::Skipped, JobStatus::Skipped]
+        );
+        assert_eq!(run(true), [JobStatus::Failed; 3]);
+    }
+
+    #[test]
+    fn test_is_loose_file() {
+        assert!(is_loose_file("Marvel/Content/Hero/a.json"));
+        assert!(is_loose_file(
+            "Marvel/Content/Localization/Game/en/Game.LOCRES"
+        ));
+        assert!(!is_loose_file("Marvel/Content/Hero/a.uasset"));
+        assert!(!is_loose_file("Marvel/Content/Hero/json"));
+    }
+
+    #[test]
+    fn test_companion_pak_loose_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("Mod");
+        let hero = input.join("Marvel/Content/Hero");
+        fs::create_dir_all(&hero).unwrap();
+        fs::write(hero.join("a.uasset"), "asset").unwrap();
+        fs::write(hero.join("a.json"), "{}").unwrap();
+
+        let rel_paths = relative_files(&input).unwrap();
+        assert_eq!(
+            rel_paths,
+            ["Marvel/Content/Hero/a.json", "Marvel/Content/Hero/a.uasset"]
+        );
+        let loose = rel_paths
+            .iter()
+            .filter(|path| is_loose_file(path))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        // the IoStore conversion only sees the asset
+        let iostore_input = iostore_only_dir(&input, &rel_paths).unwrap();
+        assert_eq!(
+            relative_files(iostore_input.path()).unwrap(),
+            ["Marvel/Content/Hero/a.uasset"]
+        );
+
+        let output = dir.path().join("Mod_9999999_P.pak");
+        let options = PackOptions {
+            compression: CompressionArg::None,
+            loose_files_in_pak: true,
+            ..PackOptions::default()
+        };
+        write_companion_pak(&input, &output, &rel_paths, &loose, &options).unwrap();
+
+        let mut reader = BufReader::new(File::open(&output).unwrap());
+        let pak = pak_reader_builder().reader(&mut reader).unwrap();
+        let mut files = pak.files();
+        files.sort();
+        assert_eq!(files, ["Marvel/Content/Hero/a.json", "chunknames"]);
+        assert_eq!(
+            pak.get("chunknames", &mut reader).unwrap(),
+            b"Marvel/Content/Hero/a.uasset"
+        );
+        assert_eq!(
+            pak.get("Marvel/Content/Hero/a.json", &mut reader).unwrap(),
+            b"{}"
+        );
     }
 }
@@ -0,0 +1,183 @@
+use crate::error::{Error, Result};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum JobStatus {
+    Ok,
+    Failed,
+    /// Not started because an earlier item failed without --keep-going.
+    Skipped,
+}
+
+impl JobStatus {
+    fn label(self) -> &'static str {
+        match self {
+            JobStatus::Ok => "ok",
+            JobStatus::Failed => "FAILED",
+            JobStatus::Skipped => "skipped",
+        }
+    }
+}
+
+/// Result of packing one item found by `pack-dir`.
+#[derive(Debug, Serialize)]
+pub struct JobReport {
+    pub input: PathBuf,
+    /// Same names as the `type` field of `info --json`.
+    pub kind: &'static str,
+    pub status: JobStatus,
+    pub outputs: Vec<PathBuf>,
+    pub duration_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Kind of the error, as printed by `--json-errors`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_kind: Option<&'static str>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+}
+
+impl JobReport {
+    pub fn new(input: &Path, kind: &'static str) -> Self {
+        Self {
+            input: input.to_path_buf(),
+            kind,
+            status: JobStatus::Skipped,
+            outputs: Vec::new(),
+            duration_ms: 0,
+            error: None,
+            error_kind: None,
+            warnings: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct PackReport {
+    pub root: PathBuf,
+    pub jobs: usize,
+    pub duration_ms: u64,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub skipped: usize,
+    pub items: Vec<JobReport>,
+}
+
+impl PackReport {
+    pub fn new(root: &Path, jobs: usize, duration: Duration, mut items: Vec<JobReport>) -> Self {
+        warn_output_collisions(&mut items);
+        let count = |status| items.iter().filter(|item| item.status == status).count();
+        Self {
+            root: root.to_path_buf(),
+            jobs,
+            duration_ms: duration.as_millis() as u64,
+            succeeded: count(JobStatus::Ok),
+            failed: count(JobStatus::Failed),
+            skipped: count(JobStatus::Skipped),
+            items,
+        }
+    }
+
+    pub fn print_table(&self) {
+        let rows = self
+            .items
+            .iter()
+            .map(|item| {
+                (
+                    item.status.label(),
+                    item.kind,
+                    format!("{:.1}s", item.duration_ms as f64 / 1000.0),
+                    display_relative(&item.input, &self.root),
+                )
+            })
+            .collect::<Vec<_>>();
+        let kind_width = rows.iter().map(|row| row.1.len()).max().unwrap_or(0);
+        let time_width = rows.iter().map(|row| row.2.len()).max().unwrap_or(0);
+
+        println!();
+        for ((status, kind, time, input), item) in rows.iter().zip(&self.items) {
+            println!("{status:<7}  {kind:<kind_width$}  {time:>time_width$}  {input}");
+            for output in &item.outputs {
+                println!("    -> {}", output.display());
+            }
+            for warning in &item.warnings {
+                println!("    warning: {warning}");
+            }
+            if let Some(error) = &item.error {
+                println!("    error: {error}");
+            }
+        }
+        println!(
+            "Packed {} of {} mods in {:.1}s ({} failed, {} skipped)",
+            self.succeeded,
+            self.items.len(),
+            self.duration_ms as f64 / 1000.0,
+            self.failed,
+            self.skipped
+        );
+    }
+
+    pub fn write_json(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| Error::Other(format!("Failed to serialize pack report: {e}")))?;
+        fs::write(path, json)
+            .map_err(|e| Error::io(format!("Failed to write pack report {}", path.display()), e))?;
+        println!("Wrote report to {}", path.display());
+        Ok(())
+    }
+}
+
+/// Items writing the same output run one at a time in listed order, so the output of the last
+/// one replaces the others'.
+fn warn_output_collisions(items: &mut [JobReport]) {
+    let mut writers = HashMap::<String, Vec<(usize, PathBuf)>>::new();
+    for (index, item) in items.iter().enumerate() {
+        for output in &item.outputs {
+            // a mod is written as a `.utoc` or a `.pak` of the same name, and Windows treats
+            // names differing only in case as the same file
+            let name = output.with_extension("").to_string_lossy().to_lowercase();
+            writers
+                .entry(name)
+                .or_default()
+                .push((index, output.clone()));
+        }
+    }
+    let mut collisions = writers
+        .into_values()
+        .filter(|writers| writers.iter().any(|(index, _)| *index != writers[0].0))
+        .collect::<Vec<_>>();
+    collisions.sort();
+    for writers in collisions {
+        let (last, kept) = writers.last().unwrap().clone();
+        for (index, output) in &writers[..writers.len() - 1] {
+            let warning = format!(
+                "{} was replaced by {} from {}",
+                output.display(),
+                kept.display(),
+                items[last].input.display()
+            );
+            items[*index].warnings.push(warning);
+        }
+        let replaced = writers[..writers.len() - 1]
+            .iter()
+            .map(|(index, _)| items[*index].input.display().to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        items[last].warnings.push(format!(
+            "{} replaced the output of {replaced}",
+            kept.display()
+        ));
+    }
+}
+
+fn display_relative(path: &Path, root: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .display()
+        .to_string()
+}
@@ -0,0 +1,59 @@
+use crate::cli::{ProfileApplyArgs, ProfileArgs, ProfileCommand};
+use crate::config::read_saved_state;
+use crate::error::{Error, Result};
+use crate::util::migrate_legacy_disabled_mods;
+use repak_manager::installed::scan_installed_mods;
+use repak_manager::mod_profiles;
+
+pub fn profile(args: ProfileArgs) -> Result<()> {
+    match args.command {
+        ProfileCommand::List => list_profiles(),
+        ProfileCommand::Apply(args) => apply_profile(args),
+    }
+}
+
+fn list_profiles() -> Result<()> {
+    let state = read_saved_state()?;
+    if state.mod_profiles.is_empty() {
+        println!("No profiles saved. Create one from the Profiles menu in repak-gui.");
+    }
+    for profile in &state.mod_profiles {
+        println!("{} ({} mods)", profile.name, profile.enabled_mods.len());
+    }
+    Ok(())
+}
+
+fn apply_profile(args: ProfileApplyArgs) -> Result<()> {
+    let state = read_saved_state()?;
+    let profile = state
+        .mod_profiles
+        .iter()
+        .find(|profile| profile.name == args.name)
+        .ok_or_else(|| Error::Other(format!("No profile named {:?}", args.name)))?;
+    let mods_dir = args.mods_dir.unwrap_or(state.game_path);
+    if !mods_dir.is_dir() {
+        return Err(Error::InputNotFound(mods_dir));
+    }
+    migrate_legacy_disabled_mods(&mods_dir);
+
+    let installed = scan_installed_mods(&mods_dir)
+        .into_iter()
+        .map(|installed| (installed.path, installed.enabled))
+        .collect::<Vec<_>>();
+    let toggled = mod_profiles::apply_profile(&installed, profile)
+        .map_err(|e| Error::Other(format!("{e} No mods were changed.")))?;
+    for (path, new_path) in &toggled {
+        let enabled = new_path.extension().and_then(|ext| ext.to_str()) == Some("pak");
+        println!(
+            "{} {}",
+            if enabled { "Enabled" } else { "Disabled" },
+            path.display()
+        );
+    }
+    println!(
+        "Applied profile {} ({} changes)",
+        profile.name,
+        toggled.len()
+    );
+    Ok(())
+}
@@ -0,0 +1,37 @@
+use std::path::Path;
+
+/// Progress of a batch of mods, reported once before and once after each item.
+#[derive(Clone, Copy, Debug)]
+pub enum Progress<'a> {
+    Started {
+        index: usize,
+        total: usize,
+        input: &'a Path,
+    },
+    Finished {
+        index: usize,
+        total: usize,
+        input: &'a Path,
+        ok: bool,
+    },
+}
+
+/// Receives [`Progress`] of a batch. `pack_directory` calls it from its worker threads.
+pub type ProgressFn<'a> = &'a (dyn Fn(Progress<'_>) + Sync);
+
+/// A [`ProgressFn`] ignoring all progress.
+pub fn no_progress(_: Progress<'_>) {}
+
+/// Prints `[i/n] <verb> <input>` when an item starts, as the CLI commands do.
+pub fn print_started(verb: &str) -> impl Fn(Progress<'_>) + Sync + '_ {
+    move |progress| {
+        if let Progress::Started {
+            index,
+            total,
+            input,
+        } = progress
+        {
+            println!("[{}/{}] {verb} {}", index + 1, total, input.display());
+        }
+    }
+}
@@ -0,0 +1,39 @@
+use crate::cli::RecoverArgs;
+use crate::config::read_saved_state;
+use crate::error::{Error, Result};
+use repak_manager::install_mod::install_mod_logic::transaction::recover_interrupted_installs;
+
+pub fn recover(args: RecoverArgs) -> Result<()> {
+    let mods_dir = match args.mods_dir {
+        Some(dir) => dir,
+        None => read_saved_state()?.game_path,
+    };
+    let report = recover_interrupted_installs(&mods_dir, args.rollback);
+    if report.is_empty() {
+        println!("No interrupted installs in {}", mods_dir.display());
+        return Ok(());
+    }
+
+    for name in &report.completed {
+        println!("Completed install of {name}");
+    }
+    for name in &report.rolled_back {
+        println!("Rolled back install of {name}");
+    }
+    if report.discarded > 0 {
+        println!(
+            "Discarded {} unfinished install staging folder(s)",
+            report.discarded
+        );
+    }
+    for error in &report.errors {
+        eprintln!("{error}");
+    }
+    if !report.errors.is_empty() {
+        return Err(Error::Other(format!(
+            "{} interrupted install(s) could not be recovered",
+            report.errors.len()
+        )));
+    }
+    Ok(())
+}
@@ -1,3 +1,4 @@
+use crate::error::{Error, Result};
 use std::collections::HashSet;
 use std::path::{Path, PathBuf};
 use walkdir::WalkDir;
@@ -33,7 +34,10 @@ pub enum PackageSource {
     },
 }
 
-pub fn classify_path(path: &Path) -> Result<PackageSource, String> {
+pub fn classify_path(path: &Path) -> Result<PackageSource> {
+    if !path.exists() {
+        return Err(Error::InputNotFound(path.to_path_buf()));
+    }
     if path.is_dir() {
         let (iostore, legacy_paks, archives) = scan_directory_packages(path);
         if !has_uasset(path)
@@ -49,19 +53,18 @@ pub fn classify_path(path: &Path) -> Result<PackageSource, String> {
         return Ok(PackageSource::RawDirectory(path.to_path_buf()));
     }
 
-    let ext = lower_ext(path);
-    if matches!(ext.as_deref(), Some("7z" | "rar" | "zip")) {
+    if repak_manager::archive::is_archive(path) {
         return Ok(PackageSource::Archive(path.to_path_buf()));
     }
 
-    if matches!(ext.as_deref(), Some("pak" | "utoc" | "ucas")) {
+    if matches!(lower_ext(path).as_deref(), Some("pak" | "utoc" | "ucas")) {
         return classify_package_file(path);
     }
 
-    Err(format!("Unsupported input: {}", path.display()))
+    Err(Error::UnsupportedInput(path.to_path_buf()))
 }
 
-pub fn classify_package_file(path: &Path) -> Result<PackageSource, String> {
+pub fn classify_package_file(path: &Path) -> Result<PackageSource> {
     let pak = companion_path(path, "pak").unwrap_or_else(|| path.with_extension("pak"));
     let utoc = companion_path(path, "utoc").unwrap_or_else(|| path.with_extension("utoc"));
     let ucas = companion_path(path, "ucas").unwrap_or_else(|| path.with_extension("ucas"));
@@ -74,10 +77,7 @@ pub fn classify_package_file(path: &Path) -> Result<PackageSource, String> {
         return Ok(PackageSource::LegacyPak(pak));
     }
 
-    Err(format!(
-        "Missing IoStore companions for {}. Need .pak, .utoc, and .ucas with same stem",
-        path.display()
-    ))
+    Err(Error::MissingCompanion(path.to_path_buf()))
 }
 
 pub fn scan_directory_packages(dir: &Path) -> (Vec<IoStorePackage>, Vec<PathBuf>, Vec<PathBuf>) {
@@ -91,7 +91,7 @@ pub fn scan_directory_packages(dir: &Path) -> (Vec<IoStorePackage>, Vec<PathBuf>
             continue;
         }
         let path = entry.path();
-        if matches!(lower_ext(path).as_deref(), Some("7z" | "rar" | "zip")) {
+        if repak_manager::archive::is_archive(path) {
             archives.push(path.to_path_buf());
             continue;
         }
@@ -1,42 +1,91 @@
 use crate::archive;
 use crate::cli::{UnpackArgs, UnpackDirArgs};
+use crate::error::{Error, Result};
 use crate::iostore_ops;
+use crate::progress::{no_progress, Progress, ProgressFn};
 use crate::source::{classify_path, scan_directory_packages, IoStorePackage, PackageSource};
-use crate::util::pak_aes_key;
+use crate::util::pak_reader_builder;
 use path_clean::PathClean;
 use std::fs::{self, File};
 use std::io::{BufReader, Write};
 use std::path::{Path, PathBuf};
 
-pub fn unpack(aes_key: retoc::AesKey, args: UnpackArgs) -> Result<(), String> {
-    if args.output.is_some() && args.input.len() != 1 {
-        return Err("--output can only be used with a single input".to_string());
+/// Settings of [`unpack_inputs`].
+#[derive(Clone, Debug, Default)]
+pub struct UnpackOptions {
+    /// Output directory. Only valid with a single input. Defaults to a sibling directory named
+    /// after the input's file stem.
+    pub output: Option<PathBuf>,
+    /// Asset/package filters, IoStore only. Defaults to every package of the container.
+    pub filters: Vec<String>,
+    /// Game Paks directory for IoStore dependency containers. Falls back to the saved config.
+    pub game_paks_dir: Option<PathBuf>,
+    /// Open every game container instead of the fast-path ones.
+    pub full_iostore_check: bool,
+    /// Verbose retoc output.
+    pub verbose: bool,
+}
+
+pub fn unpack(aes_key: retoc::AesKey, args: UnpackArgs) -> Result<()> {
+    unpack_inputs(&aes_key, &args.input, &args.options(), &no_progress)?;
+    Ok(())
+}
+
+/// Extracts each package, archive or package directory to legacy assets and returns the
+/// output directories.
+pub fn unpack_inputs(
+    aes_key: &retoc::AesKey,
+    inputs: &[PathBuf],
+    options: &UnpackOptions,
+    progress: ProgressFn,
+) -> Result<Vec<PathBuf>> {
+    if options.output.is_some() && inputs.len() != 1 {
+        return Err(Error::Other(
+            "--output can only be used with a single input".to_string(),
+        ));
     }
 
-    let game_paks_dir = iostore_ops::resolve_game_paks_dir(&args.game_paks_dir)?;
-    for input in &args.input {
-        let output = args
+    let game_paks_dir = iostore_ops::resolve_game_paks_dir(&options.game_paks_dir)?;
+    let mut outputs = Vec::with_capacity(inputs.len());
+    for (index, input) in inputs.iter().enumerate() {
+        let total = inputs.len();
+        progress(Progress::Started {
+            index,
+            total,
+            input,
+        });
+        let output = options
             .output
             .clone()
             .unwrap_or_else(|| default_unpack_output(input));
-        unpack_source(
-            &aes_key,
-            classify_path(input)?,
-            &output,
-            &args.filter,
-            game_paks_dir.as_deref(),
-            args.full_iostore_check,
-            args.verbose,
-            true,
-        )?;
+        let result = classify_path(input).and_then(|source| {
+            unpack_source(
+                aes_key,
+                source,
+                &output,
+                &options.filters,
+                game_paks_dir.as_deref(),
+                options.full_iostore_check,
+                options.verbose,
+                true,
+            )
+        });
+        progress(Progress::Finished {
+            index,
+            total,
+            input,
+            ok: result.is_ok(),
+        });
+        result?;
+        outputs.push(output);
     }
-    Ok(())
+    Ok(outputs)
 }
 
-pub fn unpack_dir(aes_key: retoc::AesKey, args: UnpackDirArgs) -> Result<(), String> {
+pub fn unpack_dir(aes_key: retoc::AesKey, args: UnpackDirArgs) -> Result<()> {
     let (iostore, legacy_paks, _) = scan_directory_packages(&args.input);
     if iostore.is_empty() && legacy_paks.is_empty() {
-        return Err(format!("No packages found below {}", args.input.display()));
+        return Err(Error::NoMods(args.input));
     }
 
     let game_paks_dir = iostore_ops::resolve_game_paks_dir(&args.game_paks_dir)?;
@@ -60,7 +109,7 @@ pub fn unpack_dir(aes_key: retoc::AesKey, args: UnpackDirArgs) -> Result<(), Str
             }
             Err(error) => {
                 for package in &iostore {
-                    failure_log.push((package.utoc.clone(), error.clone()));
+                    failure_log.push((package.utoc.clone(), error.report()));
                 }
             }
         }
@@ -76,7 +125,7 @@ pub fn unpack_dir(aes_key: retoc::AesKey, args: UnpackDirArgs) -> Result<(), Str
         ));
         match unpack_legacy_pak_to_dir(&pak, &output) {
             Ok(()) => success_log.push((pak, output)),
-            Err(error) => failure_log.push((pak, error)),
+            Err(error) => failure_log.push((pak, error.report())),
         }
     }
 
@@ -93,24 +142,31 @@ pub fn unpack_dir(aes_key: retoc::AesKey, args: UnpackDirArgs) -> Result<(), Str
     if failure_log.is_empty() {
         Ok(())
     } else {
-        Err("One or more extractions failed".to_string())
+        Err(Error::BatchFailed {
+            failed: failure_log.len(),
+            total: success_log.len() + failure_log.len(),
+        })
     }
 }
 
-pub fn unpack_legacy_pak_to_dir(pak_path: &Path, output: &Path) -> Result<(), String> {
+pub fn unpack_legacy_pak_to_dir(pak_path: &Path, output: &Path) -> Result<()> {
     fs::create_dir_all(output)
-        .map_err(|e| format!("Failed to create {}: {e}", output.display()))?;
+        .map_err(|e| Error::io(format!("Failed to create {}", output.display()), e))?;
     let output_root = output
         .canonicalize()
-        .map_err(|e| format!("Failed to resolve {}: {e}", output.display()))?;
+        .map_err(|e| Error::io(format!("Failed to resolve {}", output.display()), e))?;
 
-    let file =
-        File::open(pak_path).map_err(|e| format!("Failed to open {}: {e}", pak_path.display()))?;
+    let file = File::open(pak_path)
+        .map_err(|e| Error::io(format!("Failed to open {}", pak_path.display()), e))?;
     let mut reader = BufReader::new(file);
-    let pak = repak::PakBuilder::new()
-        .key(pak_aes_key()?.0)
+    let pak = pak_reader_builder()
         .reader(&mut reader)
-        .map_err(|e| format!("Failed to read legacy pak {}: {e}", pak_path.display()))?;
+        .map_err(|e| {
+            Error::pak(
+                format!("Failed to read legacy pak {}", pak_path.display()),
+                e,
+            )
+        })?;
 
     let mount_point = PathBuf::from(pak.mount_point());
     let prefix = Path::new("../../../");
@@ -121,25 +177,27 @@ pub fn unpack_legacy_pak_to_dir(pak_path: &Path, output: &Path) -> Result<(), St
     for entry in entries {
         let full_path = mount_point.join(&entry);
         let rel_path = full_path.strip_prefix(prefix).map_err(|_| {
-            format!(
+            Error::Other(format!(
                 "Pak entry has unsupported mount path: {}",
                 full_path.display()
-            )
+            ))
         })?;
         let out_path = output_root.join(rel_path).clean();
         if !out_path.starts_with(&output_root) {
-            return Err(format!("Pak entry would write outside output: {}", entry));
+            return Err(Error::Other(format!(
+                "Pak entry would write outside output: {entry}"
+            )));
         }
         if let Some(parent) = out_path.parent() {
             fs::create_dir_all(parent)
-                .map_err(|e| format!("Failed to create {}: {e}", parent.display()))?;
+                .map_err(|e| Error::io(format!("Failed to create {}", parent.display()), e))?;
         }
         let data = pak
             .get(&entry, &mut reader)
-            .map_err(|e| format!("Failed to read pak entry {entry}: {e}"))?;
+            .map_err(|e| Error::pak(format!("Failed to read pak entry {entry}"), e))?;
         File::create(&out_path)
             .and_then(|mut file| file.write_all(&data))
-            .map_err(|e| format!("Failed to write {}: {e}", out_path.display()))?;
+            .map_err(|e| Error::io(format!("Failed to write {}", out_path.display()), e))?;
     }
 
     Ok(())
@@ -154,7 +212,7 @@ fn unpack_source(
     full_iostore_check: bool,
     verbose: bool,
     flat_single: bool,
-) -> Result<(), String> {
+) -> Result<()> {
     match source {
         PackageSource::IoStore(package) => {
             iostore_ops::to_legacy_single(
@@ -169,10 +227,7 @@ fn unpack_source(
             Ok(())
         }
         PackageSource::LegacyPak(path) => unpack_legacy_pak_to_dir(&path, output),
-        PackageSource::RawDirectory(path) => Err(format!(
-            "Input is a raw asset directory, not a package: {}",
-            path.display()
-        )),
+        PackageSource::RawDirectory(path) => Err(Error::UnsupportedInput(path)),
         PackageSource::Archive(path) => {
             let temp = archive::extract_archive(&path)?;
             let root = archive_payload_root(temp.path());
@@ -216,10 +271,10 @@ fn unpack_package_set(
     full_iostore_check: bool,
     verbose: bool,
     flat_single: bool,
-) -> Result<(), String> {
+) -> Result<()> {
     let total = iostore.len() + legacy_paks.len();
     if total == 0 {
-        return Err("No packages found".to_string());
+        return Err(Error::Other("No packages found".to_string()));
     }
 
     if iostore.len() == 1 && legacy_paks.is_empty() && flat_single {
@@ -261,10 +316,7 @@ fn unpack_package_set(
 }
 
 fn default_unpack_output(input: &Path) -> PathBuf {
-    let stem = input
-        .file_stem()
-        .and_then(|stem| stem.to_str())
-        .unwrap_or("unpacked");
+    let stem = repak_manager::archive::archive_stem(input).unwrap_or("unpacked");
     input.parent().unwrap_or_else(|| Path::new(".")).join(stem)
 }
 
@@ -1,13 +1,43 @@
+use repak::keys::DEFAULT_GAME;
 use repak::utils::AesKey as PakAesKey;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
 use crate::cli::CompressionArg;
-use crate::{MOD_NAME_SUFFIX, RIVALS_AES_KEY};
+use crate::error::{Error, Result};
+use repak_manager::keys::KEYS;
+use repak_manager::load_order::{mod_priority, with_mod_priority, DEFAULT_MOD_PRIORITY};
 
-pub fn pak_aes_key() -> Result<PakAesKey, String> {
-    PakAesKey::from_str(RIVALS_AES_KEY).map_err(|e| format!("Failed to parse AES key: {e}"))
+/// The pak key of the game for writing, from the shared key configuration, see
+/// [`repak_manager::keys`].
+pub fn pak_aes_key() -> Result<PakAesKey> {
+    KEYS.key(DEFAULT_GAME)
+        .map_err(|e| Error::AesKey(e.into()))
+}
+
+/// A pak builder reading with the key of each pak's encryption key GUID.
+pub fn pak_reader_builder() -> repak::PakBuilder {
+    repak::PakBuilder::new().keys(KEYS.clone())
+}
+
+/// The IoStore key: `--aes-key` when given, otherwise the configured key.
+pub fn iostore_aes_key(arg: Option<retoc::AesKey>) -> Result<retoc::AesKey> {
+    if let Some(key) = arg {
+        return Ok(key);
+    }
+    let key = KEYS
+        .key_str(DEFAULT_GAME)
+        .ok_or_else(|| Error::AesKey(format!("no AES key configured for {DEFAULT_GAME}").into()))?;
+    retoc::AesKey::from_str(key).map_err(|e| Error::AesKey(e.to_string().into()))
+}
+
+/// Moves mods disabled by older versions into `disabled/` with their companions, as repak-gui does
+/// on startup, so they are enabled and disabled as a unit.
+pub fn migrate_legacy_disabled_mods(mods_dir: &Path) {
+    for (old, new) in repak_manager::installed::migrate_legacy_disabled_mods(mods_dir) {
+        println!("Moved disabled mod {} to {}", old.display(), new.display());
+    }
 }
 
 pub fn collect_files(paths: &mut Vec<PathBuf>, dir: &Path) -> std::io::Result<()> {
@@ -23,10 +53,20 @@ pub fn collect_files(paths: &mut Vec<PathBuf>, dir: &Path) -> std::io::Result<()
 }
 
 pub fn ensure_mod_name_suffix(name: &str) -> String {
-    if name.ends_with(MOD_NAME_SUFFIX) {
+    if mod_priority(name).is_some() {
         name.to_string()
     } else {
-        format!("{name}{MOD_NAME_SUFFIX}")
+        with_mod_priority(name, DEFAULT_MOD_PRIORITY)
+    }
+}
+
+/// Output name for a packed mod: an explicit priority replaces any existing one, otherwise the
+/// default suffix is added unless suffixes are turned off.
+pub fn output_mod_name(name: &str, no_mod_suffix: bool, priority: Option<u32>) -> String {
+    match priority {
+        Some(priority) => with_mod_priority(name, priority),
+        None if no_mod_suffix => name.to_string(),
+        None => ensure_mod_name_suffix(name),
     }
 }
 
@@ -50,8 +90,12 @@ pub fn repak_compression(value: CompressionArg) -> Vec<repak::Compression> {
     }
 }
 
-pub fn parse_path_hash_seed(path_hash_seed: &str) -> Result<u64, String> {
+pub fn parse_path_hash_seed(path_hash_seed: &str) -> Result<u64> {
     u64::from_str_radix(path_hash_seed.trim_start_matches("0x"), 16)
         .or_else(|_| path_hash_seed.parse())
-        .map_err(|e| format!("Failed to parse path hash seed '{path_hash_seed}': {e}"))
+        .map_err(|e| {
+            Error::Other(format!(
+                "Failed to parse path hash seed '{path_hash_seed}': {e}"
+            ))
+        })
 }
@@ -7,6 +7,8 @@ use std::arch::x86_64::{
     __m128i, __m256i, _mm256_cmpeq_epi8, _mm256_loadu_si256, _mm256_movemask_epi8, _mm_cmpeq_epi8,
     _mm_loadu_si128, _mm_movemask_epi8,
 };
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{BuildHasher, RandomState};
 use std::simd::cmp::SimdPartialEq;
 use std::simd::Simd;
 /// Compares two 16-byte slices using a 16-lane SIMD vector.
@@ -217,10 +219,15 @@ unsafe fn compare_bytes_intrinsics_dynamic(a: &[u8], b: &[u8]) -> bool {
     }
 }
 
+/// Like [`compare_string_vectors`], but compares with AVX2 intrinsics. Falls back to
+/// [`compare_string_vectors`] on CPUs without AVX2.
 pub fn compare_string_vectors_simd(
     haystack1: &[String],
     haystack2: &[String],
 ) -> Vec<(usize, usize)> {
+    if !is_x86_feature_detected!("avx2") {
+        return compare_string_vectors(haystack1, haystack2);
+    }
     haystack1
         .par_iter()
         .enumerate()
@@ -229,6 +236,7 @@ pub fn compare_string_vectors_simd(
             haystack2
                 .par_iter()
                 .enumerate()
+                // SAFETY: AVX2 support was checked above.
                 .filter_map(move |(j, s2)| unsafe {
                     let bytearray2 = s2.as_bytes();
                     if bytearray1.len() != bytearray2.len() {
@@ -281,6 +289,57 @@ pub fn compare_string_vectors_naive(
     conflicts
 }
 
+/// Finds every string that appears in more than one haystack.
+///
+/// Strings are bucketed by hash, so each one is only compared with the strings sharing its hash
+/// and the cost grows with the total number of strings rather than with every pair of haystacks.
+/// Candidates are compared with AVX2 intrinsics when the CPU supports them, and with portable
+/// SIMD otherwise. The returned map holds each shared string together with the indices of the
+/// haystacks containing it, in ascending order.
+pub fn find_shared_strings(haystacks: &[Vec<String>]) -> BTreeMap<String, Vec<usize>> {
+    let avx2 = is_x86_feature_detected!("avx2");
+    let hasher = RandomState::new();
+    let mut buckets: HashMap<u64, Vec<(&str, Vec<usize>)>> = HashMap::new();
+    for (idx, haystack) in haystacks.iter().enumerate() {
+        for value in haystack {
+            let bucket = buckets
+                .entry(hasher.hash_one(value.as_bytes()))
+                .or_default();
+            let position = bucket
+                .iter()
+                .position(|(other, _)| bytes_eq(other.as_bytes(), value.as_bytes(), avx2));
+            let value_owners = match position {
+                Some(position) => &mut bucket[position].1,
+                None => {
+                    bucket.push((value, Vec::new()));
+                    &mut bucket.last_mut().unwrap().1
+                }
+            };
+            // haystacks are visited in order, so a repeat within one haystack is always last
+            if value_owners.last() != Some(&idx) {
+                value_owners.push(idx);
+            }
+        }
+    }
+    buckets
+        .into_values()
+        .flatten()
+        .filter(|(_, value_owners)| value_owners.len() > 1)
+        .map(|(value, value_owners)| (value.to_string(), value_owners))
+        .collect()
+}
+
+/// Compares with [`compare_bytes_intrinsics_dynamic`] when `avx2` is set, which the caller
+/// checked at runtime, and with [`compare_bytes_simd_dynamic`] otherwise.
+fn bytes_eq(a: &[u8], b: &[u8], avx2: bool) -> bool {
+    if avx2 {
+        // SAFETY: the caller checked AVX2 support.
+        unsafe { compare_bytes_intrinsics_dynamic(a, b) }
+    } else {
+        compare_bytes_simd_dynamic(a, b)
+    }
+}
+
 #[cfg(test)]
 #[cfg(target_arch = "x86_64")]
 mod tests {
@@ -406,6 +465,37 @@ mod tests {
             compare_string_vectors_simd(&haystack1, &haystack2)
         );
     }
+    // Test: strings shared by several haystacks report every owner once.
+    #[test]
+    fn test_find_shared_strings() {
+        let haystacks = vec![
+            vec!["a/b.uasset".to_string(), "a/c.uasset".to_string()],
+            vec!["a/b.uasset".to_string()],
+            vec!["a/c.uasset".to_string(), "a/b.uasset".to_string()],
+            vec!["d.uasset".to_string(), "d.uasset".to_string()],
+        ];
+        let shared = find_shared_strings(&haystacks);
+        assert_eq!(shared.len(), 2);
+        assert_eq!(shared["a/b.uasset"], vec![0, 1, 2]);
+        assert_eq!(shared["a/c.uasset"], vec![0, 2]);
+        assert!(!shared.contains_key("d.uasset"));
+    }
+
+    // Test: long strings differing only in their last byte stay apart.
+    #[test]
+    fn test_find_shared_long_strings() {
+        let path = format!("Marvel/Content/{}", "a".repeat(100));
+        let haystacks = vec![
+            vec![format!("{path}.uasset"), format!("{path}.uexp")],
+            vec![format!("{path}.uasset"), format!("{path}.uexq")],
+        ];
+        let shared = find_shared_strings(&haystacks);
+        assert_eq!(shared.len(), 1);
+        assert_eq!(shared[&format!("{path}.uasset")], vec![0, 1]);
+        for (a, b) in [("abc", "abc"), ("abc", "abd"), ("", "")] {
+            assert_eq!(bytes_eq(a.as_bytes(), b.as_bytes(), false), a == b);
+        }
+    }
 }
 
 #[cfg(test)]